@@ -0,0 +1,16 @@
+CREATE TABLE IF NOT EXISTS heartbeats (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    timestamp INTEGER NOT NULL,
+    category TEXT NOT NULL,
+    app TEXT NOT NULL,
+    entity_name TEXT NOT NULL,
+    entity_type TEXT NOT NULL,
+    project_path TEXT NOT NULL,
+    branch TEXT,
+    language TEXT,
+    source TEXT NOT NULL,
+    is_write BOOLEAN DEFAULT FALSE,
+    synced BOOLEAN DEFAULT FALSE
+);
+
+CREATE INDEX IF NOT EXISTS idx_heartbeats_synced_timestamp ON heartbeats(synced, timestamp);
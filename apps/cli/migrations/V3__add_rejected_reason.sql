@@ -0,0 +1,2 @@
+ALTER TABLE events ADD COLUMN rejected_reason TEXT;
+ALTER TABLE heartbeats ADD COLUMN rejected_reason TEXT;
@@ -0,0 +1,3 @@
+-- JSON array of tag names, NULL when untagged
+ALTER TABLE events ADD COLUMN tags TEXT;
+ALTER TABLE heartbeats ADD COLUMN tags TEXT;
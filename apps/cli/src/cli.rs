@@ -1,5 +1,8 @@
 use clap::{Parser, Subcommand};
 
+/// Default idle gap used when merging heartbeats into events
+pub const DEFAULT_IDLE_GAP_SECS: i64 = 300;
+
 #[derive(Parser, Debug)]
 #[command(
     name = "skopio-cli",
@@ -50,8 +53,55 @@ pub enum Commands {
         #[arg(long)]
         /// The end timestamp of the event (Unix timestamp)
         end_timestamp: i32,
+
+        #[arg(long = "tag")]
+        /// A tag of the event, eg. the client the work is billed to. Can be repeated
+        tags: Vec<String>,
+    },
+
+    /// Record a heartbeat (a single point of editor activity)
+    Heartbeat {
+        #[arg(long, short)]
+        /// The entity path
+        entity: String,
+
+        #[arg(long, default_value = "File")]
+        /// The entity type, be it an App, File or Url
+        entity_type: String,
+
+        #[arg(long, short)]
+        /// The full path of the currently open project
+        project: String,
+
+        #[arg(long, short)]
+        /// The name of the extension/plugin that has generated the heartbeat
+        source: String,
+
+        #[arg(long, short)]
+        /// The app being tracked
+        app: String,
+
+        #[arg(long, short, default_value = "Coding")]
+        /// The heartbeat category, eg. Coding, Debugging, etc.
+        category: String,
+
+        #[arg(long, short)]
+        /// The time of the heartbeat (Unix timestamp). Defaults to now
+        timestamp: Option<i64>,
+
+        #[arg(long)]
+        /// Whether the heartbeat was triggered by saving the entity
+        is_write: bool,
+
+        #[arg(long = "tag")]
+        /// A tag of the heartbeat, eg. the client the work is billed to. Can be repeated
+        tags: Vec<String>,
     },
 
     /// Sync stored data to the main server
-    Sync,
+    Sync {
+        #[arg(long, default_value_t = DEFAULT_IDLE_GAP_SECS)]
+        /// The longest gap (in seconds) between heartbeats that still counts as continuous activity
+        idle_gap: i64,
+    },
 }
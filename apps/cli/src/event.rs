@@ -2,7 +2,7 @@ use common::git::find_git_branch;
 use rusqlite::{Connection, params};
 use tracing::info;
 
-use crate::error::CliError;
+use crate::{error::CliError, utils::encode_tags};
 
 pub struct EventData {
     pub timestamp: i32,
@@ -15,14 +15,15 @@ pub struct EventData {
     pub language: Option<String>,
     pub source: String,
     pub end_timestamp: i32,
+    pub tags: Vec<String>,
 }
 
 pub fn save_event(conn: &Connection, event_data: EventData) -> Result<(), CliError> {
     let branch = find_git_branch(&event_data.project);
 
     conn.execute(
-        "INSERT INTO events (timestamp, category, app, entity_name, entity_type, duration, project_path, branch, language, source, end_timestamp, synced)
-        VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, 0)",
+        "INSERT INTO events (timestamp, category, app, entity_name, entity_type, duration, project_path, branch, language, source, end_timestamp, tags, synced)
+        VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, 0)",
         params![
             event_data.timestamp,
             event_data.category,
@@ -35,6 +36,7 @@ pub fn save_event(conn: &Connection, event_data: EventData) -> Result<(), CliErr
             event_data.language,
             event_data.source,
             event_data.end_timestamp,
+            encode_tags(&event_data.tags),
         ],
     )?;
 
@@ -66,6 +68,7 @@ mod tests {
             language: Some("Rust".into()),
             source: "skopio-vsode".into(),
             end_timestamp: 2020,
+            tags: vec!["client-acme".into()],
         };
 
         save_event(&conn, test_event).unwrap();
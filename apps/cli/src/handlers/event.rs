@@ -19,6 +19,7 @@ pub fn handle_event(conn: &Connection, command: Commands) -> Result<(), CliError
             project,
             source,
             end_timestamp,
+            tags,
         } => {
             let language = detect_language(&entity);
 
@@ -33,6 +34,7 @@ pub fn handle_event(conn: &Connection, command: Commands) -> Result<(), CliError
                 language,
                 source,
                 end_timestamp,
+                tags,
             };
 
             event::save_event(conn, event_data)?;
@@ -0,0 +1,44 @@
+use chrono::Utc;
+use common::language::detect_language;
+use rusqlite::Connection;
+
+use crate::{
+    cli::Commands,
+    error::CliError,
+    heartbeat::{self, HeartbeatData},
+};
+
+pub fn handle_heartbeat(conn: &Connection, command: Commands) -> Result<(), CliError> {
+    match command {
+        Commands::Heartbeat {
+            entity,
+            entity_type,
+            project,
+            source,
+            app,
+            category,
+            timestamp,
+            is_write,
+            tags,
+        } => {
+            let language = detect_language(&entity);
+
+            let data = HeartbeatData {
+                timestamp: timestamp.unwrap_or_else(|| Utc::now().timestamp()),
+                category,
+                app,
+                entity,
+                entity_type,
+                project,
+                language,
+                source,
+                is_write,
+                tags,
+            };
+
+            heartbeat::save_heartbeat(conn, data)?;
+            Ok(())
+        }
+        _ => Err(CliError::VariantMismatch("Heartbeat".to_string())),
+    }
+}
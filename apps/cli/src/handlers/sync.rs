@@ -4,8 +4,8 @@ use crate::{cli::Commands, error::CliError, sync};
 
 pub async fn handle_sync(conn: &Connection, command: Commands) -> Result<(), CliError> {
     match command {
-        Commands::Sync => {
-            sync::sync_data(conn).await?;
+        Commands::Sync { idle_gap } => {
+            sync::sync_data(conn, idle_gap).await?;
             Ok(())
         }
         _ => Err(CliError::VariantMismatch("Sync".to_string())),
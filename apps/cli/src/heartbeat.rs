@@ -0,0 +1,312 @@
+use common::git::find_git_branch;
+use rusqlite::{Connection, Row, params};
+use tracing::debug;
+
+use crate::{
+    error::CliError,
+    utils::{decode_tags, encode_tags},
+};
+
+pub struct HeartbeatData {
+    pub timestamp: i64,
+    pub category: String,
+    pub app: String,
+    pub entity: String,
+    pub entity_type: String,
+    pub project: String,
+    pub language: Option<String>,
+    pub source: String,
+    pub is_write: bool,
+    pub tags: Vec<String>,
+}
+
+/// A heartbeat row as stored in the CLI database
+#[derive(Debug, Clone)]
+pub struct Heartbeat {
+    pub id: i64,
+    pub timestamp: i64,
+    pub category: String,
+    pub app: String,
+    pub entity_name: String,
+    pub entity_type: String,
+    pub project_path: String,
+    pub branch: Option<String>,
+    pub language: Option<String>,
+    pub source: String,
+    pub tags: Vec<String>,
+}
+
+impl Heartbeat {
+    /// Whether both heartbeats describe the same activity (app, entity, project, branch
+    /// and tags)
+    fn same_activity(&self, other: &Heartbeat) -> bool {
+        self.app == other.app
+            && self.entity_name == other.entity_name
+            && self.project_path == other.project_path
+            && self.branch == other.branch
+            && self.tags == other.tags
+    }
+}
+
+/// A run of consecutive heartbeats describing the same activity
+#[derive(Debug)]
+pub struct HeartbeatSession {
+    /// The first heartbeat of the run, which describes the activity
+    pub head: Heartbeat,
+    /// Ids of every heartbeat merged into this session
+    pub heartbeat_ids: Vec<i64>,
+    pub start: i64,
+    pub end: i64,
+}
+
+impl HeartbeatSession {
+    fn new(heartbeat: &Heartbeat) -> Self {
+        Self {
+            head: heartbeat.clone(),
+            heartbeat_ids: vec![heartbeat.id],
+            start: heartbeat.timestamp,
+            end: heartbeat.timestamp,
+        }
+    }
+
+    fn continues_with(&self, heartbeat: &Heartbeat, idle_gap: i64) -> bool {
+        self.head.same_activity(heartbeat) && heartbeat.timestamp - self.end <= idle_gap
+    }
+
+    pub fn duration(&self) -> i64 {
+        self.end - self.start
+    }
+}
+
+pub fn save_heartbeat(conn: &Connection, data: HeartbeatData) -> Result<(), CliError> {
+    let branch = find_git_branch(&data.project);
+
+    conn.execute(
+        "INSERT INTO heartbeats (timestamp, category, app, entity_name, entity_type, project_path, branch, language, source, is_write, tags, synced)
+        VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, 0)",
+        params![
+            data.timestamp,
+            data.category,
+            data.app,
+            data.entity,
+            data.entity_type,
+            data.project,
+            branch,
+            data.language,
+            data.source,
+            data.is_write,
+            encode_tags(&data.tags),
+        ],
+    )?;
+
+    debug!("Heartbeat saved for {} at {}", data.entity, data.timestamp);
+
+    Ok(())
+}
+
+/// Fetches all unsynced heartbeats that have not been rejected, in chronological order
+pub fn fetch_unsynced_heartbeats(conn: &Connection) -> Result<Vec<Heartbeat>, CliError> {
+    let mut stmt = conn.prepare(
+        "SELECT id, timestamp, category, app, entity_name, entity_type, project_path, branch, language, source, tags
+             FROM heartbeats WHERE synced = 0 AND rejected_reason IS NULL
+             ORDER BY timestamp, id",
+    )?;
+    let rows = stmt.query_map([], parse_heartbeat)?;
+    Ok(rows.flatten().collect())
+}
+
+fn parse_heartbeat(row: &Row) -> rusqlite::Result<Heartbeat> {
+    Ok(Heartbeat {
+        id: row.get(0)?,
+        timestamp: row.get(1)?,
+        category: row.get(2)?,
+        app: row.get(3)?,
+        entity_name: row.get(4)?,
+        entity_type: row.get(5)?,
+        project_path: row.get(6)?,
+        branch: row.get(7)?,
+        language: row.get(8)?,
+        source: row.get(9)?,
+        tags: decode_tags(row.get(10)?),
+    })
+}
+
+/// Merges chronologically ordered heartbeats into sessions.
+///
+/// Consecutive heartbeats for the same activity belong to one session as long as
+/// the gap between them does not exceed `idle_gap`. When the activity changes within
+/// `idle_gap`, the previous session is extended up to the switch. The trailing session
+/// is only returned once it has been idle for longer than `idle_gap` as of `now`,
+/// since later heartbeats may still extend it.
+pub fn coalesce_heartbeats(
+    heartbeats: &[Heartbeat],
+    idle_gap: i64,
+    now: i64,
+) -> Vec<HeartbeatSession> {
+    let mut sessions = Vec::new();
+    let mut current: Option<HeartbeatSession> = None;
+
+    for heartbeat in heartbeats {
+        if let Some(session) = current.as_mut()
+            && session.continues_with(heartbeat, idle_gap)
+        {
+            session.end = heartbeat.timestamp;
+            session.heartbeat_ids.push(heartbeat.id);
+            continue;
+        }
+
+        if let Some(mut session) = current.take() {
+            if heartbeat.timestamp - session.end <= idle_gap {
+                session.end = heartbeat.timestamp;
+            }
+            sessions.push(session);
+        }
+
+        current = Some(HeartbeatSession::new(heartbeat));
+    }
+
+    if let Some(session) = current
+        && now - session.end > idle_gap
+    {
+        sessions.push(session);
+    }
+
+    sessions
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::{setup_test_conn, update_synced_in};
+
+    fn heartbeat(id: i64, timestamp: i64, entity: &str) -> Heartbeat {
+        Heartbeat {
+            id,
+            timestamp,
+            category: "Coding".into(),
+            app: "Code".into(),
+            entity_name: entity.into(),
+            entity_type: "File".into(),
+            project_path: "/tmp/project".into(),
+            branch: Some("main".into()),
+            language: Some("Rust".into()),
+            source: "skopio-vscode".into(),
+            tags: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_save_heartbeat_inserts_into_db() {
+        let conn = setup_test_conn();
+
+        let data = HeartbeatData {
+            timestamp: 1720,
+            category: "Coding".into(),
+            app: "Code".into(),
+            entity: "main.rs".into(),
+            entity_type: "File".into(),
+            project: "/tmp/my-project".into(),
+            language: Some("Rust".into()),
+            source: "skopio-vscode".into(),
+            is_write: true,
+            tags: Vec::new(),
+        };
+
+        save_heartbeat(&conn, data).unwrap();
+
+        let heartbeats = fetch_unsynced_heartbeats(&conn).unwrap();
+        assert_eq!(heartbeats.len(), 1);
+
+        let is_write: bool = conn
+            .query_row("SELECT is_write FROM heartbeats", [], |row| row.get(0))
+            .unwrap();
+        assert!(is_write);
+    }
+
+    #[test]
+    fn test_coalesce_merges_heartbeats_within_idle_gap() {
+        let heartbeats = vec![
+            heartbeat(1, 1000, "main.rs"),
+            heartbeat(2, 1060, "main.rs"),
+            heartbeat(3, 1120, "main.rs"),
+        ];
+
+        let sessions = coalesce_heartbeats(&heartbeats, 120, 2000);
+        assert_eq!(sessions.len(), 1);
+        assert_eq!(sessions[0].start, 1000);
+        assert_eq!(sessions[0].end, 1120);
+        assert_eq!(sessions[0].heartbeat_ids, vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn test_coalesce_extends_session_until_entity_switch() {
+        let heartbeats = vec![
+            heartbeat(1, 1000, "main.rs"),
+            heartbeat(2, 1060, "main.rs"),
+            heartbeat(3, 1090, "lib.rs"),
+            heartbeat(4, 1150, "lib.rs"),
+        ];
+
+        let sessions = coalesce_heartbeats(&heartbeats, 120, 2000);
+        assert_eq!(sessions.len(), 2);
+        assert_eq!(sessions[0].end, 1090);
+        assert_eq!(sessions[1].head.entity_name, "lib.rs");
+        assert_eq!(sessions[1].duration(), 60);
+    }
+
+    #[test]
+    fn test_coalesce_splits_sessions_when_tags_change() {
+        let mut tagged = heartbeat(2, 1060, "main.rs");
+        tagged.tags = vec!["client-acme".into()];
+        let heartbeats = vec![heartbeat(1, 1000, "main.rs"), tagged];
+
+        let sessions = coalesce_heartbeats(&heartbeats, 120, 2000);
+        assert_eq!(sessions.len(), 2);
+        assert_eq!(sessions[0].end, 1060);
+        assert_eq!(sessions[1].head.tags, ["client-acme"]);
+    }
+
+    #[test]
+    fn test_coalesce_keeps_open_session_pending() {
+        let heartbeats = vec![
+            heartbeat(1, 1000, "main.rs"),
+            heartbeat(2, 1060, "lib.rs"),
+            heartbeat(3, 1100, "lib.rs"),
+        ];
+
+        let sessions = coalesce_heartbeats(&heartbeats, 120, 1150);
+        assert_eq!(sessions.len(), 1);
+        assert_eq!(sessions[0].heartbeat_ids, vec![1]);
+    }
+
+    #[test]
+    fn test_update_synced_in_only_marks_given_heartbeats() {
+        let conn = setup_test_conn();
+
+        for ts in [1000, 1060] {
+            save_heartbeat(
+                &conn,
+                HeartbeatData {
+                    timestamp: ts,
+                    category: "Coding".into(),
+                    app: "Code".into(),
+                    entity: "main.rs".into(),
+                    entity_type: "File".into(),
+                    project: "/tmp/project".into(),
+                    language: None,
+                    source: "skopio-vscode".into(),
+                    is_write: false,
+                    tags: Vec::new(),
+                },
+            )
+            .unwrap();
+        }
+
+        let heartbeats = fetch_unsynced_heartbeats(&conn).unwrap();
+        update_synced_in(&conn, "heartbeats", &[heartbeats[0].id]).unwrap();
+
+        let remaining = fetch_unsynced_heartbeats(&conn).unwrap();
+        assert_eq!(remaining.len(), 1);
+        assert_eq!(remaining[0].timestamp, 1060);
+    }
+}
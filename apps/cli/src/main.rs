@@ -2,6 +2,7 @@ use crate::cli::Cli;
 use crate::db::init_db;
 use crate::error::CliError;
 use crate::handlers::event::handle_event;
+use crate::handlers::heartbeat::handle_heartbeat;
 use crate::handlers::sync::handle_sync;
 use crate::utils::init_tracing;
 use clap::Parser;
@@ -12,6 +13,7 @@ mod db;
 mod error;
 mod event;
 mod handlers;
+mod heartbeat;
 mod sync;
 mod utils;
 
@@ -32,7 +34,8 @@ async fn run() -> Result<(), CliError> {
 
     match cli.command {
         Some(cmd @ cli::Commands::Event { .. }) => handle_event(&conn, cmd),
-        Some(cmd @ cli::Commands::Sync) => handle_sync(&conn, cmd).await,
+        Some(cmd @ cli::Commands::Heartbeat { .. }) => handle_heartbeat(&conn, cmd),
+        Some(cmd @ cli::Commands::Sync { .. }) => handle_sync(&conn, cmd).await,
         None => Ok(()),
     }
 }
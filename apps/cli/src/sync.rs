@@ -1,57 +1,220 @@
-use crate::{error::CliError, utils::extract_project_name};
+use crate::{
+    error::CliError,
+    heartbeat::{HeartbeatSession, coalesce_heartbeats, fetch_unsynced_heartbeats},
+    utils::{decode_tags, extract_project_name, update_rejected_in, update_synced_in},
+};
 use chrono::{Duration, TimeZone, Utc};
-use common::{client::Transport, models::inputs::EventInput};
-use rusqlite::{Connection, Row};
-use tracing::{debug, info};
+use common::{
+    client::Transport,
+    models::{inputs::EventInput, outputs::IngestReport},
+};
+use rusqlite::{Connection, Row, params};
+use tracing::{debug, info, warn};
 
-pub async fn sync_data(conn: &Connection) -> Result<(), CliError> {
+/// Maximum number of rows posted to the server in a single request
+const SYNC_CHUNK_SIZE: usize = 100;
+
+pub async fn sync_data(conn: &Connection, idle_gap: i64) -> Result<(), CliError> {
     let transport = Transport::new()?;
-    let events = fetch_unsynced_events(conn)?;
 
-    if events.is_empty() {
+    let synced_events = sync_events(conn, &transport).await?;
+    let synced_heartbeats = sync_heartbeats(conn, &transport, idle_gap).await?;
+
+    if synced_events == 0 && synced_heartbeats == 0 {
         debug!("No data to sync");
         return Ok(());
     }
 
-    if !events.is_empty() {
-        sync_to_server(&transport, "events", &events).await?;
-        conn.execute("UPDATE events SET synced = 1 WHERE synced = 0", [])?;
-        info!("{} events synced successfully!", events.len())
-    }
-
     delete_synced_data(conn)?;
 
     Ok(())
 }
 
-fn fetch_unsynced_events(conn: &Connection) -> Result<Vec<EventInput>, CliError> {
+/// Posts unsynced events in chunks, marking only the rows that were actually sent.
+///
+/// Each chunk is settled as soon as the server answers: accepted and duplicate rows are
+/// marked as synced, rejected rows are set aside with the server's reason. A failed
+/// chunk leaves the remaining rows untouched for the next sync to pick up.
+async fn sync_events(conn: &Connection, transport: &Transport) -> Result<usize, CliError> {
+    let mut after_id = 0;
+    let mut total = 0;
+
+    loop {
+        let chunk = fetch_unsynced_events(conn, after_id, SYNC_CHUNK_SIZE)?;
+        let Some(&(last_id, _)) = chunk.last() else {
+            break;
+        };
+        after_id = last_id;
+
+        let (ids, events): (Vec<i64>, Vec<EventInput>) = chunk.into_iter().unzip();
+        let report = sync_to_server(transport, "events", &events).await?;
+
+        let rows: Vec<Vec<i64>> = ids.into_iter().map(|id| vec![id]).collect();
+        total += settle_chunk(conn, "events", &rows, report)?;
+    }
+
+    if total > 0 {
+        info!("{} events synced successfully!", total);
+    }
+
+    Ok(total)
+}
+
+/// Posts closed heartbeat sessions as events in chunks, settling the merged heartbeats
+/// of each chunk once the server answers.
+async fn sync_heartbeats(
+    conn: &Connection,
+    transport: &Transport,
+    idle_gap: i64,
+) -> Result<usize, CliError> {
+    let sessions = fetch_closed_sessions(conn, idle_gap)?;
+    let mut total = 0;
+
+    for chunk in sessions.chunks(SYNC_CHUNK_SIZE) {
+        let (sendable, empty): (Vec<&HeartbeatSession>, Vec<&HeartbeatSession>) =
+            chunk.iter().partition(|session| session.duration() > 0);
+
+        // Zero-length sessions carry no time, so they are consumed without being sent
+        let empty_ids: Vec<i64> = empty
+            .iter()
+            .flat_map(|session| session.heartbeat_ids.iter().copied())
+            .collect();
+        update_synced_in(conn, "heartbeats", &empty_ids)?;
+        total += empty_ids.len();
+
+        if sendable.is_empty() {
+            continue;
+        }
+
+        let events: Vec<EventInput> = sendable
+            .iter()
+            .map(|session| session_to_event(session))
+            .collect();
+        let report = sync_to_server(transport, "events", &events).await?;
+
+        let rows: Vec<Vec<i64>> = sendable
+            .iter()
+            .map(|session| session.heartbeat_ids.clone())
+            .collect();
+        total += settle_chunk(conn, "heartbeats", &rows, report)?;
+    }
+
+    if total > 0 {
+        info!("{} heartbeats synced as {} sessions", total, sessions.len());
+    }
+
+    Ok(total)
+}
+
+/// Applies the server's ingest report to the local rows of a posted chunk.
+///
+/// `rows[i]` holds the local row ids behind item `i` of the payload. Accepted and
+/// duplicate items are marked as synced, rejected items are set aside so they are
+/// not retried. Returns the number of rows marked as synced.
+fn settle_chunk(
+    conn: &Connection,
+    table: &str,
+    rows: &[Vec<i64>],
+    report: IngestReport,
+) -> Result<usize, CliError> {
+    let row_ids = |index: usize| rows.get(index).into_iter().flatten().copied();
+
+    let synced: Vec<i64> = report
+        .accepted
+        .iter()
+        .chain(&report.duplicate)
+        .flat_map(|item| row_ids(item.index))
+        .collect();
+
+    let mut rejected = Vec::new();
+    for item in &report.rejected {
+        let reason = item.reason.clone().unwrap_or_default();
+        warn!(
+            "Server rejected {} row(s) {:?}: {}",
+            table,
+            rows.get(item.index),
+            reason
+        );
+        rejected.extend(row_ids(item.index).map(|id| (id, reason.clone())));
+    }
+
+    update_synced_in(conn, table, &synced)?;
+    update_rejected_in(conn, table, &rejected)?;
+
+    Ok(synced.len())
+}
+
+/// Merges unsynced heartbeats into sessions, leaving out the session that may still be in progress
+fn fetch_closed_sessions(
+    conn: &Connection,
+    idle_gap: i64,
+) -> Result<Vec<HeartbeatSession>, CliError> {
+    let heartbeats = fetch_unsynced_heartbeats(conn)?;
+    Ok(coalesce_heartbeats(
+        &heartbeats,
+        idle_gap,
+        Utc::now().timestamp(),
+    ))
+}
+
+fn session_to_event(session: &HeartbeatSession) -> EventInput {
+    let head = &session.head;
+
+    EventInput {
+        timestamp: Utc.timestamp_opt(session.start, 0).single(),
+        category: head.category.clone(),
+        app_name: head.app.clone(),
+        entity_name: head.entity_name.clone(),
+        entity_type: head.entity_type.clone(),
+        duration: Some(session.duration()),
+        project_name: extract_project_name(&head.project_path),
+        project_path: head.project_path.clone(),
+        branch_name: head.branch.clone(),
+        language_name: head.language.clone(),
+        source_name: head.source.clone(),
+        end_timestamp: Utc.timestamp_opt(session.end, 0).single(),
+        tags: head.tags.clone(),
+    }
+}
+
+/// Fetches up to `limit` unsynced events with an id greater than `after_id`, paired with their row ids
+fn fetch_unsynced_events(
+    conn: &Connection,
+    after_id: i64,
+    limit: usize,
+) -> Result<Vec<(i64, EventInput)>, CliError> {
     let mut stmt = conn
         .prepare(
-            "SELECT timestamp, category, app, entity_name, entity_type, duration, project_path, branch, language, source, end_timestamp
-                 FROM events WHERE synced = 0"
+            "SELECT id, timestamp, category, app, entity_name, entity_type, duration, project_path, branch, language, source, end_timestamp, tags
+                 FROM events WHERE synced = 0 AND rejected_reason IS NULL AND id > ?1
+                 ORDER BY id
+                 LIMIT ?2"
                 )?;
-    let rows = stmt.query_map([], parse_event)?;
+    let rows = stmt.query_map(params![after_id, limit as i64], |row| {
+        Ok((row.get(0)?, parse_event(row)?))
+    })?;
     Ok(rows.flatten().collect())
 }
 
 fn parse_event(row: &Row) -> rusqlite::Result<EventInput> {
-    let ts: Option<i64> = row.get(0)?;
-    let end_ts: Option<i64> = row.get(10)?;
-    let project_path: String = row.get(6)?;
+    let ts: Option<i64> = row.get(1)?;
+    let end_ts: Option<i64> = row.get(11)?;
+    let project_path: String = row.get(7)?;
 
     Ok(EventInput {
         timestamp: ts.map(|t| Utc.timestamp_opt(t, 0).single().unwrap_or_default()),
-        category: row.get(1)?,
-        app_name: row.get(2)?,
-        entity_name: row.get(3)?,
-        entity_type: row.get(4)?,
-        duration: row.get(5)?,
+        category: row.get(2)?,
+        app_name: row.get(3)?,
+        entity_name: row.get(4)?,
+        entity_type: row.get(5)?,
+        duration: row.get(6)?,
         project_name: extract_project_name(&project_path),
         project_path,
-        branch_name: row.get(7)?,
-        language_name: row.get(8)?,
-        source_name: row.get(9)?,
+        branch_name: row.get(8)?,
+        language_name: row.get(9)?,
+        source_name: row.get(10)?,
         end_timestamp: end_ts.map(|t| Utc.timestamp_opt(t, 0).single().unwrap_or_default()),
+        tags: decode_tags(row.get(12)?),
     })
 }
 
@@ -59,12 +222,12 @@ async fn sync_to_server<T: serde::Serialize>(
     transport: &Transport,
     path: &str,
     data: &T,
-) -> Result<(), CliError> {
+) -> Result<IngestReport, CliError> {
     let path = Transport::build_path(path);
     let json = serde_json::to_vec(data)?;
 
-    let _ = transport.post(&path, json).await?;
-    Ok(())
+    let payload = transport.post(&path, json).await?;
+    Ok(serde_json::from_slice(&payload)?)
 }
 
 fn delete_synced_data(conn: &Connection) -> Result<(), CliError> {
@@ -76,7 +239,15 @@ fn delete_synced_data(conn: &Connection) -> Result<(), CliError> {
         [cutoff_unix],
     )?;
 
-    debug!("Deleted {} old synced events", deleted_events);
+    let deleted_heartbeats = conn.execute(
+        "DELETE FROM heartbeats WHERE synced = 1 AND timestamp < ?1",
+        [cutoff_unix],
+    )?;
+
+    debug!(
+        "Deleted {} old synced events and {} old synced heartbeats",
+        deleted_events, deleted_heartbeats
+    );
     Ok(())
 }
 
@@ -85,8 +256,10 @@ mod tests {
     use super::*;
     use crate::{
         event::{EventData, save_event},
+        heartbeat::{HeartbeatData, save_heartbeat},
         utils::setup_test_conn,
     };
+    use common::models::outputs::IngestItemResult;
     use rusqlite::params;
 
     #[test]
@@ -105,14 +278,123 @@ mod tests {
             project: "/tmp/project".into(),
             language: Some("Rust".into()),
             end_timestamp: (now + 100) as i32,
+            tags: vec!["client-acme".into()],
         };
 
         save_event(&conn, test_event).unwrap();
 
-        let events = fetch_unsynced_events(&conn).unwrap();
+        let events = fetch_unsynced_events(&conn, 0, SYNC_CHUNK_SIZE).unwrap();
         assert_eq!(events.len(), 1);
-        assert_eq!(events[0].category, "Coding");
-        assert_eq!(events[0].project_path, "/tmp/project");
+        assert_eq!(events[0].1.category, "Coding");
+        assert_eq!(events[0].1.project_path, "/tmp/project");
+        assert_eq!(events[0].1.tags, ["client-acme"]);
+    }
+
+    #[test]
+    fn test_marking_synced_ids_leaves_new_events_unsynced() {
+        let conn = setup_test_conn();
+        let insert = |ts: i64| {
+            conn.execute(
+                "INSERT INTO events (timestamp, category, app, entity_name, entity_type, duration, project_path, branch, language, source, end_timestamp, synced)
+                 VALUES (?1, 'Coding', 'Code', 'main.rs', 'File', 100, '/tmp/project', 'main', 'Rust', 'skopio-vscode', ?2, 0)",
+                params![ts, ts + 100],
+            )
+            .unwrap();
+        };
+
+        insert(1000);
+        insert(2000);
+        let sent = fetch_unsynced_events(&conn, 0, 1).unwrap();
+        assert_eq!(sent.len(), 1);
+
+        // Written by a concurrent `skopio-cli event` call while the chunk was in flight
+        insert(3000);
+
+        let ids: Vec<i64> = sent.iter().map(|(id, _)| *id).collect();
+        update_synced_in(&conn, "events", &ids).unwrap();
+
+        let remaining = fetch_unsynced_events(&conn, 0, SYNC_CHUNK_SIZE).unwrap();
+        assert_eq!(remaining.len(), 2);
+
+        let resumed = fetch_unsynced_events(&conn, ids[0], 1).unwrap();
+        assert_eq!(resumed[0].1.timestamp.unwrap().timestamp(), 2000);
+    }
+
+    #[test]
+    fn test_settle_chunk_sets_rejected_rows_aside() {
+        let conn = setup_test_conn();
+        for ts in [1000, 2000, 3000] {
+            conn.execute(
+                "INSERT INTO events (timestamp, category, app, entity_name, entity_type, duration, project_path, branch, language, source, end_timestamp, synced)
+                 VALUES (?1, 'Coding', 'Code', 'main.rs', 'File', 100, '/tmp/project', 'main', 'Rust', 'skopio-vscode', ?2, 0)",
+                params![ts, ts + 100],
+            )
+            .unwrap();
+        }
+
+        let chunk = fetch_unsynced_events(&conn, 0, SYNC_CHUNK_SIZE).unwrap();
+        let rows: Vec<Vec<i64>> = chunk.iter().map(|(id, _)| vec![*id]).collect();
+        let item = |index: usize, reason: Option<&str>| IngestItemResult {
+            index,
+            id: None,
+            reason: reason.map(String::from),
+        };
+        let report = IngestReport {
+            accepted: vec![item(0, None)],
+            duplicate: vec![item(1, Some("Already stored"))],
+            rejected: vec![item(2, Some("Duration is negative"))],
+        };
+
+        let synced = settle_chunk(&conn, "events", &rows, report).unwrap();
+        assert_eq!(synced, 2);
+        assert!(
+            fetch_unsynced_events(&conn, 0, SYNC_CHUNK_SIZE)
+                .unwrap()
+                .is_empty()
+        );
+
+        let reason: String = conn
+            .query_row(
+                "SELECT rejected_reason FROM events WHERE id = ?1 AND synced = 0",
+                [rows[2][0]],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(reason, "Duration is negative");
+    }
+
+    #[test]
+    fn test_closed_sessions_become_events() {
+        let conn = setup_test_conn();
+        let now = Utc::now().timestamp();
+
+        for (offset, entity) in [(-900, "main.rs"), (-840, "main.rs"), (-60, "lib.rs")] {
+            save_heartbeat(
+                &conn,
+                HeartbeatData {
+                    timestamp: now + offset,
+                    category: "Coding".into(),
+                    app: "Code".into(),
+                    entity: entity.into(),
+                    entity_type: "File".into(),
+                    project: "/tmp/project".into(),
+                    language: Some("Rust".into()),
+                    source: "skopio-vscode".into(),
+                    is_write: false,
+                    tags: vec!["client-acme".into()],
+                },
+            )
+            .unwrap();
+        }
+
+        let sessions = fetch_closed_sessions(&conn, 300).unwrap();
+        assert_eq!(sessions.len(), 1);
+
+        let event = session_to_event(&sessions[0]);
+        assert_eq!(event.entity_name, "main.rs");
+        assert_eq!(event.duration, Some(60));
+        assert_eq!(event.project_name, "project");
+        assert_eq!(event.tags, ["client-acme"]);
     }
 
     #[test]
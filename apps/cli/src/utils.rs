@@ -4,7 +4,7 @@ use std::{
 };
 
 use common::keyring::Keyring;
-use rusqlite::Connection;
+use rusqlite::{Connection, params, params_from_iter};
 use tracing::Level;
 use tracing_subscriber::{EnvFilter, fmt::writer::MakeWriterExt};
 
@@ -19,6 +19,51 @@ pub fn extract_project_name<T: AsRef<Path>>(project_path: T) -> String {
         .unwrap_or_default()
 }
 
+/// Encodes tags for the `tags` column, `NULL` when there are none
+pub fn encode_tags(tags: &[String]) -> Option<String> {
+    if tags.is_empty() {
+        return None;
+    }
+    serde_json::to_string(tags).ok()
+}
+
+/// Decodes the `tags` column, treating malformed values as no tags
+pub fn decode_tags(value: Option<String>) -> Vec<String> {
+    value
+        .and_then(|value| serde_json::from_str(&value).ok())
+        .unwrap_or_default()
+}
+
+/// Marks the rows with the given ids in `table` as synced
+pub fn update_synced_in(conn: &Connection, table: &str, ids: &[i64]) -> Result<(), CliError> {
+    if ids.is_empty() {
+        return Ok(());
+    }
+
+    let placeholders = std::iter::repeat_n("?", ids.len())
+        .collect::<Vec<_>>()
+        .join(", ");
+    let sql = format!("UPDATE {table} SET synced = 1 WHERE id IN ({placeholders})");
+
+    conn.execute(&sql, params_from_iter(ids))?;
+    Ok(())
+}
+
+/// Sets rows in `table` aside with the reason the server rejected them, so they are not synced again
+pub fn update_rejected_in(
+    conn: &Connection,
+    table: &str,
+    rejected: &[(i64, String)],
+) -> Result<(), CliError> {
+    let sql = format!("UPDATE {table} SET rejected_reason = ?1 WHERE id = ?2");
+    let mut stmt = conn.prepare(&sql)?;
+
+    for (id, reason) in rejected {
+        stmt.execute(params![reason, id])?;
+    }
+    Ok(())
+}
+
 pub fn init_tracing() {
     let default_level = if cfg!(debug_assertions) {
         "debug"
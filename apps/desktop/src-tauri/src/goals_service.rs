@@ -3,7 +3,7 @@ use std::{sync::Arc, time::Duration as StdDuration};
 use chrono::{Datelike, Duration, Local, TimeZone, Utc, Weekday};
 use common::{
     models::inputs::SummaryQueryInput,
-    time::{TimeRange, TimeRangePreset},
+    time::{TimeRange, TimeRangePreset, WeekStart},
 };
 use db::{
     DBContext,
@@ -12,13 +12,14 @@ use db::{
         goals::{Goal, GoalInput, GoalUpdateInput, TimeSpan},
     },
 };
-use tauri::AppHandle;
+use tauri::{AppHandle, Manager};
 use tokio::{sync::broadcast, task::JoinHandle};
 use tracing::{debug, error, info};
 
 use crate::{
     network::summaries::fetch_total_time,
     ui::window::{NotificationPayload, WindowExt},
+    utils::config::ConfigStore,
 };
 
 #[derive(Clone)]
@@ -57,13 +58,14 @@ impl GoalService {
 
     pub async fn check_goals(&self, app: &AppHandle) -> anyhow::Result<()> {
         let goals = Goal::fetch_all(&self.db).await?;
+        let week_start = app.state::<ConfigStore>().get().await.week_start;
         for goal in goals {
             if is_today_excluded(&goal.excluded_days) {
                 debug!("Goal {} is excluded today", goal.id);
                 continue;
             }
 
-            let total_tracked = self.evaluate_goal(&goal).await?;
+            let total_tracked = self.evaluate_goal(&goal, week_start).await?;
             debug!(
                 "Goal {} | Target: {}s | Tracked: {}s",
                 goal.id, goal.target_seconds, total_tracked,
@@ -73,7 +75,7 @@ impl GoalService {
                 continue;
             }
 
-            let period_key = current_period_key(&goal.time_span);
+            let period_key = current_period_key(&goal.time_span, week_start);
             let already_shown = GoalNotification::has_shown_notification(
                 &self.db,
                 goal.id,
@@ -107,8 +109,8 @@ impl GoalService {
         Ok(())
     }
 
-    async fn evaluate_goal(&self, goal: &Goal) -> anyhow::Result<i64> {
-        let range = resolve_time_range(&goal.time_span)
+    async fn evaluate_goal(&self, goal: &Goal, week_start: WeekStart) -> anyhow::Result<i64> {
+        let range = resolve_time_range(&goal.time_span, week_start)
             .ok_or_else(|| anyhow::anyhow!("Invalid time_span: {:?}", goal.time_span))?;
 
         let query = SummaryQueryInput {
@@ -128,6 +130,11 @@ impl GoalService {
             branches: None,
             languages: None,
             projects: None,
+            sources: None,
+            entity_types: None,
+            tags: None,
+            exclude_afk: None,
+            timezone: None,
         };
 
         let total = fetch_total_time(query).await.map_err(anyhow::Error::msg)?;
@@ -186,10 +193,14 @@ pub async fn remove_goal(db: tauri::State<'_, Arc<DBContext>>, goal_id: i64) ->
     Ok(())
 }
 
-fn resolve_time_range(time_span: &TimeSpan) -> Option<TimeRange> {
+fn resolve_time_range(time_span: &TimeSpan, week_start: WeekStart) -> Option<TimeRange> {
     match time_span {
         TimeSpan::Day => Some(TimeRange::from(TimeRangePreset::Today)),
-        TimeSpan::Week => Some(TimeRange::from(TimeRangePreset::ThisWeek)),
+        TimeSpan::Week => Some(TimeRange::resolve(
+            TimeRangePreset::ThisWeek,
+            None,
+            week_start,
+        )),
         TimeSpan::Month => Some(TimeRange::from(TimeRangePreset::ThisMonth)),
         TimeSpan::Year => {
             let now = Utc::now();
@@ -207,11 +218,11 @@ fn resolve_time_range(time_span: &TimeSpan) -> Option<TimeRange> {
     }
 }
 
-fn current_period_key(span: &TimeSpan) -> String {
+fn current_period_key(span: &TimeSpan, week_start: WeekStart) -> String {
     let now = Utc::now();
     match span {
         TimeSpan::Day => now.format("%Y-%m-%d").to_string(),
-        TimeSpan::Week => format!("{}-W{}", now.iso_week().year(), now.iso_week().week()),
+        TimeSpan::Week => week_start.label(Local::now().date_naive()),
         TimeSpan::Month => now.format("%Y-%m").to_string(),
         TimeSpan::Year => now.format("%Y").to_string(),
     }
@@ -229,6 +229,7 @@ fn make_specta_builder<R: Runtime>() -> tauri_specta::Builder<R> {
             crate::utils::config::get_config::<tauri::Wry>,
             crate::utils::config::set_theme::<tauri::Wry>,
             crate::utils::config::set_afk_timeout::<tauri::Wry>,
+            crate::utils::config::set_week_start::<tauri::Wry>,
             crate::utils::config::set_tracked_apps::<tauri::Wry>,
             crate::utils::config::set_global_shortcut::<tauri::Wry>,
             crate::utils::permissions::get_permissions,
@@ -237,6 +238,7 @@ fn make_specta_builder<R: Runtime>() -> tauri_specta::Builder<R> {
             crate::utils::permissions::open_permission_settings,
             crate::network::summaries::fetch_bucketed_summary,
             crate::network::summaries::fetch_total_time,
+            crate::network::summaries::fetch_summary_comparison,
             crate::goals_service::add_goal,
             crate::goals_service::get_goals,
             crate::goals_service::update_goal,
@@ -245,9 +247,17 @@ fn make_specta_builder<R: Runtime>() -> tauri_specta::Builder<R> {
             crate::network::data::fetch_categories,
             crate::network::data::fetch_projects,
             crate::network::data::fetch_project,
+            crate::network::data::fetch_project_stats,
+            crate::network::data::fetch_languages,
+            crate::network::data::fetch_sources,
+            crate::network::data::fetch_project_branches,
+            crate::network::data::fetch_project_entities,
+            crate::network::data::fetch_search_results,
             crate::network::insights::fetch_insights,
             crate::network::events::fetch_events,
+            crate::network::events::fetch_event_page,
             crate::network::afk_events::fetch_afk_events,
+            crate::network::afk_events::fetch_afk_event_page,
             crate::ui::window::dismiss_notification_window::<tauri::Wry>,
             crate::ui::window::show_window::<tauri::Wry>,
             crate::monitored_app::get_open_apps,
@@ -1,9 +1,28 @@
-use common::models::{inputs::BucketSummaryInput, outputs::FullEvent};
+use common::models::{
+    inputs::{BucketSummaryInput, EventPageInput},
+    outputs::{EventPage, FullEvent},
+};
 
-use crate::network::req_json;
+use crate::network::{PageQuery, req_json};
 
 #[tauri::command]
 #[specta::specta]
 pub async fn fetch_afk_events(query: BucketSummaryInput) -> Result<Vec<FullEvent>, String> {
     req_json("afk", Some(&query)).await
 }
+
+#[tauri::command]
+#[specta::specta]
+pub async fn fetch_afk_event_page(
+    query: BucketSummaryInput,
+    page: EventPageInput,
+) -> Result<EventPage, String> {
+    req_json(
+        "afk",
+        Some(&PageQuery {
+            query: &query,
+            page: &page,
+        }),
+    )
+    .await
+}
@@ -1,4 +1,8 @@
-use common::models::{Project, inputs::ProjectListQuery, outputs::PaginatedProjects};
+use common::models::{
+    Project,
+    inputs::{DimensionListQuery, ProjectListQuery, ProjectStatsInput, SearchInput},
+    outputs::{DimensionPage, PaginatedProjects, ProjectStats, SearchResult},
+};
 use db::models::{App, Category};
 use uuid::Uuid;
 
@@ -28,3 +32,51 @@ pub async fn fetch_project(id: Uuid) -> Result<Option<Project>, String> {
     let path = format!("projects/{}", id);
     req_json::<Option<Project>, ()>(&path, None::<&()>).await
 }
+
+#[tauri::command]
+#[specta::specta]
+pub async fn fetch_project_stats(
+    id: Uuid,
+    query: ProjectStatsInput,
+) -> Result<ProjectStats, String> {
+    let path = format!("projects/{}/stats", id);
+    req_json(&path, Some(&query)).await
+}
+
+#[tauri::command]
+#[specta::specta]
+pub async fn fetch_languages(query: DimensionListQuery) -> Result<DimensionPage, String> {
+    req_json("languages", Some(&query)).await
+}
+
+#[tauri::command]
+#[specta::specta]
+pub async fn fetch_sources(query: DimensionListQuery) -> Result<DimensionPage, String> {
+    req_json("sources", Some(&query)).await
+}
+
+#[tauri::command]
+#[specta::specta]
+pub async fn fetch_project_branches(
+    id: Uuid,
+    query: DimensionListQuery,
+) -> Result<DimensionPage, String> {
+    let path = format!("projects/{}/branches", id);
+    req_json(&path, Some(&query)).await
+}
+
+#[tauri::command]
+#[specta::specta]
+pub async fn fetch_project_entities(
+    id: Uuid,
+    query: DimensionListQuery,
+) -> Result<DimensionPage, String> {
+    let path = format!("projects/{}/entities", id);
+    req_json(&path, Some(&query)).await
+}
+
+#[tauri::command]
+#[specta::specta]
+pub async fn fetch_search_results(query: SearchInput) -> Result<Vec<SearchResult>, String> {
+    req_json("search", Some(&query)).await
+}
@@ -1,9 +1,28 @@
-use common::models::{inputs::BucketSummaryInput, outputs::EventGroupResult};
+use common::models::{
+    inputs::{BucketSummaryInput, EventPageInput},
+    outputs::{EventGroupResult, EventPage},
+};
 
-use crate::network::req_json;
+use crate::network::{PageQuery, req_json};
 
 #[tauri::command]
 #[specta::specta]
 pub async fn fetch_events(query: BucketSummaryInput) -> Result<EventGroupResult, String> {
     req_json("events", Some(&query)).await
 }
+
+#[tauri::command]
+#[specta::specta]
+pub async fn fetch_event_page(
+    query: BucketSummaryInput,
+    page: EventPageInput,
+) -> Result<EventPage, String> {
+    req_json(
+        "events",
+        Some(&PageQuery {
+            query: &query,
+            page: &page,
+        }),
+    )
+    .await
+}
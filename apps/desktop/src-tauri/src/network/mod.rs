@@ -4,9 +4,21 @@ pub mod events;
 pub mod insights;
 pub mod summaries;
 
-use common::client::Transport;
+use common::{
+    client::Transport,
+    models::inputs::{BucketSummaryInput, EventPageInput},
+};
 use serde::{Deserialize, Serialize};
 
+/// Query string of a paginated event request: the range and filters plus the page
+#[derive(Serialize)]
+pub struct PageQuery<'a> {
+    #[serde(flatten)]
+    pub query: &'a BucketSummaryInput,
+    #[serde(flatten)]
+    pub page: &'a EventPageInput,
+}
+
 pub async fn req_json<TRes, TQuery>(path: &str, query: Option<&TQuery>) -> Result<TRes, String>
 where
     TRes: for<'de> Deserialize<'de>,
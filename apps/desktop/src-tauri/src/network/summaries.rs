@@ -1,4 +1,7 @@
-use common::models::inputs::{BucketSummaryInput, SummaryQueryInput};
+use common::models::{
+    inputs::{BucketSummaryInput, SummaryCompareInput, SummaryQueryInput},
+    outputs::SummaryComparison,
+};
 use db::models::BucketTimeSummary;
 
 use crate::network::req_json;
@@ -16,3 +19,11 @@ pub async fn fetch_bucketed_summary(
 ) -> Result<Vec<BucketTimeSummary>, String> {
     req_json("summary/buckets", Some(&query)).await
 }
+
+#[tauri::command]
+#[specta::specta]
+pub async fn fetch_summary_comparison(
+    query: SummaryCompareInput,
+) -> Result<SummaryComparison, String> {
+    req_json("summary/compare", Some(&query)).await
+}
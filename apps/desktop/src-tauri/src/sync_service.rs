@@ -1,13 +1,16 @@
 use std::sync::Arc;
 
 use async_trait::async_trait;
-use common::models::inputs::{AFKEventInput, EventInput};
+use common::models::{
+    inputs::{AFKEventInput, EventInput},
+    outputs::IngestReport,
+};
 use db::DBContext;
 use db::desktop::{afk_events::AFKEvent, events::Event};
 use tokio::sync::{Mutex, mpsc, oneshot, watch};
 use tokio::task::JoinHandle;
 use tokio::time::{Duration, Instant, interval};
-use tracing::{error, info};
+use tracing::{error, info, warn};
 
 use crate::network::post_json;
 use crate::tracking_service::TrackingService;
@@ -215,13 +218,22 @@ async fn sync_with_server(db_context: &Arc<DBContext>) -> Result<(), anyhow::Err
                 language_name: ev.language_name.clone(),
                 source_name: ev.source_name.clone(),
                 end_timestamp: ev.end_timestamp,
+                tags: Vec::new(),
             })
             .collect();
 
-        match post_json::<Vec<EventInput>, ()>("/events", &payload).await {
-            Ok(_) => {
-                Event::mark_as_synced(db_context, &events).await?;
-                info!("Synced {} events", events.len());
+        match post_json::<Vec<EventInput>, IngestReport>("/events", &payload).await {
+            Ok(report) => {
+                let ids: Vec<Option<i64>> = events.iter().map(|ev| ev.id).collect();
+                let (synced, rejected) = split_report(&ids, &report);
+
+                Event::mark_as_synced(db_context, &select(&events, &synced)).await?;
+                Event::mark_as_rejected(db_context, &rejected).await?;
+                info!(
+                    "Synced {} events ({} rejected)",
+                    synced.len(),
+                    rejected.len()
+                );
                 Event::delete_synced(db_context).await?;
             }
             Err(e) => {
@@ -241,10 +253,18 @@ async fn sync_with_server(db_context: &Arc<DBContext>) -> Result<(), anyhow::Err
             })
             .collect();
 
-        match post_json::<Vec<AFKEventInput>, ()>("/afk", &payload).await {
-            Ok(_) => {
-                AFKEvent::mark_as_synced(db_context, &afk_events).await?;
-                info!("Synced {} afk events", afk_events.len());
+        match post_json::<Vec<AFKEventInput>, IngestReport>("/afk", &payload).await {
+            Ok(report) => {
+                let ids: Vec<Option<i64>> = afk_events.iter().map(|afk| afk.id).collect();
+                let (synced, rejected) = split_report(&ids, &report);
+
+                AFKEvent::mark_as_synced(db_context, &select(&afk_events, &synced)).await?;
+                AFKEvent::mark_as_rejected(db_context, &rejected).await?;
+                info!(
+                    "Synced {} afk events ({} rejected)",
+                    synced.len(),
+                    rejected.len()
+                );
                 AFKEvent::delete_synced(db_context).await?;
             }
             Err(e) => {
@@ -255,3 +275,38 @@ async fn sync_with_server(db_context: &Arc<DBContext>) -> Result<(), anyhow::Err
 
     Ok(())
 }
+
+/// Splits a posted batch by the server's verdict.
+///
+/// Returns the payload indices that were accepted or already stored, and the local
+/// ids of the rejected rows paired with the server's reason.
+fn split_report(ids: &[Option<i64>], report: &IngestReport) -> (Vec<usize>, Vec<(i64, String)>) {
+    let synced = report
+        .accepted
+        .iter()
+        .chain(&report.duplicate)
+        .map(|item| item.index)
+        .collect();
+
+    let rejected = report
+        .rejected
+        .iter()
+        .filter_map(|item| {
+            let reason = item.reason.clone().unwrap_or_default();
+            warn!("Server rejected item {}: {}", item.index, reason);
+            ids.get(item.index)
+                .copied()
+                .flatten()
+                .map(|id| (id, reason))
+        })
+        .collect();
+
+    (synced, rejected)
+}
+
+fn select<T: Clone>(rows: &[T], indices: &[usize]) -> Vec<T> {
+    indices
+        .iter()
+        .filter_map(|&index| rows.get(index).cloned())
+        .collect()
+}
@@ -143,6 +143,11 @@ impl TrayExt for App {
                         entities: None,
                         branches: None,
                         languages: None,
+                        sources: None,
+                        entity_types: None,
+                        tags: None,
+                        exclude_afk: None,
+                        timezone: None,
                     };
 
                     let time_text = match fetch_total_time(query).await {
@@ -5,6 +5,7 @@ use std::{
     sync::Arc,
 };
 
+use common::time::WeekStart;
 use serde::{Deserialize, Serialize};
 use specta::Type;
 use tauri::{AppHandle, Manager, Runtime};
@@ -26,6 +27,8 @@ pub struct AppConfig {
     pub sync_interval: u64,
     pub global_shortcut: String,
     pub tracked_apps: Vec<TrackedApp>,
+    #[serde(default)]
+    pub week_start: WeekStart,
 }
 
 impl Default for AppConfig {
@@ -37,6 +40,7 @@ impl Default for AppConfig {
             sync_interval: 180,
             global_shortcut: String::from("CommandOrControl+S"),
             tracked_apps: vec![],
+            week_start: WeekStart::Monday,
         }
     }
 }
@@ -213,3 +217,18 @@ pub async fn set_tracked_apps<R: Runtime>(
         .await
         .map_err(|e| e.to_string())
 }
+
+#[tauri::command]
+#[specta::specta]
+pub async fn set_week_start<R: Runtime>(
+    week_start: WeekStart,
+    app: AppHandle<R>,
+) -> Result<(), String> {
+    let config_store = app.state::<ConfigStore>();
+    config_store
+        .update(&app, |config| {
+            config.week_start = week_start;
+        })
+        .await
+        .map_err(|e| e.to_string())
+}
@@ -25,7 +25,13 @@ import HotkeyField from "@/components/settings/HotkeyField";
 import { AFK, AFK_KEYS, AFK_SECONDS, AFK_LABELS } from "@/utils/constants";
 import { useAutostart } from "@/hooks/useAutostart";
 import { useGlobalShortcut } from "@/hooks/useGlobalShortcut";
-import { commands, OpenApp, TrackedApp, Theme } from "@/types/tauri.gen";
+import {
+  commands,
+  OpenApp,
+  TrackedApp,
+  Theme,
+  WeekStart,
+} from "@/types/tauri.gen";
 import { useOpenApps } from "@/hooks/useOpenApps";
 import { HelpCircle, Monitor, Moon, Sun } from "lucide-react";
 import { useTheme } from "@/utils/theme";
@@ -39,6 +45,13 @@ const TrackedAppSchema = z.object({
 const THEME_OPTIONS = ["light", "dark", "system"] as Theme[];
 type ThemeValue = (typeof THEME_OPTIONS)[number];
 
+const WEEK_START_OPTIONS = ["monday", "sunday", "saturday"] as WeekStart[];
+const WEEK_START_LABELS: Record<WeekStart, string> = {
+  monday: "Monday",
+  sunday: "Sunday",
+  saturday: "Saturday",
+};
+
 const settingsSchema = z.object({
   launchOnStartup: z.boolean(),
   globalShortcut: z
@@ -51,6 +64,7 @@ const settingsSchema = z.object({
   afkSensitivity: z.enum(AFK_KEYS),
   trackedApps: z.array(TrackedAppSchema),
   theme: z.enum(THEME_OPTIONS),
+  weekStart: z.enum(WEEK_START_OPTIONS),
 });
 
 type GeneralSettingsValues = z.infer<typeof settingsSchema>;
@@ -78,6 +92,7 @@ const General = () => {
       afkSensitivity: "1m",
       trackedApps: [],
       theme: "system",
+      weekStart: "monday",
     },
     mode: "onChange",
     reValidateMode: "onChange",
@@ -89,12 +104,14 @@ const General = () => {
   const afkTimer = useRef<number | null>(null);
   const trackedTimer = useRef<number | null>(null);
   const themeTimer = useRef<number | null>(null);
+  const weekStartTimer = useRef<number | null>(null);
 
   const lastAfk = useRef<string>("");
   const lastSaved = useRef<boolean>(false);
   const lastShortcut = useRef<string>("");
   const lastTrackedJSON = useRef<string>("[]");
   const lastTheme = useRef<ThemeValue>("system");
+  const lastWeekStart = useRef<WeekStart>("monday");
   const { startTour } = useGuidedTour();
 
   useEffect(() => {
@@ -107,6 +124,7 @@ const General = () => {
         const tracked = cfg.trackedApps ?? [];
         const afk = cfg.afkTimeout;
         const theme = cfg.theme;
+        const weekStart = cfg.weekStart ?? "monday";
         const seconds = AFK_LABELS[afk];
 
         form.reset(
@@ -116,6 +134,7 @@ const General = () => {
             afkSensitivity: seconds,
             trackedApps: tracked,
             theme,
+            weekStart,
           },
           { keepDirty: false, keepTouched: true },
         );
@@ -125,6 +144,7 @@ const General = () => {
         lastAfk.current = seconds;
         lastTrackedJSON.current = JSON.stringify([...tracked].sort());
         lastTheme.current = theme;
+        lastWeekStart.current = weekStart;
         hydratedRef.current = true;
       } catch (e) {
         console.error("Hydration failed: ", e);
@@ -146,6 +166,7 @@ const General = () => {
   });
   const trackedApps = useWatch({ control: form.control, name: "trackedApps" });
   const themeValue = useWatch({ control: form.control, name: "theme" });
+  const weekStart = useWatch({ control: form.control, name: "weekStart" });
 
   // Launch on startup
   useEffect(() => {
@@ -245,6 +266,26 @@ const General = () => {
     };
   }, [themeValue, form, setTheme]);
 
+  // Week start
+  useEffect(() => {
+    if (!hydratedRef.current) return;
+    if (weekStart === lastWeekStart.current) return;
+    if (weekStartTimer.current) window.clearTimeout(weekStartTimer.current);
+    weekStartTimer.current = window.setTimeout(async () => {
+      const ok = await form.trigger("weekStart", { shouldFocus: false });
+      if (!ok) return;
+      try {
+        await commands.setWeekStart(weekStart);
+        lastWeekStart.current = weekStart;
+      } catch (e) {
+        console.error("Failed to set week start: ", e);
+      }
+    }, 250) as number;
+    return () => {
+      if (weekStartTimer.current) window.clearTimeout(weekStartTimer.current);
+    };
+  }, [weekStart, form]);
+
   return (
     <div className="mx-auto w-full max-w-2xl p-2">
       <Form {...form}>
@@ -408,6 +449,36 @@ const General = () => {
 
           <Separator />
 
+          <FormField
+            control={form.control}
+            name="weekStart"
+            render={({ field }) => (
+              <FormItem>
+                <FormLabel>First day of the week</FormLabel>
+                <FormDescription>
+                  Used for weekly goals and week ranges.
+                </FormDescription>
+                <FormControl>
+                  <Select value={field.value} onValueChange={field.onChange}>
+                    <SelectTrigger className="w-55">
+                      <SelectValue placeholder="Select day" />
+                    </SelectTrigger>
+                    <SelectContent>
+                      {WEEK_START_OPTIONS.map((day) => (
+                        <SelectItem key={day} value={day}>
+                          {WEEK_START_LABELS[day]}
+                        </SelectItem>
+                      ))}
+                    </SelectContent>
+                  </Select>
+                </FormControl>
+                <FormMessage />
+              </FormItem>
+            )}
+          />
+
+          <Separator />
+
           <FormField
             control={form.control}
             name="trackedApps"
@@ -78,7 +78,7 @@ export const TimelineView: React.FC<TimelineViewProps> = ({
 
   const combinedGroups: EventGroup[] = useMemo(() => {
     const afk = (afkEvents ?? []).length
-      ? [{ group: AFK_GROUP_KEY, events: afkEvents ?? [] }]
+      ? [{ group: AFK_GROUP_KEY, events: afkEvents ?? [], subgroups: [] }]
       : [];
     return [...afk, ...groupedEvents];
   }, [groupedEvents, afkEvents]);
@@ -15,6 +15,9 @@ export const commands = {
   async setAfkTimeout(timeout: number): Promise<null> {
     return await TAURI_INVOKE("set_afk_timeout", { timeout });
   },
+  async setWeekStart(weekStart: WeekStart): Promise<null> {
+    return await TAURI_INVOKE("set_week_start", { weekStart });
+  },
   async setTrackedApps(apps: TrackedApp[]): Promise<null> {
     return await TAURI_INVOKE("set_tracked_apps", { apps });
   },
@@ -41,6 +44,11 @@ export const commands = {
   async fetchTotalTime(query: SummaryQueryInput): Promise<number> {
     return await TAURI_INVOKE("fetch_total_time", { query });
   },
+  async fetchSummaryComparison(
+    query: SummaryCompareInput,
+  ): Promise<SummaryComparison> {
+    return await TAURI_INVOKE("fetch_summary_comparison", { query });
+  },
   async addGoal(input: GoalInput): Promise<null> {
     return await TAURI_INVOKE("add_goal", { input });
   },
@@ -65,15 +73,54 @@ export const commands = {
   async fetchProject(id: string): Promise<Project | null> {
     return await TAURI_INVOKE("fetch_project", { id });
   },
+  async fetchProjectStats(
+    id: string,
+    query: ProjectStatsInput,
+  ): Promise<ProjectStats> {
+    return await TAURI_INVOKE("fetch_project_stats", { id, query });
+  },
+  async fetchLanguages(query: DimensionListQuery): Promise<DimensionPage> {
+    return await TAURI_INVOKE("fetch_languages", { query });
+  },
+  async fetchSources(query: DimensionListQuery): Promise<DimensionPage> {
+    return await TAURI_INVOKE("fetch_sources", { query });
+  },
+  async fetchProjectBranches(
+    id: string,
+    query: DimensionListQuery,
+  ): Promise<DimensionPage> {
+    return await TAURI_INVOKE("fetch_project_branches", { id, query });
+  },
+  async fetchProjectEntities(
+    id: string,
+    query: DimensionListQuery,
+  ): Promise<DimensionPage> {
+    return await TAURI_INVOKE("fetch_project_entities", { id, query });
+  },
+  async fetchSearchResults(query: SearchInput): Promise<SearchResult[]> {
+    return await TAURI_INVOKE("fetch_search_results", { query });
+  },
   async fetchInsights(query: InsightQueryPayload): Promise<InsightResult> {
     return await TAURI_INVOKE("fetch_insights", { query });
   },
   async fetchEvents(query: BucketSummaryInput): Promise<EventGroupResult> {
     return await TAURI_INVOKE("fetch_events", { query });
   },
+  async fetchEventPage(
+    query: BucketSummaryInput,
+    page: EventPageInput,
+  ): Promise<EventPage> {
+    return await TAURI_INVOKE("fetch_event_page", { query, page });
+  },
   async fetchAfkEvents(query: BucketSummaryInput): Promise<FullEvent[]> {
     return await TAURI_INVOKE("fetch_afk_events", { query });
   },
+  async fetchAfkEventPage(
+    query: BucketSummaryInput,
+    page: EventPageInput,
+  ): Promise<EventPage> {
+    return await TAURI_INVOKE("fetch_afk_event_page", { query, page });
+  },
   async dismissNotificationWindow(): Promise<null> {
     return await TAURI_INVOKE("dismiss_notification_window");
   },
@@ -108,9 +155,11 @@ export type AppConfig = {
   syncInterval: number;
   globalShortcut: string;
   trackedApps: TrackedApp[];
+  weekStart: WeekStart;
 };
 /**
- * Query input for bucketed summaries (based on a preset time range)
+ * Query input for bucketed summaries (based on a preset time range).
+ * Dimension lists behave as in [`SummaryQueryInput`].
  */
 export type BucketSummaryInput = {
   preset: TimeRangePreset;
@@ -120,7 +169,34 @@ export type BucketSummaryInput = {
   categories?: string[] | null;
   branches?: string[] | null;
   languages?: string[] | null;
+  sources?: string[] | null;
+  /**
+   * Entity types (e.g. "File", "Url")
+   */
+  entityTypes?: string[] | null;
+  /**
+   * Tags of the event or its project
+   */
+  tags?: string[] | null;
   groupBy?: Group | null;
+  /**
+   * Further dimensions splitting each `groupBy` group, in order (e.g. `language`
+   * for time per language inside each project)
+   */
+  subgroupBy?: Group[] | null;
+  /**
+   * Subtract time that overlaps AFK periods
+   */
+  excludeAfk?: boolean | null;
+  /**
+   * IANA timezone (e.g. "Europe/Berlin") that calendar boundaries and bucket labels
+   * are computed in. Defaults to the server's local timezone.
+   */
+  timezone?: string | null;  /**
+   * First day of the week for week presets, buckets and "yyyy-W##" ranges.
+   * Defaults to Monday.
+   */
+  weekStart?: WeekStart | null;
 };
 /**
  * A single time bucket with grouped values.
@@ -134,12 +210,92 @@ export type BucketTimeSummary = {
    * A map of group_key: total_seconds
    */
   groupedValues: Partial<{ [key in string]: number }>;
+  /**
+   * Values of the `subgroup_by` dimensions, in order, that further split this group.
+   * Empty without `subgroup_by`.
+   */
+  subgroups: string[];
   /**
    * Optional per-group metadata (e.g. entity type when grouping by Entity)
    */
   groupMeta: string | null;
 };
 export type Category = { id: string; name: string };
+/**
+ * Query input for listing languages, sources, branches or entities.
+ *
+ * With a `preset`, each row carries its total time and last use within that range.
+ */
+export type DimensionListQuery = {
+  preset?: TimeRangePreset | null;
+  /**
+   * Defaults to `name`. Sorting by usage requires a `preset`.
+   */
+  sort?: DimensionSort | null;
+  limit?: number | null;
+  /**
+   * Number of rows to skip
+   */
+  offset?: number | null;
+  /**
+   * Subtract time that overlaps AFK periods
+   */
+  excludeAfk?: boolean | null;
+  /**
+   * IANA timezone (e.g. "Europe/Berlin") that calendar boundaries are computed in.
+   * Defaults to the server's local timezone.
+   */
+  timezone?: string | null;
+  /**
+   * First day of the week for week presets. Defaults to Monday.
+   */
+  weekStart?: WeekStart | null;
+};
+/**
+ * One page of a dimension listing
+ */
+export type DimensionPage = {
+  data: DimensionUsage[];
+  /**
+   * Number of rows across all pages
+   */
+  total: number;
+};
+/**
+ * Order of dimension listings
+ */
+export type DimensionSort =
+  /**
+   * Alphabetical, ignoring case
+   */
+  | "name"
+  /**
+   * Most time in the range first
+   */
+  | "totalTime"
+  /**
+   * Most recently used in the range first
+   */
+  | "lastSeen";
+/**
+ * A language, source, branch or entity, with its usage when a range was requested
+ */
+export type DimensionUsage = {
+  id: string;
+  name: string;
+  /**
+   * The type of an entity (e.g. "File", "Url"), `None` for other dimensions
+   */
+  entityType: string | null;
+  /**
+   * Seconds spent in the range
+   */
+  totalSeconds: number | null;
+  /**
+   * End of the last event in the range, clipped to the range
+   */
+  lastSeen: string | null;
+};
 /**
  * A collection of events that share a common grouping key.
  */
@@ -149,9 +305,14 @@ export type EventGroup = {
    */
   group: string;
   /**
-   * The list of `FullEvent` rows belonging to this group
+   * The list of `FullEvent` rows belonging to this group. Empty when the group is
+   * split into `subgroups`.
    */
   events: FullEvent[];
+  /**
+   * The group split by the next `subgroup_by` dimension
+   */
+  subgroups: EventGroup[];
 };
 /**
  * The result of fetching events for a time/window query
@@ -160,7 +321,7 @@ export type EventGroup = {
  * matching event row.
  * - `Grouped(Vec<EventGroup>)` - returned when a `group_by` dimension is set.
  * Each `EventGroup` holds a `group` key (e.g., a category name) and **all**
- * events that belong to that group.
+ * events that belong to that group, nested into `subgroups` per `subgroup_by` dimension.
  */
 export type EventGroupResult =
   /**
@@ -171,6 +332,26 @@ export type EventGroupResult =
    * Events grouped by a group key (e.g., category, app, project, source, etc.)
    */
   | { Grouped: EventGroup[] };
+/**
+ * A page of events in chronological order
+ */
+export type EventPage = {
+  events: FullEvent[];
+  /**
+   * Cursor to pass as `after` for the next page, `None` on the last page
+   */
+  nextCursor: string | null;
+};
+/**
+ * Keyset pagination over events ordered by start time and id
+ */
+export type EventPageInput = {
+  /**
+   * The `nextCursor` of the previous page
+   */
+  after?: string | null;
+  limit?: number | null;
+};
 /**
  * A fully materialized event row
  */
@@ -225,6 +406,33 @@ export type GoalUpdateInput = {
   apps?: string[] | null;
   categories?: string[] | null;
   excludedDays?: string[] | null;
+  /**
+   * Whether heatmap cells hold total or average seconds (`hourlyHeatmap` only)
+   */
+  metric?: HeatmapMetric | null;
+};
+/**
+ * Totals of one group in both periods of a comparison
+ */
+export type GroupComparison = {
+  group: string;
+  /**
+   * Seconds in the current period
+   */
+  current: number;
+  /**
+   * Seconds in the previous period
+   */
+  previous: number;
+  /**
+   * `current - previous`, in seconds
+   */
+  delta: number;
+  /**
+   * Change relative to the previous period, in percent. `None` when the group has no
+   * time in the previous period.
+   */
+  deltaPercent: number | null;
 };
 export type Group =
   | "app"
@@ -233,7 +441,40 @@ export type Group =
   | "branch"
   | "category"
   | "entity"
-  | "source";
+  | "source"
+  | "tag";
+/**
+ * How the cells of an hourly heatmap are aggregated
+ */
+export type HeatmapMetric =
+  /**
+   * Total active seconds in each weekday and hour
+   */
+  | "total"
+  /**
+   * Active seconds averaged over the occurrences of each weekday and hour in the range
+   */
+  | "average";
+/**
+ * Active seconds per weekday and local hour for one group
+ */
+export type HeatmapSeries = {
+  group: string;
+  /**
+   * `values[day][hour]`, with days ordered as in [`HourlyHeatmap::weekdays`]
+   */
+  values: number[][];
+};
+export type HourlyHeatmap = {
+  /**
+   * Row labels (e.g. "Mon"), starting on the requested first day of the week
+   */
+  weekdays: string[];
+  /**
+   * One heatmap per group, ordered by total time
+   */
+  series: HeatmapSeries[];
+};
 export type InsightBucket = "day" | "week" | "month" | "year";
 /**
  * Query payload for insights
@@ -244,17 +485,63 @@ export type InsightQueryPayload = {
   groupBy?: Group | null;
   limit?: number | null;
   bucket?: InsightBucket | null;
+  /**
+   * Longest break in seconds that still continues a session (`sessions` only)
+   */
+  sessionGap?: number | null;
+  /**
+   * Minutes of activity a day needs to count towards a streak (`streak` only)
+   */
+  minDailyMinutes?: number | null;
+  /**
+   * Weekdays (e.g. "saturday") that neither count towards nor break a streak (`streak` only)
+   */
+  excludedDays?: string[] | null;
+  apps?: string[] | null;
+  projects?: string[] | null;
+  categories?: string[] | null;
+  entities?: string[] | null;
+  branches?: string[] | null;
+  languages?: string[] | null;
+  sources?: string[] | null;
+  /**
+   * Entity types (e.g. "File", "Url")
+   */
+  entityTypes?: string[] | null;
+  /**
+   * Tags of the event or its project
+   */
+  tags?: string[] | null;
+  /**
+   * Subtract time that overlaps AFK periods
+   */
+  excludeAfk?: boolean | null;
+  /**
+   * IANA timezone (e.g. "Europe/Berlin") that calendar boundaries and bucket labels
+   * are computed in. Defaults to the server's local timezone.
+   */
+  timezone?: string | null;  /**
+   * First day of the week for week presets, buckets and "yyyy-W##" ranges.
+   * Defaults to Monday.
+   */
+  weekStart?: WeekStart | null;
 };
 export type InsightResult =
   | { activeYears: number[] }
   | { topN: [string, number][] }
   | { mostActiveDay: { date: string; total_duration: number } }
-  | { aggregatedAverage: Partial<{ [key in string]: [string, number][] }> };
+  | { aggregatedAverage: Partial<{ [key in string]: [string, number][] }> }
+  | { sessions: SessionInsight }
+  | { streak: StreakInsight }
+  | { hourlyHeatmap: HourlyHeatmap };
 export type InsightType =
   | "activeYears"
   | "topN"
   | "mostActiveDay"
-  | "aggregatedAverage";
+  | "aggregatedAverage"
+  | "sessions"
+  | "streak"
+  | "hourlyHeatmap";
 export type NotificationPayload = {
   title: string;
   durationMs: number;
@@ -287,6 +574,106 @@ export type ProjectListQuery = {
   limit?: number | null;
   query?: string | null;
 };
+/**
+ * Time spent on one project over a range, as returned by `/projects/{id}/stats`.
+ * Breakdowns are `(name, seconds)` pairs ordered by time.
+ */
+export type ProjectStats = {
+  project: Project;
+  totalSeconds: number;
+  /**
+   * Start of the first event in the range, clipped to the range
+   */
+  firstActivity: string | null;
+  /**
+   * End of the last event in the range, clipped to the range
+   */
+  lastActivity: string | null;
+  languages: [string, number][];
+  branches: [string, number][];
+  categories: [string, number][];
+  sources: [string, number][];
+  /**
+   * The entities (e.g. files) with the most time
+   */
+  topEntities: [string, number][];
+  /**
+   * Seconds per "yyyy-mm-dd" day of the range, including days without activity
+   */
+  daily: [string, number][];
+};
+/**
+ * Query input for the statistics of a single project
+ */
+export type ProjectStatsInput = {
+  preset: TimeRangePreset;
+  /**
+   * Number of entities listed in `topEntities`. Defaults to 10.
+   */
+  entityLimit?: number | null;
+  /**
+   * Subtract time that overlaps AFK periods
+   */
+  excludeAfk?: boolean | null;
+  /**
+   * IANA timezone (e.g. "Europe/Berlin") that calendar boundaries and days are
+   * computed in. Defaults to the server's local timezone.
+   */
+  timezone?: string | null;
+  /**
+   * First day of the week for week presets. Defaults to Monday.
+   */
+  weekStart?: WeekStart | null;
+};
+/**
+ * Query input for searching projects, entities, branches and apps by name
+ */
+export type SearchInput = {
+  /**
+   * Words to look for. Each word matches the start of a word in the name.
+   */
+  q: string;
+  /**
+   * Kinds of results to include: `project`, `entity`, `branch` and `app`.
+   * Defaults to all of them.
+   */
+  kinds?: Group[] | null;
+  /**
+   * Range that `totalSeconds` is computed over. Defaults to all time.
+   */
+  preset?: TimeRangePreset | null;
+  limit?: number | null;
+  /**
+   * Subtract time that overlaps AFK periods
+   */
+  excludeAfk?: boolean | null;
+  /**
+   * IANA timezone (e.g. "Europe/Berlin") that calendar boundaries are computed in.
+   * Defaults to the server's local timezone.
+   */
+  timezone?: string | null;
+  /**
+   * First day of the week for week presets. Defaults to Monday.
+   */
+  weekStart?: WeekStart | null;
+};
+/**
+ * A project, entity, branch or app matching a search
+ */
+export type SearchResult = {
+  kind: Group;
+  id: string;
+  name: string;
+  /**
+   * The project of an entity or branch
+   */
+  projectId: string | null;
+  projectName: string | null;
+  /**
+   * Seconds spent in the searched range
+   */
+  totalSeconds: number;
+};
 export type ServerStatus =
   | { state: "offline" }
   | { state: "checking" }
@@ -301,8 +688,97 @@ export type ServerStatus =
   | { state: "running" }
   | { state: "updating" }
   | { state: "error"; message: string };
+export type SessionInsight = {
+  sessions: WorkSession[];
+  stats: SessionStats;
+};
+/**
+ * Summary statistics over the active seconds of a set of sessions
+ */
+export type SessionStats = {
+  count: number;
+  meanSeconds: number;
+  medianSeconds: number;
+  longestSeconds: number;
+};
+export type StreakInsight = {
+  /**
+   * The run that includes today, or yesterday while today is not yet active
+   */
+  current: StreakRun;
+  longest: StreakRun;
+};
+/**
+ * A run of consecutive active days. Dates are local "yyyy-mm-dd" days.
+ */
+export type StreakRun = {
+  days: number;
+  start: string | null;
+  end: string | null;
+};
+/**
+ * Query input for comparing per-group totals of two periods.
+ *
+ * The current period is either `preset` or `start`/`end`. The previous period is
+ * `previousStart`/`previousEnd`, or the period of the same length right before the
+ * current one when those are omitted.
+ */
+export type SummaryCompareInput = {
+  preset?: TimeRangePreset | null;
+  start?: string | null;
+  end?: string | null;
+  previousStart?: string | null;
+  previousEnd?: string | null;
+  apps?: string[] | null;
+  projects?: string[] | null;
+  entities?: string[] | null;
+  categories?: string[] | null;
+  branches?: string[] | null;
+  languages?: string[] | null;
+  sources?: string[] | null;
+  /**
+   * Entity types (e.g. "File", "Url")
+   */
+  entityTypes?: string[] | null;
+  /**
+   * Tags of the event or its project
+   */
+  tags?: string[] | null;
+  groupBy?: Group | null;
+  /**
+   * Subtract time that overlaps AFK periods
+   */
+  excludeAfk?: boolean | null;
+  /**
+   * IANA timezone (e.g. "Europe/Berlin") that calendar boundaries and bucket labels
+   * are computed in. Defaults to the server's local timezone.
+   */
+  timezone?: string | null;
+  /**
+   * First day of the week for week presets, buckets and "yyyy-W##" ranges.
+   * Defaults to Monday.
+   */
+  weekStart?: WeekStart | null;
+};
+/**
+ * Per-group totals of two periods, as returned by `/summary/compare`
+ */
+export type SummaryComparison = {
+  currentStart: string;
+  currentEnd: string;
+  previousStart: string;
+  previousEnd: string;
+  /**
+   * Groups present in either period, ordered by current then previous time
+   */
+  groups: GroupComparison[];
+};
 /**
- * Query input for requesting summaries over a range of time
+ * Query input for requesting summaries over a range of time.
+ *
+ * Each dimension list matches exact values, GLOB patterns (e.g. `src/**/*.rs`, where
+ * `**/` matches zero or more directories) and excludes values prefixed with `!`
+ * (e.g. `!scratch-*`).
  */
 export type SummaryQueryInput = {
   start?: string | null;
@@ -313,6 +789,24 @@ export type SummaryQueryInput = {
   entities?: string[] | null;
   branches?: string[] | null;
   languages?: string[] | null;
+  sources?: string[] | null;
+  /**
+   * Entity types (e.g. "File", "Url")
+   */
+  entityTypes?: string[] | null;
+  /**
+   * Tags of the event or its project
+   */
+  tags?: string[] | null;
+  /**
+   * Subtract time that overlaps AFK periods
+   */
+  excludeAfk?: boolean | null;
+  /**
+   * IANA timezone (e.g. "Europe/Berlin") that calendar boundaries and bucket labels
+   * are computed in. Defaults to the server's local timezone.
+   */
+  timezone?: string | null;
 };
 export type Theme = "light" | "dark" | "system";
 /**
@@ -358,7 +852,7 @@ export type TimeRangePreset =
    */
   | "yesterday"
   /**
-   * The current calendar week starting from Monday.
+   * The current calendar week, starting on the configured [`WeekStart`].
    */
   | "thisWeek"
   /**
@@ -414,6 +908,45 @@ export type TimeRangePreset =
     };
 export type TimeSpan = "day" | "week" | "month" | "year";
 export type TrackedApp = { name: string; bundleId: string };
+/**
+ * The first day of a calendar week
+ */
+export type WeekStart =
+  /**
+   * Weeks start on Monday, as in ISO 8601
+   */
+  | "monday"
+  /**
+   * Weeks start on Sunday
+   */
+  | "sunday"
+  /**
+   * Weeks start on Saturday
+   */
+  | "saturday";
+/**
+ * A continuous stretch of work, merged from events separated by short breaks
+ */
+export type WorkSession = {
+  start: string;
+  end: string;
+  /**
+   * Seconds covered by events within the session, excluding AFK time
+   */
+  activeSeconds: number;
+  /**
+   * The project with the most time in the session
+   */
+  project: string | null;
+  /**
+   * The category with the most time in the session
+   */
+  category: string | null;
+  /**
+   * Languages used in the session, by time spent
+   */
+  languages: string[];
+};
 export type WindowKind = "main" | "settings" | "notification";
 
 /** tauri-specta globals **/
@@ -1,11 +1,18 @@
 use crate::routes::afk_events::afk_event_routes;
 use crate::routes::apps::app_routes;
+use crate::routes::archive::archive_routes;
+use crate::routes::billing::billing_routes;
 use crate::routes::categories::category_routes;
 use crate::routes::events::event_routes;
 use crate::routes::health::health_routes;
 use crate::routes::insights::insights_routes;
+use crate::routes::languages::language_routes;
 use crate::routes::projects::project_routes;
+use crate::routes::rules::rule_routes;
+use crate::routes::search::search_routes;
+use crate::routes::sources::source_routes;
 use crate::routes::summary::summary_routes;
+use crate::routes::tags::tag_routes;
 use axum::Router;
 use db::DBContext;
 use std::sync::Arc;
@@ -19,7 +26,14 @@ pub async fn create_app(db: Arc<DBContext>) -> Router {
         .merge(summary_routes(db.clone()))
         .merge(app_routes(db.clone()))
         .merge(category_routes(db.clone()))
+        .merge(language_routes(db.clone()))
         .merge(project_routes(db.clone()))
+        .merge(rule_routes(db.clone()))
+        .merge(search_routes(db.clone()))
+        .merge(source_routes(db.clone()))
+        .merge(tag_routes(db.clone()))
+        .merge(billing_routes(db.clone()))
+        .merge(archive_routes(db.clone()))
         .merge(insights_routes(db.clone()));
 
     if cfg!(debug_assertions) {
@@ -0,0 +1,412 @@
+use std::{io, sync::Arc};
+
+use common::models::{ArchiveFormat, ArchiveRecord, outputs::ArchiveImportReport};
+use db::{
+    DBContext,
+    error::DBError,
+    server::archive::{Archive, ArchiveImport},
+};
+use futures_util::{Stream, StreamExt};
+use serde_json::{Map, Value};
+use thiserror::Error;
+use uuid::Uuid;
+
+use crate::{pagination::EXPORT_PAGE_SIZE, utils::csv_field};
+
+/// Columns of a CSV archive, named like the NDJSON fields
+const CSV_COLUMNS: [&str; 24] = [
+    "record",
+    "version",
+    "id",
+    "name",
+    "rootPath",
+    "entityType",
+    "priority",
+    "timestamp",
+    "endTimestamp",
+    "duration",
+    "appId",
+    "categoryId",
+    "projectId",
+    "entityId",
+    "branchId",
+    "languageId",
+    "sourceId",
+    "tagId",
+    "enabled",
+    "conditions",
+    "actions",
+    "hourlyRate",
+    "billable",
+    "tags",
+];
+
+/// CSV columns holding JSON numbers, booleans, objects or lists rather than text
+const CSV_JSON_COLUMNS: [&str; 11] = [
+    "version",
+    "priority",
+    "timestamp",
+    "endTimestamp",
+    "duration",
+    "enabled",
+    "conditions",
+    "actions",
+    "hourlyRate",
+    "billable",
+    "tags",
+];
+
+/// Records restored per transaction during an import
+const IMPORT_CHUNK_SIZE: usize = 1000;
+
+/// Errors reading an archive. `index` is the line of an NDJSON archive or the row of a
+/// CSV archive, counting from 1.
+#[derive(Debug, Error)]
+pub enum ArchiveError {
+    #[error("Invalid record {index}: {source}")]
+    Record {
+        index: usize,
+        source: serde_json::Error,
+    },
+
+    #[error("Invalid line {line}: not UTF-8")]
+    Encoding { line: usize },
+
+    #[error("Invalid CSV row {index}: expected {expected} columns, found {found}")]
+    Columns {
+        index: usize,
+        expected: usize,
+        found: usize,
+    },
+
+    #[error("CSV archives must start with the header row")]
+    MissingHeader,
+
+    #[error("Could not read the archive: {0}")]
+    Io(#[from] io::Error),
+
+    #[error(transparent)]
+    Db(#[from] DBError),
+}
+
+/// Picks the format of an archive file from its extension, NDJSON unless it is `.csv`
+pub fn format_of(path: &std::path::Path) -> ArchiveFormat {
+    match path.extension().and_then(|ext| ext.to_str()) {
+        Some(ext) if ext.eq_ignore_ascii_case("csv") => ArchiveFormat::Csv,
+        _ => ArchiveFormat::Ndjson,
+    }
+}
+
+/// Encodes a record as one NDJSON line or CSV row, including the line break
+pub fn encode(format: ArchiveFormat, record: &ArchiveRecord) -> Result<String, serde_json::Error> {
+    match format {
+        ArchiveFormat::Ndjson => Ok(format!("{}\n", serde_json::to_string(record)?)),
+        ArchiveFormat::Csv => {
+            let fields = serde_json::to_value(record)?;
+            let cells: Vec<String> = CSV_COLUMNS
+                .iter()
+                .map(|column| match fields.get(*column) {
+                    Some(Value::String(text)) => csv_field(text),
+                    Some(value @ (Value::Array(_) | Value::Object(_))) => {
+                        csv_field(&value.to_string())
+                    }
+                    Some(value) => value.to_string(),
+                    None => String::new(),
+                })
+                .collect();
+            Ok(format!("{}\n", cells.join(",")))
+        }
+    }
+}
+
+/// Decodes an archive a line at a time
+#[derive(Debug)]
+struct Decoder {
+    format: ArchiveFormat,
+    /// Lines read so far
+    lines: usize,
+    /// CSV rows read so far, the header included
+    rows: usize,
+    /// Text of a CSV row whose quoted cell continues on the next line
+    pending: String,
+}
+
+impl Decoder {
+    fn new(format: ArchiveFormat) -> Self {
+        Self {
+            format,
+            lines: 0,
+            rows: 0,
+            pending: String::new(),
+        }
+    }
+
+    /// Decodes the next line, without its line break. Returns `None` for blank lines,
+    /// the CSV header and lines that end inside a quoted CSV cell.
+    fn line(&mut self, line: &str) -> Result<Option<ArchiveRecord>, ArchiveError> {
+        self.lines += 1;
+        match self.format {
+            ArchiveFormat::Ndjson => {
+                if line.trim().is_empty() {
+                    return Ok(None);
+                }
+                serde_json::from_str(line)
+                    .map(Some)
+                    .map_err(|source| ArchiveError::Record {
+                        index: self.lines,
+                        source,
+                    })
+            }
+            ArchiveFormat::Csv => {
+                if !self.pending.is_empty() {
+                    self.pending.push('\n');
+                }
+                self.pending.push_str(line);
+                // Quotes come in pairs, escaped ones included, unless a cell is still open
+                if !self.pending.matches('"').count().is_multiple_of(2) {
+                    return Ok(None);
+                }
+                self.csv_row()
+            }
+        }
+    }
+
+    /// Decodes what is left once the archive ends
+    fn finish(&mut self) -> Result<Option<ArchiveRecord>, ArchiveError> {
+        match self.format {
+            ArchiveFormat::Csv if !self.pending.is_empty() => self.csv_row(),
+            ArchiveFormat::Csv if self.rows == 0 => Err(ArchiveError::MissingHeader),
+            _ => Ok(None),
+        }
+    }
+
+    /// Decodes the pending CSV row, which must be the header if it is the first
+    fn csv_row(&mut self) -> Result<Option<ArchiveRecord>, ArchiveError> {
+        let text = std::mem::take(&mut self.pending);
+        let row = csv_rows(&text).into_iter().next().unwrap_or_default();
+        self.rows += 1;
+        let index = self.rows;
+
+        if index == 1 {
+            if row != CSV_COLUMNS {
+                return Err(ArchiveError::MissingHeader);
+            }
+            return Ok(None);
+        }
+        if row.iter().all(|cell| cell.is_empty()) {
+            return Ok(None);
+        }
+        if row.len() != CSV_COLUMNS.len() {
+            return Err(ArchiveError::Columns {
+                index,
+                expected: CSV_COLUMNS.len(),
+                found: row.len(),
+            });
+        }
+
+        let mut fields = Map::new();
+        for (column, cell) in CSV_COLUMNS.iter().zip(row) {
+            if cell.is_empty() {
+                continue;
+            }
+            let value = if CSV_JSON_COLUMNS.contains(column) {
+                serde_json::from_str(&cell)
+                    .map_err(|source| ArchiveError::Record { index, source })?
+            } else {
+                Value::String(cell)
+            };
+            fields.insert(column.to_string(), value);
+        }
+        serde_json::from_value(Value::Object(fields))
+            .map(Some)
+            .map_err(|source| ArchiveError::Record { index, source })
+    }
+}
+
+/// Splits CSV text into rows of unquoted cells. Quoted cells may span lines.
+fn csv_rows(text: &str) -> Vec<Vec<String>> {
+    let mut rows = Vec::new();
+    let mut row = Vec::new();
+    let mut cell = String::new();
+    let mut quoted = false;
+    let mut chars = text.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match (c, quoted) {
+            ('"', true) if chars.peek() == Some(&'"') => {
+                cell.push('"');
+                chars.next();
+            }
+            ('"', true) => quoted = false,
+            ('"', false) if cell.is_empty() => quoted = true,
+            (',', false) => row.push(std::mem::take(&mut cell)),
+            ('\r', false) if chars.peek() == Some(&'\n') => {}
+            ('\n', false) => {
+                row.push(std::mem::take(&mut cell));
+                rows.push(std::mem::take(&mut row));
+            }
+            (c, _) => cell.push(c),
+        }
+    }
+    if !cell.is_empty() || !row.is_empty() {
+        row.push(cell);
+        rows.push(row);
+    }
+
+    rows
+}
+
+/// The part of the archive written next
+enum Stage {
+    Header,
+    Dimensions,
+    Settings,
+    Events(Option<Uuid>),
+    AfkEvents(Option<Uuid>),
+    DeletedEvents(Option<Uuid>),
+    Done,
+}
+
+/// The cursor of the page after `page`, or `None` if it was the last. A full page means
+/// there may be more rows after its last id.
+fn next_page(page: &[ArchiveRecord]) -> Option<Option<Uuid>> {
+    (page.len() == EXPORT_PAGE_SIZE as usize).then(|| page.last().and_then(|r| r.id))
+}
+
+/// Reads the records of `stage` and returns them with the stage that follows
+async fn read_stage(db: &DBContext, stage: Stage) -> Result<(Vec<ArchiveRecord>, Stage), DBError> {
+    Ok(match stage {
+        Stage::Header => (vec![Archive::header()], Stage::Dimensions),
+        Stage::Dimensions => (Archive::dimensions(db).await?, Stage::Settings),
+        Stage::Settings => (Archive::settings(db).await?, Stage::Events(None)),
+        Stage::Events(after) => {
+            let page = Archive::events(db, after, EXPORT_PAGE_SIZE).await?;
+            let next = next_page(&page).map_or(Stage::AfkEvents(None), Stage::Events);
+            (page, next)
+        }
+        Stage::AfkEvents(after) => {
+            let page = Archive::afk_events(db, after, EXPORT_PAGE_SIZE).await?;
+            let next = next_page(&page).map_or(Stage::DeletedEvents(None), Stage::AfkEvents);
+            (page, next)
+        }
+        Stage::DeletedEvents(after) => {
+            let page = Archive::deleted_events(db, after, EXPORT_PAGE_SIZE).await?;
+            let next = next_page(&page).map_or(Stage::Done, Stage::DeletedEvents);
+            (page, next)
+        }
+        Stage::Done => (Vec::new(), Stage::Done),
+    })
+}
+
+/// Streams the whole database as an archive. Events are read a page at a time as the
+/// stream is polled, so the archive is never held in memory.
+pub fn export_stream(
+    db: Arc<DBContext>,
+    format: ArchiveFormat,
+) -> impl Stream<Item = Result<String, io::Error>> + Send + 'static {
+    futures_util::stream::try_unfold((db, Stage::Header), move |(db, stage)| async move {
+        if matches!(stage, Stage::Done) {
+            return Ok(None);
+        }
+
+        let is_header = matches!(stage, Stage::Header);
+        let (records, next) = read_stage(&db, stage).await.map_err(io::Error::other)?;
+
+        let mut chunk = String::new();
+        if is_header && format == ArchiveFormat::Csv {
+            chunk.push_str(&CSV_COLUMNS.join(","));
+            chunk.push('\n');
+        }
+        for record in &records {
+            chunk.push_str(&encode(format, record)?);
+        }
+        Ok(Some((chunk, (db, next))))
+    })
+}
+
+/// Restores an archive fed to it a line at a time, a chunk of records per transaction,
+/// so the archive is never held in memory
+#[derive(Debug)]
+pub struct ArchiveReader {
+    decoder: Decoder,
+    import: ArchiveImport,
+    records: Vec<ArchiveRecord>,
+}
+
+impl ArchiveReader {
+    pub fn new(format: ArchiveFormat) -> Self {
+        Self {
+            decoder: Decoder::new(format),
+            import: ArchiveImport::new(),
+            records: Vec::with_capacity(IMPORT_CHUNK_SIZE),
+        }
+    }
+
+    /// Reads the next line of the archive, without its line break
+    pub async fn line(&mut self, db: &DBContext, line: &str) -> Result<(), ArchiveError> {
+        if let Some(record) = self.decoder.line(line)? {
+            self.records.push(record);
+            if self.records.len() == IMPORT_CHUNK_SIZE {
+                self.flush(db).await?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Restores the records left once the archive ends and reports what was imported
+    pub async fn finish(mut self, db: &DBContext) -> Result<ArchiveImportReport, ArchiveError> {
+        if let Some(record) = self.decoder.finish()? {
+            self.records.push(record);
+        }
+        self.flush(db).await?;
+        Ok(self.import.finish()?)
+    }
+
+    async fn flush(&mut self, db: &DBContext) -> Result<(), ArchiveError> {
+        if !self.records.is_empty() {
+            self.import.apply(db, &self.records).await?;
+            self.records.clear();
+        }
+        Ok(())
+    }
+}
+
+/// Restores an archive read from a stream of byte chunks, such as a request body
+pub async fn import_stream<S, B, E>(
+    db: &DBContext,
+    format: ArchiveFormat,
+    stream: S,
+) -> Result<ArchiveImportReport, ArchiveError>
+where
+    S: Stream<Item = Result<B, E>>,
+    B: AsRef<[u8]>,
+    E: std::error::Error + Send + Sync + 'static,
+{
+    let mut reader = ArchiveReader::new(format);
+    let mut buffer: Vec<u8> = Vec::new();
+    let mut stream = std::pin::pin!(stream);
+
+    while let Some(chunk) = stream.next().await {
+        buffer.extend_from_slice(chunk.map_err(io::Error::other)?.as_ref());
+        let mut start = 0;
+        while let Some(end) = buffer[start..].iter().position(|&b| b == b'\n') {
+            let line = &buffer[start..start + end];
+            reader.line(db, decode_line(&reader, line)?).await?;
+            start += end + 1;
+        }
+        buffer.drain(..start);
+    }
+    if !buffer.is_empty() {
+        reader.line(db, decode_line(&reader, &buffer)?).await?;
+    }
+
+    reader.finish(db).await
+}
+
+/// Reads a line as UTF-8 without a trailing carriage return
+fn decode_line<'a>(reader: &ArchiveReader, line: &'a [u8]) -> Result<&'a str, ArchiveError> {
+    let line = line.strip_suffix(b"\r").unwrap_or(line);
+    std::str::from_utf8(line).map_err(|_| ArchiveError::Encoding {
+        line: reader.decoder.lines + 1,
+    })
+}
@@ -0,0 +1,98 @@
+use std::{path::PathBuf, sync::Arc};
+
+use anyhow::{Context, bail};
+use common::models::ArchiveFormat;
+use db::DBContext;
+use futures_util::StreamExt;
+use tokio::{
+    fs::File,
+    io::{AsyncBufReadExt, AsyncWriteExt, BufReader},
+};
+
+use crate::archive::{ArchiveReader, export_stream, format_of};
+
+const USAGE: &str = "Usage: skopio-server [export <file> | import <file>] [--format ndjson|csv]";
+
+/// A maintenance command run against the database instead of starting the server
+#[derive(Debug)]
+pub enum Command {
+    /// Writes every dimension, event and AFK event to an archive file
+    Export {
+        path: PathBuf,
+        format: ArchiveFormat,
+    },
+    /// Restores an archive file written by `export` or `/export`
+    Import {
+        path: PathBuf,
+        format: ArchiveFormat,
+    },
+}
+
+impl Command {
+    /// Parses the arguments after the binary name. Returns `None` when there are none,
+    /// which starts the server. The format defaults to the file extension.
+    pub fn parse(args: impl IntoIterator<Item = String>) -> anyhow::Result<Option<Self>> {
+        let mut args = args.into_iter();
+        let Some(name) = args.next() else {
+            return Ok(None);
+        };
+
+        let mut path = None;
+        let mut format = None;
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--format" => {
+                    format = Some(match args.next().as_deref() {
+                        Some("ndjson") => ArchiveFormat::Ndjson,
+                        Some("csv") => ArchiveFormat::Csv,
+                        _ => bail!("--format must be ndjson or csv\n{USAGE}"),
+                    })
+                }
+                _ if path.is_none() => path = Some(PathBuf::from(arg)),
+                _ => bail!("Unexpected argument: {arg}\n{USAGE}"),
+            }
+        }
+
+        let Some(path) = path else {
+            bail!("Missing archive file\n{USAGE}");
+        };
+        let format = format.unwrap_or_else(|| format_of(&path));
+        match name.as_str() {
+            "export" => Ok(Some(Command::Export { path, format })),
+            "import" => Ok(Some(Command::Import { path, format })),
+            _ => bail!("Unknown command: {name}\n{USAGE}"),
+        }
+    }
+
+    pub async fn run(self, db: Arc<DBContext>) -> anyhow::Result<()> {
+        match self {
+            Command::Export { path, format } => {
+                let mut file = File::create(&path)
+                    .await
+                    .with_context(|| format!("Could not create {}", path.display()))?;
+                let mut stream = Box::pin(export_stream(db, format));
+                while let Some(chunk) = stream.next().await {
+                    file.write_all(chunk?.as_bytes()).await?;
+                }
+                file.flush().await?;
+
+                println!("Exported archive to {}", path.display());
+            }
+            Command::Import { path, format } => {
+                let file = File::open(&path)
+                    .await
+                    .with_context(|| format!("Could not read {}", path.display()))?;
+                let mut lines = BufReader::new(file).lines();
+                let mut reader = ArchiveReader::new(format);
+                while let Some(line) = lines.next_line().await? {
+                    reader.line(&db, &line).await?;
+                }
+                let report = reader.finish(&db).await?;
+
+                println!("{}", serde_json::to_string_pretty(&report)?);
+            }
+        }
+
+        Ok(())
+    }
+}
@@ -10,6 +10,8 @@ use serde::Serialize;
 use thiserror::Error;
 use tracing::{error, warn};
 
+use crate::archive::ArchiveError;
+
 /// This type represents all possible errors that can occur when interacting
 /// with the server app
 #[derive(Debug, Error)]
@@ -18,11 +20,9 @@ pub enum ServerError {
     #[error("Bad request: {0}")]
     BadRequest(String),
 
-    #[allow(dead_code)]
     #[error("Not found")]
     NotFound,
 
-    #[allow(dead_code)]
     #[error("Conflict: {0}")]
     Conflict(String),
 
@@ -69,6 +69,7 @@ impl From<DBError> for ServerError {
             }
             DBError::Parse(err) => Self::BadRequest(err.to_string()),
             DBError::Uuid(err) => ServerError::BadRequest(err.to_string()),
+            DBError::Pattern(err) => ServerError::BadRequest(err.to_string()),
             DBError::Unsupported(msg) => ServerError::Unprocessable(msg.to_string()),
             DBError::Sqlx(err) => ServerError::Internal(anyhow!(err.to_string())),
             DBError::Migration(err) => Self::Internal(anyhow!(err.to_string())),
@@ -76,6 +77,15 @@ impl From<DBError> for ServerError {
     }
 }
 
+impl From<ArchiveError> for ServerError {
+    fn from(e: ArchiveError) -> Self {
+        match e {
+            ArchiveError::Db(err) => err.into(),
+            err => ServerError::BadRequest(err.to_string()),
+        }
+    }
+}
+
 impl From<TimeError> for ServerError {
     fn from(e: TimeError) -> Self {
         match e {
@@ -0,0 +1,149 @@
+use std::collections::HashSet;
+
+use chrono::{DateTime, Duration, Utc};
+use common::models::{
+    inputs::{AFKEventInput, EventInput},
+    outputs::{IngestItemResult, IngestReport},
+};
+use db::server::dimensions::normalize_root_path;
+use thiserror::Error;
+use uuid::Uuid;
+
+/// How far ahead of the server clock a timestamp may be before it is rejected.
+/// Leaves room for clock skew between clients and the server.
+const MAX_FUTURE_SKEW: Duration = Duration::hours(1);
+
+/// Reasons an ingested item is rejected
+#[derive(Debug, Error)]
+pub enum Rejection {
+    #[error("App name is empty")]
+    EmptyAppName,
+
+    #[error("End timestamp is before the start timestamp")]
+    EndBeforeStart,
+
+    #[error("Duration is negative")]
+    NegativeDuration,
+
+    #[error("Timestamp is too far in the future")]
+    FutureTimestamp,
+}
+
+/// Validates an event before any of its dimensions are resolved
+pub fn validate_event(event: &EventInput, now: DateTime<Utc>) -> Result<(), Rejection> {
+    if event.app_name.trim().is_empty() {
+        return Err(Rejection::EmptyAppName);
+    }
+
+    validate_span(event.timestamp, event.end_timestamp, event.duration, now)
+}
+
+/// The id a rejected event is reported with. Rejected events never get their dimensions
+/// resolved, so the id is derived from the values the client sent rather than from
+/// dimension rows, and stays the same when the client retries.
+pub fn rejected_event_id(event: &EventInput) -> Uuid {
+    let key = format!(
+        "{}|{}|{}|{}|{}|{}|{}|{}|{}",
+        event.app_name,
+        event.entity_name,
+        normalize_root_path(&event.project_path, &event.project_name),
+        event.category,
+        event.source_name,
+        event.timestamp.unwrap_or_default().timestamp(),
+        event.end_timestamp.unwrap_or_default().timestamp(),
+        event.branch_name.as_deref().unwrap_or_default(),
+        event.language_name.as_deref().unwrap_or_default(),
+    );
+    Uuid::new_v5(&Uuid::NAMESPACE_URL, key.as_bytes())
+}
+
+/// Validates an AFK event
+pub fn validate_afk_event(afk: &AFKEventInput, now: DateTime<Utc>) -> Result<(), Rejection> {
+    validate_span(Some(afk.afk_start), afk.afk_end, afk.duration, now)
+}
+
+fn validate_span(
+    start: Option<DateTime<Utc>>,
+    end: Option<DateTime<Utc>>,
+    duration: Option<i64>,
+    now: DateTime<Utc>,
+) -> Result<(), Rejection> {
+    if duration.is_some_and(|d| d < 0) {
+        return Err(Rejection::NegativeDuration);
+    }
+
+    if let (Some(start), Some(end)) = (start, end)
+        && end < start
+    {
+        return Err(Rejection::EndBeforeStart);
+    }
+
+    let limit = now + MAX_FUTURE_SKEW;
+    if start.is_some_and(|t| t > limit) || end.is_some_and(|t| t > limit) {
+        return Err(Rejection::FutureTimestamp);
+    }
+
+    Ok(())
+}
+
+/// Tracks the outcome of every item of an ingest request and builds the
+/// [`IngestReport`] once the staged rows have been written.
+#[derive(Default)]
+pub struct IngestTracker {
+    report: IngestReport,
+    seen: HashSet<Uuid>,
+    staged: Vec<(usize, Uuid)>,
+}
+
+impl IngestTracker {
+    pub fn reject(&mut self, index: usize, id: Option<Uuid>, reason: Rejection) {
+        self.report.rejected.push(IngestItemResult {
+            index,
+            id,
+            reason: Some(reason.to_string()),
+        });
+    }
+
+    /// Stages an item for insertion.
+    ///
+    /// Returns `false` if an earlier item in the same request has the same id, in
+    /// which case the item is reported as a duplicate and should not be written.
+    pub fn stage(&mut self, index: usize, id: Uuid) -> bool {
+        if !self.seen.insert(id) {
+            self.report.duplicate.push(IngestItemResult {
+                index,
+                id: Some(id),
+                reason: Some("Repeated within the request".to_string()),
+            });
+            return false;
+        }
+
+        self.staged.push((index, id));
+        true
+    }
+
+    /// Splits the staged items into accepted and duplicate using the ids that
+    /// were actually inserted.
+    pub fn finish(mut self, inserted: &[Uuid]) -> IngestReport {
+        let inserted: HashSet<&Uuid> = inserted.iter().collect();
+
+        for (index, id) in self.staged {
+            if inserted.contains(&id) {
+                self.report.accepted.push(IngestItemResult {
+                    index,
+                    id: Some(id),
+                    reason: None,
+                });
+            } else {
+                self.report.duplicate.push(IngestItemResult {
+                    index,
+                    id: Some(id),
+                    reason: Some("Already stored or deleted".to_string()),
+                });
+            }
+        }
+
+        self.report.duplicate.sort_by_key(|item| item.index);
+        self.report
+    }
+}
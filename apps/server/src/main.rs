@@ -1,4 +1,4 @@
-use crate::{app::create_app, utils::init_tracing};
+use crate::{app::create_app, commands::Command, utils::init_tracing};
 
 use db::DBContext;
 use std::sync::Arc;
@@ -6,18 +6,32 @@ use tokio::net::TcpListener;
 use tracing::{error, info};
 
 mod app;
+mod archive;
 mod auth;
+mod commands;
 mod error;
+mod ingest;
 mod net;
+mod pagination;
 mod routes;
 mod utils;
 
 #[tokio::main]
 async fn main() {
+    let command = match Command::parse(std::env::args().skip(1)) {
+        Ok(command) => command,
+        Err(e) => {
+            eprintln!("{e}");
+            std::process::exit(2);
+        }
+    };
+
     if let Err(e) = init_tracing() {
         error!("Failed to init tracing: {e}");
     }
-    info!("🚀 Starting server...");
+    if command.is_none() {
+        info!("🚀 Starting server...");
+    }
 
     let db_path = utils::get_db_path();
     let db_url = format!("sqlite://{}", db_path.to_str().unwrap());
@@ -30,6 +44,14 @@ async fn main() {
         }
     };
 
+    if let Some(command) = command {
+        if let Err(e) = command.run(db).await {
+            eprintln!("{e:#}");
+            std::process::exit(1);
+        }
+        return;
+    }
+
     let mut app = create_app(db.clone()).await;
 
     if !cfg!(debug_assertions) {
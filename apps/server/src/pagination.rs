@@ -0,0 +1,80 @@
+use std::io;
+
+use axum::{
+    body::Body,
+    http::header,
+    response::{IntoResponse, Response},
+};
+use common::models::{inputs::EventPageInput, outputs::EventPage};
+use db::{error::DBError, server::utils::cursor::EventCursor};
+
+use crate::error::{ServerError, ServerResult};
+
+/// Events per page when a paginated request sets no `limit`
+const DEFAULT_PAGE_SIZE: u32 = 500;
+
+/// Largest `limit` a paginated request may ask for
+const MAX_PAGE_SIZE: u32 = 5000;
+
+/// Events fetched per query while streaming an export
+pub const EXPORT_PAGE_SIZE: u32 = 1000;
+
+/// Whether the request asks for a page rather than the whole range
+pub fn is_paginated(page: &EventPageInput) -> bool {
+    page.after.is_some() || page.limit.is_some()
+}
+
+/// Decodes the cursor and clamps the limit of a paginated request
+pub fn page_params(page: &EventPageInput) -> ServerResult<(Option<EventCursor>, u32)> {
+    let after = match page.after.as_deref() {
+        Some(cursor) => Some(
+            EventCursor::decode(cursor)
+                .ok_or_else(|| ServerError::BadRequest(format!("Invalid cursor: {cursor}")))?,
+        ),
+        None => None,
+    };
+    let limit = page
+        .limit
+        .unwrap_or(DEFAULT_PAGE_SIZE)
+        .clamp(1, MAX_PAGE_SIZE);
+
+    Ok((after, limit))
+}
+
+/// Streams every page returned by `fetch_page` as newline-delimited JSON, one event per
+/// line. Pages are fetched as the client reads, so the whole range is never held in memory.
+pub fn ndjson_response<F, Fut>(fetch_page: F) -> Response
+where
+    F: Fn(Option<EventCursor>) -> Fut + Send + 'static,
+    Fut: Future<Output = Result<EventPage, DBError>> + Send,
+{
+    // The state holds the cursor of the next page, or `None` once the last page was sent
+    let stream = futures_util::stream::try_unfold(
+        (fetch_page, Some(None)),
+        |(fetch_page, next)| async move {
+            let Some(after) = next else {
+                return Ok(None);
+            };
+
+            let page = fetch_page(after).await.map_err(io::Error::other)?;
+            let mut chunk = String::new();
+            for event in &page.events {
+                chunk.push_str(&serde_json::to_string(event)?);
+                chunk.push('\n');
+            }
+
+            let next = page
+                .next_cursor
+                .as_deref()
+                .and_then(EventCursor::decode)
+                .map(Some);
+            Ok::<_, io::Error>(Some((chunk, (fetch_page, next))))
+        },
+    );
+
+    (
+        [(header::CONTENT_TYPE, "application/x-ndjson")],
+        Body::from_stream(stream),
+    )
+        .into_response()
+}
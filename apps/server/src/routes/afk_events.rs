@@ -1,11 +1,13 @@
 use axum::{
     Json, Router,
     extract::State,
+    response::{IntoResponse, Response},
     routing::{get, post},
 };
+use chrono::Utc;
 use common::models::{
-    inputs::{AFKEventInput, BucketSummaryInput},
-    outputs::FullEvent,
+    inputs::{AFKEventInput, BucketSummaryInput, EventPageInput},
+    outputs::IngestReport,
 };
 use db::{
     DBContext,
@@ -16,57 +18,101 @@ use std::sync::Arc;
 use tracing::info;
 use uuid::Uuid;
 
-use crate::error::ServerResult;
+use crate::{
+    error::ServerResult,
+    ingest::{IngestTracker, validate_afk_event},
+    pagination::{EXPORT_PAGE_SIZE, is_paginated, ndjson_response, page_params},
+};
 
 async fn handle_afk_events(
     State(db): State<Arc<DBContext>>,
     Json(payload): Json<Vec<AFKEventInput>>,
-) -> ServerResult<()> {
+) -> ServerResult<Json<IngestReport>> {
     info!("Handling {} afk events", payload.len());
 
-    let mut events: Vec<AFKEvent> = payload
-        .into_iter()
-        .map(|afk| {
-            let key = format!(
-                "{}|{}|{}",
-                afk.afk_start,
-                afk.afk_end.unwrap_or_default(),
-                afk.duration.unwrap_or_default(),
-            );
-
-            let id = Uuid::new_v5(&Uuid::NAMESPACE_URL, key.as_bytes());
-
-            AFKEvent {
-                id,
-                afk_start: afk.afk_start.timestamp(),
-                afk_end: afk.afk_end.map(|t| t.timestamp()),
-                duration: afk.duration,
-            }
-        })
-        .collect();
-
-    events.sort_by_key(|e| e.id);
-    events.dedup_by_key(|e| e.id);
+    let now = Utc::now();
+    let mut tracker = IngestTracker::default();
+    let mut events: Vec<AFKEvent> = Vec::with_capacity(payload.len());
+
+    for (index, afk) in payload.into_iter().enumerate() {
+        let key = format!(
+            "{}|{}|{}",
+            afk.afk_start,
+            afk.afk_end.unwrap_or_default(),
+            afk.duration.unwrap_or_default(),
+        );
+
+        let id = Uuid::new_v5(&Uuid::NAMESPACE_URL, key.as_bytes());
+
+        if let Err(reason) = validate_afk_event(&afk, now) {
+            tracker.reject(index, Some(id), reason);
+            continue;
+        }
+
+        if !tracker.stage(index, id) {
+            continue;
+        }
+
+        events.push(AFKEvent {
+            id,
+            afk_start: afk.afk_start.timestamp(),
+            afk_end: afk.afk_end.map(|t| t.timestamp()),
+            duration: afk.duration,
+        });
+    }
 
     let inserted = AFKEvent::bulk_create(&db, &events).await?;
+    let report = tracker.finish(&inserted);
 
-    info!("Inserted {} AFK events", inserted);
-    Ok(())
+    info!(
+        "Inserted {} AFK events ({} duplicate, {} rejected)",
+        report.accepted.len(),
+        report.duplicate.len(),
+        report.rejected.len()
+    );
+    Ok(Json(report))
 }
 
+/// Returns the AFK events of the range, or a single `EventPage` when `limit` or `after` is given
 async fn fetch_afk_events(
     State(db): State<Arc<DBContext>>,
     QsQuery(payload): QsQuery<BucketSummaryInput>,
-) -> ServerResult<Json<Vec<FullEvent>>> {
+    QsQuery(page): QsQuery<EventPageInput>,
+) -> ServerResult<Response> {
     let builder = SummaryQueryBuilder::from(payload);
+
+    if is_paginated(&page) {
+        let (after, limit) = page_params(&page)?;
+        let page = builder.fetch_afk_event_page(&db, after, limit).await?;
+        return Ok(Json(page).into_response());
+    }
+
     let events = builder.fetch_afk_event_range(&db).await?;
+    Ok(Json(events).into_response())
+}
+
+/// Streams every AFK event of the range as NDJSON, in chronological order
+async fn export_afk_events(
+    State(db): State<Arc<DBContext>>,
+    QsQuery(payload): QsQuery<BucketSummaryInput>,
+) -> Response {
+    let builder = Arc::new(SummaryQueryBuilder::from(payload));
 
-    Ok(Json(events))
+    ndjson_response(move |after| {
+        let db = db.clone();
+        let builder = builder.clone();
+        async move {
+            builder
+                .fetch_afk_event_page(&db, after, EXPORT_PAGE_SIZE)
+                .await
+        }
+    })
 }
 
 pub fn afk_event_routes(db: Arc<DBContext>) -> Router {
     Router::new()
         .route("/afk", post(handle_afk_events))
         .route("/afk", get(fetch_afk_events))
+        .route("/afk/export", get(export_afk_events))
         .with_state(db)
 }
@@ -0,0 +1,69 @@
+use std::sync::Arc;
+
+use axum::{
+    Json, Router,
+    body::Body,
+    extract::State,
+    http::header,
+    response::{IntoResponse, Response},
+    routing::{get, post},
+};
+use chrono::Utc;
+use common::models::{ArchiveFormat, inputs::ArchiveInput, outputs::ArchiveImportReport};
+use db::DBContext;
+use serde_qs::axum::QsQuery;
+use tracing::info;
+
+use crate::{
+    archive::{export_stream, import_stream},
+    error::ServerResult,
+};
+
+/// Streams every dimension, event and AFK event as a versioned archive
+async fn export_archive(
+    State(db): State<Arc<DBContext>>,
+    QsQuery(payload): QsQuery<ArchiveInput>,
+) -> Response {
+    let format = payload.format.unwrap_or_default();
+    let (content_type, extension) = match format {
+        ArchiveFormat::Ndjson => ("application/x-ndjson", "ndjson"),
+        ArchiveFormat::Csv => ("text/csv; charset=utf-8", "csv"),
+    };
+    let filename = format!(
+        "attachment; filename=\"skopio-{}.{extension}\"",
+        Utc::now().format("%Y-%m-%d")
+    );
+
+    (
+        [
+            (header::CONTENT_TYPE, content_type.to_string()),
+            (header::CONTENT_DISPOSITION, filename),
+        ],
+        Body::from_stream(export_stream(db, format)),
+    )
+        .into_response()
+}
+
+/// Restores an archive written by `/export`. The body is read and restored as it
+/// arrives, so archives of any size can be imported.
+async fn import_archive(
+    State(db): State<Arc<DBContext>>,
+    QsQuery(payload): QsQuery<ArchiveInput>,
+    body: Body,
+) -> ServerResult<Json<ArchiveImportReport>> {
+    let format = payload.format.unwrap_or_default();
+    let report = import_stream(&db, format, body.into_data_stream()).await?;
+
+    info!(
+        "Imported {} events ({} duplicate, {} skipped) and {} AFK events",
+        report.events, report.duplicate_events, report.skipped_events, report.afk_events
+    );
+    Ok(Json(report))
+}
+
+pub fn archive_routes(db: Arc<DBContext>) -> Router {
+    Router::new()
+        .route("/export", get(export_archive))
+        .route("/import", post(import_archive))
+        .with_state(db)
+}
@@ -0,0 +1,247 @@
+use std::sync::Arc;
+
+use axum::{
+    Json, Router,
+    extract::{Path, State},
+    http::{StatusCode, header},
+    response::{IntoResponse, Response},
+    routing::{get, put},
+};
+use chrono::{DateTime, Local, Utc};
+use common::{
+    models::{
+        BillingRate, Group, InvoiceFormat,
+        inputs::{BillingRateInput, InvoiceInput},
+        outputs::InvoiceReport,
+    },
+    time::Tz,
+};
+use db::{
+    DBContext,
+    server::billing::{BillingRates, InvoiceQuery},
+};
+use serde_qs::axum::QsQuery;
+use tracing::info;
+use uuid::Uuid;
+
+use crate::{
+    error::{ServerError, ServerResult},
+    utils::csv_field,
+};
+
+async fn fetch_rates(State(db): State<Arc<DBContext>>) -> ServerResult<Json<Vec<BillingRate>>> {
+    let rates = BillingRates::all(&db).await?;
+    Ok(Json(rates))
+}
+
+async fn set_rate(
+    db: &DBContext,
+    kind: Group,
+    id: Uuid,
+    payload: BillingRateInput,
+) -> ServerResult<Json<BillingRate>> {
+    let rate = BillingRates::set(db, kind, id, payload)
+        .await?
+        .ok_or(ServerError::NotFound)?;
+    info!("Set {kind:?} rate of {} ({id})", rate.name);
+    Ok(Json(rate))
+}
+
+async fn delete_rate(db: &DBContext, kind: Group, id: Uuid) -> ServerResult<StatusCode> {
+    if !BillingRates::delete(db, kind, id).await? {
+        return Err(ServerError::NotFound);
+    }
+
+    info!("Removed {kind:?} rate of {id}");
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn set_project_rate(
+    State(db): State<Arc<DBContext>>,
+    Path(id): Path<Uuid>,
+    Json(payload): Json<BillingRateInput>,
+) -> ServerResult<Json<BillingRate>> {
+    set_rate(&db, Group::Project, id, payload).await
+}
+
+async fn delete_project_rate(
+    State(db): State<Arc<DBContext>>,
+    Path(id): Path<Uuid>,
+) -> ServerResult<StatusCode> {
+    delete_rate(&db, Group::Project, id).await
+}
+
+async fn set_tag_rate(
+    State(db): State<Arc<DBContext>>,
+    Path(id): Path<Uuid>,
+    Json(payload): Json<BillingRateInput>,
+) -> ServerResult<Json<BillingRate>> {
+    set_rate(&db, Group::Tag, id, payload).await
+}
+
+async fn delete_tag_rate(
+    State(db): State<Arc<DBContext>>,
+    Path(id): Path<Uuid>,
+) -> ServerResult<StatusCode> {
+    delete_rate(&db, Group::Tag, id).await
+}
+
+async fn set_category_rate(
+    State(db): State<Arc<DBContext>>,
+    Path(id): Path<Uuid>,
+    Json(payload): Json<BillingRateInput>,
+) -> ServerResult<Json<BillingRate>> {
+    set_rate(&db, Group::Category, id, payload).await
+}
+
+async fn delete_category_rate(
+    State(db): State<Arc<DBContext>>,
+    Path(id): Path<Uuid>,
+) -> ServerResult<StatusCode> {
+    delete_rate(&db, Group::Category, id).await
+}
+
+async fn fetch_invoice(
+    State(db): State<Arc<DBContext>>,
+    QsQuery(payload): QsQuery<InvoiceInput>,
+) -> ServerResult<Response> {
+    let format = payload.format.unwrap_or_default();
+    let timezone = payload.timezone;
+    let report = InvoiceQuery::from(payload)
+        .execute(&db)
+        .await?
+        .ok_or(ServerError::NotFound)?;
+
+    Ok(match format {
+        InvoiceFormat::Json => Json(report).into_response(),
+        InvoiceFormat::Csv => (
+            [(header::CONTENT_TYPE, "text/csv; charset=utf-8")],
+            invoice_csv(&report),
+        )
+            .into_response(),
+        InvoiceFormat::Markdown => (
+            [(header::CONTENT_TYPE, "text/markdown; charset=utf-8")],
+            invoice_markdown(&report, timezone),
+        )
+            .into_response(),
+    })
+}
+
+/// Formats an optional amount with two decimals, empty when missing
+fn money(value: Option<f64>) -> String {
+    value.map(|v| format!("{v:.2}")).unwrap_or_default()
+}
+
+/// Formats a bound of the invoiced range in `timezone`, or the server's local timezone
+fn invoice_time(time: DateTime<Utc>, timezone: Option<Tz>) -> String {
+    match timezone {
+        Some(tz) => time.with_timezone(&tz).format("%Y-%m-%d %H:%M %Z"),
+        None => time.with_timezone(&Local).format("%Y-%m-%d %H:%M %:z"),
+    }
+    .to_string()
+}
+
+/// Quotes a CSV field of an invoice. Fields starting with `=`, `+`, `-`, `@`, a tab or a
+/// carriage return are prefixed with `'` so spreadsheets don't run them as formulas.
+fn invoice_field(value: &str) -> String {
+    if value.starts_with(['=', '+', '-', '@', '\t', '\r']) {
+        csv_field(&format!("'{value}"))
+    } else {
+        csv_field(value)
+    }
+}
+
+/// Escapes pipes and flattens line breaks, which would otherwise end a Markdown table row
+fn markdown_cell(value: &str) -> String {
+    value
+        .replace('|', "\\|")
+        .replace("\r\n", " ")
+        .replace(['\n', '\r'], " ")
+}
+
+/// Renders an invoice as CSV, one row per line followed by a total row
+fn invoice_csv(report: &InvoiceReport) -> String {
+    let mut csv = String::from("description,hours,rate,amount\n");
+    for line in &report.lines {
+        csv.push_str(&format!(
+            "{},{:.2},{},{}\n",
+            invoice_field(&line.description),
+            line.hours,
+            money(line.rate),
+            money(line.amount)
+        ));
+    }
+    csv.push_str(&format!(
+        "Total,{:.2},,{:.2}\n",
+        report.total_hours, report.total_amount
+    ));
+    csv
+}
+
+/// Renders an invoice as a Markdown table with a bold total row, dated in `timezone`
+fn invoice_markdown(report: &InvoiceReport, timezone: Option<Tz>) -> String {
+    let subject = [report.project.as_deref(), report.tag.as_deref()]
+        .into_iter()
+        .flatten()
+        .collect::<Vec<_>>()
+        .join(" / ");
+
+    let mut md = format!(
+        "# Invoice: {subject}\n\n{} to {}\n\n",
+        invoice_time(report.start, timezone),
+        invoice_time(report.end, timezone)
+    );
+    md.push_str("| Description | Hours | Rate | Amount |\n");
+    md.push_str("| --- | ---: | ---: | ---: |\n");
+    for line in &report.lines {
+        md.push_str(&format!(
+            "| {} | {:.2} | {} | {} |\n",
+            markdown_cell(&line.description),
+            line.hours,
+            money(line.rate),
+            money(line.amount)
+        ));
+    }
+    md.push_str(&format!(
+        "| **Total** | **{:.2}** | | **{:.2}** |\n",
+        report.total_hours, report.total_amount
+    ));
+    if report.non_billable_seconds > 0 {
+        md.push_str(&format!(
+            "\n{:.2} non-billable hours are not included.\n",
+            report.non_billable_seconds as f64 / 3600.0
+        ));
+    }
+    md
+}
+
+pub fn billing_routes(db: Arc<DBContext>) -> Router {
+    Router::new()
+        .route("/rates", get(fetch_rates))
+        .route(
+            "/projects/{id}/rate",
+            put(set_project_rate).delete(delete_project_rate),
+        )
+        .route("/tags/{id}/rate", put(set_tag_rate).delete(delete_tag_rate))
+        .route(
+            "/categories/{id}/rate",
+            put(set_category_rate).delete(delete_category_rate),
+        )
+        .route("/reports/invoice", get(fetch_invoice))
+        .with_state(db)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_export_fields_cannot_break_out() {
+        assert_eq!(invoice_field("=SUM(A1)"), "'=SUM(A1)");
+        assert_eq!(invoice_field("\tcmd"), "'\tcmd");
+        assert_eq!(invoice_field("\rcmd"), "\"'\rcmd\"");
+        assert_eq!(invoice_field("main.rs"), "main.rs");
+
+        assert_eq!(markdown_cell("a|b\r\nc\nd"), "a\\|b c d");
+    }
+}
@@ -1,93 +1,290 @@
-use crate::error::ServerResult;
-use axum::extract::State;
-use axum::routing::{get, post};
+use crate::error::{ServerError, ServerResult};
+use crate::ingest::{IngestTracker, rejected_event_id, validate_event};
+use crate::pagination::{EXPORT_PAGE_SIZE, is_paginated, ndjson_response, page_params};
+use axum::extract::{Path, State};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::routing::{delete, get, post};
 use axum::{Json, Router};
-use common::models::inputs::{BucketSummaryInput, EventInput};
-use common::models::outputs::EventGroupResult;
+use chrono::{Duration, Utc};
+use common::models::inputs::{
+    BucketSummaryInput, EventInput, EventMergeInput, EventPageInput, EventPatchInput,
+    EventSplitInput, OverlapQueryInput,
+};
+use common::models::outputs::{DeleteReport, EventOverlap, FullEvent, IngestReport};
 use db::DBContext;
-use db::models::{App, Category, Source};
-use db::server::branches::Branch;
-use db::server::entities::Entity;
+use db::server::dimensions::resolve_dimensions;
 use db::server::events::Event;
-use db::server::languages::Language;
-use db::server::projects::ServerProject;
+use db::server::rules::RuleSet;
 use db::server::summary::SummaryQueryBuilder;
+use db::server::tags::Tags;
 use serde_qs::axum::QsQuery;
 use std::sync::Arc;
 use tracing::info;
 use uuid::Uuid;
 
+/// Days before `end` searched for overlaps when no `start` is given
+const DEFAULT_OVERLAP_DAYS: i64 = 7;
+
 async fn insert_events(
     State(db): State<Arc<DBContext>>,
     Json(payload): Json<Vec<EventInput>>,
-) -> ServerResult<()> {
+) -> ServerResult<Json<IngestReport>> {
     info!("Handling {} events", payload.len());
 
-    let mut staged: Vec<Event> = Vec::with_capacity(payload.len());
-
-    for event in payload {
-        let app_id = App::find_or_insert(&db, &event.app_name).await?;
-        let project_id =
-            ServerProject::find_or_insert(&db, &event.project_name, &event.project_path).await?;
-        let branch_id = Branch::find_or_insert(&db, project_id, &event.branch_name).await?;
-        let entity_id =
-            Entity::find_or_insert(&db, project_id, &event.entity_name, &event.entity_type).await?;
-        let language_id = Language::find_or_insert(&db, &event.language_name).await?;
-        let category_id = Category::find_or_insert(&db, &event.category).await?;
-        let source_id = Source::find_or_insert(&db, &event.source_name).await?;
-
-        let key = format!(
-            "{}|{:?}|{:?}|{}|{}|{}|{}|{}|{}",
-            app_id,
-            entity_id,
-            project_id,
-            category_id,
-            source_id,
-            event.timestamp.unwrap_or_default().timestamp(),
-            event.end_timestamp.unwrap_or_default().timestamp(),
-            branch_id.unwrap_or_default(),
-            language_id.unwrap_or_default(),
-        );
-
-        let id = Uuid::new_v5(&Uuid::NAMESPACE_URL, key.as_bytes());
+    let now = Utc::now();
+    let mut tracker = IngestTracker::default();
+
+    let mut valid: Vec<(usize, &EventInput)> = Vec::with_capacity(payload.len());
+    for (index, event) in payload.iter().enumerate() {
+        match validate_event(event, now) {
+            Ok(()) => valid.push((index, event)),
+            Err(reason) => tracker.reject(index, Some(rejected_event_id(event)), reason),
+        }
+    }
+
+    let inputs: Vec<&EventInput> = valid.iter().map(|(_, event)| *event).collect();
+    // Ids are derived from the reported dimensions, so re-sent events stay duplicates
+    // whatever the rules make of them
+    let reported = resolve_dimensions(&db, &inputs).await?;
+
+    let rules = RuleSet::load(&db).await?;
+    let rewritten = rules.rewrite(&db, &inputs).await?;
+    let changed: Vec<&EventInput> = rewritten.iter().flatten().collect();
+    let mut changed_dimensions = resolve_dimensions(&db, &changed).await?.into_iter();
+
+    let mut staged: Vec<Event> = Vec::with_capacity(valid.len());
+    let mut tags: Vec<(Uuid, &[String])> = Vec::new();
+
+    for (((index, event), key_dims), rewritten) in valid.into_iter().zip(reported).zip(&rewritten) {
+        let timestamp = event.timestamp.unwrap_or_default().timestamp();
+        let end_timestamp = event.end_timestamp.map(|t| t.timestamp());
+        let id = Event::from_dimensions(&key_dims, timestamp, event.duration, end_timestamp).id;
+
+        // `changed_dimensions` follows the order of the rewritten events
+        let dims = rewritten
+            .as_ref()
+            .and_then(|_| changed_dimensions.next())
+            .unwrap_or(key_dims);
+
+        if !tracker.stage(index, id) {
+            continue;
+        }
+
+        let event_tags = rewritten.as_ref().map_or(&event.tags, |r| &r.tags);
+        if !event_tags.is_empty() {
+            tags.push((id, event_tags.as_slice()));
+        }
 
         staged.push(Event {
             id,
-            timestamp: event.timestamp.unwrap_or_default().timestamp(),
-            duration: event.duration,
-            category_id,
-            app_id,
-            entity_id: Some(entity_id),
-            project_id: Some(project_id),
-            branch_id,
-            language_id,
-            source_id,
-            end_timestamp: event.end_timestamp.map(|t| t.timestamp()),
+            ..Event::from_dimensions(&dims, timestamp, event.duration, end_timestamp)
         });
     }
 
-    staged.sort_by_key(|e| e.id);
-    staged.dedup_by_key(|e| e.id);
-
     let inserted = Event::bulk_create(&db, &staged).await?;
-    info!("Inserted {} events", inserted);
+    tags.retain(|(id, _)| inserted.contains(id));
+    Tags::tag_events(&db, &tags).await?;
+
+    let report = tracker.finish(&inserted);
+    info!(
+        "Inserted {} events ({} duplicate, {} rejected)",
+        report.accepted.len(),
+        report.duplicate.len(),
+        report.rejected.len()
+    );
 
-    Ok(())
+    Ok(Json(report))
 }
 
+/// Returns the events of the range as an `EventGroupResult`, or a single `EventPage`
+/// when `limit` or `after` is given
 async fn fetch_events(
     State(db): State<Arc<DBContext>>,
     QsQuery(payload): QsQuery<BucketSummaryInput>,
-) -> ServerResult<Json<EventGroupResult>> {
+    QsQuery(page): QsQuery<EventPageInput>,
+) -> ServerResult<Response> {
     let builder = SummaryQueryBuilder::from(payload);
+
+    if is_paginated(&page) {
+        if builder.filters.group_by.is_some() {
+            return Err(ServerError::BadRequest(
+                "groupBy cannot be combined with limit or after".to_string(),
+            ));
+        }
+
+        let (after, limit) = page_params(&page)?;
+        let page = builder.fetch_event_page(&db, after, limit).await?;
+        return Ok(Json(page).into_response());
+    }
+
     let result = builder.fetch_event_range(&db).await?;
+    Ok(Json(result).into_response())
+}
+
+/// Streams every event of the range as NDJSON, in chronological order
+async fn export_events(
+    State(db): State<Arc<DBContext>>,
+    QsQuery(payload): QsQuery<BucketSummaryInput>,
+) -> Response {
+    let builder = Arc::new(SummaryQueryBuilder::from(payload));
+
+    ndjson_response(move |after| {
+        let db = db.clone();
+        let builder = builder.clone();
+        async move { builder.fetch_event_page(&db, after, EXPORT_PAGE_SIZE).await }
+    })
+}
 
-    Ok(Json(result))
+async fn fetch_overlaps(
+    State(db): State<Arc<DBContext>>,
+    QsQuery(payload): QsQuery<OverlapQueryInput>,
+) -> ServerResult<Json<Vec<EventOverlap>>> {
+    let limit = payload.limit.unwrap_or(100).min(1000);
+    let end = payload.end.unwrap_or_else(Utc::now);
+    let start = payload
+        .start
+        .unwrap_or(end - Duration::days(DEFAULT_OVERLAP_DAYS));
+    if start >= end {
+        return Err(ServerError::BadRequest(
+            "Expected start before end".to_string(),
+        ));
+    }
+
+    let overlaps = Event::find_overlaps(&db, start.timestamp(), end.timestamp(), limit).await?;
+
+    Ok(Json(overlaps))
+}
+
+async fn fetch_event(
+    State(db): State<Arc<DBContext>>,
+    Path(id): Path<Uuid>,
+) -> ServerResult<Json<FullEvent>> {
+    let event = Event::find_by_id(&db, id)
+        .await?
+        .ok_or(ServerError::NotFound)?;
+    Ok(Json(event))
+}
+
+async fn update_event(
+    State(db): State<Arc<DBContext>>,
+    Path(id): Path<Uuid>,
+    Json(payload): Json<EventPatchInput>,
+) -> ServerResult<Json<FullEvent>> {
+    let event = Event::update(&db, id, payload)
+        .await?
+        .ok_or(ServerError::NotFound)?;
+    info!("Updated event {id}");
+    Ok(Json(event))
+}
+
+async fn delete_event(
+    State(db): State<Arc<DBContext>>,
+    Path(id): Path<Uuid>,
+) -> ServerResult<StatusCode> {
+    if !Event::delete(&db, id).await? {
+        return Err(ServerError::NotFound);
+    }
+
+    info!("Deleted event {id}");
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Deletes every event of the range matching the filters
+async fn delete_events(
+    State(db): State<Arc<DBContext>>,
+    QsQuery(payload): QsQuery<BucketSummaryInput>,
+) -> ServerResult<Json<DeleteReport>> {
+    let deleted = SummaryQueryBuilder::from(payload)
+        .delete_events(&db)
+        .await?;
+    info!("Deleted {deleted} events");
+    Ok(Json(DeleteReport { deleted }))
+}
+
+async fn split_event(
+    State(db): State<Arc<DBContext>>,
+    Path(id): Path<Uuid>,
+    Json(payload): Json<EventSplitInput>,
+) -> ServerResult<Json<Vec<FullEvent>>> {
+    let parts = Event::split(&db, id, payload.at)
+        .await?
+        .ok_or(ServerError::NotFound)?;
+    Ok(Json(parts))
+}
+
+async fn merge_events(
+    State(db): State<Arc<DBContext>>,
+    Json(payload): Json<EventMergeInput>,
+) -> ServerResult<Json<FullEvent>> {
+    let event = Event::merge(&db, &payload.ids)
+        .await?
+        .ok_or(ServerError::NotFound)?;
+    info!("Merged {} events into {}", payload.ids.len(), event.id);
+    Ok(Json(event))
 }
 
 pub fn event_routes(db: Arc<DBContext>) -> Router {
     Router::new()
         .route("/events", post(insert_events))
         .route("/events", get(fetch_events))
+        .route("/events", delete(delete_events))
+        .route("/events/overlaps", get(fetch_overlaps))
+        .route("/events/export", get(export_events))
+        .route("/events/merge", post(merge_events))
+        .route(
+            "/events/{id}",
+            get(fetch_event).patch(update_event).delete(delete_event),
+        )
+        .route("/events/{id}/split", post(split_event))
         .with_state(db)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use db::models::{App, Category, Source};
+
+    fn event() -> EventInput {
+        let start = Utc::now() - Duration::minutes(10);
+        EventInput {
+            timestamp: Some(start),
+            duration: Some(60),
+            category: "Coding".into(),
+            app_name: "Code".into(),
+            entity_name: "main.rs".into(),
+            entity_type: "File".into(),
+            project_name: "api".into(),
+            project_path: "/work/api".into(),
+            branch_name: Some("main".into()),
+            language_name: Some("Rust".into()),
+            source_name: "skopio-vscode".into(),
+            end_timestamp: Some(start - Duration::minutes(1)),
+            tags: Vec::new(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_rejected_events_resolve_no_dimensions() {
+        let db = Arc::new(DBContext::new("sqlite::memory:").await.unwrap());
+
+        let input = event();
+        let Json(report) = insert_events(State(db.clone()), Json(vec![input.clone()]))
+            .await
+            .unwrap();
+
+        assert!(report.accepted.is_empty());
+        assert_eq!(report.rejected.len(), 1);
+        assert_eq!(report.rejected[0].id, Some(rejected_event_id(&input)));
+
+        // A retry is reported under the same id
+        let Json(retry) = insert_events(State(db.clone()), Json(vec![input]))
+            .await
+            .unwrap();
+        assert_eq!(retry.rejected[0].id, report.rejected[0].id);
+
+        assert!(App::get_all(&db).await.unwrap().is_empty());
+        assert!(Category::get_all(&db).await.unwrap().is_empty());
+        assert!(Source::get_all(&db).await.unwrap().is_empty());
+    }
+}
@@ -1,36 +1,69 @@
 use std::sync::Arc;
 
-use axum::{
-    Json, Router,
-    extract::{Query, State},
-    routing::get,
-};
+use axum::{Json, Router, extract::State, routing::get};
+use chrono::Weekday;
 use common::{
     models::{inputs::InsightQueryPayload, outputs::InsightResult},
     time::insight::InsightRange,
 };
 use db::{
     DBContext,
-    server::insights::{InsightProvider, InsightQuery, Insights},
+    server::{
+        insights::{InsightProvider, InsightQuery, Insights},
+        utils::summary_filter::SummaryFilters,
+    },
 };
+use serde_qs::axum::QsQuery;
 
-use crate::error::ServerResult;
+use crate::error::{ServerError, ServerResult};
 
 pub async fn fetch_insight(
     State(db): State<Arc<DBContext>>,
-    Query(payload): Query<InsightQueryPayload>,
+    QsQuery(payload): QsQuery<InsightQueryPayload>,
 ) -> ServerResult<Json<InsightResult>> {
+    let week_start = payload.week_start.unwrap_or_default();
     let insight_range = match &payload.insight_range {
-        Some(s) => Some(InsightRange::try_from(s.clone())?),
+        Some(s) => Some(InsightRange::resolve(s, payload.timezone, week_start)?),
         None => None,
     };
 
+    let excluded_days = payload
+        .excluded_days
+        .unwrap_or_default()
+        .iter()
+        .map(|day| {
+            day.parse::<Weekday>()
+                .map_err(|_| ServerError::BadRequest(format!("Unknown weekday: {day}")))
+        })
+        .collect::<ServerResult<Vec<_>>>()?;
+
+    let filters = SummaryFilters {
+        apps: payload.apps,
+        projects: payload.projects,
+        categories: payload.categories,
+        entities: payload.entities,
+        branches: payload.branches,
+        languages: payload.languages,
+        sources: payload.sources,
+        entity_types: payload.entity_types,
+        tags: payload.tags,
+        ..Default::default()
+    };
+
     let query = InsightQuery {
         insight_type: payload.insight_type,
         insight_range,
         group_by: payload.group_by,
         limit: payload.limit,
         bucket: payload.bucket,
+        session_gap: payload.session_gap,
+        min_daily_minutes: payload.min_daily_minutes,
+        excluded_days,
+        metric: payload.metric.unwrap_or_default(),
+        filters,
+        exclude_afk: payload.exclude_afk.unwrap_or_default(),
+        timezone: payload.timezone,
+        week_start,
     };
 
     let result = Insights::execute(&db, query).await?;
@@ -0,0 +1,24 @@
+use std::sync::Arc;
+
+use axum::{Json, Router, extract::State, routing::get};
+use common::models::{Group, inputs::DimensionListQuery, outputs::DimensionPage};
+use db::{DBContext, server::usage::DimensionUsageQuery};
+use serde_qs::axum::QsQuery;
+
+use crate::error::ServerResult;
+
+async fn fetch_languages(
+    State(db): State<Arc<DBContext>>,
+    QsQuery(query): QsQuery<DimensionListQuery>,
+) -> ServerResult<Json<DimensionPage>> {
+    let page = DimensionUsageQuery::from(query)
+        .execute(&db, Group::Language, None)
+        .await?;
+    Ok(Json(page))
+}
+
+pub fn language_routes(db: Arc<DBContext>) -> Router {
+    Router::new()
+        .route("/languages", get(fetch_languages))
+        .with_state(db)
+}
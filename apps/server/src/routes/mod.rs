@@ -1,8 +1,15 @@
 pub mod afk_events;
 pub mod apps;
+pub mod archive;
+pub mod billing;
 pub mod categories;
 pub mod events;
 pub mod health;
 pub mod insights;
+pub mod languages;
 pub mod projects;
+pub mod rules;
+pub mod search;
+pub mod sources;
 pub mod summary;
+pub mod tags;
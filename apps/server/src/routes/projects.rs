@@ -3,16 +3,30 @@ use std::sync::Arc;
 use axum::{
     Json, Router,
     extract::{Path, Query, State},
-    routing::get,
+    routing::{get, post},
+};
+use common::models::{
+    Group, Project, Tag,
+    inputs::{
+        DimensionListQuery, ProjectListQuery, ProjectMergeInput, ProjectRenameInput,
+        ProjectStatsInput,
+    },
+    outputs::{DimensionPage, PaginatedProjects, ProjectStats},
 };
-use common::models::{Project, inputs::ProjectListQuery, outputs::PaginatedProjects};
 use db::{
     DBContext,
-    server::projects::{ServerProject, cursor::ProjectCursor},
+    server::{
+        projects::{ServerProject, cursor::ProjectCursor},
+        summary::SummaryQueryBuilder,
+        tags::Tags,
+        usage::DimensionUsageQuery,
+    },
 };
+use serde_qs::axum::QsQuery;
+use tracing::info;
 use uuid::Uuid;
 
-use crate::error::ServerResult;
+use crate::error::{ServerError, ServerResult};
 
 pub async fn get_projects(
     State(db): State<Arc<DBContext>>,
@@ -48,9 +62,127 @@ pub async fn get_project_by_id(
     Ok(Json(project))
 }
 
+async fn get_project_stats(
+    State(db): State<Arc<DBContext>>,
+    Path(id): Path<Uuid>,
+    QsQuery(payload): QsQuery<ProjectStatsInput>,
+) -> ServerResult<Json<ProjectStats>> {
+    let project = ServerProject::find_by_id(&db, id)
+        .await?
+        .ok_or(ServerError::NotFound)?;
+    let entity_limit = payload.entity_limit.unwrap_or(10);
+
+    let stats = SummaryQueryBuilder::from(payload)
+        .execute_project_stats(&db, project, entity_limit)
+        .await?;
+    Ok(Json(stats))
+}
+
+async fn get_project_branches(
+    State(db): State<Arc<DBContext>>,
+    Path(id): Path<Uuid>,
+    QsQuery(query): QsQuery<DimensionListQuery>,
+) -> ServerResult<Json<DimensionPage>> {
+    list_project_dimension(&db, id, Group::Branch, query).await
+}
+
+async fn get_project_entities(
+    State(db): State<Arc<DBContext>>,
+    Path(id): Path<Uuid>,
+    QsQuery(query): QsQuery<DimensionListQuery>,
+) -> ServerResult<Json<DimensionPage>> {
+    list_project_dimension(&db, id, Group::Entity, query).await
+}
+
+async fn list_project_dimension(
+    db: &DBContext,
+    id: Uuid,
+    group: Group,
+    query: DimensionListQuery,
+) -> ServerResult<Json<DimensionPage>> {
+    ServerProject::find_by_id(db, id)
+        .await?
+        .ok_or(ServerError::NotFound)?;
+
+    let page = DimensionUsageQuery::from(query)
+        .execute(db, group, Some(id))
+        .await?;
+    Ok(Json(page))
+}
+
+async fn get_project_tags(
+    State(db): State<Arc<DBContext>>,
+    Path(id): Path<Uuid>,
+) -> ServerResult<Json<Vec<Tag>>> {
+    ServerProject::find_by_id(&db, id)
+        .await?
+        .ok_or(ServerError::NotFound)?;
+
+    let tags = Tags::for_project(&db, id).await?;
+    Ok(Json(tags))
+}
+
+/// Replaces the tags of a project. Every event of the project carries them.
+async fn set_project_tags(
+    State(db): State<Arc<DBContext>>,
+    Path(id): Path<Uuid>,
+    Json(payload): Json<Vec<String>>,
+) -> ServerResult<Json<Vec<Tag>>> {
+    ServerProject::find_by_id(&db, id)
+        .await?
+        .ok_or(ServerError::NotFound)?;
+
+    let tags = Tags::set_for_project(&db, id, &payload).await?;
+    info!("Set {} tags on project {id}", tags.len());
+    Ok(Json(tags))
+}
+
+async fn rename_project(
+    State(db): State<Arc<DBContext>>,
+    Path(id): Path<Uuid>,
+    Json(payload): Json<ProjectRenameInput>,
+) -> ServerResult<Json<Project>> {
+    let name = payload.name.trim();
+    if name.is_empty() {
+        return Err(ServerError::BadRequest(
+            "Project name cannot be empty".to_string(),
+        ));
+    }
+
+    let project = ServerProject::rename(&db, id, name)
+        .await?
+        .ok_or(ServerError::NotFound)?;
+    info!("Renamed project {id} to {name}");
+    Ok(Json(project))
+}
+
+async fn merge_projects(
+    State(db): State<Arc<DBContext>>,
+    Json(payload): Json<ProjectMergeInput>,
+) -> ServerResult<Json<Project>> {
+    let project = ServerProject::merge(&db, payload.target, &payload.sources)
+        .await?
+        .ok_or(ServerError::NotFound)?;
+    info!(
+        "Merged {} projects into {}",
+        payload.sources.len(),
+        payload.target
+    );
+    Ok(Json(project))
+}
+
 pub fn project_routes(db: Arc<DBContext>) -> Router {
     Router::new()
         .route("/projects", get(get_projects))
+        .route("/projects/merge", post(merge_projects))
         .route("/projects/{id}", get(get_project_by_id))
+        .route("/projects/{id}/rename", post(rename_project))
+        .route("/projects/{id}/stats", get(get_project_stats))
+        .route("/projects/{id}/branches", get(get_project_branches))
+        .route("/projects/{id}/entities", get(get_project_entities))
+        .route(
+            "/projects/{id}/tags",
+            get(get_project_tags).put(set_project_tags),
+        )
         .with_state(db)
 }
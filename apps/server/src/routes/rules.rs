@@ -0,0 +1,101 @@
+use std::sync::Arc;
+
+use axum::{
+    Json, Router,
+    extract::{Path, State},
+    http::StatusCode,
+    routing::{get, post, put},
+};
+use common::{
+    models::{
+        Rule,
+        inputs::{RuleApplyInput, RuleInput},
+        outputs::RuleApplyReport,
+    },
+    time::TimeRange,
+};
+use db::{
+    DBContext,
+    server::rules::{RuleSet, Rules},
+};
+use tracing::info;
+use uuid::Uuid;
+
+use crate::error::{ServerError, ServerResult};
+
+async fn fetch_rules(State(db): State<Arc<DBContext>>) -> ServerResult<Json<Vec<Rule>>> {
+    let rules = Rules::all(&db).await?;
+    Ok(Json(rules))
+}
+
+async fn create_rule(
+    State(db): State<Arc<DBContext>>,
+    Json(payload): Json<RuleInput>,
+) -> ServerResult<Json<Rule>> {
+    let rule = Rules::create(&db, payload).await?;
+    info!("Created rule {} ({})", rule.name, rule.id);
+    Ok(Json(rule))
+}
+
+async fn update_rule(
+    State(db): State<Arc<DBContext>>,
+    Path(id): Path<Uuid>,
+    Json(payload): Json<RuleInput>,
+) -> ServerResult<Json<Rule>> {
+    let rule = Rules::update(&db, id, payload)
+        .await?
+        .ok_or(ServerError::NotFound)?;
+    info!("Updated rule {id}");
+    Ok(Json(rule))
+}
+
+async fn delete_rule(
+    State(db): State<Arc<DBContext>>,
+    Path(id): Path<Uuid>,
+) -> ServerResult<StatusCode> {
+    if !Rules::delete(&db, id).await? {
+        return Err(ServerError::NotFound);
+    }
+
+    info!("Deleted rule {id}");
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Re-runs the enabled rules over the events of a range, or previews the changes
+/// with `dryRun`
+async fn apply_rules(
+    State(db): State<Arc<DBContext>>,
+    Json(payload): Json<RuleApplyInput>,
+) -> ServerResult<Json<RuleApplyReport>> {
+    let range = TimeRange::resolve(
+        payload.preset,
+        payload.timezone,
+        payload.week_start.unwrap_or_default(),
+    );
+    let dry_run = payload.dry_run.unwrap_or(false);
+
+    let report = RuleSet::load(&db)
+        .await?
+        .apply_to_range(
+            &db,
+            range.start().timestamp(),
+            range.end().timestamp(),
+            dry_run,
+        )
+        .await?;
+    if !dry_run {
+        info!(
+            "Rules changed {} of {} events",
+            report.changed, report.scanned
+        );
+    }
+    Ok(Json(report))
+}
+
+pub fn rule_routes(db: Arc<DBContext>) -> Router {
+    Router::new()
+        .route("/rules", get(fetch_rules).post(create_rule))
+        .route("/rules/apply", post(apply_rules))
+        .route("/rules/{id}", put(update_rule).delete(delete_rule))
+        .with_state(db)
+}
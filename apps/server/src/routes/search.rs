@@ -0,0 +1,26 @@
+use std::sync::Arc;
+
+use axum::{Json, Router, extract::State, routing::get};
+use common::models::{inputs::SearchInput, outputs::SearchResult};
+use db::{DBContext, server::search::SearchQuery};
+use serde_qs::axum::QsQuery;
+
+use crate::error::{ServerError, ServerResult};
+
+async fn search(
+    State(db): State<Arc<DBContext>>,
+    QsQuery(input): QsQuery<SearchInput>,
+) -> ServerResult<Json<Vec<SearchResult>>> {
+    if input.q.trim().is_empty() {
+        return Err(ServerError::BadRequest(
+            "Search query cannot be empty".to_string(),
+        ));
+    }
+
+    let results = SearchQuery::from(input).execute(&db).await?;
+    Ok(Json(results))
+}
+
+pub fn search_routes(db: Arc<DBContext>) -> Router {
+    Router::new().route("/search", get(search)).with_state(db)
+}
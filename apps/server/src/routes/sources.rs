@@ -0,0 +1,46 @@
+use std::sync::Arc;
+
+use axum::{
+    Json, Router,
+    extract::{Path, State},
+    http::StatusCode,
+    routing::{get, put},
+};
+use common::models::{
+    Group,
+    inputs::{DimensionListQuery, SourcePriorityInput},
+    outputs::DimensionPage,
+};
+use db::{DBContext, models::Source, server::usage::DimensionUsageQuery};
+use serde_qs::axum::QsQuery;
+
+use crate::error::{ServerError, ServerResult};
+
+async fn fetch_sources(
+    State(db): State<Arc<DBContext>>,
+    QsQuery(query): QsQuery<DimensionListQuery>,
+) -> ServerResult<Json<DimensionPage>> {
+    let page = DimensionUsageQuery::from(query)
+        .execute(&db, Group::Source, None)
+        .await?;
+    Ok(Json(page))
+}
+
+async fn update_source_priority(
+    State(db): State<Arc<DBContext>>,
+    Path(name): Path<String>,
+    Json(payload): Json<SourcePriorityInput>,
+) -> ServerResult<StatusCode> {
+    if !Source::set_priority(&db, &name, payload.priority).await? {
+        return Err(ServerError::NotFound);
+    }
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+pub fn source_routes(db: Arc<DBContext>) -> Router {
+    Router::new()
+        .route("/sources", get(fetch_sources))
+        .route("/sources/{name}/priority", put(update_source_priority))
+        .with_state(db)
+}
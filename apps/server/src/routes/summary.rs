@@ -1,11 +1,17 @@
 use std::sync::Arc;
 
 use axum::{Json, Router, extract::State, routing::get};
-use common::models::inputs::{BucketSummaryInput, SummaryQueryInput};
+use common::{
+    models::{
+        inputs::{BucketSummaryInput, SummaryCompareInput, SummaryQueryInput},
+        outputs::SummaryComparison,
+    },
+    time::TimeRange,
+};
 use db::{DBContext, models::BucketTimeSummary, server::summary::SummaryQueryBuilder};
 use serde_qs::axum::QsQuery;
 
-use crate::error::ServerResult;
+use crate::error::{ServerError, ServerResult};
 
 pub async fn total_time_handler(
     State(db): State<Arc<DBContext>>,
@@ -28,9 +34,57 @@ pub async fn get_bucketed_summary(
     Ok(Json(records))
 }
 
+pub async fn compare_summary(
+    State(db): State<Arc<DBContext>>,
+    QsQuery(payload): QsQuery<SummaryCompareInput>,
+) -> ServerResult<Json<SummaryComparison>> {
+    let week_start = payload.week_start.unwrap_or_default();
+
+    let (start, end) = match (&payload.preset, payload.start, payload.end) {
+        (Some(preset), None, None) => {
+            let range = TimeRange::resolve(preset.clone(), payload.timezone, week_start);
+            (range.start, range.end)
+        }
+        (None, Some(start), Some(end)) if start < end => (start, end),
+        _ => {
+            return Err(ServerError::BadRequest(
+                "Expected either a preset or a start before an end".to_string(),
+            ));
+        }
+    };
+
+    let (previous_start, previous_end) = match (payload.previous_start, payload.previous_end) {
+        (Some(previous_start), Some(previous_end)) if previous_start < previous_end => {
+            (previous_start, previous_end)
+        }
+        (None, None) => match &payload.preset {
+            Some(preset) => {
+                let range = TimeRange::previous(preset.clone(), payload.timezone, week_start);
+                (range.start, range.end)
+            }
+            None => (start - (end - start), start),
+        },
+        _ => {
+            return Err(ServerError::BadRequest(
+                "Expected both previousStart and previousEnd, with previousStart first".to_string(),
+            ));
+        }
+    };
+
+    let builder = SummaryQueryBuilder::from(payload)
+        .start(start.timestamp())
+        .end(end.timestamp());
+    let comparison = builder
+        .execute_comparison(&db, previous_start, previous_end)
+        .await?;
+
+    Ok(Json(comparison))
+}
+
 pub fn summary_routes(db: Arc<DBContext>) -> Router {
     Router::new()
         .route("/summary/total", get(total_time_handler))
         .route("/summary/buckets", get(get_bucketed_summary))
+        .route("/summary/compare", get(compare_summary))
         .with_state(db)
 }
@@ -0,0 +1,87 @@
+use std::sync::Arc;
+
+use axum::{
+    Json, Router,
+    extract::{Path, State},
+    http::StatusCode,
+    routing::{get, put},
+};
+use common::models::{
+    Group, Tag,
+    inputs::{DimensionListQuery, TagInput},
+    outputs::DimensionPage,
+};
+use db::{
+    DBContext,
+    server::{tags::Tags, usage::DimensionUsageQuery},
+};
+use serde_qs::axum::QsQuery;
+use tracing::info;
+use uuid::Uuid;
+
+use crate::error::{ServerError, ServerResult};
+
+async fn fetch_tags(
+    State(db): State<Arc<DBContext>>,
+    QsQuery(query): QsQuery<DimensionListQuery>,
+) -> ServerResult<Json<DimensionPage>> {
+    let page = DimensionUsageQuery::from(query)
+        .execute(&db, Group::Tag, None)
+        .await?;
+    Ok(Json(page))
+}
+
+async fn create_tag(
+    State(db): State<Arc<DBContext>>,
+    Json(payload): Json<TagInput>,
+) -> ServerResult<Json<Tag>> {
+    if let Some(tag) = Tags::find_by_name(&db, &payload.name).await? {
+        return Err(ServerError::Conflict(format!(
+            "Tag {} already exists",
+            tag.name
+        )));
+    }
+
+    let tag = Tags::create(&db, &payload.name).await?;
+    info!("Created tag {} ({})", tag.name, tag.id);
+    Ok(Json(tag))
+}
+
+async fn rename_tag(
+    State(db): State<Arc<DBContext>>,
+    Path(id): Path<Uuid>,
+    Json(payload): Json<TagInput>,
+) -> ServerResult<Json<Tag>> {
+    let existing = Tags::find_by_name(&db, &payload.name).await?;
+    if let Some(tag) = existing.filter(|tag| tag.id != id) {
+        return Err(ServerError::Conflict(format!(
+            "Tag {} already exists",
+            tag.name
+        )));
+    }
+
+    let tag = Tags::rename(&db, id, &payload.name)
+        .await?
+        .ok_or(ServerError::NotFound)?;
+    info!("Renamed tag {id} to {}", tag.name);
+    Ok(Json(tag))
+}
+
+async fn delete_tag(
+    State(db): State<Arc<DBContext>>,
+    Path(id): Path<Uuid>,
+) -> ServerResult<StatusCode> {
+    if !Tags::delete(&db, id).await? {
+        return Err(ServerError::NotFound);
+    }
+
+    info!("Deleted tag {id}");
+    Ok(StatusCode::NO_CONTENT)
+}
+
+pub fn tag_routes(db: Arc<DBContext>) -> Router {
+    Router::new()
+        .route("/tags", get(fetch_tags).post(create_tag))
+        .route("/tags/{id}", put(rename_tag).delete(delete_tag))
+        .with_state(db)
+}
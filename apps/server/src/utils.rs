@@ -18,6 +18,16 @@ pub fn get_db_path() -> PathBuf {
         .join(get_db_name())
 }
 
+/// Quotes a CSV field if it contains a delimiter, quote or line break. The value is
+/// kept as is otherwise, so it reads back unchanged.
+pub fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
 pub fn init_tracing() -> anyhow::Result<()> {
     let level = if cfg!(debug_assertions) {
         "debug"
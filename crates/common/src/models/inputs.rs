@@ -1,13 +1,17 @@
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
+use uuid::Uuid;
 
 use crate::{
-    models::{Group, InsightBucket, InsightType},
-    time::TimeRangePreset,
+    models::{
+        ArchiveFormat, DimensionSort, Group, HeatmapMetric, InsightBucket, InsightType,
+        InvoiceFormat, InvoiceLines, InvoiceRounding, RuleActions, RuleConditions,
+    },
+    time::{TimeRangePreset, Tz, WeekStart},
 };
 
 /// Input payload for inserting a new event.
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct EventInput {
     pub timestamp: Option<DateTime<Utc>>,
     pub duration: Option<i64>,
@@ -21,6 +25,9 @@ pub struct EventInput {
     pub language_name: Option<String>,
     pub source_name: String,
     pub end_timestamp: Option<DateTime<Utc>>,
+    /// Free-form labels, e.g. the client the work is billed to
+    #[serde(default)]
+    pub tags: Vec<String>,
 }
 
 /// Input payload for AFK (Away From Keyboard) events
@@ -31,7 +38,11 @@ pub struct AFKEventInput {
     pub duration: Option<i64>,
 }
 
-/// Query input for requesting summaries over a range of time
+/// Query input for requesting summaries over a range of time.
+///
+/// Each dimension list matches exact values, GLOB patterns (e.g. `src/**/*.rs`, where
+/// `**/` matches zero or more directories) and excludes values prefixed with `!`
+/// (e.g. `!scratch-*`).
 #[derive(Debug, Serialize, Deserialize, specta::Type)]
 #[serde(rename_all = "camelCase")]
 pub struct SummaryQueryInput {
@@ -57,9 +68,30 @@ pub struct SummaryQueryInput {
     #[specta(optional)]
     #[serde(skip_serializing_if = "Option::is_none")]
     pub languages: Option<Vec<String>>,
+    #[specta(optional)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sources: Option<Vec<String>>,
+    /// Entity types (e.g. "File", "Url")
+    #[specta(optional)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub entity_types: Option<Vec<String>>,
+    /// Tags of the event or its project
+    #[specta(optional)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tags: Option<Vec<String>>,
+    /// Subtract time that overlaps AFK periods
+    #[specta(optional)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub exclude_afk: Option<bool>,
+    /// IANA timezone (e.g. "Europe/Berlin") that calendar boundaries and bucket labels
+    /// are computed in. Defaults to the server's local timezone.
+    #[specta(optional, type = Option<String>)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub timezone: Option<Tz>,
 }
 
-/// Query input for bucketed summaries (based on a preset time range)
+/// Query input for bucketed summaries (based on a preset time range).
+/// Dimension lists behave as in [`SummaryQueryInput`].
 #[derive(Debug, Serialize, Deserialize, specta::Type)]
 #[serde(rename_all = "camelCase")]
 pub struct BucketSummaryInput {
@@ -84,7 +116,108 @@ pub struct BucketSummaryInput {
     pub languages: Option<Vec<String>>,
     #[specta(optional)]
     #[serde(skip_serializing_if = "Option::is_none")]
+    pub sources: Option<Vec<String>>,
+    /// Entity types (e.g. "File", "Url")
+    #[specta(optional)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub entity_types: Option<Vec<String>>,
+    /// Tags of the event or its project
+    #[specta(optional)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tags: Option<Vec<String>>,
+    #[specta(optional)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub group_by: Option<Group>,
+    /// Further dimensions splitting each `groupBy` group, in order (e.g. `language`
+    /// for time per language inside each project)
+    #[specta(optional)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub subgroup_by: Option<Vec<Group>>,
+    /// Subtract time that overlaps AFK periods
+    #[specta(optional)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub exclude_afk: Option<bool>,
+    /// IANA timezone (e.g. "Europe/Berlin") that calendar boundaries and bucket labels
+    /// are computed in. Defaults to the server's local timezone.
+    #[specta(optional, type = Option<String>)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub timezone: Option<Tz>,
+    /// First day of the week for week presets, buckets and "yyyy-W##" ranges.
+    /// Defaults to Monday.
+    #[specta(optional)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub week_start: Option<WeekStart>,
+}
+
+/// Query input for comparing per-group totals of two periods.
+///
+/// The current period is either `preset` or `start`/`end`. The previous period is
+/// `previousStart`/`previousEnd`, or the period of the same length right before the
+/// current one when those are omitted.
+#[derive(Debug, Serialize, Deserialize, specta::Type)]
+#[serde(rename_all = "camelCase")]
+pub struct SummaryCompareInput {
+    #[specta(optional)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub preset: Option<TimeRangePreset>,
+    #[specta(optional)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub start: Option<DateTime<Utc>>,
+    #[specta(optional)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub end: Option<DateTime<Utc>>,
+    #[specta(optional)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub previous_start: Option<DateTime<Utc>>,
+    #[specta(optional)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub previous_end: Option<DateTime<Utc>>,
+    #[specta(optional)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub apps: Option<Vec<String>>,
+    #[specta(optional)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub projects: Option<Vec<String>>,
+    #[specta(optional)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub entities: Option<Vec<String>>,
+    #[specta(optional)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub categories: Option<Vec<String>>,
+    #[specta(optional)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub branches: Option<Vec<String>>,
+    #[specta(optional)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub languages: Option<Vec<String>>,
+    #[specta(optional)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sources: Option<Vec<String>>,
+    /// Entity types (e.g. "File", "Url")
+    #[specta(optional)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub entity_types: Option<Vec<String>>,
+    /// Tags of the event or its project
+    #[specta(optional)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tags: Option<Vec<String>>,
+    #[specta(optional)]
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub group_by: Option<Group>,
+    /// Subtract time that overlaps AFK periods
+    #[specta(optional)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub exclude_afk: Option<bool>,
+    /// IANA timezone (e.g. "Europe/Berlin") that calendar boundaries and bucket labels
+    /// are computed in. Defaults to the server's local timezone.
+    #[specta(optional, type = Option<String>)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub timezone: Option<Tz>,
+    /// First day of the week for week presets, buckets and "yyyy-W##" ranges.
+    /// Defaults to Monday.
+    #[specta(optional)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub week_start: Option<WeekStart>,
 }
 
 #[derive(Serialize, Deserialize, Debug, specta::Type)]
@@ -98,6 +231,299 @@ pub struct ProjectListQuery {
     pub query: Option<String>,
 }
 
+/// New display name of a project
+#[derive(Serialize, Deserialize, Debug, specta::Type)]
+#[serde(rename_all = "camelCase")]
+pub struct ProjectRenameInput {
+    pub name: String,
+}
+
+/// Merges the `sources` projects into `target`
+#[derive(Serialize, Deserialize, Debug, specta::Type)]
+#[serde(rename_all = "camelCase")]
+pub struct ProjectMergeInput {
+    pub target: Uuid,
+    pub sources: Vec<Uuid>,
+}
+
+/// Query input for the statistics of a single project
+#[derive(Serialize, Deserialize, Debug, specta::Type)]
+#[serde(rename_all = "camelCase")]
+pub struct ProjectStatsInput {
+    pub preset: TimeRangePreset,
+    /// Number of entities listed in `topEntities`. Defaults to 10.
+    #[specta(optional)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub entity_limit: Option<usize>,
+    /// Subtract time that overlaps AFK periods
+    #[specta(optional)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub exclude_afk: Option<bool>,
+    /// IANA timezone (e.g. "Europe/Berlin") that calendar boundaries and days are
+    /// computed in. Defaults to the server's local timezone.
+    #[specta(optional, type = Option<String>)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub timezone: Option<Tz>,
+    /// First day of the week for week presets. Defaults to Monday.
+    #[specta(optional)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub week_start: Option<WeekStart>,
+}
+
+/// Query input for listing languages, sources, branches or entities.
+///
+/// With a `preset`, each row carries its total time and last use within that range.
+#[derive(Serialize, Deserialize, Debug, Default, specta::Type)]
+#[serde(rename_all = "camelCase")]
+pub struct DimensionListQuery {
+    #[specta(optional)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub preset: Option<TimeRangePreset>,
+    /// Defaults to `name`. Sorting by usage requires a `preset`.
+    #[specta(optional)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sort: Option<DimensionSort>,
+    #[specta(optional)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub limit: Option<u32>,
+    /// Number of rows to skip
+    #[specta(optional)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub offset: Option<u32>,
+    /// Subtract time that overlaps AFK periods
+    #[specta(optional)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub exclude_afk: Option<bool>,
+    /// IANA timezone (e.g. "Europe/Berlin") that calendar boundaries are computed in.
+    /// Defaults to the server's local timezone.
+    #[specta(optional, type = Option<String>)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub timezone: Option<Tz>,
+    /// First day of the week for week presets. Defaults to Monday.
+    #[specta(optional)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub week_start: Option<WeekStart>,
+}
+
+/// Query input for searching projects, entities, branches and apps by name
+#[derive(Serialize, Deserialize, Debug, specta::Type)]
+#[serde(rename_all = "camelCase")]
+pub struct SearchInput {
+    /// Words to look for. Each word matches the start of a word in the name.
+    pub q: String,
+    /// Kinds of results to include: `project`, `entity`, `branch` and `app`.
+    /// Defaults to all of them.
+    #[specta(optional)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub kinds: Option<Vec<Group>>,
+    /// Range that `totalSeconds` is computed over. Defaults to all time.
+    #[specta(optional)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub preset: Option<TimeRangePreset>,
+    #[specta(optional)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub limit: Option<u32>,
+    /// Subtract time that overlaps AFK periods
+    #[specta(optional)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub exclude_afk: Option<bool>,
+    /// IANA timezone (e.g. "Europe/Berlin") that calendar boundaries are computed in.
+    /// Defaults to the server's local timezone.
+    #[specta(optional, type = Option<String>)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub timezone: Option<Tz>,
+    /// First day of the week for week presets. Defaults to Monday.
+    #[specta(optional)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub week_start: Option<WeekStart>,
+}
+
+/// A tag to create, or the new name of an existing one
+#[derive(Serialize, Deserialize, Debug, specta::Type)]
+#[serde(rename_all = "camelCase")]
+pub struct TagInput {
+    pub name: String,
+}
+
+/// A recategorization rule to create, or to replace an existing one with
+#[derive(Serialize, Deserialize, Debug, specta::Type)]
+#[serde(rename_all = "camelCase")]
+pub struct RuleInput {
+    pub name: String,
+    /// Rules with a higher priority are evaluated first. Defaults to 0.
+    #[specta(optional)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub priority: Option<i64>,
+    /// Defaults to `true`
+    #[specta(optional)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub enabled: Option<bool>,
+    pub conditions: RuleConditions,
+    pub actions: RuleActions,
+}
+
+/// Re-runs the enabled rules over the events of a range
+#[derive(Serialize, Deserialize, Debug, specta::Type)]
+#[serde(rename_all = "camelCase")]
+pub struct RuleApplyInput {
+    pub preset: TimeRangePreset,
+    /// Report the changes without storing them
+    #[specta(optional)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub dry_run: Option<bool>,
+    /// IANA timezone (e.g. "Europe/Berlin") that calendar boundaries are computed in.
+    /// Defaults to the server's local timezone.
+    #[specta(optional, type = Option<String>)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub timezone: Option<Tz>,
+    /// First day of the week for week presets. Defaults to Monday.
+    #[specta(optional)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub week_start: Option<WeekStart>,
+}
+
+/// Billing settings of a project, tag or category
+#[derive(Serialize, Deserialize, Debug, specta::Type)]
+#[serde(rename_all = "camelCase")]
+pub struct BillingRateInput {
+    /// Hourly rate. Without one, the rate of the invoiced project or tag applies.
+    #[specta(optional)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hourly_rate: Option<f64>,
+    /// Whether the time is billed at all. Defaults to `true`.
+    #[specta(optional)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub billable: Option<bool>,
+}
+
+/// Query input for an invoice over a range. Needs a `project`, a `tag` or both.
+#[derive(Serialize, Deserialize, Debug, specta::Type)]
+#[serde(rename_all = "camelCase")]
+pub struct InvoiceInput {
+    pub preset: TimeRangePreset,
+    #[specta(optional)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub project: Option<Uuid>,
+    /// Name of a tag. Only events carrying it are invoiced.
+    #[specta(optional)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tag: Option<String>,
+    /// Defaults to `day`
+    #[specta(optional)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub lines: Option<InvoiceLines>,
+    /// Rounds billed time up to a multiple of this many minutes, e.g. 6, 15 or 30
+    #[specta(optional)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub round_minutes: Option<u32>,
+    /// Defaults to `line`
+    #[specta(optional)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub round_each: Option<InvoiceRounding>,
+    /// Subtract time that overlaps AFK periods
+    #[specta(optional)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub exclude_afk: Option<bool>,
+    /// IANA timezone (e.g. "Europe/Berlin") that days are computed in.
+    /// Defaults to the server's local timezone.
+    #[specta(optional, type = Option<String>)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub timezone: Option<Tz>,
+    /// First day of the week for week presets. Defaults to Monday.
+    #[specta(optional)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub week_start: Option<WeekStart>,
+    /// Defaults to `json`
+    #[specta(optional)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub format: Option<InvoiceFormat>,
+}
+
+/// Format of a data archive written by `/export` or read by `/import`
+#[derive(Serialize, Deserialize, Debug, Default, specta::Type)]
+#[serde(rename_all = "camelCase")]
+pub struct ArchiveInput {
+    /// Defaults to `ndjson`
+    #[specta(optional)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub format: Option<ArchiveFormat>,
+}
+
+/// Keyset pagination over events ordered by start time and id
+#[derive(Serialize, Deserialize, Debug, Default, specta::Type)]
+#[serde(rename_all = "camelCase")]
+pub struct EventPageInput {
+    /// The `nextCursor` of the previous page
+    #[specta(optional)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub after: Option<String>,
+    #[specta(optional)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub limit: Option<u32>,
+}
+
+/// Changes applied to a stored event. Omitted fields keep their current value.
+///
+/// Moving an event to another project keeps its entity and branch names, scoped to
+/// the new project.
+#[derive(Serialize, Deserialize, Debug, Default, specta::Type)]
+#[serde(rename_all = "camelCase")]
+pub struct EventPatchInput {
+    #[specta(optional)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub category: Option<String>,
+    #[specta(optional)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub project_name: Option<String>,
+    /// Root path stored when `project_name` names a project that does not exist yet
+    #[specta(optional)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub project_path: Option<String>,
+    #[specta(optional)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub entity_name: Option<String>,
+    #[specta(optional)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub entity_type: Option<String>,
+    /// Replaces the event's own tags. Tags of its project are not affected.
+    #[specta(optional)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tags: Option<Vec<String>>,
+}
+
+/// Splits an event in two at `at`, which must fall strictly inside the event
+#[derive(Serialize, Deserialize, Debug, specta::Type)]
+#[serde(rename_all = "camelCase")]
+pub struct EventSplitInput {
+    pub at: DateTime<Utc>,
+}
+
+/// Merges events of the same activity into the earliest of them
+#[derive(Serialize, Deserialize, Debug, specta::Type)]
+#[serde(rename_all = "camelCase")]
+pub struct EventMergeInput {
+    pub ids: Vec<Uuid>,
+}
+
+/// Query input for listing overlapping events from different sources
+#[derive(Serialize, Deserialize, Debug, specta::Type)]
+#[serde(rename_all = "camelCase")]
+pub struct OverlapQueryInput {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub start: Option<DateTime<Utc>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub end: Option<DateTime<Utc>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub limit: Option<u32>,
+}
+
+/// Overlap priority of a source. `None` restores the default policy.
+#[derive(Serialize, Deserialize, Debug, specta::Type)]
+#[serde(rename_all = "camelCase")]
+pub struct SourcePriorityInput {
+    pub priority: Option<i64>,
+}
+
 /// Query payload for insights
 #[derive(Serialize, Deserialize, Debug, specta::Type)]
 #[serde(rename_all = "camelCase")]
@@ -115,4 +541,63 @@ pub struct InsightQueryPayload {
     #[specta(optional)]
     #[serde(skip_serializing_if = "Option::is_none")]
     pub bucket: Option<InsightBucket>,
+    /// Longest break in seconds that still continues a session (`sessions` only)
+    #[specta(optional)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub session_gap: Option<i64>,
+    /// Minutes of activity a day needs to count towards a streak (`streak` only)
+    #[specta(optional)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub min_daily_minutes: Option<i64>,
+    /// Weekdays (e.g. "saturday") that neither count towards nor break a streak (`streak` only)
+    #[specta(optional)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub excluded_days: Option<Vec<String>>,
+    /// Whether heatmap cells hold total or average seconds (`hourlyHeatmap` only)
+    #[specta(optional)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub metric: Option<HeatmapMetric>,
+    #[specta(optional)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub apps: Option<Vec<String>>,
+    #[specta(optional)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub projects: Option<Vec<String>>,
+    #[specta(optional)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub categories: Option<Vec<String>>,
+    #[specta(optional)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub entities: Option<Vec<String>>,
+    #[specta(optional)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub branches: Option<Vec<String>>,
+    #[specta(optional)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub languages: Option<Vec<String>>,
+    #[specta(optional)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sources: Option<Vec<String>>,
+    /// Entity types (e.g. "File", "Url")
+    #[specta(optional)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub entity_types: Option<Vec<String>>,
+    /// Tags of the event or its project
+    #[specta(optional)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tags: Option<Vec<String>>,
+    /// Subtract time that overlaps AFK periods
+    #[specta(optional)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub exclude_afk: Option<bool>,
+    /// IANA timezone (e.g. "Europe/Berlin") that calendar boundaries and bucket labels
+    /// are computed in. Defaults to the server's local timezone.
+    #[specta(optional, type = Option<String>)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub timezone: Option<Tz>,
+    /// First day of the week for week presets, buckets and "yyyy-W##" ranges.
+    /// Defaults to Monday.
+    #[specta(optional)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub week_start: Option<WeekStart>,
 }
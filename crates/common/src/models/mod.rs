@@ -12,7 +12,7 @@ pub enum InsightBucket {
     Year,
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone, Copy, specta::Type)]
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, specta::Type)]
 #[serde(rename_all = "camelCase")]
 pub enum Group {
     App,
@@ -22,6 +22,7 @@ pub enum Group {
     Category,
     Entity,
     Source,
+    Tag,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone, Copy, specta::Type)]
@@ -31,6 +32,33 @@ pub enum InsightType {
     TopN,
     MostActiveDay,
     AggregatedAverage,
+    Sessions,
+    Streak,
+    HourlyHeatmap,
+}
+
+/// How the cells of an hourly heatmap are aggregated
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, specta::Type)]
+#[serde(rename_all = "camelCase")]
+pub enum HeatmapMetric {
+    /// Total active seconds in each weekday and hour
+    #[default]
+    Total,
+    /// Active seconds averaged over the occurrences of each weekday and hour in the range
+    Average,
+}
+
+/// Order of dimension listings
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, specta::Type)]
+#[serde(rename_all = "camelCase")]
+pub enum DimensionSort {
+    /// Alphabetical, ignoring case
+    #[default]
+    Name,
+    /// Most time in the range first
+    TotalTime,
+    /// Most recently used in the range first
+    LastSeen,
 }
 
 #[derive(Serialize, Deserialize, Debug, specta::Type)]
@@ -41,3 +69,238 @@ pub struct Project {
     pub root_path: Option<String>,
     pub last_updated: Option<i64>,
 }
+
+/// Conditions of a recategorization rule. Every condition that is set has to hold.
+///
+/// App, entity, project and source names match exact values or GLOB patterns
+/// (e.g. `*.md`), like summary filters.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, specta::Type)]
+#[serde(rename_all = "camelCase")]
+pub struct RuleConditions {
+    #[specta(optional)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub app: Option<String>,
+    #[specta(optional)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub entity: Option<String>,
+    /// Host of a visited URL, subdomains included (`example.com` matches
+    /// `wiki.example.com`)
+    #[specta(optional)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub domain: Option<String>,
+    /// Regular expression searched for in the path of a visited URL
+    #[specta(optional)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub path: Option<String>,
+    #[specta(optional)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub project: Option<String>,
+    #[specta(optional)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub source: Option<String>,
+}
+
+/// Changes a rule makes to matching events. Unset actions keep the event's value.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, specta::Type)]
+#[serde(rename_all = "camelCase")]
+pub struct RuleActions {
+    #[specta(optional)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub category: Option<String>,
+    /// Name of the project to move events to
+    #[specta(optional)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub project: Option<String>,
+    /// Tags added to events. Unlike other actions, the tags of every matching rule
+    /// are added.
+    #[specta(optional)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tags: Option<Vec<String>>,
+}
+
+/// A stored recategorization rule
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, specta::Type)]
+#[serde(rename_all = "camelCase")]
+pub struct Rule {
+    pub id: uuid::Uuid,
+    pub name: String,
+    pub priority: i64,
+    pub enabled: bool,
+    pub conditions: RuleConditions,
+    pub actions: RuleActions,
+}
+
+/// A free-form label on events and projects
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, specta::Type)]
+#[serde(rename_all = "camelCase")]
+pub struct Tag {
+    pub id: uuid::Uuid,
+    pub name: String,
+}
+
+/// Billing settings of a project, tag or category
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, specta::Type)]
+#[serde(rename_all = "camelCase")]
+pub struct BillingRate {
+    /// `project`, `tag` or `category`
+    pub kind: Group,
+    pub id: uuid::Uuid,
+    pub name: String,
+    /// Hourly rate, `None` to fall back to the rate of the invoiced project or tag
+    pub hourly_rate: Option<f64>,
+    /// Whether the time is billed at all
+    pub billable: bool,
+}
+
+/// How the line items of an invoice are split
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq, Eq, specta::Type)]
+#[serde(rename_all = "camelCase")]
+pub enum InvoiceLines {
+    /// One line per day
+    #[default]
+    Day,
+    /// One line per entity (file, URL or app)
+    Entity,
+}
+
+/// What billed time is rounded up for
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq, Eq, specta::Type)]
+#[serde(rename_all = "camelCase")]
+pub enum InvoiceRounding {
+    /// Each day of a line, so every day is billed in whole increments
+    Day,
+    /// Each line as a whole
+    #[default]
+    Line,
+}
+
+/// Formats an invoice is rendered in
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq, Eq, specta::Type)]
+#[serde(rename_all = "camelCase")]
+pub enum InvoiceFormat {
+    #[default]
+    Json,
+    Csv,
+    Markdown,
+}
+
+/// Formats a data archive is written in
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq, Eq, specta::Type)]
+#[serde(rename_all = "camelCase")]
+pub enum ArchiveFormat {
+    /// One JSON record per line
+    #[default]
+    Ndjson,
+    /// One record per row under a fixed header
+    Csv,
+}
+
+/// What an archive record holds
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq, Eq, specta::Type)]
+#[serde(rename_all = "camelCase")]
+pub enum ArchiveRecordKind {
+    /// First record of every archive, carrying its `version`
+    #[default]
+    Header,
+    Tag,
+    App,
+    Category,
+    Language,
+    Source,
+    Project,
+    /// Root path of a merged project, with the `projectId` it was merged into
+    ProjectAlias,
+    Branch,
+    Entity,
+    Event,
+    Afk,
+    /// Id of a deleted event that must not be ingested again
+    Deleted,
+    /// Recategorization rule, with the tags it adds in `tags`
+    Rule,
+    /// Billing settings of the project, tag or category in `projectId`, `tagId` or
+    /// `categoryId`
+    Rate,
+}
+
+/// A row of a data archive. Dimension rows come before the events referring to them,
+/// and every field a kind does not use is `None`.
+///
+/// Timestamps are unix seconds. AFK records keep their start and end in `timestamp`
+/// and `endTimestamp`, deleted records the deletion time in `timestamp`.
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq, specta::Type)]
+#[serde(rename_all = "camelCase")]
+pub struct ArchiveRecord {
+    pub record: ArchiveRecordKind,
+    #[specta(optional)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<u32>,
+    #[specta(optional)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<uuid::Uuid>,
+    #[specta(optional)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[specta(optional)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub root_path: Option<String>,
+    #[specta(optional)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub entity_type: Option<String>,
+    #[specta(optional)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub priority: Option<i64>,
+    #[specta(optional)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timestamp: Option<i64>,
+    #[specta(optional)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub end_timestamp: Option<i64>,
+    #[specta(optional)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub duration: Option<i64>,
+    #[specta(optional)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub app_id: Option<uuid::Uuid>,
+    #[specta(optional)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub category_id: Option<uuid::Uuid>,
+    #[specta(optional)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub project_id: Option<uuid::Uuid>,
+    #[specta(optional)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub entity_id: Option<uuid::Uuid>,
+    #[specta(optional)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub branch_id: Option<uuid::Uuid>,
+    #[specta(optional)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub language_id: Option<uuid::Uuid>,
+    #[specta(optional)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source_id: Option<uuid::Uuid>,
+    #[specta(optional)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tag_id: Option<uuid::Uuid>,
+    #[specta(optional)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub enabled: Option<bool>,
+    #[specta(optional)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub conditions: Option<RuleConditions>,
+    /// Actions of a rule, without its tags
+    #[specta(optional)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub actions: Option<RuleActions>,
+    #[specta(optional)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hourly_rate: Option<f64>,
+    #[specta(optional)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub billable: Option<bool>,
+    /// Tag names of a project, event or rule
+    #[specta(optional)]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+}
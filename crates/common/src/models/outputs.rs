@@ -1,6 +1,6 @@
 use std::collections::BTreeMap;
 
-use super::Project;
+use super::{Group, Project};
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
@@ -20,6 +20,237 @@ pub enum InsightResult {
     TopN(Vec<(String, i64)>),
     MostActiveDay { date: String, total_duration: i64 },
     AggregatedAverage(BTreeMap<String, Vec<(String, f64)>>),
+    Sessions(SessionInsight),
+    Streak(StreakInsight),
+    HourlyHeatmap(HourlyHeatmap),
+}
+
+/// A continuous stretch of work, merged from events separated by short breaks
+#[derive(Debug, Serialize, Deserialize, specta::Type)]
+#[serde(rename_all = "camelCase")]
+pub struct WorkSession {
+    pub start: DateTime<Utc>,
+    pub end: DateTime<Utc>,
+    /// Seconds covered by events within the session, excluding AFK time
+    pub active_seconds: i64,
+    /// The project with the most time in the session
+    pub project: Option<String>,
+    /// The category with the most time in the session
+    pub category: Option<String>,
+    /// Languages used in the session, by time spent
+    pub languages: Vec<String>,
+}
+
+/// Summary statistics over the active seconds of a set of sessions
+#[derive(Debug, Default, Serialize, Deserialize, specta::Type)]
+#[serde(rename_all = "camelCase")]
+pub struct SessionStats {
+    pub count: usize,
+    pub mean_seconds: f64,
+    pub median_seconds: f64,
+    pub longest_seconds: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize, specta::Type)]
+#[serde(rename_all = "camelCase")]
+pub struct SessionInsight {
+    pub sessions: Vec<WorkSession>,
+    pub stats: SessionStats,
+}
+
+/// A run of consecutive active days. Dates are local "yyyy-mm-dd" days.
+#[derive(Debug, Default, Clone, Serialize, Deserialize, specta::Type)]
+#[serde(rename_all = "camelCase")]
+pub struct StreakRun {
+    pub days: u32,
+    pub start: Option<String>,
+    pub end: Option<String>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, specta::Type)]
+#[serde(rename_all = "camelCase")]
+pub struct StreakInsight {
+    /// The run that includes today, or yesterday while today is not yet active
+    pub current: StreakRun,
+    pub longest: StreakRun,
+}
+
+/// Active seconds per weekday and local hour for one group
+#[derive(Debug, Serialize, Deserialize, specta::Type)]
+#[serde(rename_all = "camelCase")]
+pub struct HeatmapSeries {
+    pub group: String,
+    /// `values[day][hour]`, with days ordered as in [`HourlyHeatmap::weekdays`]
+    pub values: Vec<Vec<f64>>,
+}
+
+#[derive(Debug, Serialize, Deserialize, specta::Type)]
+#[serde(rename_all = "camelCase")]
+pub struct HourlyHeatmap {
+    /// Row labels (e.g. "Mon"), starting on the requested first day of the week
+    pub weekdays: Vec<String>,
+    /// One heatmap per group, ordered by total time
+    pub series: Vec<HeatmapSeries>,
+}
+
+/// Totals of one group in both periods of a comparison
+#[derive(Debug, Serialize, Deserialize, specta::Type)]
+#[serde(rename_all = "camelCase")]
+pub struct GroupComparison {
+    pub group: String,
+    /// Seconds in the current period
+    pub current: i64,
+    /// Seconds in the previous period
+    pub previous: i64,
+    /// `current - previous`, in seconds
+    pub delta: i64,
+    /// Change relative to the previous period, in percent. `None` when the group has no
+    /// time in the previous period.
+    pub delta_percent: Option<f64>,
+}
+
+/// Per-group totals of two periods, as returned by `/summary/compare`
+#[derive(Debug, Serialize, Deserialize, specta::Type)]
+#[serde(rename_all = "camelCase")]
+pub struct SummaryComparison {
+    pub current_start: DateTime<Utc>,
+    pub current_end: DateTime<Utc>,
+    pub previous_start: DateTime<Utc>,
+    pub previous_end: DateTime<Utc>,
+    /// Groups present in either period, ordered by current then previous time
+    pub groups: Vec<GroupComparison>,
+}
+
+/// Time spent on one project over a range, as returned by `/projects/{id}/stats`.
+/// Breakdowns are `(name, seconds)` pairs ordered by time.
+#[derive(Debug, Serialize, Deserialize, specta::Type)]
+#[serde(rename_all = "camelCase")]
+pub struct ProjectStats {
+    pub project: Project,
+    pub total_seconds: i64,
+    /// Start of the first event in the range, clipped to the range
+    pub first_activity: Option<DateTime<Utc>>,
+    /// End of the last event in the range, clipped to the range
+    pub last_activity: Option<DateTime<Utc>>,
+    pub languages: Vec<(String, i64)>,
+    pub branches: Vec<(String, i64)>,
+    pub categories: Vec<(String, i64)>,
+    pub sources: Vec<(String, i64)>,
+    /// The entities (e.g. files) with the most time
+    pub top_entities: Vec<(String, i64)>,
+    /// Seconds per "yyyy-mm-dd" day of the range, including days without activity
+    pub daily: Vec<(String, i64)>,
+}
+
+/// A language, source, branch or entity, with its usage when a range was requested
+#[derive(Debug, Serialize, Deserialize, specta::Type)]
+#[serde(rename_all = "camelCase")]
+pub struct DimensionUsage {
+    pub id: Uuid,
+    pub name: String,
+    /// The type of an entity (e.g. "File", "Url"), `None` for other dimensions
+    pub entity_type: Option<String>,
+    /// Seconds spent in the range
+    pub total_seconds: Option<i64>,
+    /// End of the last event in the range, clipped to the range
+    pub last_seen: Option<DateTime<Utc>>,
+}
+
+/// One page of a dimension listing
+#[derive(Debug, Serialize, Deserialize, specta::Type)]
+#[serde(rename_all = "camelCase")]
+pub struct DimensionPage {
+    pub data: Vec<DimensionUsage>,
+    /// Number of rows across all pages
+    pub total: u32,
+}
+
+/// A project, entity, branch or app matching a search
+#[derive(Debug, Serialize, Deserialize, specta::Type)]
+#[serde(rename_all = "camelCase")]
+pub struct SearchResult {
+    pub kind: Group,
+    pub id: Uuid,
+    pub name: String,
+    /// The project of an entity or branch
+    pub project_id: Option<Uuid>,
+    pub project_name: Option<String>,
+    /// Seconds spent in the searched range
+    pub total_seconds: i64,
+}
+
+/// A value changed by a rule
+#[derive(Debug, Serialize, Deserialize, specta::Type)]
+#[serde(rename_all = "camelCase")]
+pub struct ValueChange {
+    pub from: Option<String>,
+    pub to: String,
+}
+
+/// The changes rules make to one event
+#[derive(Debug, Serialize, Deserialize, specta::Type)]
+#[serde(rename_all = "camelCase")]
+pub struct RuleChange {
+    pub event_id: Uuid,
+    pub timestamp: DateTime<Utc>,
+    pub app: Option<String>,
+    pub entity: Option<String>,
+    /// The rules that set a value, highest priority first
+    pub rules: Vec<Uuid>,
+    pub category: Option<ValueChange>,
+    pub project: Option<ValueChange>,
+    /// Tags added to the event
+    pub tags: Vec<String>,
+}
+
+/// Outcome of re-running the rules over a range
+#[derive(Debug, Default, Serialize, Deserialize, specta::Type)]
+#[serde(rename_all = "camelCase")]
+pub struct RuleApplyReport {
+    /// Whether the changes were only previewed
+    pub dry_run: bool,
+    /// Events in the range
+    pub scanned: u64,
+    /// Events the rules change
+    pub changed: u64,
+    /// The first changes, in event order
+    pub changes: Vec<RuleChange>,
+}
+
+/// A line item of an invoice
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, specta::Type)]
+#[serde(rename_all = "camelCase")]
+pub struct InvoiceLine {
+    /// The day (`yyyy-mm-dd`) or entity the line bills
+    pub description: String,
+    /// Billed seconds, after rounding
+    pub seconds: i64,
+    /// Billed hours, to two decimals
+    pub hours: f64,
+    /// Hourly rate, `None` when no rate applies
+    pub rate: Option<f64>,
+    /// Billed time times `rate`, to two decimals
+    pub amount: Option<f64>,
+}
+
+/// Billable time of a project or tag over a range, as invoice line items
+#[derive(Debug, Clone, Serialize, Deserialize, specta::Type)]
+#[serde(rename_all = "camelCase")]
+pub struct InvoiceReport {
+    pub start: DateTime<Utc>,
+    pub end: DateTime<Utc>,
+    /// Name of the invoiced project
+    pub project: Option<String>,
+    /// Name of the invoiced tag
+    pub tag: Option<String>,
+    pub lines: Vec<InvoiceLine>,
+    /// Billed seconds of all lines
+    pub total_seconds: i64,
+    pub total_hours: f64,
+    /// Sum of the line amounts
+    pub total_amount: f64,
+    /// Tracked seconds left out because they are not billable
+    pub non_billable_seconds: i64,
 }
 
 /// A fully materialized event row
@@ -45,8 +276,20 @@ pub struct FullEvent {
 pub struct EventGroup {
     /// The group key value
     pub group: String,
-    /// The list of `FullEvent` rows belonging to this group
+    /// The list of `FullEvent` rows belonging to this group. Empty when the group is
+    /// split into `subgroups`.
+    pub events: Vec<FullEvent>,
+    /// The group split by the next `subgroup_by` dimension
+    pub subgroups: Vec<EventGroup>,
+}
+
+/// A page of events in chronological order
+#[derive(Debug, Serialize, Deserialize, specta::Type)]
+#[serde(rename_all = "camelCase")]
+pub struct EventPage {
     pub events: Vec<FullEvent>,
+    /// Cursor to pass as `after` for the next page, `None` on the last page
+    pub next_cursor: Option<String>,
 }
 
 /// The result of fetching events for a time/window query
@@ -55,7 +298,7 @@ pub struct EventGroup {
 ///   matching event row.
 /// - `Grouped(Vec<EventGroup>)` - returned when a `group_by` dimension is set.
 ///   Each `EventGroup` holds a `group` key (e.g., a category name) and **all**
-///   events that belong to that group.
+///   events that belong to that group, nested into `subgroups` per `subgroup_by` dimension.
 #[derive(Debug, Serialize, Deserialize, specta::Type)]
 pub enum EventGroupResult {
     /// Ungrouped list of events.
@@ -64,6 +307,87 @@ pub enum EventGroupResult {
     Grouped(Vec<EventGroup>),
 }
 
+/// The outcome of a single item in an ingest request
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IngestItemResult {
+    /// Position of the item in the request payload
+    pub index: usize,
+    /// The deterministic (UUIDv5) id of the item, when it could be derived
+    pub id: Option<Uuid>,
+    /// Why the item was not inserted
+    pub reason: Option<String>,
+}
+
+/// Per-item report returned by the ingest endpoints (`POST /events`, `POST /afk`)
+///
+/// - `accepted` - items inserted by this request.
+/// - `duplicate` - items that were already stored or deleted by a correction, or
+///   repeated within the request.
+/// - `rejected` - items that failed validation and were not stored. Retrying them
+///   without changes will fail again.
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IngestReport {
+    pub accepted: Vec<IngestItemResult>,
+    pub duplicate: Vec<IngestItemResult>,
+    pub rejected: Vec<IngestItemResult>,
+}
+
+/// Result of importing a data archive
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ArchiveImportReport {
+    /// Dimension rows (apps, projects, tags, ...) that did not exist yet
+    pub dimensions: u64,
+    /// Events inserted
+    pub events: u64,
+    /// Events that already existed or were deleted before
+    pub duplicate_events: u64,
+    /// AFK events inserted
+    pub afk_events: u64,
+    /// AFK events that already existed
+    pub duplicate_afk_events: u64,
+    /// Events skipped because they refer to dimensions missing from the archive
+    pub skipped_events: u64,
+    /// Rules that did not exist yet
+    pub rules: u64,
+    /// Billing rates of projects, tags and categories that had none
+    pub rates: u64,
+}
+
+/// Result of deleting events matching a filter
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DeleteReport {
+    /// Number of events deleted
+    pub deleted: u64,
+}
+
+/// One side of an [`EventOverlap`]
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OverlapEvent {
+    pub id: Uuid,
+    pub source: String,
+    pub app: Option<String>,
+    pub entity: Option<String>,
+}
+
+/// A span of time recorded by events from two different sources.
+///
+/// The time is credited to `kept`; `shadowed` loses it in summaries and insights.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EventOverlap {
+    pub start: DateTime<Utc>,
+    pub end: DateTime<Utc>,
+    /// Length of the overlap in seconds
+    pub duration: i64,
+    pub kept: OverlapEvent,
+    pub shadowed: OverlapEvent,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct HealthStatus {
     pub status: String,
@@ -0,0 +1,219 @@
+use chrono::{
+    DateTime, Datelike, Duration, Local, Months, NaiveDateTime, NaiveTime, TimeZone, Timelike, Utc,
+};
+
+use crate::time::{TimeBucket, Tz, WeekStart, local_to_utc};
+
+/// A single bucket of a bucketed query, with its boundaries already resolved to UTC
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BucketSpan {
+    /// Start of the bucket (inclusive), clipped to the queried range
+    pub start: DateTime<Utc>,
+    /// End of the bucket (exclusive), clipped to the queried range
+    pub end: DateTime<Utc>,
+    /// Wall-clock start of the bucket in the requested timezone, before clipping
+    pub local_start: NaiveDateTime,
+    /// The bucket label (e.g. "2025-08-01" for a day)
+    pub label: String,
+}
+
+/// Splits `[start, end)` into calendar buckets in `timezone`, or in the server's
+/// local timezone when `None`.
+pub fn bucket_spans_in(
+    timezone: Option<Tz>,
+    week_start: WeekStart,
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+    bucket: Option<TimeBucket>,
+) -> Vec<BucketSpan> {
+    match timezone {
+        Some(tz) => bucket_spans(&tz, week_start, start, end, bucket),
+        None => bucket_spans(&Local, week_start, start, end, bucket),
+    }
+}
+
+/// Splits `[start, end)` into calendar buckets in `tz`.
+///
+/// Bucket boundaries follow the wall clock, so a day bucket lasts 23 or 25 hours on
+/// DST transitions. The first and last buckets are clipped to the range. Without a
+/// bucket, the whole range is returned as a single "Unbucketed" span. Week buckets
+/// start on `week_start` and are labelled "yyyy-W##" (see [`WeekStart::label`]).
+pub fn bucket_spans<Z: TimeZone>(
+    tz: &Z,
+    week_start: WeekStart,
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+    bucket: Option<TimeBucket>,
+) -> Vec<BucketSpan> {
+    if start >= end {
+        return Vec::new();
+    }
+
+    let first = start.with_timezone(tz).naive_local();
+    let Some(bucket) = bucket else {
+        return vec![BucketSpan {
+            start,
+            end,
+            local_start: first,
+            label: "Unbucketed".to_string(),
+        }];
+    };
+
+    let mut spans = Vec::new();
+    let mut local = floor(bucket, week_start, first);
+
+    loop {
+        let next = step(bucket, local);
+        let span_start = local_to_utc(tz, local).max(start);
+        let next_utc = local_to_utc(tz, next);
+        let span_end = next_utc.min(end);
+
+        // Hours skipped by DST resolve to an empty span
+        if span_start < span_end {
+            spans.push(BucketSpan {
+                start: span_start,
+                end: span_end,
+                local_start: local,
+                label: label(bucket, week_start, local),
+            });
+        }
+
+        if next_utc >= end || next <= local {
+            break;
+        }
+        local = next;
+    }
+
+    spans
+}
+
+/// Returns the wall-clock start of the bucket containing `local`
+fn floor(bucket: TimeBucket, week_start: WeekStart, local: NaiveDateTime) -> NaiveDateTime {
+    let date = local.date();
+    let day = match bucket {
+        TimeBucket::Hour => {
+            return date.and_time(NaiveTime::from_hms_opt(local.hour(), 0, 0).unwrap_or_default());
+        }
+        TimeBucket::Day => date,
+        TimeBucket::Week => week_start.start_of_week(date),
+        TimeBucket::Month => date.with_day(1).unwrap_or(date),
+        TimeBucket::Year => date.with_ordinal(1).unwrap_or(date),
+    };
+    day.and_time(NaiveTime::MIN)
+}
+
+/// Returns the wall-clock start of the bucket following the one starting at `local`
+fn step(bucket: TimeBucket, local: NaiveDateTime) -> NaiveDateTime {
+    match bucket {
+        TimeBucket::Hour => local + Duration::hours(1),
+        TimeBucket::Day => local + Duration::days(1),
+        TimeBucket::Week => local + Duration::days(7),
+        TimeBucket::Month => local + Months::new(1),
+        TimeBucket::Year => local + Months::new(12),
+    }
+}
+
+fn label(bucket: TimeBucket, week_start: WeekStart, local: NaiveDateTime) -> String {
+    let format = match bucket {
+        TimeBucket::Hour => "%Y-%m-%d %H:00:00",
+        TimeBucket::Day => "%Y-%m-%d",
+        TimeBucket::Week => return week_start.label(local.date()),
+        TimeBucket::Month => "%Y-%m",
+        TimeBucket::Year => "%Y",
+    };
+    local.format(format).to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+    use chrono_tz::Europe::Berlin;
+
+    #[test]
+    fn day_buckets_follow_dst_in_timezone() {
+        // Clocks in Berlin go forward on 2025-03-30
+        let start = Utc.with_ymd_and_hms(2025, 3, 28, 23, 0, 0).unwrap();
+        let end = Utc.with_ymd_and_hms(2025, 3, 31, 22, 0, 0).unwrap();
+
+        let spans = bucket_spans(
+            &Berlin,
+            WeekStart::Monday,
+            start,
+            end,
+            Some(TimeBucket::Day),
+        );
+        let labels: Vec<&str> = spans.iter().map(|s| s.label.as_str()).collect();
+        assert_eq!(labels, ["2025-03-29", "2025-03-30", "2025-03-31"]);
+        assert_eq!(spans[1].end - spans[1].start, Duration::hours(23));
+        assert_eq!(
+            spans[2].start,
+            Utc.with_ymd_and_hms(2025, 3, 30, 22, 0, 0).unwrap()
+        );
+    }
+
+    #[test]
+    fn hour_buckets_skip_missing_hour() {
+        let start = Utc.with_ymd_and_hms(2025, 3, 29, 23, 0, 0).unwrap();
+        let end = Utc.with_ymd_and_hms(2025, 3, 30, 3, 0, 0).unwrap();
+
+        let spans = bucket_spans(
+            &Berlin,
+            WeekStart::Monday,
+            start,
+            end,
+            Some(TimeBucket::Hour),
+        );
+        let labels: Vec<&str> = spans.iter().map(|s| s.label.as_str()).collect();
+        assert_eq!(
+            labels,
+            [
+                "2025-03-30 00:00:00",
+                "2025-03-30 01:00:00",
+                "2025-03-30 03:00:00",
+                "2025-03-30 04:00:00"
+            ]
+        );
+    }
+
+    #[test]
+    fn spans_are_clipped_to_range() {
+        let start = Utc.with_ymd_and_hms(2025, 1, 15, 12, 0, 0).unwrap();
+        let end = Utc.with_ymd_and_hms(2025, 2, 10, 0, 0, 0).unwrap();
+
+        let spans = bucket_spans(&Utc, WeekStart::Monday, start, end, Some(TimeBucket::Month));
+        assert_eq!(spans.len(), 2);
+        assert_eq!(spans[0].start, start);
+        assert_eq!(spans[0].label, "2025-01");
+        assert_eq!(spans[1].end, end);
+    }
+
+    #[test]
+    fn week_buckets_use_week_start() {
+        // 2025-06-08 is a Sunday
+        let start = Utc.with_ymd_and_hms(2025, 6, 8, 12, 0, 0).unwrap();
+        let end = Utc.with_ymd_and_hms(2025, 6, 10, 0, 0, 0).unwrap();
+
+        let spans = bucket_spans(&Utc, WeekStart::Monday, start, end, Some(TimeBucket::Week));
+        let labels: Vec<&str> = spans.iter().map(|s| s.label.as_str()).collect();
+        assert_eq!(labels, ["2025-W23", "2025-W24"]);
+
+        let spans = bucket_spans(&Utc, WeekStart::Sunday, start, end, Some(TimeBucket::Week));
+        assert_eq!(spans.len(), 1);
+        assert_eq!(spans[0].label, "2025-W24");
+        assert_eq!(
+            spans[0].local_start.date(),
+            NaiveDate::from_ymd_opt(2025, 6, 8).unwrap()
+        );
+    }
+
+    #[test]
+    fn missing_bucket_is_single_span() {
+        let start = Utc.with_ymd_and_hms(2025, 1, 1, 0, 0, 0).unwrap();
+        let end = Utc.with_ymd_and_hms(2025, 1, 3, 0, 0, 0).unwrap();
+
+        let spans = bucket_spans(&Utc, WeekStart::Monday, start, end, None);
+        assert_eq!(spans.len(), 1);
+        assert_eq!(spans[0].label, "Unbucketed");
+    }
+}
@@ -1,9 +1,7 @@
-use chrono::{
-    DateTime, Datelike, Duration, Local, NaiveDate, TimeZone, Utc, Weekday, offset::LocalResult,
-};
+use chrono::{DateTime, Datelike, Duration, Local, NaiveDate, TimeZone, Utc};
 use serde::{Deserialize, Serialize};
 
-use crate::time::TimeError;
+use crate::time::{TimeError, Tz, WeekStart, midnight_utc};
 
 #[derive(Debug, Serialize, Deserialize, specta::Type)]
 pub struct InsightRange {
@@ -11,30 +9,48 @@ pub struct InsightRange {
     pub end: DateTime<Utc>,
 }
 
-fn local_midnight_to_utc(date: NaiveDate) -> Result<DateTime<Utc>, TimeError> {
-    let local_dt = match Local.with_ymd_and_hms(date.year(), date.month(), date.day(), 0, 0, 0) {
-        LocalResult::Single(dt) => dt,
-        LocalResult::Ambiguous(dt1, _dt2) => dt1,
-        LocalResult::None => return Err(TimeError::InvalidDate),
-    };
-    Ok(local_dt.with_timezone(&Utc))
-}
-
 impl TryFrom<String> for InsightRange {
     type Error = TimeError;
 
     fn try_from(value: String) -> Result<Self, Self::Error> {
+        Self::parse_in(&value, &Local, WeekStart::default())
+    }
+}
+
+impl InsightRange {
+    /// Parses a range in `timezone`, or in the server's local timezone when `None`
+    pub fn resolve(
+        value: &str,
+        timezone: Option<Tz>,
+        week_start: WeekStart,
+    ) -> Result<Self, TimeError> {
+        match timezone {
+            Some(tz) => Self::parse_in(value, &tz, week_start),
+            None => Self::parse_in(value, &Local, week_start),
+        }
+    }
+
+    /// Parses "yyyy-mm-dd", "yyyy-mm", "yyyy-W##" or "yyyy" into the matching
+    /// calendar range, with boundaries at midnight in `tz`. Weeks start on
+    /// `week_start` and are numbered as in [`WeekStart::week_of`].
+    pub fn parse_in<Z: TimeZone>(
+        value: &str,
+        tz: &Z,
+        week_start: WeekStart,
+    ) -> Result<Self, TimeError> {
+        let range = |start: NaiveDate, end: NaiveDate| InsightRange {
+            start: midnight_utc(tz, start),
+            end: midnight_utc(tz, end),
+        };
+
         // Handle "yyyy-mm-dd"
-        if let Ok(date) = NaiveDate::parse_from_str(&value, "%Y-%m-%d") {
-            let start = local_midnight_to_utc(date)?;
-            let end = start + Duration::days(1);
-            return Ok(InsightRange { start, end });
+        if let Ok(date) = NaiveDate::parse_from_str(value, "%Y-%m-%d") {
+            return Ok(range(date, date + Duration::days(1)));
         }
 
         // Handle "yyyy-mm"
         if let Ok(first_of_month) = NaiveDate::parse_from_str(&format!("{}-01", value), "%Y-%m-%d")
         {
-            let start = local_midnight_to_utc(first_of_month)?;
             let next_month = if first_of_month.month() == 12 {
                 NaiveDate::from_ymd_opt(first_of_month.year() + 1, 1, 1)
                     .ok_or(TimeError::InvalidDate)?
@@ -43,8 +59,7 @@ impl TryFrom<String> for InsightRange {
                     .ok_or(TimeError::InvalidDate)?
             };
 
-            let end = local_midnight_to_utc(next_month)?;
-            return Ok(InsightRange { start, end });
+            return Ok(range(first_of_month, next_month));
         }
 
         // Handle "yyyy-W##"
@@ -54,13 +69,11 @@ impl TryFrom<String> for InsightRange {
                 .parse::<u32>()
                 .map_err(|_| TimeError::InvalidDate)?;
 
-            let iso_week_start = NaiveDate::from_isoywd_opt(year, week, Weekday::Mon)
+            let first_day = week_start
+                .first_day_of(year, week)
                 .ok_or(TimeError::InvalidDate)?;
 
-            let start = local_midnight_to_utc(iso_week_start)?;
-            let end = start + Duration::days(7);
-
-            return Ok(InsightRange { start, end });
+            return Ok(range(first_day, first_day + Duration::days(7)));
         }
 
         // Handle "yyyy"
@@ -68,9 +81,7 @@ impl TryFrom<String> for InsightRange {
             let start_date = NaiveDate::from_ymd_opt(year, 1, 1).ok_or(TimeError::InvalidDate)?;
             let end_date = NaiveDate::from_ymd_opt(year + 1, 1, 1).ok_or(TimeError::InvalidDate)?;
 
-            let start = local_midnight_to_utc(start_date)?;
-            let end = local_midnight_to_utc(end_date)?;
-            return Ok(InsightRange { start, end });
+            return Ok(range(start_date, end_date));
         }
 
         Err(TimeError::InvalidDate)
@@ -127,6 +138,36 @@ mod tests {
         assert!(r.start < r.end);
     }
 
+    #[test]
+    fn parse_week_with_sunday_start() {
+        // With Sunday weeks, week 1 of 2021 starts on 2021-01-03
+        let r = InsightRange::parse_in("2021-W01", &Utc, WeekStart::Sunday).unwrap();
+        assert_eq!(r.start, Utc.with_ymd_and_hms(2021, 1, 3, 0, 0, 0).unwrap());
+        assert_eq!(r.end, Utc.with_ymd_and_hms(2021, 1, 10, 0, 0, 0).unwrap());
+    }
+
+    #[test]
+    fn week_labels_round_trip() {
+        let date = NaiveDate::from_ymd_opt(2024, 12, 30).unwrap();
+        for week_start in [WeekStart::Monday, WeekStart::Sunday, WeekStart::Saturday] {
+            let label = week_start.label(date);
+            let r = InsightRange::parse_in(&label, &Utc, week_start).unwrap();
+            assert_eq!(r.start.date_naive(), week_start.start_of_week(date));
+        }
+    }
+
+    #[test]
+    fn parse_day_in_timezone_on_dst_change() {
+        // Clocks in Berlin go back on 2025-10-26, so the day lasts 25 hours
+        let r = InsightRange::parse_in("2025-10-26", &chrono_tz::Europe::Berlin, WeekStart::Monday)
+            .unwrap();
+        assert_eq!(
+            r.start,
+            Utc.with_ymd_and_hms(2025, 10, 25, 22, 0, 0).unwrap()
+        );
+        assert_eq!(r.end, Utc.with_ymd_and_hms(2025, 10, 26, 23, 0, 0).unwrap());
+    }
+
     #[test]
     fn reject_bad_day_format() {
         let err = InsightRange::try_from("2025/03/15".to_string()).unwrap_err();
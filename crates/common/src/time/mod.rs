@@ -1,6 +1,11 @@
+pub mod bucket;
 pub mod insight;
 
-use chrono::{DateTime, Datelike, Duration, Local, TimeZone, Utc};
+use chrono::{
+    DateTime, Datelike, Duration, Local, Months, NaiveDate, NaiveDateTime, NaiveTime, TimeZone,
+    Utc, Weekday,
+};
+pub use chrono_tz::Tz;
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
 
@@ -32,6 +37,60 @@ pub enum TimeBucket {
     Year,
 }
 
+/// The first day of a calendar week
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, specta::Type)]
+#[serde(rename_all = "camelCase")]
+pub enum WeekStart {
+    /// Weeks start on Monday, as in ISO 8601
+    #[default]
+    Monday,
+    /// Weeks start on Sunday
+    Sunday,
+    /// Weeks start on Saturday
+    Saturday,
+}
+
+impl WeekStart {
+    pub fn weekday(self) -> Weekday {
+        match self {
+            WeekStart::Monday => Weekday::Mon,
+            WeekStart::Sunday => Weekday::Sun,
+            WeekStart::Saturday => Weekday::Sat,
+        }
+    }
+
+    /// Returns the first day of the week containing `date`
+    pub fn start_of_week(self, date: NaiveDate) -> NaiveDate {
+        let offset =
+            (date.weekday().num_days_from_monday() + 7 - self.weekday().num_days_from_monday()) % 7;
+        date - Duration::days(offset as i64)
+    }
+
+    /// Returns the week-numbering year and the week number of the week containing `date`.
+    ///
+    /// Weeks are numbered like ISO 8601 weeks: a week belongs to the year its fourth
+    /// day falls in, so week 1 is the week containing January 4th. With `Monday` this
+    /// is exactly the ISO week.
+    pub fn week_of(self, date: NaiveDate) -> (i32, u32) {
+        let fourth_day = self.start_of_week(date) + Duration::days(3);
+        (fourth_day.year(), fourth_day.ordinal0() / 7 + 1)
+    }
+
+    /// Formats the week containing `date` as "yyyy-W##"
+    pub fn label(self, date: NaiveDate) -> String {
+        let (year, week) = self.week_of(date);
+        format!("{year}-W{week:02}")
+    }
+
+    /// Returns the first day of the given week, the inverse of [`WeekStart::week_of`].
+    /// Returns `None` if the year has no such week.
+    pub fn first_day_of(self, year: i32, week: u32) -> Option<NaiveDate> {
+        let first_week = self.start_of_week(NaiveDate::from_ymd_opt(year, 1, 4)?);
+        let start = first_week + Duration::weeks(week.checked_sub(1)? as i64);
+        (self.week_of(start) == (year, week)).then_some(start)
+    }
+}
+
 /// A predefined range of time used to filter or summarize data.
 #[derive(Debug, Clone, Serialize, Deserialize, specta::Type)]
 #[serde(rename_all = "camelCase")]
@@ -40,7 +99,7 @@ pub enum TimeRangePreset {
     Today,
     /// Represents yesterday (midnight to midnight)
     Yesterday,
-    /// The current calendar week starting from Monday.
+    /// The current calendar week, starting on the configured [`WeekStart`].
     ThisWeek,
     /// The current calendar week before this one.
     LastWeek,
@@ -79,107 +138,76 @@ pub struct TimeRange {
 
 impl From<TimeRangePreset> for TimeRange {
     fn from(preset: TimeRangePreset) -> Self {
-        let local_now = Local::now();
-        let local_today = local_now.date_naive();
-
-        let today_start_local = Local
-            .with_ymd_and_hms(
-                local_today.year(),
-                local_today.month(),
-                local_today.day(),
-                0,
-                0,
-                0,
-            )
-            .single()
-            .unwrap();
-        let today_start_utc = today_start_local.with_timezone(&Utc);
+        Self::at(preset, Local::now(), WeekStart::default())
+    }
+}
+
+/// Converts a wall-clock time in `tz` to UTC.
+///
+/// Ambiguous times (when clocks go back) resolve to the earlier instant. Times skipped
+/// when clocks go forward resolve to the first wall-clock time after the gap.
+pub fn local_to_utc<Z: TimeZone>(tz: &Z, local: NaiveDateTime) -> DateTime<Utc> {
+    let mut probe = local;
+    // DST gaps are at most a couple of hours long
+    for _ in 0..(24 * 4) {
+        if let Some(dt) = tz.from_local_datetime(&probe).earliest() {
+            return dt.with_timezone(&Utc);
+        }
+        probe += Duration::minutes(15);
+    }
+    Utc.from_utc_datetime(&local)
+}
+
+/// Returns the start of `date` in `tz` as UTC
+pub fn midnight_utc<Z: TimeZone>(tz: &Z, date: NaiveDate) -> DateTime<Utc> {
+    local_to_utc(tz, date.and_time(NaiveTime::MIN))
+}
+
+impl TimeRange {
+    /// Resolves a preset in `timezone`, or in the server's local timezone when `None`
+    pub fn resolve(preset: TimeRangePreset, timezone: Option<Tz>, week_start: WeekStart) -> Self {
+        match timezone {
+            Some(tz) => Self::at(preset, Utc::now().with_timezone(&tz), week_start),
+            None => Self::at(preset, Local::now(), week_start),
+        }
+    }
+
+    /// Resolves a preset relative to `now`. Day, week, month and year boundaries are
+    /// computed in the timezone of `now`, so days are not assumed to last 24 hours.
+    pub fn at<Z: TimeZone>(
+        preset: TimeRangePreset,
+        now: DateTime<Z>,
+        week_start: WeekStart,
+    ) -> Self {
+        let tz = now.timezone();
+        let today = now.date_naive();
+        let midnight = |date: NaiveDate| midnight_utc(&tz, date);
+        let span = |start: NaiveDate, end: NaiveDate, bucket: TimeBucket| Self {
+            start: midnight(start),
+            end: midnight(end),
+            bucket: Some(bucket),
+        };
+
+        let week_start = week_start.start_of_week(today);
+        let month_start = today.with_day(1).unwrap_or(today);
 
         match preset {
-            TimeRangePreset::Today => {
-                let start = today_start_utc;
-                let end = start + Duration::days(1);
-                Self {
-                    start,
-                    end,
-                    bucket: Some(TimeBucket::Hour),
-                }
-            }
-            TimeRangePreset::Yesterday => {
-                let start = today_start_utc - Duration::days(1);
-                let end = today_start_utc;
-                Self {
-                    start,
-                    end,
-                    bucket: Some(TimeBucket::Hour),
-                }
-            }
+            TimeRangePreset::Today => span(today, today + Duration::days(1), TimeBucket::Hour),
+            TimeRangePreset::Yesterday => span(today - Duration::days(1), today, TimeBucket::Hour),
             TimeRangePreset::ThisWeek => {
-                let weekday = local_today.weekday().num_days_from_monday() as i64;
-                let week_start_local = today_start_local - Duration::days(weekday);
-                let week_start_utc = week_start_local.with_timezone(&Utc);
-                let end = week_start_utc + Duration::days(7);
-                Self {
-                    start: week_start_utc,
-                    end,
-                    bucket: Some(TimeBucket::Day),
-                }
+                span(week_start, week_start + Duration::days(7), TimeBucket::Day)
             }
             TimeRangePreset::LastWeek => {
-                let weekday = local_today.weekday().num_days_from_monday() as i64;
-                let last_week_start_local = today_start_local - Duration::days(weekday + 7);
-                let start = last_week_start_local.with_timezone(&Utc);
-                let end = start + Duration::days(7);
-                Self {
-                    start,
-                    end,
-                    bucket: Some(TimeBucket::Day),
-                }
+                span(week_start - Duration::days(7), week_start, TimeBucket::Day)
             }
             TimeRangePreset::ThisMonth => {
-                let start_local = Local
-                    .with_ymd_and_hms(local_today.year(), local_today.month(), 1, 0, 0, 0)
-                    .single()
-                    .unwrap();
-                let end_local = if local_today.month() == 12 {
-                    Local
-                        .with_ymd_and_hms(local_today.year() + 1, 1, 1, 0, 0, 0)
-                        .single()
-                        .unwrap()
-                } else {
-                    Local
-                        .with_ymd_and_hms(local_today.year(), local_today.month() + 1, 1, 0, 0, 0)
-                        .single()
-                        .unwrap()
-                };
-                Self {
-                    start: start_local.with_timezone(&Utc),
-                    end: end_local.with_timezone(&Utc),
-                    bucket: Some(TimeBucket::Day),
-                }
+                span(month_start, month_start + Months::new(1), TimeBucket::Day)
             }
             TimeRangePreset::LastMonth => {
-                let (year, month) = if local_today.month() == 1 {
-                    (local_today.year() - 1, 12)
-                } else {
-                    (local_today.year(), local_today.month() - 1)
-                };
-                let start_local = Local
-                    .with_ymd_and_hms(year, month, 1, 0, 0, 0)
-                    .single()
-                    .unwrap();
-                let end_local = Local
-                    .with_ymd_and_hms(local_today.year(), local_today.month(), 1, 0, 0, 0)
-                    .single()
-                    .unwrap();
-                Self {
-                    start: start_local.with_timezone(&Utc),
-                    end: end_local.with_timezone(&Utc),
-                    bucket: Some(TimeBucket::Day),
-                }
+                span(month_start - Months::new(1), month_start, TimeBucket::Day)
             }
             TimeRangePreset::LastNMinutes(n) => {
-                let end = Utc::now();
+                let end = now.with_timezone(&Utc);
                 let start = end - Duration::minutes(n);
                 Self {
                     start,
@@ -189,95 +217,35 @@ impl From<TimeRangePreset> for TimeRange {
             }
             TimeRangePreset::LastNDays(n, include_this) => {
                 let clamped = n.clamp(1, 31);
-                let start =
-                    today_start_utc - Duration::days(clamped - if include_this { 0 } else { 1 });
-                let end = today_start_utc
-                    + if include_this {
-                        Duration::days(1)
-                    } else {
-                        Duration::zero()
-                    };
-                Self {
-                    start,
-                    end,
-                    bucket: Some(TimeBucket::Day),
-                }
+                let start = today - Duration::days(clamped - if include_this { 0 } else { 1 });
+                let end = today + Duration::days(if include_this { 1 } else { 0 });
+                span(start, end, TimeBucket::Day)
             }
             TimeRangePreset::LastNWeeks(n, include_this) => {
                 let clamped = n.clamp(1, 9);
-                let weekday = local_today.weekday().num_days_from_monday() as i64;
-                let this_week_start_local = today_start_local - Duration::days(weekday);
-                let end = this_week_start_local.with_timezone(&Utc)
-                    + if include_this {
-                        Duration::days(7)
-                    } else {
-                        Duration::zero()
-                    };
+                let end = week_start + Duration::days(if include_this { 7 } else { 0 });
                 let start = end - Duration::weeks(clamped);
-                Self {
-                    start,
-                    end,
-                    bucket: Some(TimeBucket::Week),
-                }
+                span(start, end, TimeBucket::Week)
             }
             TimeRangePreset::LastNMonths(n, include_this) => {
-                let clamped = n.clamp(1, 12);
-                let mut year = local_today.year();
-                let mut month = local_today.month() as i32;
-
-                if !include_this {
-                    month -= 1;
-                    if month <= 0 {
-                        month += 12;
-                        year -= 1;
-                    }
-                }
-
-                let mut start_month = month - clamped as i32 + 1;
-                let mut start_year = year;
-                while start_month <= 0 {
-                    start_month += 12;
-                    start_year -= 1;
-                }
-
-                let start_local = Local
-                    .with_ymd_and_hms(start_year, start_month as u32, 1, 0, 0, 0)
-                    .single()
-                    .unwrap();
-                let end_local = Local
-                    .with_ymd_and_hms(year, month as u32 + 1, 1, 0, 0, 0)
-                    .single()
-                    .unwrap_or_else(|| {
-                        Local
-                            .with_ymd_and_hms(year + 1, 1, 1, 0, 0, 0)
-                            .single()
-                            .unwrap()
-                    });
-
-                Self {
-                    start: start_local.with_timezone(&Utc),
-                    end: end_local.with_timezone(&Utc),
-                    bucket: Some(TimeBucket::Month),
-                }
+                let clamped = n.clamp(1, 12) as u32;
+                let end = if include_this {
+                    month_start + Months::new(1)
+                } else {
+                    month_start
+                };
+                let start = end - Months::new(clamped);
+                span(start, end, TimeBucket::Month)
             }
             TimeRangePreset::LastNYears(n, include_this) => {
-                let clamped = n.clamp(1, 12);
-                let end_year = local_today.year() + if include_this { 1 } else { 0 };
-                let start_year = end_year - clamped as i32;
-                let start_local = Local
-                    .with_ymd_and_hms(start_year, 1, 1, 0, 0, 0)
-                    .single()
-                    .unwrap();
-                let end_local = Local
-                    .with_ymd_and_hms(end_year, 1, 1, 0, 0, 0)
-                    .single()
-                    .unwrap();
-
-                Self {
-                    start: start_local.with_timezone(&Utc),
-                    end: end_local.with_timezone(&Utc),
-                    bucket: Some(TimeBucket::Year),
-                }
+                let clamped = n.clamp(1, 12) as i32;
+                let end_year = today.year() + if include_this { 1 } else { 0 };
+                let year_start = |year: i32| NaiveDate::from_ymd_opt(year, 1, 1).unwrap_or(today);
+                span(
+                    year_start(end_year - clamped),
+                    year_start(end_year),
+                    TimeBucket::Year,
+                )
             }
             TimeRangePreset::Custom { start, end, bucket } => Self {
                 start,
@@ -286,9 +254,60 @@ impl From<TimeRangePreset> for TimeRange {
             },
         }
     }
-}
 
-impl TimeRange {
+    /// Resolves the period before the one `preset` resolves to, in `timezone` or in
+    /// the server's local timezone when `None`
+    pub fn previous(preset: TimeRangePreset, timezone: Option<Tz>, week_start: WeekStart) -> Self {
+        match timezone {
+            Some(tz) => Self::previous_at(preset, Utc::now().with_timezone(&tz), week_start),
+            None => Self::previous_at(preset, Local::now(), week_start),
+        }
+    }
+
+    /// Resolves the period before the one `preset` resolves to at `now`.
+    ///
+    /// The preset is resolved as of one period earlier, so the previous period of
+    /// `ThisMonth` is last month and the previous period of `LastNDays` covers as many
+    /// days right before it. Custom ranges are shifted back by their own length.
+    pub fn previous_at<Z: TimeZone>(
+        preset: TimeRangePreset,
+        now: DateTime<Z>,
+        week_start: WeekStart,
+    ) -> Self {
+        let tz = now.timezone();
+        let local = now.naive_local();
+        let months_back = |months: i64| {
+            local
+                .checked_sub_months(Months::new(months as u32))
+                .unwrap_or(local)
+        };
+
+        let earlier_local = match &preset {
+            TimeRangePreset::Today | TimeRangePreset::Yesterday => local - Duration::days(1),
+            TimeRangePreset::ThisWeek | TimeRangePreset::LastWeek => local - Duration::weeks(1),
+            TimeRangePreset::ThisMonth | TimeRangePreset::LastMonth => months_back(1),
+            TimeRangePreset::LastNMinutes(n) => local - Duration::minutes(*n),
+            TimeRangePreset::LastNDays(..) => {
+                let current = Self::at(preset.clone(), now.clone(), week_start);
+                let day = |t: DateTime<Utc>| t.with_timezone(&tz).date_naive();
+                local - (day(current.end) - day(current.start))
+            }
+            TimeRangePreset::LastNWeeks(n, _) => local - Duration::weeks((*n).clamp(1, 9)),
+            TimeRangePreset::LastNMonths(n, _) => months_back((*n).clamp(1, 12)),
+            TimeRangePreset::LastNYears(n, _) => months_back(12 * (*n).clamp(1, 12)),
+            TimeRangePreset::Custom { start, end, bucket } => {
+                return Self {
+                    start: *start - (*end - *start),
+                    end: *start,
+                    bucket: Some(*bucket),
+                };
+            }
+        };
+
+        let earlier = local_to_utc(&tz, earlier_local).with_timezone(&tz);
+        Self::at(preset, earlier, week_start)
+    }
+
     pub fn start(&self) -> DateTime<Utc> {
         self.start
     }
@@ -512,6 +531,72 @@ mod tests {
         assert_eq!(r.end, end);
     }
 
+    #[test]
+    fn preset_today_in_timezone_on_dst_change() {
+        // Clocks in Berlin go forward on 2025-03-30, so the day lasts 23 hours
+        let now = chrono_tz::Europe::Berlin
+            .with_ymd_and_hms(2025, 3, 30, 12, 0, 0)
+            .unwrap();
+        let r = TimeRange::at(TimeRangePreset::Today, now, WeekStart::Monday);
+        assert_eq!(
+            r.start,
+            Utc.with_ymd_and_hms(2025, 3, 29, 23, 0, 0).unwrap()
+        );
+        assert_eq!(r.end, Utc.with_ymd_and_hms(2025, 3, 30, 22, 0, 0).unwrap());
+    }
+
+    #[test]
+    fn preset_this_month_in_timezone_uses_local_midnight() {
+        let now = chrono_tz::America::New_York
+            .with_ymd_and_hms(2025, 3, 31, 22, 0, 0)
+            .unwrap();
+        let r = TimeRange::at(TimeRangePreset::ThisMonth, now, WeekStart::Monday);
+        assert_eq!(r.start, Utc.with_ymd_and_hms(2025, 3, 1, 5, 0, 0).unwrap());
+        assert_eq!(r.end, Utc.with_ymd_and_hms(2025, 4, 1, 4, 0, 0).unwrap());
+    }
+
+    #[test]
+    fn preset_this_week_with_sunday_start() {
+        // 2025-06-11 is a Wednesday
+        let now = Utc.with_ymd_and_hms(2025, 6, 11, 12, 0, 0).unwrap();
+        let r = TimeRange::at(TimeRangePreset::ThisWeek, now, WeekStart::Sunday);
+        assert_eq!(r.start, Utc.with_ymd_and_hms(2025, 6, 8, 0, 0, 0).unwrap());
+        assert_eq!(r.end, Utc.with_ymd_and_hms(2025, 6, 15, 0, 0, 0).unwrap());
+
+        let r = TimeRange::at(TimeRangePreset::LastWeek, now, WeekStart::Saturday);
+        assert_eq!(r.start, Utc.with_ymd_and_hms(2025, 5, 31, 0, 0, 0).unwrap());
+        assert_eq!(r.end, Utc.with_ymd_and_hms(2025, 6, 7, 0, 0, 0).unwrap());
+    }
+
+    #[test]
+    fn previous_period_of_presets() {
+        let now = Utc.with_ymd_and_hms(2025, 3, 31, 12, 0, 0).unwrap();
+
+        let r = TimeRange::previous_at(TimeRangePreset::ThisMonth, now, WeekStart::Monday);
+        assert_eq!(r.start, Utc.with_ymd_and_hms(2025, 2, 1, 0, 0, 0).unwrap());
+        assert_eq!(r.end, Utc.with_ymd_and_hms(2025, 3, 1, 0, 0, 0).unwrap());
+
+        let current = TimeRange::at(TimeRangePreset::LastNDays(7, false), now, WeekStart::Monday);
+        let r =
+            TimeRange::previous_at(TimeRangePreset::LastNDays(7, false), now, WeekStart::Monday);
+        assert_eq!(r.end, current.start);
+        assert_eq!(r.end - r.start, current.end - current.start);
+    }
+
+    #[test]
+    fn week_start_numbers_weeks_like_iso() {
+        let date = NaiveDate::from_ymd_opt(2021, 1, 3).unwrap();
+        assert_eq!(WeekStart::Monday.label(date), "2020-W53");
+        assert_eq!(WeekStart::Sunday.label(date), "2021-W01");
+
+        for week_start in [WeekStart::Monday, WeekStart::Sunday, WeekStart::Saturday] {
+            let first = week_start.first_day_of(2021, 1).unwrap();
+            assert_eq!(first.weekday(), week_start.weekday());
+            assert_eq!(week_start.week_of(first), (2021, 1));
+        }
+        assert!(WeekStart::Monday.first_day_of(2021, 53).is_none());
+    }
+
     #[test]
     fn preset_custom_passthrough() {
         let start = Utc.with_ymd_and_hms(2025, 1, 1, 0, 0, 0).unwrap();
@@ -0,0 +1,2 @@
+ALTER TABLE events ADD COLUMN rejected_reason TEXT;
+ALTER TABLE afk_events ADD COLUMN rejected_reason TEXT;
@@ -0,0 +1,3 @@
+-- Priority used to decide which source is credited when events from different
+-- sources overlap. NULL falls back to the default policy in the db crate.
+ALTER TABLE sources ADD COLUMN priority INTEGER;
@@ -0,0 +1,6 @@
+-- Ids of events removed by a correction (delete or merge). Ingest skips these ids
+-- so a client re-syncing old data does not bring the events back.
+CREATE TABLE IF NOT EXISTS deleted_events (
+    id         BLOB(16) NOT NULL PRIMARY KEY,
+    deleted_at INTEGER NOT NULL
+);
@@ -0,0 +1,227 @@
+-- Projects are keyed by their normalized root path instead of their name, so two
+-- checkouts with the same directory name stay separate projects. The name is kept
+-- as a display attribute. URLs are keyed by their origin, and projects stored
+-- without a path by their name.
+--
+-- The projects table is rebuilt to drop the UNIQUE constraint on name. Migrations
+-- run with foreign keys off, so dropping it does not cascade to branches, entities
+-- and events.
+
+-- Normalized key of every project: `/` separators, no trailing separator
+CREATE TEMP TABLE project_keys AS
+SELECT
+    id,
+    NULLIF(RTRIM(TRIM(REPLACE(root_path, '\', '/')), '/'), '') AS root_path,
+    NULL AS path_key
+FROM projects;
+
+-- URLs (from browser tracking) are keyed by their origin
+UPDATE project_keys
+SET root_path = SUBSTR(
+    root_path,
+    1,
+    INSTR(root_path, '://') + 1 + INSTR(SUBSTR(root_path, INSTR(root_path, '://') + 3), '/')
+)
+WHERE INSTR(root_path, '://') > 0
+  AND INSTR(SUBSTR(root_path, INSTR(root_path, '://') + 3), '/') > 0;
+
+UPDATE project_keys
+SET path_key = COALESCE(root_path, (SELECT name FROM projects WHERE id = project_keys.id));
+
+-- Projects reported under different names for the same root are merged into the
+-- most recently updated one
+CREATE TEMP TABLE project_remap AS
+WITH ranked AS (
+    SELECT
+        k.id,
+        k.path_key,
+        ROW_NUMBER() OVER (PARTITION BY k.path_key ORDER BY p.last_updated DESC, k.id) AS rank
+    FROM project_keys k
+    JOIN projects p ON p.id = k.id
+)
+SELECT r.id AS old_id, c.id AS new_id
+FROM ranked r
+JOIN ranked c ON c.path_key = r.path_key AND c.rank = 1
+WHERE r.rank > 1;
+
+-- Branches and entities of merged projects are folded into the rows of the same
+-- name in the target project
+CREATE TEMP TABLE branch_remap AS
+WITH targets AS (
+    SELECT b.id, COALESCE(r.new_id, b.project_id) AS project_id, b.name
+    FROM branches b
+    LEFT JOIN project_remap r ON r.old_id = b.project_id
+)
+SELECT t.id AS old_id, MIN(t2.id) AS new_id, t.project_id
+FROM targets t
+JOIN targets t2 ON t2.project_id = t.project_id AND t2.name = t.name
+GROUP BY t.id;
+
+CREATE TEMP TABLE entity_remap AS
+WITH targets AS (
+    SELECT e.id, COALESCE(r.new_id, e.project_id) AS project_id, e.name
+    FROM entities e
+    LEFT JOIN project_remap r ON r.old_id = e.project_id
+)
+SELECT t.id AS old_id, MIN(t2.id) AS new_id, t.project_id
+FROM targets t
+JOIN targets t2 ON t2.project_id = t.project_id AND t2.name = t.name
+GROUP BY t.id;
+
+UPDATE events
+SET branch_id = (SELECT new_id FROM branch_remap WHERE old_id = events.branch_id)
+WHERE branch_id IN (SELECT old_id FROM branch_remap WHERE old_id != new_id);
+
+UPDATE events
+SET entity_id = (SELECT new_id FROM entity_remap WHERE old_id = events.entity_id)
+WHERE entity_id IN (SELECT old_id FROM entity_remap WHERE old_id != new_id);
+
+UPDATE events
+SET project_id = (SELECT new_id FROM project_remap WHERE old_id = events.project_id)
+WHERE project_id IN (SELECT old_id FROM project_remap);
+
+DELETE FROM branches WHERE id IN (SELECT old_id FROM branch_remap WHERE old_id != new_id);
+DELETE FROM entities WHERE id IN (SELECT old_id FROM entity_remap WHERE old_id != new_id);
+
+UPDATE branches
+SET project_id = (SELECT new_id FROM project_remap WHERE old_id = branches.project_id)
+WHERE project_id IN (SELECT old_id FROM project_remap);
+
+UPDATE entities
+SET project_id = (SELECT new_id FROM project_remap WHERE old_id = entities.project_id)
+WHERE project_id IN (SELECT old_id FROM project_remap);
+
+DELETE FROM projects WHERE id IN (SELECT old_id FROM project_remap);
+
+-- Projects that collapsed checkouts with the same directory name are split where
+-- entities are stored as absolute paths: an entity outside the project root whose
+-- path contains `/<project name>/` belongs to the checkout ending at that segment.
+CREATE TEMP TABLE entity_splits AS
+SELECT entity_id, project_id, name, root_path
+FROM (
+    SELECT
+        e.id AS entity_id,
+        p.id AS project_id,
+        p.name,
+        SUBSTR(
+            REPLACE(e.name, '\', '/'),
+            1,
+            INSTR(REPLACE(e.name, '\', '/'), '/' || p.name || '/') + LENGTH(p.name)
+        ) AS root_path
+    FROM entities e
+    JOIN projects p ON p.id = e.project_id
+    JOIN project_keys k ON k.id = p.id
+    WHERE k.root_path IS NOT NULL
+      AND INSTR(k.root_path, '://') = 0
+      AND INSTR(REPLACE(e.name, '\', '/'), '/' || p.name || '/') > 0
+      AND SUBSTR(REPLACE(e.name, '\', '/'), 1, LENGTH(k.root_path) + 1) != k.root_path || '/'
+)
+WHERE root_path NOT IN (SELECT path_key FROM project_keys);
+
+CREATE TEMP TABLE split_projects AS
+SELECT randomblob(16) AS id, root_path, name, project_id AS old_id
+FROM (SELECT DISTINCT root_path, name, project_id FROM entity_splits);
+
+CREATE TABLE projects_new (
+    id           BLOB(16) NOT NULL PRIMARY KEY,
+    name         TEXT NOT NULL,
+    root_path    TEXT NOT NULL UNIQUE,
+    last_updated INTEGER
+);
+INSERT INTO projects_new (id, name, root_path, last_updated)
+SELECT p.id, p.name, k.path_key, p.last_updated
+FROM projects p
+JOIN project_keys k ON k.id = p.id;
+DROP TABLE projects;
+ALTER TABLE projects_new RENAME TO projects;
+
+CREATE INDEX IF NOT EXISTS idx_projects_name ON projects(name);
+
+CREATE TRIGGER IF NOT EXISTS projects_ai
+AFTER INSERT ON projects
+BEGIN
+    INSERT INTO projects_fts(rowid, name) VALUES (NULL, new.name);
+    INSERT INTO projects_fts_map(docid, project_id)
+    VALUES (last_insert_rowid(), new.id);
+END;
+
+CREATE TRIGGER IF NOT EXISTS projects_au
+AFTER UPDATE OF name ON projects
+BEGIN
+    UPDATE projects_fts
+       SET name = new.name
+     WHERE rowid = (SELECT docid FROM projects_fts_map WHERE project_id = old.id);
+END;
+
+-- Runs before the delete: the cascade on projects_fts_map removes the row that
+-- locates the index entry
+CREATE TRIGGER IF NOT EXISTS projects_ad
+BEFORE DELETE ON projects
+BEGIN
+    DELETE FROM projects_fts
+     WHERE rowid = (SELECT docid FROM projects_fts_map WHERE project_id = old.id);
+    DELETE FROM projects_fts_map WHERE project_id = old.id;
+END;
+
+-- Entries left behind by deletes that ran before the fix above
+DELETE FROM projects_fts WHERE rowid NOT IN (SELECT docid FROM projects_fts_map);
+
+INSERT INTO projects (id, name, root_path, last_updated)
+SELECT s.id, s.name, s.root_path, p.last_updated
+FROM split_projects s
+JOIN projects p ON p.id = s.old_id;
+
+-- Events of split entities follow them, with their branches recreated in the new project
+INSERT INTO branches (id, project_id, name, last_updated)
+SELECT randomblob(16), s.id, b.name, MAX(b.last_updated)
+FROM events ev
+JOIN entity_splits es ON es.entity_id = ev.entity_id
+JOIN split_projects s ON s.root_path = es.root_path AND s.old_id = es.project_id
+JOIN branches b ON b.id = ev.branch_id
+GROUP BY s.id, b.name;
+
+UPDATE events
+SET branch_id = (
+    SELECT nb.id
+    FROM entity_splits es
+    JOIN split_projects s ON s.root_path = es.root_path AND s.old_id = es.project_id
+    JOIN branches ob ON ob.id = events.branch_id
+    JOIN branches nb ON nb.project_id = s.id AND nb.name = ob.name
+    WHERE es.entity_id = events.entity_id
+)
+WHERE branch_id IS NOT NULL
+  AND entity_id IN (SELECT entity_id FROM entity_splits);
+
+UPDATE events
+SET project_id = (
+    SELECT s.id
+    FROM entity_splits es
+    JOIN split_projects s ON s.root_path = es.root_path AND s.old_id = es.project_id
+    WHERE es.entity_id = events.entity_id
+)
+WHERE entity_id IN (SELECT entity_id FROM entity_splits);
+
+UPDATE entities
+SET project_id = (
+    SELECT s.id
+    FROM entity_splits es
+    JOIN split_projects s ON s.root_path = es.root_path AND s.old_id = es.project_id
+    WHERE es.entity_id = entities.id
+)
+WHERE id IN (SELECT entity_id FROM entity_splits);
+
+-- Root paths of projects merged into another. Events still reported under them are
+-- credited to the project they were merged into instead of recreating it.
+CREATE TABLE project_aliases (
+    root_path  TEXT NOT NULL PRIMARY KEY,
+    project_id BLOB(16) NOT NULL,
+    FOREIGN KEY (project_id) REFERENCES projects (id) ON DELETE CASCADE
+);
+CREATE INDEX idx_project_aliases_project ON project_aliases(project_id);
+
+DROP TABLE project_keys;
+DROP TABLE project_remap;
+DROP TABLE branch_remap;
+DROP TABLE entity_remap;
+DROP TABLE entity_splits;
+DROP TABLE split_projects;
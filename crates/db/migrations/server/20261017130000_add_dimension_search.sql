@@ -0,0 +1,129 @@
+-- Full-text indexes over entity, branch and app names, kept in sync the same way as
+-- projects_fts. The map tables locate the index entry of a row, as the FTS rowid
+-- cannot reference a UUID key.
+CREATE TABLE IF NOT EXISTS entities_fts_map
+(
+    docid     INTEGER PRIMARY KEY,
+    entity_id BLOB(16) NOT NULL UNIQUE,
+    FOREIGN KEY (entity_id) REFERENCES entities(id) ON DELETE CASCADE
+);
+
+CREATE VIRTUAL TABLE IF NOT EXISTS entities_fts
+USING fts5(
+    name,
+    tokenize = 'porter unicode61'
+);
+
+CREATE TABLE IF NOT EXISTS branches_fts_map
+(
+    docid     INTEGER PRIMARY KEY,
+    branch_id BLOB(16) NOT NULL UNIQUE,
+    FOREIGN KEY (branch_id) REFERENCES branches(id) ON DELETE CASCADE
+);
+
+CREATE VIRTUAL TABLE IF NOT EXISTS branches_fts
+USING fts5(
+    name,
+    tokenize = 'porter unicode61'
+);
+
+CREATE TABLE IF NOT EXISTS apps_fts_map
+(
+    docid  INTEGER PRIMARY KEY,
+    app_id BLOB(16) NOT NULL UNIQUE,
+    FOREIGN KEY (app_id) REFERENCES apps(id) ON DELETE CASCADE
+);
+
+CREATE VIRTUAL TABLE IF NOT EXISTS apps_fts
+USING fts5(
+    name,
+    tokenize = 'porter unicode61'
+);
+
+-- Index the existing rows
+INSERT INTO entities_fts_map (docid, entity_id)
+SELECT ROW_NUMBER() OVER (ORDER BY id), id FROM entities;
+INSERT INTO entities_fts (rowid, name)
+SELECT m.docid, e.name FROM entities_fts_map m JOIN entities e ON e.id = m.entity_id;
+
+INSERT INTO branches_fts_map (docid, branch_id)
+SELECT ROW_NUMBER() OVER (ORDER BY id), id FROM branches;
+INSERT INTO branches_fts (rowid, name)
+SELECT m.docid, b.name FROM branches_fts_map m JOIN branches b ON b.id = m.branch_id;
+
+INSERT INTO apps_fts_map (docid, app_id)
+SELECT ROW_NUMBER() OVER (ORDER BY id), id FROM apps;
+INSERT INTO apps_fts (rowid, name)
+SELECT m.docid, a.name FROM apps_fts_map m JOIN apps a ON a.id = m.app_id;
+
+CREATE TRIGGER IF NOT EXISTS entities_ai
+AFTER INSERT ON entities
+BEGIN
+    INSERT INTO entities_fts(rowid, name) VALUES (NULL, new.name);
+    INSERT INTO entities_fts_map(docid, entity_id)
+    VALUES (last_insert_rowid(), new.id);
+END;
+
+CREATE TRIGGER IF NOT EXISTS entities_au
+AFTER UPDATE OF name ON entities
+BEGIN
+    UPDATE entities_fts
+       SET name = new.name
+     WHERE rowid = (SELECT docid FROM entities_fts_map WHERE entity_id = old.id);
+END;
+
+CREATE TRIGGER IF NOT EXISTS entities_ad
+BEFORE DELETE ON entities
+BEGIN
+    DELETE FROM entities_fts
+     WHERE rowid = (SELECT docid FROM entities_fts_map WHERE entity_id = old.id);
+    DELETE FROM entities_fts_map WHERE entity_id = old.id;
+END;
+
+CREATE TRIGGER IF NOT EXISTS branches_ai
+AFTER INSERT ON branches
+BEGIN
+    INSERT INTO branches_fts(rowid, name) VALUES (NULL, new.name);
+    INSERT INTO branches_fts_map(docid, branch_id)
+    VALUES (last_insert_rowid(), new.id);
+END;
+
+CREATE TRIGGER IF NOT EXISTS branches_au
+AFTER UPDATE OF name ON branches
+BEGIN
+    UPDATE branches_fts
+       SET name = new.name
+     WHERE rowid = (SELECT docid FROM branches_fts_map WHERE branch_id = old.id);
+END;
+
+CREATE TRIGGER IF NOT EXISTS branches_ad
+BEFORE DELETE ON branches
+BEGIN
+    DELETE FROM branches_fts
+     WHERE rowid = (SELECT docid FROM branches_fts_map WHERE branch_id = old.id);
+    DELETE FROM branches_fts_map WHERE branch_id = old.id;
+END;
+
+CREATE TRIGGER IF NOT EXISTS apps_ai
+AFTER INSERT ON apps
+BEGIN
+    INSERT INTO apps_fts(rowid, name) VALUES (NULL, new.name);
+    INSERT INTO apps_fts_map(docid, app_id)
+    VALUES (last_insert_rowid(), new.id);
+END;
+
+CREATE TRIGGER IF NOT EXISTS apps_au
+AFTER UPDATE OF name ON apps
+BEGIN
+    UPDATE apps_fts
+       SET name = new.name
+     WHERE rowid = (SELECT docid FROM apps_fts_map WHERE app_id = old.id);
+END;
+
+CREATE TRIGGER IF NOT EXISTS apps_ad
+BEFORE DELETE ON apps
+BEGIN
+    DELETE FROM apps_fts
+     WHERE rowid = (SELECT docid FROM apps_fts_map WHERE app_id = old.id);
+    DELETE FROM apps_fts_map WHERE app_id = old.id;
+END;
@@ -0,0 +1,18 @@
+-- Rules that recategorize events at ingest and over historical ranges. Conditions
+-- left NULL match every event; actions left NULL keep the event's value.
+CREATE TABLE IF NOT EXISTS rules
+(
+    id            BLOB(16) NOT NULL PRIMARY KEY,
+    name          TEXT NOT NULL,
+    priority      INTEGER NOT NULL DEFAULT 0,
+    enabled       INTEGER NOT NULL DEFAULT 1,
+    match_app     TEXT,
+    match_entity  TEXT,
+    match_domain  TEXT,
+    match_path    TEXT,
+    match_project TEXT,
+    match_source  TEXT,
+    set_category  TEXT,
+    set_project   TEXT,
+    last_updated  INTEGER
+);
@@ -0,0 +1,39 @@
+-- Free-form labels linked to events and projects. An event carries its own tags and
+-- the tags of its project. Rules link the tags they add to matching events.
+CREATE TABLE IF NOT EXISTS tags
+(
+    id           BLOB(16) NOT NULL PRIMARY KEY,
+    name         TEXT NOT NULL UNIQUE COLLATE NOCASE,
+    last_updated INTEGER
+);
+
+CREATE TABLE IF NOT EXISTS event_tags
+(
+    event_id BLOB(16) NOT NULL,
+    tag_id   BLOB(16) NOT NULL,
+    PRIMARY KEY (event_id, tag_id),
+    FOREIGN KEY (event_id) REFERENCES events (id) ON DELETE CASCADE,
+    FOREIGN KEY (tag_id) REFERENCES tags (id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS idx_event_tags_tag_id ON event_tags (tag_id);
+
+CREATE TABLE IF NOT EXISTS project_tags
+(
+    project_id BLOB(16) NOT NULL,
+    tag_id     BLOB(16) NOT NULL,
+    PRIMARY KEY (project_id, tag_id),
+    FOREIGN KEY (project_id) REFERENCES projects (id) ON DELETE CASCADE,
+    FOREIGN KEY (tag_id) REFERENCES tags (id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS idx_project_tags_tag_id ON project_tags (tag_id);
+
+CREATE TABLE IF NOT EXISTS rule_tags
+(
+    rule_id BLOB(16) NOT NULL,
+    tag_id  BLOB(16) NOT NULL,
+    PRIMARY KEY (rule_id, tag_id),
+    FOREIGN KEY (rule_id) REFERENCES rules (id) ON DELETE CASCADE,
+    FOREIGN KEY (tag_id) REFERENCES tags (id) ON DELETE CASCADE
+);
@@ -0,0 +1,16 @@
+-- Billing settings used by invoice reports. Each row belongs to exactly one project,
+-- tag or category. A category's rate takes precedence over the invoiced project's,
+-- which takes precedence over the invoiced tag's.
+CREATE TABLE IF NOT EXISTS billing_rates
+(
+    project_id   BLOB(16) UNIQUE,
+    tag_id       BLOB(16) UNIQUE,
+    category_id  BLOB(16) UNIQUE,
+    hourly_rate  REAL,
+    billable     INTEGER NOT NULL DEFAULT 1,
+    last_updated INTEGER,
+    CHECK ((project_id IS NOT NULL) + (tag_id IS NOT NULL) + (category_id IS NOT NULL) = 1),
+    FOREIGN KEY (project_id) REFERENCES projects (id) ON DELETE CASCADE,
+    FOREIGN KEY (tag_id) REFERENCES tags (id) ON DELETE CASCADE,
+    FOREIGN KEY (category_id) REFERENCES categories (id) ON DELETE CASCADE
+);
@@ -1,9 +1,13 @@
 use crate::error::DBError;
+#[cfg(feature = "server")]
+use crate::server::dimensions::DimensionCache;
 use crate::utils::extract_db_file_path;
 use common::keyring::Keyring;
 use sqlx::sqlite::SqliteConnectOptions;
 use sqlx::{SqlitePool, sqlite::SqlitePoolOptions};
 use std::str::FromStr;
+#[cfg(feature = "server")]
+use std::sync::Arc;
 use uuid::Uuid;
 
 #[cfg(all(feature = "desktop", not(feature = "server")))]
@@ -15,6 +19,8 @@ static MIGRATOR: sqlx::migrate::Migrator = sqlx::migrate!("./migrations/server")
 #[derive(Clone)]
 pub struct DBContext {
     pool: SqlitePool,
+    #[cfg(feature = "server")]
+    dimension_cache: Arc<DimensionCache>,
 }
 
 impl DBContext {
@@ -43,15 +49,48 @@ impl DBContext {
             all(feature = "desktop", not(feature = "server")),
             all(feature = "server", not(feature = "desktop"))
         ))]
-        MIGRATOR.run(&pool).await?;
+        run_migrations(&pool, &MIGRATOR).await?;
 
-        Ok(Self { pool })
+        Ok(Self {
+            pool,
+            #[cfg(feature = "server")]
+            dimension_cache: Arc::default(),
+        })
     }
 
     /// Return a reference to the internal `SqlitePool`.
     pub fn pool(&self) -> &SqlitePool {
         &self.pool
     }
+
+    /// Return the dimension id cache shared by every clone of this context.
+    #[cfg(feature = "server")]
+    pub fn dimension_cache(&self) -> &DimensionCache {
+        &self.dimension_cache
+    }
+}
+
+/// Runs pending migrations on one connection with foreign keys off. Migrations rebuild
+/// tables to change their constraints, and dropping a table with foreign keys on deletes
+/// the rows referencing it. The pragma is a no-op inside a transaction, so it is set
+/// before the migrator begins one.
+#[cfg(any(
+    all(feature = "desktop", not(feature = "server")),
+    all(feature = "server", not(feature = "desktop"))
+))]
+pub(crate) async fn run_migrations(
+    pool: &SqlitePool,
+    migrator: &sqlx::migrate::Migrator,
+) -> Result<(), DBError> {
+    let mut conn = pool.acquire().await?;
+    sqlx::query("PRAGMA foreign_keys = OFF")
+        .execute(&mut *conn)
+        .await?;
+    migrator.run(&mut *conn).await?;
+    sqlx::query("PRAGMA foreign_keys = ON")
+        .execute(&mut *conn)
+        .await?;
+    Ok(())
 }
 
 fn get_encryption_key() -> Result<Option<String>, DBError> {
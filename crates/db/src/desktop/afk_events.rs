@@ -1,7 +1,11 @@
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 
-use crate::{DBContext, error::DBError, utils::update_synced_in};
+use crate::{
+    DBContext,
+    error::DBError,
+    utils::{update_rejected_in, update_synced_in},
+};
 
 #[derive(Serialize, Deserialize, Clone, Debug, sqlx::FromRow)]
 pub struct AFKEvent {
@@ -28,6 +32,7 @@ impl AFKEvent {
         Ok(())
     }
 
+    /// Fetches up to 100 AFK events that have neither been synced nor rejected by the server
     pub async fn unsynced(db_context: &DBContext) -> Result<Vec<Self>, DBError> {
         let rows = sqlx::query!(
             "
@@ -37,7 +42,7 @@ impl AFKEvent {
              afk_end,
              duration
             FROM afk_events
-            WHERE synced = 0
+            WHERE synced = 0 AND rejected_reason IS NULL
             LIMIT 100
             "
         )
@@ -70,6 +75,14 @@ impl AFKEvent {
         update_synced_in(db_context, "afk_events", &ids).await
     }
 
+    /// Sets AFK events the server rejected aside so they are not synced again
+    pub async fn mark_as_rejected(
+        db_context: &DBContext,
+        rejected: &[(i64, String)],
+    ) -> Result<(), DBError> {
+        update_rejected_in(db_context, "afk_events", rejected).await
+    }
+
     pub async fn delete_synced(db_context: &DBContext) -> Result<(), DBError> {
         sqlx::query!(
             "DELETE FROM afk_events
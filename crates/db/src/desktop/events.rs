@@ -1,7 +1,11 @@
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 
-use crate::{DBContext, error::DBError, utils::update_synced_in};
+use crate::{
+    DBContext,
+    error::DBError,
+    utils::{update_rejected_in, update_synced_in},
+};
 
 #[derive(Serialize, Deserialize, Clone, Debug, sqlx::FromRow)]
 pub struct Event {
@@ -46,6 +50,7 @@ impl Event {
         Ok(())
     }
 
+    /// Fetches up to 100 events that have neither been synced nor rejected by the server
     pub async fn unsynced(db_context: &DBContext) -> Result<Vec<Self>, DBError> {
         let rows = sqlx::query!(
             "
@@ -64,7 +69,7 @@ impl Event {
              source_name,
              end_timestamp
             FROM events
-            WHERE synced = 0
+            WHERE synced = 0 AND rejected_reason IS NULL
             LIMIT 100
             "
         )
@@ -103,6 +108,14 @@ impl Event {
         update_synced_in(db_context, "events", &ids).await
     }
 
+    /// Sets events the server rejected aside so they are not synced again
+    pub async fn mark_as_rejected(
+        db_context: &DBContext,
+        rejected: &[(i64, String)],
+    ) -> Result<(), DBError> {
+        update_rejected_in(db_context, "events", rejected).await
+    }
+
     pub async fn delete_synced(db_context: &DBContext) -> Result<(), DBError> {
         sqlx::query!(
             "DELETE FROM events
@@ -23,6 +23,10 @@ pub enum DBError {
     #[error("Unsupported configuration: {0}")]
     Unsupported(&'static str),
 
+    /// A rule pattern is not a valid regular expression
+    #[error("Invalid pattern: {0}")]
+    Pattern(#[from] regex::Error),
+
     #[error("Migration error: {0}")]
     Migration(#[from] sqlx::migrate::MigrateError),
 }
@@ -11,6 +11,9 @@ pub struct BucketTimeSummary {
     pub bucket: String,
     /// A map of group_key: total_seconds
     pub grouped_values: HashMap<String, i64>,
+    /// Values of the `subgroup_by` dimensions, in order, that further split this group.
+    /// Empty without `subgroup_by`.
+    pub subgroups: Vec<String>,
     /// Optional per-group metadata (e.g. entity type when grouping by Entity)
     pub group_meta: Option<String>,
 }
@@ -1,8 +1,12 @@
-use crate::{DBContext, error::DBError, server::summary::SummaryQueryBuilder};
+use crate::{
+    DBContext,
+    error::DBError,
+    server::{events::into_page, summary::SummaryQueryBuilder, utils::cursor::EventCursor},
+};
 use chrono::{DateTime, Utc};
-use common::models::outputs::FullEvent;
+use common::models::outputs::{EventPage, FullEvent};
 use serde::{Deserialize, Serialize};
-use sqlx::{QueryBuilder, Row, Sqlite};
+use sqlx::{QueryBuilder, Row, Sqlite, sqlite::SqliteRow};
 use uuid::Uuid;
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -14,14 +18,17 @@ pub struct AFKEvent {
 }
 
 impl AFKEvent {
-    /// Bulk inserts AFK events into the database
-    pub async fn bulk_create(db_context: &DBContext, events: &[Self]) -> Result<u64, DBError> {
+    /// Bulk inserts AFK events into the database, returning the ids of the rows that were inserted
+    pub async fn bulk_create(
+        db_context: &DBContext,
+        events: &[Self],
+    ) -> Result<Vec<Uuid>, DBError> {
         if events.is_empty() {
-            return Ok(0);
+            return Ok(Vec::new());
         }
 
         let mut tx = db_context.pool().begin().await?;
-        let mut total_inserted: u64 = 0;
+        let mut inserted = Vec::with_capacity(events.len());
 
         for ev in events {
             let res = sqlx::query!(
@@ -36,17 +43,77 @@ impl AFKEvent {
             .execute(&mut *tx)
             .await?;
 
-            total_inserted += res.rows_affected();
+            if res.rows_affected() > 0 {
+                inserted.push(ev.id);
+            }
         }
 
         tx.commit().await?;
-        Ok(total_inserted)
+        Ok(inserted)
     }
 }
 
 impl SummaryQueryBuilder {
     /// Fetch AFK events overlapping the configured time range
     pub async fn fetch_afk_event_range(&self, db: &DBContext) -> Result<Vec<FullEvent>, DBError> {
+        let mut qb = self.afk_range_query();
+        qb.push(" ORDER BY afk_start_i");
+
+        let query = qb.build();
+
+        #[cfg(debug_assertions)]
+        {
+            use crate::utils::explain_query;
+            use log::{info, warn};
+            use sqlx::Execute;
+
+            let sql = query.sql();
+            info!("Executing AFK range query: {}", sql);
+            if let Err(e) = explain_query(db.pool(), sql).await {
+                warn!("Failed to explain AFK query: {}", e);
+            }
+        }
+
+        let rows = query.fetch_all(db.pool()).await?;
+
+        rows.iter().map(afk_event_from_row).collect()
+    }
+
+    /// Fetches up to `limit` AFK events overlapping the configured time range, ordered by
+    /// start and id, that come after `after`
+    pub async fn fetch_afk_event_page(
+        &self,
+        db: &DBContext,
+        after: Option<EventCursor>,
+        limit: u32,
+    ) -> Result<EventPage, DBError> {
+        let mut qb = self.afk_range_query();
+
+        if let Some(cursor) = after {
+            qb.push(" AND (afk_start_i > ")
+                .push_bind(cursor.timestamp)
+                .push(" OR (afk_start_i = ")
+                .push_bind(cursor.timestamp)
+                .push(" AND id > ")
+                .push_bind(cursor.id)
+                .push("))");
+        }
+
+        qb.push(" ORDER BY afk_start_i, id LIMIT ")
+            .push_bind(i64::from(limit) + 1);
+
+        let rows = qb.build().fetch_all(db.pool()).await?;
+        let events = rows
+            .iter()
+            .map(afk_event_from_row)
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(into_page(events, limit))
+    }
+
+    /// Builds the AFK query up to its WHERE clause, normalizing legacy text timestamps
+    /// to unix seconds
+    fn afk_range_query(&self) -> QueryBuilder<'_, Sqlite> {
         let mut qb = QueryBuilder::<Sqlite>::new(
             "
             SELECT
@@ -94,53 +161,32 @@ impl SummaryQueryBuilder {
             qb.push(")");
         }
 
-        qb.push(" ORDER BY afk_start_i");
-
-        let query = qb.build();
-
-        #[cfg(debug_assertions)]
-        {
-            use crate::utils::explain_query;
-            use log::{info, warn};
-            use sqlx::Execute;
-
-            let sql = query.sql();
-            info!("Executing AFK range query: {}", sql);
-            if let Err(e) = explain_query(db.pool(), sql).await {
-                warn!("Failed to explain AFK query: {}", e);
-            }
-        }
-
-        let rows = query.fetch_all(db.pool()).await?;
-
-        let mut afk_events = Vec::with_capacity(rows.len());
-
-        for row in rows {
-            let id = row.try_get("id").map(Uuid::from_slice).unwrap()?;
-
-            let timestamp: DateTime<Utc> =
-                DateTime::<Utc>::from_timestamp(row.try_get::<i64, _>("afk_start_i")?, 0)
-                    .unwrap_or_default();
-            let end_timestamp: Option<DateTime<Utc>> =
-                DateTime::<Utc>::from_timestamp(row.try_get::<i64, _>("afk_end_i")?, 0);
-            let duration: Option<i64> = row.try_get("duration")?;
-
-            afk_events.push(FullEvent {
-                id,
-                timestamp,
-                end_timestamp,
-                duration,
-                category: "AFK".to_string(),
-                app: None,
-                entity: None,
-                entity_type: None,
-                project: None,
-                branch: None,
-                language: None,
-                source: "skopio-desktop".to_string(),
-            });
-        }
-
-        Ok(afk_events)
+        qb
     }
 }
+
+fn afk_event_from_row(row: &SqliteRow) -> Result<FullEvent, DBError> {
+    let id = row.try_get("id").map(Uuid::from_slice).unwrap()?;
+
+    let timestamp: DateTime<Utc> =
+        DateTime::<Utc>::from_timestamp(row.try_get::<i64, _>("afk_start_i")?, 0)
+            .unwrap_or_default();
+    let end_timestamp: Option<DateTime<Utc>> =
+        DateTime::<Utc>::from_timestamp(row.try_get::<i64, _>("afk_end_i")?, 0);
+    let duration: Option<i64> = row.try_get("duration")?;
+
+    Ok(FullEvent {
+        id,
+        timestamp,
+        end_timestamp,
+        duration,
+        category: "AFK".to_string(),
+        app: None,
+        entity: None,
+        entity_type: None,
+        project: None,
+        branch: None,
+        language: None,
+        source: "skopio-desktop".to_string(),
+    })
+}
@@ -0,0 +1,1121 @@
+use std::collections::{HashMap, HashSet};
+
+use chrono::Utc;
+use common::models::{
+    ArchiveRecord, ArchiveRecordKind, Group, RuleActions, inputs::RuleInput,
+    outputs::ArchiveImportReport,
+};
+use sqlx::{QueryBuilder, Sqlite, SqliteConnection};
+use uuid::Uuid;
+
+use crate::{
+    DBContext,
+    error::DBError,
+    server::{
+        afk_events::AFKEvent,
+        billing::BillingRates,
+        events::Event,
+        rules::Rules,
+        tags::{Tags, add_project_tags},
+    },
+};
+
+/// Layout version written into the header of every archive. Imports reject archives
+/// of other versions.
+pub const ARCHIVE_VERSION: u32 = 1;
+
+/// Maximum number of ids looked up by a single statement
+const CHUNK_SIZE: usize = 500;
+
+/// Dimension tables keyed by name alone, in archive order
+const NAMED_TABLES: [(ArchiveRecordKind, &str); 4] = [
+    (ArchiveRecordKind::Tag, "tags"),
+    (ArchiveRecordKind::App, "apps"),
+    (ArchiveRecordKind::Category, "categories"),
+    (ArchiveRecordKind::Language, "languages"),
+];
+
+#[derive(Debug, sqlx::FromRow)]
+struct NamedRow {
+    id: Uuid,
+    name: String,
+}
+
+#[derive(Debug, sqlx::FromRow)]
+struct SourceRow {
+    id: Uuid,
+    name: String,
+    priority: Option<i64>,
+}
+
+#[derive(Debug, sqlx::FromRow)]
+struct ProjectRow {
+    id: Uuid,
+    name: String,
+    root_path: String,
+}
+
+#[derive(Debug, sqlx::FromRow)]
+struct AliasRow {
+    root_path: String,
+    project_id: Uuid,
+}
+
+#[derive(Debug, sqlx::FromRow)]
+struct ScopedRow {
+    id: Uuid,
+    project_id: Uuid,
+    name: String,
+    entity_type: Option<String>,
+}
+
+#[derive(Debug, sqlx::FromRow)]
+struct EventRow {
+    id: Uuid,
+    timestamp: i64,
+    end_timestamp: Option<i64>,
+    duration: Option<i64>,
+    app_id: Uuid,
+    category_id: Uuid,
+    project_id: Option<Uuid>,
+    entity_id: Option<Uuid>,
+    branch_id: Option<Uuid>,
+    language_id: Option<Uuid>,
+    source_id: Uuid,
+}
+
+#[derive(Debug, sqlx::FromRow)]
+struct AfkRow {
+    id: Uuid,
+    afk_start: i64,
+    afk_end: Option<i64>,
+    duration: Option<i64>,
+}
+
+#[derive(Debug, sqlx::FromRow)]
+struct DeletedRow {
+    id: Uuid,
+    deleted_at: i64,
+}
+
+/// Reads the whole server database as archive records.
+///
+/// An archive is the [`Archive::header`], then [`Archive::dimensions`] and
+/// [`Archive::settings`], then every page of [`Archive::events`], [`Archive::afk_events`]
+/// and [`Archive::deleted_events`].
+pub struct Archive;
+
+impl Archive {
+    pub fn header() -> ArchiveRecord {
+        ArchiveRecord {
+            record: ArchiveRecordKind::Header,
+            version: Some(ARCHIVE_VERSION),
+            timestamp: Some(Utc::now().timestamp()),
+            ..Default::default()
+        }
+    }
+
+    /// Every dimension row, with projects before their aliases, branches and entities
+    pub async fn dimensions(db: &DBContext) -> Result<Vec<ArchiveRecord>, DBError> {
+        let mut records = Vec::new();
+
+        for (kind, table) in NAMED_TABLES {
+            let rows: Vec<NamedRow> =
+                sqlx::query_as(&format!("SELECT id, name FROM {table} ORDER BY name"))
+                    .fetch_all(db.pool())
+                    .await?;
+            records.extend(rows.into_iter().map(|row| ArchiveRecord {
+                record: kind,
+                id: Some(row.id),
+                name: Some(row.name),
+                ..Default::default()
+            }));
+        }
+
+        let sources: Vec<SourceRow> =
+            sqlx::query_as("SELECT id, name, priority FROM sources ORDER BY name")
+                .fetch_all(db.pool())
+                .await?;
+        records.extend(sources.into_iter().map(|row| ArchiveRecord {
+            record: ArchiveRecordKind::Source,
+            id: Some(row.id),
+            name: Some(row.name),
+            priority: row.priority,
+            ..Default::default()
+        }));
+
+        let links: Vec<(Uuid, String)> = sqlx::query_as(
+            "SELECT project_tags.project_id, tags.name FROM project_tags
+            JOIN tags ON tags.id = project_tags.tag_id
+            ORDER BY tags.name COLLATE NOCASE",
+        )
+        .fetch_all(db.pool())
+        .await?;
+        let mut project_tags: HashMap<Uuid, Vec<String>> = HashMap::new();
+        for (project_id, name) in links {
+            project_tags.entry(project_id).or_default().push(name);
+        }
+
+        let projects: Vec<ProjectRow> =
+            sqlx::query_as("SELECT id, name, root_path FROM projects ORDER BY root_path")
+                .fetch_all(db.pool())
+                .await?;
+        records.extend(projects.into_iter().map(|row| ArchiveRecord {
+            record: ArchiveRecordKind::Project,
+            id: Some(row.id),
+            name: Some(row.name),
+            root_path: Some(row.root_path),
+            tags: project_tags.remove(&row.id).unwrap_or_default(),
+            ..Default::default()
+        }));
+
+        let aliases: Vec<AliasRow> =
+            sqlx::query_as("SELECT root_path, project_id FROM project_aliases ORDER BY root_path")
+                .fetch_all(db.pool())
+                .await?;
+        records.extend(aliases.into_iter().map(|row| ArchiveRecord {
+            record: ArchiveRecordKind::ProjectAlias,
+            root_path: Some(row.root_path),
+            project_id: Some(row.project_id),
+            ..Default::default()
+        }));
+
+        let branches: Vec<ScopedRow> = sqlx::query_as(
+            "SELECT id, project_id, name, NULL AS entity_type FROM branches
+            ORDER BY project_id, name",
+        )
+        .fetch_all(db.pool())
+        .await?;
+        let entities: Vec<ScopedRow> = sqlx::query_as(
+            "SELECT id, project_id, name, type AS entity_type FROM entities
+            ORDER BY project_id, name",
+        )
+        .fetch_all(db.pool())
+        .await?;
+        for (kind, rows) in [
+            (ArchiveRecordKind::Branch, branches),
+            (ArchiveRecordKind::Entity, entities),
+        ] {
+            records.extend(rows.into_iter().map(|row| ArchiveRecord {
+                record: kind,
+                id: Some(row.id),
+                name: Some(row.name),
+                project_id: Some(row.project_id),
+                entity_type: row.entity_type,
+                ..Default::default()
+            }));
+        }
+
+        Ok(records)
+    }
+
+    /// Every rule, then the billing rate of every project, tag and category that has one
+    pub async fn settings(db: &DBContext) -> Result<Vec<ArchiveRecord>, DBError> {
+        let mut records = Vec::new();
+
+        for rule in Rules::all(db).await? {
+            let mut actions = rule.actions;
+            let tags = actions.tags.take().unwrap_or_default();
+            records.push(ArchiveRecord {
+                record: ArchiveRecordKind::Rule,
+                id: Some(rule.id),
+                name: Some(rule.name),
+                priority: Some(rule.priority),
+                enabled: Some(rule.enabled),
+                conditions: Some(rule.conditions),
+                actions: Some(actions),
+                tags,
+                ..Default::default()
+            });
+        }
+
+        for rate in BillingRates::all(db).await? {
+            let mut record = ArchiveRecord {
+                record: ArchiveRecordKind::Rate,
+                hourly_rate: rate.hourly_rate,
+                billable: Some(rate.billable),
+                ..Default::default()
+            };
+            match rate.kind {
+                Group::Project => record.project_id = Some(rate.id),
+                Group::Tag => record.tag_id = Some(rate.id),
+                _ => record.category_id = Some(rate.id),
+            }
+            records.push(record);
+        }
+
+        Ok(records)
+    }
+
+    /// Up to `limit` events with an id greater than `after`, by id
+    pub async fn events(
+        db: &DBContext,
+        after: Option<Uuid>,
+        limit: u32,
+    ) -> Result<Vec<ArchiveRecord>, DBError> {
+        let mut qb = QueryBuilder::<Sqlite>::new(
+            "SELECT id, timestamp, end_timestamp, duration, app_id, category_id, project_id,
+                entity_id, branch_id, language_id, source_id
+            FROM events",
+        );
+        push_page(&mut qb, after, limit);
+        let rows: Vec<EventRow> = qb.build_query_as().fetch_all(db.pool()).await?;
+
+        let ids: Vec<Uuid> = rows.iter().map(|row| row.id).collect();
+        let mut tags = event_tags(db, &ids).await?;
+
+        Ok(rows
+            .into_iter()
+            .map(|row| ArchiveRecord {
+                record: ArchiveRecordKind::Event,
+                id: Some(row.id),
+                timestamp: Some(row.timestamp),
+                end_timestamp: row.end_timestamp,
+                duration: row.duration,
+                app_id: Some(row.app_id),
+                category_id: Some(row.category_id),
+                project_id: row.project_id,
+                entity_id: row.entity_id,
+                branch_id: row.branch_id,
+                language_id: row.language_id,
+                source_id: Some(row.source_id),
+                tags: tags.remove(&row.id).unwrap_or_default(),
+                ..Default::default()
+            })
+            .collect())
+    }
+
+    /// Up to `limit` AFK events with an id greater than `after`, by id
+    pub async fn afk_events(
+        db: &DBContext,
+        after: Option<Uuid>,
+        limit: u32,
+    ) -> Result<Vec<ArchiveRecord>, DBError> {
+        let mut qb =
+            QueryBuilder::<Sqlite>::new("SELECT id, afk_start, afk_end, duration FROM afk_events");
+        push_page(&mut qb, after, limit);
+        let rows: Vec<AfkRow> = qb.build_query_as().fetch_all(db.pool()).await?;
+
+        Ok(rows
+            .into_iter()
+            .map(|row| ArchiveRecord {
+                record: ArchiveRecordKind::Afk,
+                id: Some(row.id),
+                timestamp: Some(row.afk_start),
+                end_timestamp: row.afk_end,
+                duration: row.duration,
+                ..Default::default()
+            })
+            .collect())
+    }
+
+    /// Up to `limit` ids of deleted events greater than `after`, by id
+    pub async fn deleted_events(
+        db: &DBContext,
+        after: Option<Uuid>,
+        limit: u32,
+    ) -> Result<Vec<ArchiveRecord>, DBError> {
+        let mut qb = QueryBuilder::<Sqlite>::new("SELECT id, deleted_at FROM deleted_events");
+        push_page(&mut qb, after, limit);
+        let rows: Vec<DeletedRow> = qb.build_query_as().fetch_all(db.pool()).await?;
+
+        Ok(rows
+            .into_iter()
+            .map(|row| ArchiveRecord {
+                record: ArchiveRecordKind::Deleted,
+                id: Some(row.id),
+                timestamp: Some(row.deleted_at),
+                ..Default::default()
+            })
+            .collect())
+    }
+}
+
+/// Restores archive records into the server database.
+///
+/// Dimension rows are matched to existing rows by id, then by their natural key (name,
+/// project root path, or name within a project), and the events of the archive are
+/// pointed at the matching rows. Events and AFK events keep their ids, so records that
+/// already exist are skipped and importing the same archive twice changes nothing.
+///
+/// Records must be passed in archive order, starting with the header, but may be split
+/// over any number of [`ArchiveImport::apply`] calls.
+#[derive(Debug, Default)]
+pub struct ArchiveImport {
+    /// Local id of every dimension row imported so far, by archive id
+    ids: HashMap<Uuid, Uuid>,
+    has_header: bool,
+    report: ArchiveImportReport,
+}
+
+impl ArchiveImport {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub async fn apply(
+        &mut self,
+        db: &DBContext,
+        records: &[ArchiveRecord],
+    ) -> Result<(), DBError> {
+        let mut archived: Vec<(Event, Uuid, &[String])> = Vec::new();
+        let mut afk_events: Vec<AFKEvent> = Vec::new();
+        let mut deleted: Vec<(Uuid, i64)> = Vec::new();
+
+        let mut tx = db.pool().begin().await?;
+        for record in records {
+            if record.record == ArchiveRecordKind::Header {
+                if record.version != Some(ARCHIVE_VERSION) {
+                    return Err(DBError::Unsupported("Unsupported archive version"));
+                }
+                self.has_header = true;
+                continue;
+            }
+            if !self.has_header {
+                return Err(DBError::MissingField("header"));
+            }
+            match record.record {
+                ArchiveRecordKind::ProjectAlias => {
+                    self.alias(&mut tx, record).await?;
+                    continue;
+                }
+                ArchiveRecordKind::Rate => {
+                    self.rate(&mut tx, record).await?;
+                    continue;
+                }
+                _ => {}
+            }
+
+            let id = record.id.ok_or(DBError::MissingField("id"))?;
+            match record.record {
+                ArchiveRecordKind::Header
+                | ArchiveRecordKind::ProjectAlias
+                | ArchiveRecordKind::Rate => {}
+                ArchiveRecordKind::Rule => self.rule(&mut tx, id, record).await?,
+                ArchiveRecordKind::Event => {
+                    let timestamp = record.timestamp.ok_or(DBError::MissingField("timestamp"))?;
+                    match self.event(id, timestamp, record) {
+                        Some(event) => {
+                            let ingest_id = event.ingest_id();
+                            archived.push((event, ingest_id, record.tags.as_slice()));
+                        }
+                        None => self.report.skipped_events += 1,
+                    }
+                }
+                ArchiveRecordKind::Afk => afk_events.push(AFKEvent {
+                    id,
+                    afk_start: record.timestamp.ok_or(DBError::MissingField("timestamp"))?,
+                    afk_end: record.end_timestamp,
+                    duration: record.duration,
+                }),
+                ArchiveRecordKind::Deleted => deleted.push((
+                    id,
+                    record.timestamp.unwrap_or_else(|| Utc::now().timestamp()),
+                )),
+                _ => self.dimension(&mut tx, id, record).await?,
+            }
+        }
+        // Events stored or deleted under their archived id, e.g. by an earlier import of
+        // the same archive, are duplicates, as are events this server ingested itself
+        // with the dimensions they map to
+        let ids: Vec<Uuid> = archived
+            .iter()
+            .flat_map(|(event, ingest_id, _)| [event.id, *ingest_id])
+            .collect();
+        let existing = existing_event_ids(&mut tx, &ids).await?;
+        tx.commit().await?;
+
+        let mut events: Vec<Event> = Vec::with_capacity(archived.len());
+        let mut tags: Vec<(Uuid, &[String])> = Vec::new();
+        for (event, ingest_id, event_tags) in archived {
+            if existing.contains(&event.id) || existing.contains(&ingest_id) {
+                self.report.duplicate_events += 1;
+                continue;
+            }
+            if !event_tags.is_empty() {
+                tags.push((event.id, event_tags));
+            }
+            events.push(event);
+        }
+
+        let inserted: HashSet<Uuid> = Event::bulk_create(db, &events).await?.into_iter().collect();
+        self.report.events += inserted.len() as u64;
+        self.report.duplicate_events += (events.len() - inserted.len()) as u64;
+        tags.retain(|(id, _)| inserted.contains(id));
+        Tags::tag_events(db, &tags).await?;
+
+        let inserted = AFKEvent::bulk_create(db, &afk_events).await?;
+        self.report.afk_events += inserted.len() as u64;
+        self.report.duplicate_afk_events += (afk_events.len() - inserted.len()) as u64;
+
+        // Written last, as they follow the events in the archive: a tombstone may carry the
+        // id an event of this chunk would be ingested under now (see `ServerProject::merge`)
+        let mut tx = db.pool().begin().await?;
+        for (id, deleted_at) in deleted {
+            sqlx::query(
+                "INSERT INTO deleted_events (id, deleted_at) VALUES (?, ?)
+                ON CONFLICT(id) DO NOTHING",
+            )
+            .bind(id)
+            .bind(deleted_at)
+            .execute(&mut *tx)
+            .await?;
+        }
+        tx.commit().await?;
+
+        Ok(())
+    }
+
+    /// Fails if no header was imported, e.g. for an empty archive
+    pub fn finish(self) -> Result<ArchiveImportReport, DBError> {
+        if !self.has_header {
+            return Err(DBError::MissingField("header"));
+        }
+        Ok(self.report)
+    }
+
+    /// Inserts a dimension row unless it or a row with the same natural key exists,
+    /// and remembers which local row it maps to
+    async fn dimension(
+        &mut self,
+        conn: &mut SqliteConnection,
+        id: Uuid,
+        record: &ArchiveRecord,
+    ) -> Result<(), DBError> {
+        let name = record
+            .name
+            .as_deref()
+            .ok_or(DBError::MissingField("name"))?;
+        let now = Utc::now().timestamp();
+
+        let (inserted, local): (bool, Option<Uuid>) = match record.record {
+            ArchiveRecordKind::Project => {
+                let root_path = record
+                    .root_path
+                    .as_deref()
+                    .ok_or(DBError::MissingField("rootPath"))?;
+                let result = sqlx::query(
+                    "INSERT INTO projects (id, name, root_path, last_updated) VALUES (?, ?, ?, ?)
+                    ON CONFLICT DO NOTHING",
+                )
+                .bind(id)
+                .bind(name)
+                .bind(root_path)
+                .bind(now)
+                .execute(&mut *conn)
+                .await?;
+                let local: Option<Uuid> = sqlx::query_scalar(
+                    "SELECT id FROM projects WHERE id = ? OR root_path = ? ORDER BY id = ? DESC",
+                )
+                .bind(id)
+                .bind(root_path)
+                .bind(id)
+                .fetch_optional(&mut *conn)
+                .await?;
+                (result.rows_affected() > 0, local)
+            }
+            ArchiveRecordKind::Branch | ArchiveRecordKind::Entity => {
+                // Rows of a project missing from the archive cannot be placed
+                let Some(project_id) = record.project_id.and_then(|id| self.local(id)) else {
+                    return Ok(());
+                };
+                let (table, detail) = match record.record {
+                    ArchiveRecordKind::Branch => ("branches", None),
+                    _ => ("entities", record.entity_type.as_deref()),
+                };
+                let mut qb = QueryBuilder::<Sqlite>::new("INSERT INTO ");
+                qb.push(table).push(" (id, project_id, name, last_updated");
+                if detail.is_some() {
+                    qb.push(", type");
+                }
+                qb.push(") VALUES (");
+                let mut values = qb.separated(", ");
+                values
+                    .push_bind(id)
+                    .push_bind(project_id)
+                    .push_bind(name)
+                    .push_bind(now);
+                if let Some(detail) = detail {
+                    values.push_bind(detail);
+                }
+                qb.push(") ON CONFLICT DO NOTHING");
+                let result = qb.build().execute(&mut *conn).await?;
+
+                let local: Option<Uuid> = sqlx::query_scalar(&format!(
+                    "SELECT id FROM {table} WHERE id = ? OR (project_id = ? AND name = ?)
+                    ORDER BY id = ? DESC"
+                ))
+                .bind(id)
+                .bind(project_id)
+                .bind(name)
+                .bind(id)
+                .fetch_optional(&mut *conn)
+                .await?;
+                (result.rows_affected() > 0, local)
+            }
+            kind => {
+                let table = match kind {
+                    ArchiveRecordKind::Source => "sources",
+                    _ => NAMED_TABLES
+                        .iter()
+                        .find(|(named, _)| *named == kind)
+                        .map(|(_, table)| *table)
+                        .ok_or(DBError::Unsupported("Unknown archive record"))?,
+                };
+                let result = sqlx::query(&format!(
+                    "INSERT INTO {table} (id, name, last_updated) VALUES (?, ?, ?)
+                    ON CONFLICT DO NOTHING"
+                ))
+                .bind(id)
+                .bind(name)
+                .bind(now)
+                .execute(&mut *conn)
+                .await?;
+                if kind == ArchiveRecordKind::Source && record.priority.is_some() {
+                    sqlx::query(
+                        "UPDATE sources SET priority = COALESCE(priority, ?)
+                        WHERE id = ? OR name = ?",
+                    )
+                    .bind(record.priority)
+                    .bind(id)
+                    .bind(name)
+                    .execute(&mut *conn)
+                    .await?;
+                }
+
+                let local: Option<Uuid> = sqlx::query_scalar(&format!(
+                    "SELECT id FROM {table} WHERE id = ? OR name = ? ORDER BY id = ? DESC"
+                ))
+                .bind(id)
+                .bind(name)
+                .bind(id)
+                .fetch_optional(&mut *conn)
+                .await?;
+                (result.rows_affected() > 0, local)
+            }
+        };
+
+        if inserted {
+            self.report.dimensions += 1;
+        }
+        if let Some(local) = local {
+            self.ids.insert(id, local);
+            if record.record == ArchiveRecordKind::Project && !record.tags.is_empty() {
+                add_project_tags(conn, local, &record.tags).await?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Points a merged root path at the local row of the project it was merged into,
+    /// unless the root path already is an alias or a project of its own
+    async fn alias(
+        &mut self,
+        conn: &mut SqliteConnection,
+        record: &ArchiveRecord,
+    ) -> Result<(), DBError> {
+        let root_path = record
+            .root_path
+            .as_deref()
+            .ok_or(DBError::MissingField("rootPath"))?;
+        let project_id = record
+            .project_id
+            .ok_or(DBError::MissingField("projectId"))?;
+        // Aliases of a project missing from the archive cannot be placed
+        let Some(project_id) = self.local(project_id) else {
+            return Ok(());
+        };
+
+        let result = sqlx::query(
+            "INSERT INTO project_aliases (root_path, project_id)
+            SELECT ?, ? WHERE NOT EXISTS (SELECT 1 FROM projects WHERE root_path = ?)
+            ON CONFLICT(root_path) DO NOTHING",
+        )
+        .bind(root_path)
+        .bind(project_id)
+        .bind(root_path)
+        .execute(&mut *conn)
+        .await?;
+        if result.rows_affected() > 0 {
+            self.report.dimensions += 1;
+        }
+        Ok(())
+    }
+
+    /// Inserts a rule unless a rule with its id exists. Rules refer to apps, projects and
+    /// categories by name, so they need no mapping.
+    async fn rule(
+        &mut self,
+        conn: &mut SqliteConnection,
+        id: Uuid,
+        record: &ArchiveRecord,
+    ) -> Result<(), DBError> {
+        let actions = record.actions.clone().unwrap_or_default();
+        let input = RuleInput {
+            name: record.name.clone().ok_or(DBError::MissingField("name"))?,
+            priority: record.priority,
+            enabled: record.enabled,
+            conditions: record.conditions.clone().unwrap_or_default(),
+            actions: RuleActions {
+                tags: (!record.tags.is_empty()).then(|| record.tags.clone()),
+                ..actions
+            },
+        };
+
+        if Rules::restore(conn, id, input).await? {
+            self.report.rules += 1;
+        }
+        Ok(())
+    }
+
+    /// Sets the billing rate of the local row of a project, tag or category, unless it
+    /// has a rate already
+    async fn rate(
+        &mut self,
+        conn: &mut SqliteConnection,
+        record: &ArchiveRecord,
+    ) -> Result<(), DBError> {
+        let (kind, id) = match (record.project_id, record.tag_id, record.category_id) {
+            (Some(id), None, None) => (Group::Project, id),
+            (None, Some(id), None) => (Group::Tag, id),
+            (None, None, Some(id)) => (Group::Category, id),
+            _ => {
+                return Err(DBError::Unsupported(
+                    "A rate needs exactly one of projectId, tagId and categoryId",
+                ));
+            }
+        };
+        // Rates of a row missing from the archive cannot be placed
+        let Some(id) = self.local(id) else {
+            return Ok(());
+        };
+
+        let billable = record.billable.unwrap_or(true);
+        if BillingRates::restore(conn, kind, id, record.hourly_rate, billable).await? {
+            self.report.rates += 1;
+        }
+        Ok(())
+    }
+
+    fn local(&self, id: Uuid) -> Option<Uuid> {
+        self.ids.get(&id).copied()
+    }
+
+    /// Maps an optional archive id to its local id. `None` if it is set but unknown.
+    fn local_optional(&self, id: Option<Uuid>) -> Option<Option<Uuid>> {
+        match id {
+            Some(id) => self.local(id).map(Some),
+            None => Some(None),
+        }
+    }
+
+    /// Builds the event of a record with local dimension ids, or `None` if it refers
+    /// to a dimension missing from the archive. The event keeps its archived id, which
+    /// was derived at ingest from the dimensions its client reported, so a recategorized
+    /// or corrected event is still recognised when that client sends it again.
+    fn event(&self, id: Uuid, timestamp: i64, record: &ArchiveRecord) -> Option<Event> {
+        Some(Event {
+            id,
+            timestamp,
+            duration: record.duration,
+            category_id: self.local(record.category_id?)?,
+            app_id: self.local(record.app_id?)?,
+            entity_id: self.local_optional(record.entity_id)?,
+            project_id: self.local_optional(record.project_id)?,
+            branch_id: self.local_optional(record.branch_id)?,
+            language_id: self.local_optional(record.language_id)?,
+            source_id: self.local(record.source_id?)?,
+            end_timestamp: record.end_timestamp,
+        })
+    }
+}
+
+/// Returns the ids of `ids` that are stored or were deleted by a correction
+async fn existing_event_ids(
+    conn: &mut SqliteConnection,
+    ids: &[Uuid],
+) -> Result<HashSet<Uuid>, DBError> {
+    let mut existing = HashSet::new();
+
+    for chunk in ids.chunks(CHUNK_SIZE) {
+        let mut qb = QueryBuilder::<Sqlite>::new("SELECT id FROM events WHERE id IN (");
+        let mut separated = qb.separated(", ");
+        for id in chunk {
+            separated.push_bind(*id);
+        }
+        qb.push(") UNION SELECT id FROM deleted_events WHERE id IN (");
+        let mut separated = qb.separated(", ");
+        for id in chunk {
+            separated.push_bind(*id);
+        }
+        qb.push(")");
+
+        let ids: Vec<Uuid> = qb.build_query_scalar().fetch_all(&mut *conn).await?;
+        existing.extend(ids);
+    }
+
+    Ok(existing)
+}
+
+/// Appends keyset pagination by id to a `SELECT` without a `WHERE` clause
+fn push_page(qb: &mut QueryBuilder<'_, Sqlite>, after: Option<Uuid>, limit: u32) {
+    if let Some(after) = after {
+        qb.push(" WHERE id > ").push_bind(after);
+    }
+    qb.push(" ORDER BY id LIMIT ").push_bind(i64::from(limit));
+}
+
+/// Names of the tags each event carries itself, by event id
+async fn event_tags(db: &DBContext, ids: &[Uuid]) -> Result<HashMap<Uuid, Vec<String>>, DBError> {
+    let mut tags: HashMap<Uuid, Vec<String>> = HashMap::new();
+
+    for chunk in ids.chunks(CHUNK_SIZE) {
+        let mut qb = QueryBuilder::<Sqlite>::new(
+            "SELECT event_tags.event_id, tags.name FROM event_tags
+            JOIN tags ON tags.id = event_tags.tag_id
+            WHERE event_tags.event_id IN (",
+        );
+        let mut separated = qb.separated(", ");
+        for id in chunk {
+            separated.push_bind(*id);
+        }
+        qb.push(") ORDER BY tags.name COLLATE NOCASE");
+
+        let rows: Vec<(Uuid, String)> = qb.build_query_as().fetch_all(db.pool()).await?;
+        for (event_id, name) in rows {
+            tags.entry(event_id).or_default().push(name);
+        }
+    }
+
+    Ok(tags)
+}
+
+#[cfg(all(test, feature = "server"))]
+mod tests {
+    use super::*;
+    use crate::server::{
+        billing::BillingRates,
+        projects::ServerProject,
+        rules::{RuleSet, Rules},
+        summary::SummaryQueryBuilder,
+        test_utils::TestEvent,
+    };
+    use common::models::{
+        Group, RuleActions, RuleConditions,
+        inputs::{BillingRateInput, RuleInput},
+    };
+
+    /// Reads a whole archive, two rows per page so paging is exercised
+    async fn export(db: &DBContext) -> Vec<ArchiveRecord> {
+        let mut records = vec![Archive::header()];
+        records.extend(Archive::dimensions(db).await.unwrap());
+        records.extend(Archive::settings(db).await.unwrap());
+
+        let mut after = None;
+        loop {
+            let page = Archive::events(db, after, 2).await.unwrap();
+            let Some(last) = page.last() else { break };
+            after = last.id;
+            records.extend(page);
+        }
+        records.extend(Archive::afk_events(db, None, 100).await.unwrap());
+        records.extend(Archive::deleted_events(db, None, 100).await.unwrap());
+        records
+    }
+
+    async fn import(db: &DBContext, records: &[ArchiveRecord]) -> ArchiveImportReport {
+        let mut import = ArchiveImport::new();
+        for chunk in records.chunks(3) {
+            import.apply(db, chunk).await.unwrap();
+        }
+        import.finish().unwrap()
+    }
+
+    async fn totals_by_tag(db: &DBContext) -> Vec<(String, i64)> {
+        let mut totals: Vec<(String, i64)> = SummaryQueryBuilder::new()
+            .start(0)
+            .end(10_000)
+            .group_by(Group::Tag)
+            .execute_range_summary_with_bucket(db)
+            .await
+            .unwrap()
+            .into_iter()
+            .flat_map(|row| row.grouped_values)
+            .collect();
+        totals.sort();
+        totals
+    }
+
+    #[tokio::test]
+    async fn test_archive_round_trips_and_imports_once() {
+        let source = DBContext::new("sqlite::memory:").await.unwrap();
+        TestEvent::new(0, 100)
+            .tags(&["billable"])
+            .insert(&source)
+            .await;
+        TestEvent::new(100, 300).insert(&source).await;
+        TestEvent::new(300, 600)
+            .app("Terminal")
+            .tags(&["billable"])
+            .insert(&source)
+            .await;
+        let deleted = TestEvent::new(600, 700).insert(&source).await.id;
+        assert!(Event::delete(&source, deleted).await.unwrap());
+        AFKEvent::bulk_create(
+            &source,
+            &[AFKEvent {
+                id: Uuid::now_v7(),
+                afk_start: 700,
+                afk_end: Some(800),
+                duration: Some(100),
+            }],
+        )
+        .await
+        .unwrap();
+
+        let records = export(&source).await;
+        let target = DBContext::new("sqlite::memory:").await.unwrap();
+        let report = import(&target, &records).await;
+        assert_eq!(report.events, 3);
+        assert_eq!(report.afk_events, 1);
+        assert_eq!(report.skipped_events, 0);
+        assert_eq!(totals_by_tag(&target).await, totals_by_tag(&source).await);
+
+        // Deleted events stay deleted
+        let restored = Archive::deleted_events(&target, None, 10).await.unwrap();
+        assert_eq!(restored.len(), 1);
+        assert_eq!(restored[0].id, Some(deleted));
+
+        let again = import(&target, &records).await;
+        assert_eq!(again.dimensions, 0);
+        assert_eq!(again.events, 0);
+        assert_eq!(again.duplicate_events, 3);
+        assert_eq!(again.duplicate_afk_events, 1);
+    }
+
+    #[tokio::test]
+    async fn test_import_maps_dimensions_onto_existing_rows() {
+        let source = DBContext::new("sqlite::memory:").await.unwrap();
+        TestEvent::new(0, 100)
+            .tags(&["billable"])
+            .insert(&source)
+            .await;
+
+        // The target knows the same project, app and tag under other ids
+        let target = DBContext::new("sqlite::memory:").await.unwrap();
+        TestEvent::new(1000, 1100)
+            .tags(&["Billable"])
+            .insert(&target)
+            .await;
+
+        let report = import(&target, &export(&source).await).await;
+        assert_eq!(report.events, 1);
+        assert_eq!(report.dimensions, 0);
+
+        let projects: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM projects")
+            .fetch_one(target.pool())
+            .await
+            .unwrap();
+        assert_eq!(projects, 1);
+        assert_eq!(
+            totals_by_tag(&target).await,
+            vec![("Billable".to_string(), 200)]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_import_recognises_events_ingested_under_other_ids() {
+        let source = DBContext::new("sqlite::memory:").await.unwrap();
+        TestEvent::new(0, 100).insert(&source).await;
+
+        // The target ingested the same event, keyed by its own dimension ids
+        let target = DBContext::new("sqlite::memory:").await.unwrap();
+        TestEvent::new(0, 100).insert(&target).await;
+
+        let report = import(&target, &export(&source).await).await;
+        assert_eq!(report.events, 0);
+        assert_eq!(report.duplicate_events, 1);
+
+        let events: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM events")
+            .fetch_one(target.pool())
+            .await
+            .unwrap();
+        assert_eq!(events, 1);
+    }
+
+    #[tokio::test]
+    async fn test_recategorized_events_keep_their_ingest_ids() {
+        let source = DBContext::new("sqlite::memory:").await.unwrap();
+        let id = TestEvent::new(0, 100).insert(&source).await.id;
+
+        Rules::create(
+            &source,
+            RuleInput {
+                name: "Debugging".into(),
+                priority: None,
+                enabled: None,
+                conditions: RuleConditions {
+                    app: Some("Code".into()),
+                    ..Default::default()
+                },
+                actions: RuleActions {
+                    category: Some("Debugging".into()),
+                    project: None,
+                    tags: None,
+                },
+            },
+        )
+        .await
+        .unwrap();
+        let rules = RuleSet::load(&source).await.unwrap();
+        assert_eq!(
+            rules
+                .apply_to_range(&source, 0, 1_000, false)
+                .await
+                .unwrap()
+                .changed,
+            1
+        );
+
+        let target = DBContext::new("sqlite::memory:").await.unwrap();
+        let report = import(&target, &export(&source).await).await;
+        assert_eq!(report.events, 1);
+        let restored = Event::find_by_id(&target, id).await.unwrap().unwrap();
+        assert_eq!(restored.category, "Debugging");
+
+        // The client sends the event again as it first reported it
+        assert_eq!(TestEvent::new(0, 100).insert(&target).await.id, id);
+        let events: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM events")
+            .fetch_one(target.pool())
+            .await
+            .unwrap();
+        assert_eq!(events, 1);
+    }
+
+    #[tokio::test]
+    async fn test_merged_projects_stay_merged_after_restore() {
+        let source = DBContext::new("sqlite::memory:").await.unwrap();
+        let target = TestEvent::new(0, 100)
+            .insert(&source)
+            .await
+            .project_id
+            .unwrap();
+        let merged = TestEvent::new(100, 200)
+            .project_path("/oss/api")
+            .insert(&source)
+            .await
+            .project_id
+            .unwrap();
+        ServerProject::merge(&source, target, &[merged])
+            .await
+            .unwrap()
+            .unwrap();
+
+        let restored = DBContext::new("sqlite::memory:").await.unwrap();
+        let report = import(&restored, &export(&source).await).await;
+        assert_eq!(report.events, 2);
+
+        // Events still reported under the merged root path go to the target
+        let event = TestEvent::new(200, 300)
+            .project_path("/oss/api")
+            .insert(&restored)
+            .await;
+        assert_eq!(event.project_id, Some(target));
+        let projects: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM projects")
+            .fetch_one(restored.pool())
+            .await
+            .unwrap();
+        assert_eq!(projects, 1);
+    }
+
+    #[tokio::test]
+    async fn test_rules_and_rates_are_restored() {
+        let source = DBContext::new("sqlite::memory:").await.unwrap();
+        let event = TestEvent::new(0, 100)
+            .tags(&["billable"])
+            .insert(&source)
+            .await;
+        Rules::create(
+            &source,
+            RuleInput {
+                name: "Reviews".into(),
+                priority: Some(5),
+                enabled: Some(false),
+                conditions: RuleConditions {
+                    entity: Some("*.md".into()),
+                    ..Default::default()
+                },
+                actions: RuleActions {
+                    category: Some("Writing".into()),
+                    project: None,
+                    tags: Some(vec!["review".into()]),
+                },
+            },
+        )
+        .await
+        .unwrap();
+        let tag_id: Uuid = sqlx::query_scalar("SELECT id FROM tags WHERE name = 'billable'")
+            .fetch_one(source.pool())
+            .await
+            .unwrap();
+        for (kind, id, hourly_rate, billable) in [
+            (Group::Project, event.project_id.unwrap(), Some(80.0), None),
+            (Group::Tag, tag_id, None, Some(false)),
+            (Group::Category, event.category_id, Some(20.5), None),
+        ] {
+            BillingRates::set(
+                &source,
+                kind,
+                id,
+                BillingRateInput {
+                    hourly_rate,
+                    billable,
+                },
+            )
+            .await
+            .unwrap()
+            .unwrap();
+        }
+
+        let records = export(&source).await;
+        let target = DBContext::new("sqlite::memory:").await.unwrap();
+        let report = import(&target, &records).await;
+        assert_eq!(report.rules, 1);
+        assert_eq!(report.rates, 3);
+
+        assert_eq!(
+            Rules::all(&target).await.unwrap(),
+            Rules::all(&source).await.unwrap()
+        );
+        assert_eq!(
+            BillingRates::all(&target).await.unwrap(),
+            BillingRates::all(&source).await.unwrap()
+        );
+
+        let again = import(&target, &records).await;
+        assert_eq!(again.rules, 0);
+        assert_eq!(again.rates, 0);
+    }
+
+    #[tokio::test]
+    async fn test_import_rejects_other_versions_and_missing_headers() {
+        let db = DBContext::new("sqlite::memory:").await.unwrap();
+        let app = ArchiveRecord {
+            record: ArchiveRecordKind::App,
+            id: Some(Uuid::now_v7()),
+            name: Some("Code".into()),
+            ..Default::default()
+        };
+
+        let mut import = ArchiveImport::new();
+        let result = import.apply(&db, std::slice::from_ref(&app)).await;
+        assert!(matches!(result, Err(DBError::MissingField("header"))));
+
+        let header = ArchiveRecord {
+            version: Some(ARCHIVE_VERSION + 1),
+            ..Archive::header()
+        };
+        let result = import.apply(&db, &[header, app]).await;
+        assert!(matches!(result, Err(DBError::Unsupported(_))));
+    }
+}
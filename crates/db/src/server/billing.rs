@@ -0,0 +1,588 @@
+use std::collections::{BTreeMap, HashMap};
+
+use chrono::{DateTime, Utc};
+use common::{
+    models::{
+        BillingRate, Group, InvoiceLines, InvoiceRounding,
+        inputs::{BillingRateInput, InvoiceInput},
+        outputs::{InvoiceLine, InvoiceReport},
+    },
+    time::{TimeBucket, TimeRange, Tz, WeekStart},
+};
+use sqlx::SqliteConnection;
+use uuid::Uuid;
+
+use crate::{DBContext, error::DBError, server::summary::SummaryQueryBuilder};
+
+/// Kinds that can carry a rate, in listing order
+const RATE_KINDS: [Group; 3] = [Group::Project, Group::Tag, Group::Category];
+
+#[derive(Debug, sqlx::FromRow)]
+struct RateRow {
+    /// Index of the kind in [`RATE_KINDS`]
+    kind: i64,
+    id: Uuid,
+    name: String,
+    hourly_rate: Option<f64>,
+    billable: bool,
+}
+
+/// Name and billing settings of a project, tag or category. Both settings are
+/// `None` when it has no rate row.
+#[derive(Debug, sqlx::FromRow)]
+struct ScopeRow {
+    name: String,
+    hourly_rate: Option<f64>,
+    billable: Option<bool>,
+}
+
+impl ScopeRow {
+    fn is_billable(&self) -> bool {
+        self.billable.unwrap_or(true)
+    }
+}
+
+/// Returns the table and `billing_rates` column of a kind that can carry a rate
+fn rate_columns(kind: Group) -> Result<(&'static str, &'static str), DBError> {
+    match kind {
+        Group::Project => Ok(("projects", "project_id")),
+        Group::Tag => Ok(("tags", "tag_id")),
+        Group::Category => Ok(("categories", "category_id")),
+        _ => Err(DBError::Unsupported(
+            "Rates apply to projects, tags and categories",
+        )),
+    }
+}
+
+/// Hourly rates and billable flags of projects, tags and categories
+pub struct BillingRates;
+
+impl BillingRates {
+    /// Lists every rate, projects first, then tags and categories, each by name
+    pub async fn all(db: &DBContext) -> Result<Vec<BillingRate>, DBError> {
+        let rows: Vec<RateRow> = sqlx::query_as(
+            "SELECT
+                CASE
+                    WHEN rates.project_id IS NOT NULL THEN 0
+                    WHEN rates.tag_id IS NOT NULL THEN 1
+                    ELSE 2
+                END AS kind,
+                COALESCE(projects.id, tags.id, categories.id) AS id,
+                COALESCE(projects.name, tags.name, categories.name) AS name,
+                rates.hourly_rate,
+                rates.billable
+            FROM billing_rates AS rates
+            LEFT JOIN projects ON projects.id = rates.project_id
+            LEFT JOIN tags ON tags.id = rates.tag_id
+            LEFT JOIN categories ON categories.id = rates.category_id
+            ORDER BY kind, name COLLATE NOCASE",
+        )
+        .fetch_all(db.pool())
+        .await?;
+
+        Ok(rows
+            .into_iter()
+            .map(|row| BillingRate {
+                kind: RATE_KINDS[row.kind as usize],
+                id: row.id,
+                name: row.name,
+                hourly_rate: row.hourly_rate,
+                billable: row.billable,
+            })
+            .collect())
+    }
+
+    /// Sets the rate of a project, tag or category, returning `None` if it does
+    /// not exist
+    pub async fn set(
+        db: &DBContext,
+        kind: Group,
+        id: Uuid,
+        input: BillingRateInput,
+    ) -> Result<Option<BillingRate>, DBError> {
+        let (table, column) = rate_columns(kind)?;
+        if input
+            .hourly_rate
+            .is_some_and(|rate| !rate.is_finite() || rate < 0.0)
+        {
+            return Err(DBError::Unsupported(
+                "Hourly rates must be zero or positive",
+            ));
+        }
+
+        let name: Option<String> =
+            sqlx::query_scalar(&format!("SELECT name FROM {table} WHERE id = ?"))
+                .bind(id)
+                .fetch_optional(db.pool())
+                .await?;
+        let Some(name) = name else {
+            return Ok(None);
+        };
+
+        let billable = input.billable.unwrap_or(true);
+        sqlx::query(&format!(
+            "INSERT INTO billing_rates ({column}, hourly_rate, billable, last_updated)
+            VALUES (?, ?, ?, ?)
+            ON CONFLICT({column}) DO UPDATE SET
+                hourly_rate = excluded.hourly_rate,
+                billable = excluded.billable,
+                last_updated = excluded.last_updated"
+        ))
+        .bind(id)
+        .bind(input.hourly_rate)
+        .bind(billable)
+        .bind(Utc::now().timestamp())
+        .execute(db.pool())
+        .await?;
+
+        Ok(Some(BillingRate {
+            kind,
+            id,
+            name,
+            hourly_rate: input.hourly_rate,
+            billable,
+        }))
+    }
+
+    /// Stores the rate an archive holds for a project, tag or category, unless it
+    /// already has one. Returns whether it was stored.
+    pub(crate) async fn restore(
+        conn: &mut SqliteConnection,
+        kind: Group,
+        id: Uuid,
+        hourly_rate: Option<f64>,
+        billable: bool,
+    ) -> Result<bool, DBError> {
+        let (_, column) = rate_columns(kind)?;
+        if hourly_rate.is_some_and(|rate| !rate.is_finite() || rate < 0.0) {
+            return Err(DBError::Unsupported(
+                "Hourly rates must be zero or positive",
+            ));
+        }
+
+        let result = sqlx::query(&format!(
+            "INSERT INTO billing_rates ({column}, hourly_rate, billable, last_updated)
+            VALUES (?, ?, ?, ?)
+            ON CONFLICT({column}) DO NOTHING"
+        ))
+        .bind(id)
+        .bind(hourly_rate)
+        .bind(billable)
+        .bind(Utc::now().timestamp())
+        .execute(&mut *conn)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Removes the rate of a project, tag or category, returning `false` if it has none
+    pub async fn delete(db: &DBContext, kind: Group, id: Uuid) -> Result<bool, DBError> {
+        let (_, column) = rate_columns(kind)?;
+        let result = sqlx::query(&format!("DELETE FROM billing_rates WHERE {column} = ?"))
+            .bind(id)
+            .execute(db.pool())
+            .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+}
+
+/// Builds invoice line items from the tracked time of a project, a tag or both.
+///
+/// Each category's rate takes precedence over the project's, which takes
+/// precedence over the tag's. Time in a category, project or tag marked as not
+/// billable is left out.
+#[derive(Debug, Clone)]
+pub struct InvoiceQuery {
+    /// Start of the range in unix seconds
+    pub start: i64,
+    /// Exclusive end of the range in unix seconds
+    pub end: i64,
+    pub project_id: Option<Uuid>,
+    /// Name of the tag events must carry
+    pub tag: Option<String>,
+    pub lines: InvoiceLines,
+    pub round_minutes: Option<u32>,
+    pub round_each: InvoiceRounding,
+    pub exclude_afk: bool,
+    pub timezone: Option<Tz>,
+    pub week_start: WeekStart,
+}
+
+impl From<InvoiceInput> for InvoiceQuery {
+    fn from(input: InvoiceInput) -> Self {
+        let week_start = input.week_start.unwrap_or_default();
+        let range = TimeRange::resolve(input.preset, input.timezone, week_start);
+
+        Self {
+            start: range.start().timestamp(),
+            end: range.end().timestamp(),
+            project_id: input.project,
+            tag: input.tag,
+            lines: input.lines.unwrap_or_default(),
+            round_minutes: input.round_minutes,
+            round_each: input.round_each.unwrap_or_default(),
+            exclude_afk: input.exclude_afk.unwrap_or(false),
+            timezone: input.timezone,
+            week_start,
+        }
+    }
+}
+
+impl InvoiceQuery {
+    /// Returns `None` if the project or tag does not exist
+    pub async fn execute(&self, db: &DBContext) -> Result<Option<InvoiceReport>, DBError> {
+        if self.project_id.is_none() && self.tag.is_none() {
+            return Err(DBError::MissingField("project"));
+        }
+        if self
+            .round_minutes
+            .is_some_and(|minutes| !(1..=60).contains(&minutes))
+        {
+            return Err(DBError::Unsupported(
+                "Rounding must be between 1 and 60 minutes",
+            ));
+        }
+
+        let project = match self.project_id {
+            Some(id) => {
+                let row: Option<ScopeRow> = sqlx::query_as(
+                    "SELECT projects.name, rates.hourly_rate, rates.billable FROM projects
+                    LEFT JOIN billing_rates AS rates ON rates.project_id = projects.id
+                    WHERE projects.id = ?",
+                )
+                .bind(id)
+                .fetch_optional(db.pool())
+                .await?;
+                match row {
+                    Some(row) => Some(row),
+                    None => return Ok(None),
+                }
+            }
+            None => None,
+        };
+        let tag = match &self.tag {
+            Some(name) => {
+                let row: Option<ScopeRow> = sqlx::query_as(
+                    "SELECT tags.name, rates.hourly_rate, rates.billable FROM tags
+                    LEFT JOIN billing_rates AS rates ON rates.tag_id = tags.id
+                    WHERE tags.name = ?",
+                )
+                .bind(name.trim())
+                .fetch_optional(db.pool())
+                .await?;
+                match row {
+                    Some(row) => Some(row),
+                    None => return Ok(None),
+                }
+            }
+            None => None,
+        };
+        let categories: HashMap<String, ScopeRow> = sqlx::query_as::<_, ScopeRow>(
+            "SELECT categories.name, rates.hourly_rate, rates.billable FROM billing_rates AS rates
+            JOIN categories ON categories.id = rates.category_id",
+        )
+        .fetch_all(db.pool())
+        .await?
+        .into_iter()
+        .map(|row| (row.name.clone(), row))
+        .collect();
+
+        let scopes = [project.as_ref(), tag.as_ref()];
+        let scope_billable = scopes.iter().flatten().all(|scope| scope.is_billable());
+        let scope_rate = scopes.iter().flatten().find_map(|scope| scope.hourly_rate);
+
+        let mut builder = SummaryQueryBuilder::new()
+            .start(self.start)
+            .end(self.end)
+            .group_by(Group::Category)
+            .time_bucket(TimeBucket::Day)
+            .exclude_afk(self.exclude_afk)
+            .week_start(self.week_start);
+        if self.lines == InvoiceLines::Entity {
+            builder = builder.subgroup_by(vec![Group::Entity]);
+        }
+        if let Some(id) = self.project_id {
+            builder = builder.project_id(id);
+        }
+        if let Some(tag) = &tag {
+            builder = builder.tags(vec![tag.name.clone()]);
+        }
+        if let Some(tz) = self.timezone {
+            builder = builder.timezone(tz);
+        }
+
+        // Seconds per day of each line, keyed by description and rate
+        let mut pieces: BTreeMap<(String, Option<u64>), BTreeMap<String, i64>> = BTreeMap::new();
+        let mut non_billable_seconds = 0;
+        for row in builder.execute_range_summary_with_bucket(db).await? {
+            for (category, seconds) in row.grouped_values {
+                if seconds <= 0 {
+                    continue;
+                }
+
+                let category = categories.get(&category);
+                if !scope_billable || category.is_some_and(|c| !c.is_billable()) {
+                    non_billable_seconds += seconds;
+                    continue;
+                }
+
+                let rate = category.and_then(|c| c.hourly_rate).or(scope_rate);
+                let description = match self.lines {
+                    InvoiceLines::Day => row.bucket.clone(),
+                    InvoiceLines::Entity => row.subgroups.first().cloned().unwrap_or_default(),
+                };
+                *pieces
+                    .entry((description, rate.map(f64::to_bits)))
+                    .or_default()
+                    .entry(row.bucket.clone())
+                    .or_default() += seconds;
+            }
+        }
+
+        let step = self.round_minutes.map(|minutes| i64::from(minutes) * 60);
+        let lines: Vec<InvoiceLine> = pieces
+            .into_iter()
+            .map(|((description, rate), days)| {
+                let seconds = match self.round_each {
+                    InvoiceRounding::Day => days.values().map(|s| round_up(*s, step)).sum(),
+                    InvoiceRounding::Line => round_up(days.values().sum(), step),
+                };
+                let rate = rate.map(f64::from_bits);
+                let hours = seconds as f64 / 3600.0;
+                InvoiceLine {
+                    description,
+                    seconds,
+                    hours: round_cents(hours),
+                    rate,
+                    amount: rate.map(|rate| round_cents(hours * rate)),
+                }
+            })
+            .collect();
+
+        let total_seconds = lines.iter().map(|line| line.seconds).sum::<i64>();
+        let total_amount = lines.iter().filter_map(|line| line.amount).sum::<f64>();
+        Ok(Some(InvoiceReport {
+            start: DateTime::from_timestamp(self.start, 0).unwrap_or_default(),
+            end: DateTime::from_timestamp(self.end, 0).unwrap_or_default(),
+            project: project.map(|p| p.name),
+            tag: tag.map(|t| t.name),
+            lines,
+            total_seconds,
+            total_hours: round_cents(total_seconds as f64 / 3600.0),
+            total_amount: round_cents(total_amount),
+            non_billable_seconds,
+        }))
+    }
+}
+
+/// Rounds `seconds` up to a multiple of `step`
+fn round_up(seconds: i64, step: Option<i64>) -> i64 {
+    match step {
+        Some(step) => (seconds + step - 1) / step * step,
+        None => seconds,
+    }
+}
+
+fn round_cents(value: f64) -> f64 {
+    (value * 100.0).round() / 100.0
+}
+
+#[cfg(all(test, feature = "server"))]
+mod tests {
+    use super::*;
+    use crate::server::test_utils::TestEvent;
+
+    fn invoice(project_id: Uuid, lines: InvoiceLines, round_each: InvoiceRounding) -> InvoiceQuery {
+        InvoiceQuery {
+            start: 0,
+            end: 2 * 86_400,
+            project_id: Some(project_id),
+            tag: None,
+            lines,
+            round_minutes: Some(15),
+            round_each,
+            exclude_afk: false,
+            timezone: Some(Tz::UTC),
+            week_start: WeekStart::default(),
+        }
+    }
+
+    fn rate(hourly_rate: Option<f64>, billable: bool) -> BillingRateInput {
+        BillingRateInput {
+            hourly_rate,
+            billable: Some(billable),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_rates_are_set_listed_and_removed() {
+        let db = DBContext::new("sqlite::memory:").await.unwrap();
+        let event = TestEvent::new(0, 600)
+            .category("Meetings")
+            .entity("notes.md")
+            .insert(&db)
+            .await;
+        let (project_id, category_id) = (event.project_id.unwrap(), event.category_id);
+
+        let missing = BillingRates::set(&db, Group::Project, Uuid::now_v7(), rate(Some(1.0), true))
+            .await
+            .unwrap();
+        assert!(missing.is_none());
+        assert!(matches!(
+            BillingRates::set(&db, Group::Project, project_id, rate(Some(-1.0), true)).await,
+            Err(DBError::Unsupported(_))
+        ));
+        assert!(matches!(
+            BillingRates::set(&db, Group::App, project_id, rate(Some(1.0), true)).await,
+            Err(DBError::Unsupported(_))
+        ));
+
+        BillingRates::set(&db, Group::Project, project_id, rate(Some(90.0), true))
+            .await
+            .unwrap();
+        BillingRates::set(&db, Group::Project, project_id, rate(Some(100.0), true))
+            .await
+            .unwrap();
+        BillingRates::set(&db, Group::Category, category_id, rate(None, false))
+            .await
+            .unwrap();
+
+        let rates = BillingRates::all(&db).await.unwrap();
+        assert_eq!(
+            rates,
+            vec![
+                BillingRate {
+                    kind: Group::Project,
+                    id: project_id,
+                    name: "api".into(),
+                    hourly_rate: Some(100.0),
+                    billable: true,
+                },
+                BillingRate {
+                    kind: Group::Category,
+                    id: category_id,
+                    name: "Meetings".into(),
+                    hourly_rate: None,
+                    billable: false,
+                },
+            ]
+        );
+
+        assert!(
+            BillingRates::delete(&db, Group::Category, category_id)
+                .await
+                .unwrap()
+        );
+        assert!(
+            !BillingRates::delete(&db, Group::Category, category_id)
+                .await
+                .unwrap()
+        );
+        assert_eq!(BillingRates::all(&db).await.unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_invoice_applies_rates_and_rounding() {
+        let db = DBContext::new("sqlite::memory:").await.unwrap();
+        let project_id = TestEvent::new(0, 3000)
+            .insert(&db)
+            .await
+            .project_id
+            .unwrap();
+        let meetings_id = TestEvent::new(3000, 3900)
+            .category("Meetings")
+            .entity("notes.md")
+            .insert(&db)
+            .await
+            .category_id;
+        TestEvent::new(86_400, 87_000)
+            .entity("lib.rs")
+            .insert(&db)
+            .await;
+
+        BillingRates::set(&db, Group::Project, project_id, rate(Some(100.0), true))
+            .await
+            .unwrap();
+        BillingRates::set(&db, Group::Category, meetings_id, rate(None, false))
+            .await
+            .unwrap();
+
+        let report = invoice(project_id, InvoiceLines::Day, InvoiceRounding::Line)
+            .execute(&db)
+            .await
+            .unwrap()
+            .unwrap();
+        let lines: Vec<_> = report
+            .lines
+            .iter()
+            .map(|line| (line.description.as_str(), line.seconds, line.amount))
+            .collect();
+        assert_eq!(
+            lines,
+            vec![
+                ("1970-01-01", 3600, Some(100.0)),
+                ("1970-01-02", 900, Some(25.0)),
+            ]
+        );
+        assert_eq!(report.project.as_deref(), Some("api"));
+        assert_eq!(report.total_hours, 1.25);
+        assert_eq!(report.total_amount, 125.0);
+        assert_eq!(report.non_billable_seconds, 900);
+
+        // A category rate overrides the project's and gets its own line
+        BillingRates::set(&db, Group::Category, meetings_id, rate(Some(80.0), true))
+            .await
+            .unwrap();
+        let report = invoice(project_id, InvoiceLines::Day, InvoiceRounding::Line)
+            .execute(&db)
+            .await
+            .unwrap()
+            .unwrap();
+        let lines: Vec<_> = report
+            .lines
+            .iter()
+            .map(|line| (line.description.as_str(), line.rate, line.amount))
+            .collect();
+        assert_eq!(
+            lines,
+            vec![
+                ("1970-01-01", Some(80.0), Some(20.0)),
+                ("1970-01-01", Some(100.0), Some(100.0)),
+                ("1970-01-02", Some(100.0), Some(25.0)),
+            ]
+        );
+        assert_eq!(report.non_billable_seconds, 0);
+
+        // Entity lines spanning days are rounded per day or once per line
+        TestEvent::new(86_400 * 2 - 60, 86_400 * 2)
+            .insert(&db)
+            .await;
+        let per_line = invoice(project_id, InvoiceLines::Entity, InvoiceRounding::Line)
+            .execute(&db)
+            .await
+            .unwrap()
+            .unwrap();
+        let per_day = invoice(project_id, InvoiceLines::Entity, InvoiceRounding::Day)
+            .execute(&db)
+            .await
+            .unwrap()
+            .unwrap();
+        let main_seconds = |report: &InvoiceReport| {
+            report
+                .lines
+                .iter()
+                .find(|line| line.description == "main.rs")
+                .map(|line| line.seconds)
+        };
+        assert_eq!(main_seconds(&per_line), Some(3600));
+        assert_eq!(main_seconds(&per_day), Some(4500));
+    }
+
+    #[tokio::test]
+    async fn test_invoice_of_unknown_project_is_none() {
+        let db = DBContext::new("sqlite::memory:").await.unwrap();
+        let query = invoice(Uuid::now_v7(), InvoiceLines::Day, InvoiceRounding::Line);
+        assert!(query.execute(&db).await.unwrap().is_none());
+    }
+}
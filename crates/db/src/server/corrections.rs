@@ -0,0 +1,594 @@
+use std::collections::HashSet;
+
+use chrono::{DateTime, Utc};
+use common::models::{
+    inputs::{EventInput, EventPatchInput},
+    outputs::FullEvent,
+};
+use sqlx::{QueryBuilder, Row, Sqlite, SqliteConnection};
+use uuid::Uuid;
+
+use crate::{
+    DBContext,
+    error::DBError,
+    server::{
+        dimensions::{DimensionCache, resolve_dimensions_with},
+        events::{Event, FULL_EVENT_COLUMNS, full_event_from_row},
+        projects::latest_root_path_with,
+        summary::SummaryQueryBuilder,
+        tags::{copy_event_tags, set_event_tags},
+        utils::query::QueryBuilderExt,
+    },
+};
+
+/// Maximum number of ids written or looked up by a single statement
+const CHUNK_SIZE: usize = 500;
+
+const EVENT_COLUMNS: &str = "SELECT id, timestamp, duration, category_id, app_id, entity_id, \
+    project_id, branch_id, language_id, source_id, end_timestamp FROM events";
+
+impl Event {
+    /// End of the event in unix seconds, derived from the duration when no end is stored
+    fn end(&self) -> Option<i64> {
+        self.end_timestamp
+            .or_else(|| self.duration.map(|d| self.timestamp + d))
+    }
+
+    /// Whether both events record the same activity
+    fn same_activity(&self, other: &Event) -> bool {
+        self.category_id == other.category_id
+            && self.app_id == other.app_id
+            && self.entity_id == other.entity_id
+            && self.project_id == other.project_id
+            && self.branch_id == other.branch_id
+            && self.language_id == other.language_id
+            && self.source_id == other.source_id
+    }
+
+    /// Fetches a single event with its dimension names
+    pub async fn find_by_id(db: &DBContext, id: Uuid) -> Result<Option<FullEvent>, DBError> {
+        let mut conn = db.pool().acquire().await?;
+        fetch_full_event(&mut conn, id).await
+    }
+
+    /// Deletes an event, returning `false` if it does not exist.
+    ///
+    /// The id is recorded so the event is not ingested again.
+    pub async fn delete(db: &DBContext, id: Uuid) -> Result<bool, DBError> {
+        let mut tx = db.pool().begin().await?;
+
+        let result = sqlx::query("DELETE FROM events WHERE id = ?")
+            .bind(id)
+            .execute(&mut *tx)
+            .await?;
+        if result.rows_affected() == 0 {
+            return Ok(false);
+        }
+
+        insert_deleted_ids(&mut tx, &[id]).await?;
+        tx.commit().await?;
+        Ok(true)
+    }
+
+    /// Changes the category, project, entity or tags of an event, keeping its id.
+    /// Returns `None` if the event does not exist.
+    pub async fn update(
+        db: &DBContext,
+        id: Uuid,
+        patch: EventPatchInput,
+    ) -> Result<Option<FullEvent>, DBError> {
+        let mut tx = db.pool().begin().await?;
+        let updated = Self::update_with(&mut tx, db.dimension_cache(), id, patch).await?;
+        tx.commit().await?;
+        Ok(updated)
+    }
+
+    /// Applies a patch like [`Event::update`] on a connection whose transaction the
+    /// caller commits, so many events can be changed at once
+    pub(crate) async fn update_with(
+        conn: &mut SqliteConnection,
+        cache: &DimensionCache,
+        id: Uuid,
+        patch: EventPatchInput,
+    ) -> Result<Option<FullEvent>, DBError> {
+        let Some(current) = fetch_full_event(conn, id).await? else {
+            return Ok(None);
+        };
+
+        let project_name = patch
+            .project_name
+            .clone()
+            .or(current.project)
+            .ok_or(DBError::MissingField("projectName"))?;
+        let project_path = match (patch.project_path, &patch.project_name) {
+            (Some(path), _) => path,
+            // The most recently used project with that name, or a new path-less one
+            (None, Some(name)) => latest_root_path_with(conn, name).await?.unwrap_or_default(),
+            (None, None) => sqlx::query_scalar::<_, String>(
+                "SELECT projects.root_path FROM events
+                JOIN projects ON projects.id = events.project_id
+                WHERE events.id = ?",
+            )
+            .bind(id)
+            .fetch_optional(&mut *conn)
+            .await?
+            .unwrap_or_default(),
+        };
+
+        let input = EventInput {
+            timestamp: Some(current.timestamp),
+            duration: current.duration,
+            category: patch.category.unwrap_or(current.category),
+            app_name: current.app.ok_or(DBError::MissingField("app"))?,
+            entity_name: patch
+                .entity_name
+                .or(current.entity)
+                .ok_or(DBError::MissingField("entityName"))?,
+            entity_type: patch
+                .entity_type
+                .or(current.entity_type)
+                .ok_or(DBError::MissingField("entityType"))?,
+            project_name,
+            project_path,
+            branch_name: current.branch,
+            language_name: current.language,
+            source_name: current.source,
+            end_timestamp: current.end_timestamp,
+            tags: Vec::new(),
+        };
+        let dims = resolve_dimensions_with(conn, cache, &[&input])
+            .await?
+            .remove(0);
+
+        sqlx::query(
+            "UPDATE events
+            SET category_id = ?, entity_id = ?, project_id = ?, branch_id = ?
+            WHERE id = ?",
+        )
+        .bind(dims.category_id)
+        .bind(dims.entity_id)
+        .bind(dims.project_id)
+        .bind(dims.branch_id)
+        .bind(id)
+        .execute(&mut *conn)
+        .await?;
+        if let Some(tags) = &patch.tags {
+            set_event_tags(conn, id, tags).await?;
+        }
+
+        fetch_full_event(conn, id).await
+    }
+
+    /// Splits an event in two at `at`. Returns `None` if the event does not exist.
+    ///
+    /// The first part keeps the id of the event, so a client re-syncing the original
+    /// event does not bring the full span back. The second part gets an id derived
+    /// from the event id and `at`.
+    pub async fn split(
+        db: &DBContext,
+        id: Uuid,
+        at: DateTime<Utc>,
+    ) -> Result<Option<Vec<FullEvent>>, DBError> {
+        let mut tx = db.pool().begin().await?;
+        let Some(event) = fetch_events(&mut tx, &[id]).await?.pop() else {
+            return Ok(None);
+        };
+
+        let at = at.timestamp();
+        let end = event
+            .end()
+            .ok_or(DBError::Unsupported("Event has no end to split at"))?;
+        if at <= event.timestamp || at >= end {
+            return Err(DBError::Unsupported(
+                "Split time must fall strictly inside the event",
+            ));
+        }
+
+        sqlx::query("UPDATE events SET end_timestamp = ?, duration = ? WHERE id = ?")
+            .bind(at)
+            .bind(at - event.timestamp)
+            .bind(id)
+            .execute(&mut *tx)
+            .await?;
+
+        let second_id = Uuid::new_v5(&id, at.to_string().as_bytes());
+        sqlx::query(
+            "INSERT INTO events (
+                id, timestamp, duration, category_id, app_id,
+                entity_id, project_id, branch_id, language_id, source_id, end_timestamp
+            ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+        )
+        .bind(second_id)
+        .bind(at)
+        .bind(end - at)
+        .bind(event.category_id)
+        .bind(event.app_id)
+        .bind(event.entity_id)
+        .bind(event.project_id)
+        .bind(event.branch_id)
+        .bind(event.language_id)
+        .bind(event.source_id)
+        .bind(end)
+        .execute(&mut *tx)
+        .await?;
+        copy_event_tags(&mut tx, &[id], second_id).await?;
+
+        let mut parts = Vec::with_capacity(2);
+        for part in [id, second_id] {
+            parts.extend(fetch_full_event(&mut tx, part).await?);
+        }
+
+        tx.commit().await?;
+        Ok(Some(parts))
+    }
+
+    /// Merges adjacent events of the same activity into the earliest of them, which
+    /// is extended to the end of the latest. Returns `None` if any event does not exist.
+    ///
+    /// Events are adjacent when no other event from the same source lies between
+    /// them. The ids of the merged-away events are recorded so they are not ingested again.
+    pub async fn merge(db: &DBContext, ids: &[Uuid]) -> Result<Option<FullEvent>, DBError> {
+        let ids: Vec<Uuid> = ids
+            .iter()
+            .copied()
+            .collect::<HashSet<_>>()
+            .into_iter()
+            .collect();
+        if ids.len() < 2 {
+            return Err(DBError::Unsupported(
+                "At least two events are needed to merge",
+            ));
+        }
+
+        let mut tx = db.pool().begin().await?;
+        let events = fetch_events(&mut tx, &ids).await?;
+        if events.len() != ids.len() {
+            return Ok(None);
+        }
+
+        let Some((first, rest)) = events.split_first() else {
+            return Ok(None);
+        };
+        if rest.iter().any(|event| !first.same_activity(event)) {
+            return Err(DBError::Unsupported(
+                "Only events of the same activity can be merged",
+            ));
+        }
+
+        let end = events
+            .iter()
+            .filter_map(Event::end)
+            .max()
+            .ok_or(DBError::Unsupported("Events have no end to merge up to"))?;
+
+        let mut qb = QueryBuilder::<Sqlite>::new("SELECT COUNT(*) FROM events WHERE source_id = ");
+        qb.push_bind(first.source_id)
+            .push(" AND timestamp < ")
+            .push_bind(end)
+            .push(" AND COALESCE(end_timestamp, timestamp + COALESCE(duration, 0)) > ")
+            .push_bind(first.timestamp)
+            .push(" AND id NOT IN (");
+        let mut sep = qb.separated(", ");
+        for id in &ids {
+            sep.push_bind(*id);
+        }
+        qb.push(")");
+        let between: i64 = qb.build().fetch_one(&mut *tx).await?.try_get(0)?;
+        if between > 0 {
+            return Err(DBError::Unsupported("Events are not adjacent"));
+        }
+
+        sqlx::query("UPDATE events SET end_timestamp = ?, duration = ? WHERE id = ?")
+            .bind(end)
+            .bind(end - first.timestamp)
+            .bind(first.id)
+            .execute(&mut *tx)
+            .await?;
+
+        let merged: Vec<Uuid> = rest.iter().map(|event| event.id).collect();
+        copy_event_tags(&mut tx, &merged, first.id).await?;
+        delete_ids(&mut tx, &merged).await?;
+        insert_deleted_ids(&mut tx, &merged).await?;
+
+        let event = fetch_full_event(&mut tx, first.id).await?;
+        tx.commit().await?;
+        Ok(event)
+    }
+}
+
+impl SummaryQueryBuilder {
+    /// Deletes every event matching the configured range and filters, returning how
+    /// many were deleted. Events overlapping the range are deleted whole.
+    ///
+    /// The ids are recorded so the events are not ingested again. A request without a
+    /// range or any filter is rejected rather than deleting every event.
+    pub async fn delete_events(&self, db: &DBContext) -> Result<u64, DBError> {
+        if self.filters.is_unrestricted() {
+            return Err(DBError::MissingField("start, end or a filter"));
+        }
+
+        let mut qb = QueryBuilder::<Sqlite>::new(
+            "DELETE FROM events WHERE id IN (SELECT events.id FROM events",
+        );
+        qb.append_standard_joins(None);
+        qb.push(" WHERE 1=1");
+        qb.append_date_range(
+            self.filters.start,
+            self.filters.end,
+            "events.timestamp",
+            "events.end_timestamp",
+        );
+        qb.append_all_filters(&self.filters);
+        qb.push(") RETURNING id");
+
+        let mut tx = db.pool().begin().await?;
+        let ids = qb
+            .build()
+            .fetch_all(&mut *tx)
+            .await?
+            .iter()
+            .map(|row| row.try_get("id"))
+            .collect::<Result<Vec<Uuid>, _>>()?;
+
+        insert_deleted_ids(&mut tx, &ids).await?;
+        tx.commit().await?;
+        Ok(ids.len() as u64)
+    }
+}
+
+/// Returns which of `ids` belong to deleted events
+pub(crate) async fn fetch_deleted_ids(
+    conn: &mut SqliteConnection,
+    ids: impl Iterator<Item = Uuid>,
+) -> Result<HashSet<Uuid>, DBError> {
+    let ids: Vec<Uuid> = ids.collect();
+    let mut deleted = HashSet::new();
+
+    for chunk in ids.chunks(CHUNK_SIZE) {
+        let mut qb = QueryBuilder::<Sqlite>::new("SELECT id FROM deleted_events WHERE id IN (");
+        let mut sep = qb.separated(", ");
+        for id in chunk {
+            sep.push_bind(*id);
+        }
+        qb.push(")");
+
+        for row in qb.build().fetch_all(&mut *conn).await? {
+            deleted.insert(row.try_get("id")?);
+        }
+    }
+
+    Ok(deleted)
+}
+
+/// Records the ids the events `ids` would be ingested under with their current
+/// dimensions, after a change that moved them to other dimension rows (such as a
+/// project merge) while they kept their ids. A client re-sending one of these events
+/// then finds it deleted rather than storing it a second time.
+///
+/// Ids of stored events are left alone, since those events are duplicates already.
+pub(crate) async fn insert_rekeyed_ids(
+    conn: &mut SqliteConnection,
+    ids: &[Uuid],
+) -> Result<(), DBError> {
+    for chunk in ids.chunks(CHUNK_SIZE) {
+        let rekeyed: Vec<Uuid> = fetch_events(&mut *conn, chunk)
+            .await?
+            .iter()
+            .map(Event::ingest_id)
+            .filter(|id| !chunk.contains(id))
+            .collect();
+        if rekeyed.is_empty() {
+            continue;
+        }
+
+        let mut qb = QueryBuilder::<Sqlite>::new("SELECT id FROM events WHERE id IN (");
+        let mut sep = qb.separated(", ");
+        for id in &rekeyed {
+            sep.push_bind(*id);
+        }
+        qb.push(")");
+        let stored: HashSet<Uuid> = qb
+            .build_query_scalar()
+            .fetch_all(&mut *conn)
+            .await?
+            .into_iter()
+            .collect();
+
+        let rekeyed: Vec<Uuid> = rekeyed
+            .into_iter()
+            .filter(|id| !stored.contains(id))
+            .collect();
+        insert_deleted_ids(conn, &rekeyed).await?;
+    }
+
+    Ok(())
+}
+
+async fn insert_deleted_ids(conn: &mut SqliteConnection, ids: &[Uuid]) -> Result<(), DBError> {
+    let deleted_at = Utc::now().timestamp();
+
+    for chunk in ids.chunks(CHUNK_SIZE) {
+        let mut qb = QueryBuilder::<Sqlite>::new("INSERT INTO deleted_events (id, deleted_at) ");
+        qb.push_values(chunk, |mut b, id| {
+            b.push_bind(*id).push_bind(deleted_at);
+        });
+        qb.push(" ON CONFLICT(id) DO NOTHING");
+        qb.build().execute(&mut *conn).await?;
+    }
+
+    Ok(())
+}
+
+async fn delete_ids(conn: &mut SqliteConnection, ids: &[Uuid]) -> Result<(), DBError> {
+    for chunk in ids.chunks(CHUNK_SIZE) {
+        let mut qb = QueryBuilder::<Sqlite>::new("DELETE FROM events WHERE id IN (");
+        let mut sep = qb.separated(", ");
+        for id in chunk {
+            sep.push_bind(*id);
+        }
+        qb.push(")");
+        qb.build().execute(&mut *conn).await?;
+    }
+
+    Ok(())
+}
+
+/// Fetches the stored rows of `ids` in chronological order
+async fn fetch_events(conn: &mut SqliteConnection, ids: &[Uuid]) -> Result<Vec<Event>, DBError> {
+    let mut qb = QueryBuilder::<Sqlite>::new(EVENT_COLUMNS);
+    qb.push(" WHERE id IN (");
+    let mut sep = qb.separated(", ");
+    for id in ids {
+        sep.push_bind(*id);
+    }
+    qb.push(") ORDER BY timestamp, id");
+
+    Ok(qb.build_query_as::<Event>().fetch_all(&mut *conn).await?)
+}
+
+async fn fetch_full_event(
+    conn: &mut SqliteConnection,
+    id: Uuid,
+) -> Result<Option<FullEvent>, DBError> {
+    let mut qb = QueryBuilder::<Sqlite>::new(FULL_EVENT_COLUMNS);
+    qb.push(" FROM events ");
+    qb.append_standard_joins(None);
+    qb.push(" WHERE events.id = ").push_bind(id);
+
+    qb.build()
+        .fetch_optional(&mut *conn)
+        .await?
+        .as_ref()
+        .map(full_event_from_row)
+        .transpose()
+}
+
+#[cfg(all(test, feature = "server"))]
+mod tests {
+    use super::*;
+    use crate::server::test_utils::TestEvent;
+
+    async fn setup_db() -> DBContext {
+        DBContext::new("sqlite::memory:").await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_deleted_events_are_not_ingested_again() {
+        let db = setup_db().await;
+        let id = TestEvent::new(0, 60).project("skopio").insert(&db).await.id;
+
+        let mut conn = db.pool().acquire().await.unwrap();
+        let event = fetch_events(&mut conn, &[id]).await.unwrap().remove(0);
+        drop(conn);
+
+        assert!(Event::delete(&db, id).await.unwrap());
+        assert!(!Event::delete(&db, id).await.unwrap());
+
+        let inserted = Event::bulk_create(&db, &[event]).await.unwrap();
+        assert!(inserted.is_empty());
+        assert!(Event::find_by_id(&db, id).await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_update_moves_event_to_project() {
+        let db = setup_db().await;
+        let id = TestEvent::new(0, 60).project("skopio").insert(&db).await.id;
+
+        let patch = EventPatchInput {
+            project_name: Some("other".into()),
+            category: Some("Debugging".into()),
+            ..Default::default()
+        };
+        let event = Event::update(&db, id, patch).await.unwrap().unwrap();
+
+        assert_eq!(event.id, id);
+        assert_eq!(event.project.as_deref(), Some("other"));
+        assert_eq!(event.category, "Debugging");
+        assert_eq!(event.entity.as_deref(), Some("main.rs"));
+        assert_eq!(event.branch.as_deref(), Some("main"));
+    }
+
+    #[tokio::test]
+    async fn test_split_and_merge_round_trip() {
+        let db = setup_db().await;
+        let id = TestEvent::new(0, 600)
+            .project("skopio")
+            .insert(&db)
+            .await
+            .id;
+        let at = DateTime::from_timestamp(200, 0).unwrap();
+
+        let parts = Event::split(&db, id, at).await.unwrap().unwrap();
+        assert_eq!(parts.len(), 2);
+        assert_eq!(parts[0].id, id);
+        assert_eq!(parts[0].duration, Some(200));
+        assert_eq!(parts[1].timestamp, at);
+        assert_eq!(parts[1].duration, Some(400));
+
+        let outside = DateTime::from_timestamp(600, 0).unwrap();
+        assert!(Event::split(&db, id, outside).await.is_err());
+
+        let merged = Event::merge(&db, &[parts[1].id, id])
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(merged.id, id);
+        assert_eq!(merged.duration, Some(600));
+        assert!(Event::find_by_id(&db, parts[1].id).await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_merge_rejects_events_with_activity_between() {
+        let db = setup_db().await;
+        let first = TestEvent::new(0, 60).project("skopio").insert(&db).await.id;
+        TestEvent::new(60, 120).project("other").insert(&db).await;
+        let last = TestEvent::new(120, 180)
+            .project("skopio")
+            .insert(&db)
+            .await
+            .id;
+
+        assert!(Event::merge(&db, &[first, last]).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_delete_events_matching_filters() {
+        let db = setup_db().await;
+        TestEvent::new(0, 60).project("skopio").insert(&db).await;
+        TestEvent::new(100, 160).project("other").insert(&db).await;
+        TestEvent::new(5_000, 5_060)
+            .project("skopio")
+            .insert(&db)
+            .await;
+
+        let deleted = SummaryQueryBuilder::new()
+            .start(0)
+            .end(1_000)
+            .projects(vec!["skopio".into()])
+            .delete_events(&db)
+            .await
+            .unwrap();
+        assert_eq!(deleted, 1);
+
+        let remaining: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM events")
+            .fetch_one(db.pool())
+            .await
+            .unwrap();
+        assert_eq!(remaining, 2);
+    }
+
+    #[tokio::test]
+    async fn test_delete_events_without_filters_is_rejected() {
+        let db = setup_db().await;
+        TestEvent::new(0, 60).project("skopio").insert(&db).await;
+
+        let result = SummaryQueryBuilder::new().delete_events(&db).await;
+        assert!(matches!(result, Err(DBError::MissingField(_))));
+
+        let remaining: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM events")
+            .fetch_one(db.pool())
+            .await
+            .unwrap();
+        assert_eq!(remaining, 1);
+    }
+}
@@ -0,0 +1,595 @@
+use std::{
+    collections::{BTreeSet, HashMap},
+    sync::Mutex,
+};
+
+use chrono::Utc;
+use common::models::inputs::EventInput;
+use sqlx::{QueryBuilder, Row, Sqlite, SqliteConnection};
+use uuid::Uuid;
+
+use crate::{DBContext, error::DBError};
+
+/// Maximum number of rows written or looked up by a single statement
+const CHUNK_SIZE: usize = 500;
+
+/// Number of ids kept in the cache before it is reset
+const MAX_CACHED_IDS: usize = 50_000;
+
+/// The dimension tables an event refers to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+enum Dimension {
+    Project,
+    App,
+    Category,
+    Language,
+    Source,
+    Branch,
+    Entity,
+}
+
+impl Dimension {
+    fn table(self) -> &'static str {
+        match self {
+            Dimension::Project => "projects",
+            Dimension::App => "apps",
+            Dimension::Category => "categories",
+            Dimension::Language => "languages",
+            Dimension::Source => "sources",
+            Dimension::Branch => "branches",
+            Dimension::Entity => "entities",
+        }
+    }
+
+    /// Branches and entities are unique per project rather than globally
+    fn is_project_scoped(self) -> bool {
+        matches!(self, Dimension::Branch | Dimension::Entity)
+    }
+
+    /// The column a row is looked up by. Projects are keyed by their normalized
+    /// root path, everything else by name.
+    fn key_column(self) -> &'static str {
+        match self {
+            Dimension::Project => "root_path",
+            _ => "name",
+        }
+    }
+
+    /// The optional column stored alongside the key when a row is created
+    fn detail_column(self) -> Option<&'static str> {
+        match self {
+            Dimension::Project => Some("name"),
+            Dimension::Entity => Some("type"),
+            _ => None,
+        }
+    }
+}
+
+/// Identifies a dimension row by its natural key
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+struct DimensionKey {
+    dimension: Dimension,
+    project_id: Option<Uuid>,
+    /// The value of the dimension's key column
+    name: String,
+}
+
+impl DimensionKey {
+    fn named(dimension: Dimension, name: &str) -> Self {
+        Self {
+            dimension,
+            project_id: None,
+            name: name.to_string(),
+        }
+    }
+
+    fn scoped(dimension: Dimension, project_id: Uuid, name: &str) -> Self {
+        Self {
+            dimension,
+            project_id: Some(project_id),
+            name: name.to_string(),
+        }
+    }
+}
+
+/// Dimension ids of a single event
+#[derive(Debug, Clone, Copy)]
+pub struct EventDimensions {
+    pub app_id: Uuid,
+    pub project_id: Uuid,
+    pub branch_id: Option<Uuid>,
+    pub entity_id: Uuid,
+    pub language_id: Option<Uuid>,
+    pub category_id: Uuid,
+    pub source_id: Uuid,
+}
+
+/// Caches dimension ids by natural key across ingest requests.
+///
+/// Entries are only added once the transaction that created or found them has
+/// committed. Deleting or rewriting dimension rows must call [`DimensionCache::clear`].
+#[derive(Debug, Default)]
+pub struct DimensionCache {
+    ids: Mutex<HashMap<DimensionKey, Uuid>>,
+}
+
+impl DimensionCache {
+    /// Drops every cached id
+    pub fn clear(&self) {
+        self.ids.lock().unwrap_or_else(|e| e.into_inner()).clear();
+    }
+
+    fn get(&self, key: &DimensionKey) -> Option<Uuid> {
+        self.ids
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .get(key)
+            .copied()
+    }
+
+    fn extend(&self, resolved: &HashMap<DimensionKey, Uuid>) {
+        let mut ids = self.ids.lock().unwrap_or_else(|e| e.into_inner());
+        if ids.len() + resolved.len() > MAX_CACHED_IDS {
+            ids.clear();
+        }
+        ids.extend(resolved.iter().map(|(k, v)| (k.clone(), *v)));
+    }
+}
+
+/// Resolves the dimension ids of a batch of events, creating missing rows.
+///
+/// Each dimension table is handled with a few set-based statements inside a single
+/// transaction: missing rows are inserted in bulk, their ids read back, and
+/// `last_updated` is refreshed for every row the batch touches. Ids already known
+/// from earlier requests are served from the [`DimensionCache`].
+pub async fn resolve_dimensions(
+    db_context: &DBContext,
+    events: &[&EventInput],
+) -> Result<Vec<EventDimensions>, DBError> {
+    if events.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let cache = db_context.dimension_cache();
+    let mut resolved: HashMap<DimensionKey, Uuid> = HashMap::new();
+    let mut tx = db_context.pool().begin().await?;
+    let dimensions = resolve_into(&mut tx, cache, &mut resolved, events).await?;
+    tx.commit().await?;
+    cache.extend(&resolved);
+
+    Ok(dimensions)
+}
+
+/// Resolves dimension ids like [`resolve_dimensions`] on a connection whose transaction
+/// the caller commits. Ids are read from the cache but not added to it, since the
+/// transaction may still roll back.
+pub(crate) async fn resolve_dimensions_with(
+    conn: &mut SqliteConnection,
+    cache: &DimensionCache,
+    events: &[&EventInput],
+) -> Result<Vec<EventDimensions>, DBError> {
+    resolve_into(conn, cache, &mut HashMap::new(), events).await
+}
+
+/// Resolves the dimension ids of `events`, collecting every id it finds in `resolved`
+async fn resolve_into(
+    conn: &mut SqliteConnection,
+    cache: &DimensionCache,
+    resolved: &mut HashMap<DimensionKey, Uuid>,
+    events: &[&EventInput],
+) -> Result<Vec<EventDimensions>, DBError> {
+    let timestamp = Utc::now().timestamp();
+
+    let projects = events.iter().map(|e| {
+        (
+            DimensionKey::named(Dimension::Project, &project_key(e)),
+            Some(e.project_name.as_str()),
+        )
+    });
+    resolve_dimension(conn, cache, resolved, projects, timestamp).await?;
+
+    let named = events.iter().flat_map(|e| {
+        [
+            Some(DimensionKey::named(Dimension::App, &e.app_name)),
+            Some(DimensionKey::named(Dimension::Category, &e.category)),
+            Some(DimensionKey::named(Dimension::Source, &e.source_name)),
+            e.language_name
+                .as_deref()
+                .map(|name| DimensionKey::named(Dimension::Language, name)),
+        ]
+        .into_iter()
+        .flatten()
+        .map(|key| (key, None))
+    });
+    resolve_dimension(conn, cache, resolved, named, timestamp).await?;
+
+    let lookup = |resolved: &HashMap<DimensionKey, Uuid>, key: &DimensionKey| {
+        resolved
+            .get(key)
+            .copied()
+            .ok_or(DBError::MissingField("dimension id"))
+    };
+
+    let mut project_ids = Vec::with_capacity(events.len());
+    for event in events {
+        let key = DimensionKey::named(Dimension::Project, &project_key(event));
+        project_ids.push(lookup(resolved, &key)?);
+    }
+
+    let scoped = events
+        .iter()
+        .zip(&project_ids)
+        .flat_map(|(e, &project_id)| {
+            [
+                Some((
+                    DimensionKey::scoped(Dimension::Entity, project_id, &e.entity_name),
+                    Some(e.entity_type.as_str()),
+                )),
+                e.branch_name.as_deref().map(|name| {
+                    (
+                        DimensionKey::scoped(Dimension::Branch, project_id, name),
+                        None,
+                    )
+                }),
+            ]
+            .into_iter()
+            .flatten()
+        });
+    resolve_dimension(conn, cache, resolved, scoped, timestamp).await?;
+
+    events
+        .iter()
+        .zip(project_ids)
+        .map(|(e, project_id)| {
+            Ok(EventDimensions {
+                app_id: lookup(resolved, &DimensionKey::named(Dimension::App, &e.app_name))?,
+                project_id,
+                branch_id: e
+                    .branch_name
+                    .as_deref()
+                    .map(|name| {
+                        lookup(
+                            resolved,
+                            &DimensionKey::scoped(Dimension::Branch, project_id, name),
+                        )
+                    })
+                    .transpose()?,
+                entity_id: lookup(
+                    resolved,
+                    &DimensionKey::scoped(Dimension::Entity, project_id, &e.entity_name),
+                )?,
+                language_id: e
+                    .language_name
+                    .as_deref()
+                    .map(|name| lookup(resolved, &DimensionKey::named(Dimension::Language, name)))
+                    .transpose()?,
+                category_id: lookup(
+                    resolved,
+                    &DimensionKey::named(Dimension::Category, &e.category),
+                )?,
+                source_id: lookup(
+                    resolved,
+                    &DimensionKey::named(Dimension::Source, &e.source_name),
+                )?,
+            })
+        })
+        .collect()
+}
+
+/// Normalizes a project root path into the key projects are stored under: backslash
+/// separators become `/` and trailing separators are dropped. URLs (from browser
+/// tracking) are keyed by their origin, and projects reported without a path by
+/// their name.
+pub fn normalize_root_path(path: &str, name: &str) -> String {
+    let path = path.trim().replace('\\', "/");
+    let mut path = path.trim_end_matches('/');
+
+    if let Some(scheme_end) = path.find("://").map(|i| i + 3)
+        && let Some(host_len) = path[scheme_end..].find('/')
+    {
+        path = &path[..scheme_end + host_len];
+    }
+
+    match path {
+        "" => name.to_string(),
+        path => path.to_string(),
+    }
+}
+
+/// The key a project is looked up by. A key that is the alias of a merged project
+/// resolves to the project it was merged into, see [`resolve_aliases`].
+fn project_key(event: &EventInput) -> String {
+    normalize_root_path(&event.project_path, &event.project_name)
+}
+
+/// Resolves the pending project keys that are aliases of merged projects to the
+/// project they were merged into, removing them from `pending`
+async fn resolve_aliases(
+    conn: &mut SqliteConnection,
+    pending: &mut HashMap<DimensionKey, Option<&str>>,
+    resolved: &mut HashMap<DimensionKey, Uuid>,
+) -> Result<(), DBError> {
+    let keys: Vec<String> = pending
+        .keys()
+        .filter(|key| key.dimension == Dimension::Project)
+        .map(|key| key.name.clone())
+        .collect();
+
+    for chunk in keys.chunks(CHUNK_SIZE) {
+        let mut qb = QueryBuilder::<Sqlite>::new(
+            "SELECT root_path, project_id FROM project_aliases WHERE root_path IN (",
+        );
+        let mut sep = qb.separated(", ");
+        for key in chunk {
+            sep.push_bind(key.clone());
+        }
+        qb.push(")");
+
+        for row in qb.build().fetch_all(&mut *conn).await? {
+            let root_path: String = row.try_get("root_path")?;
+            let key = DimensionKey::named(Dimension::Project, &root_path);
+            pending.remove(&key);
+            resolved.insert(key, row.try_get("project_id")?);
+        }
+    }
+
+    Ok(())
+}
+
+/// Resolves the given keys into `resolved`, inserting missing rows and refreshing
+/// `last_updated` for every row referenced. The detail is only used for new rows.
+async fn resolve_dimension<'a>(
+    conn: &mut SqliteConnection,
+    cache: &DimensionCache,
+    resolved: &mut HashMap<DimensionKey, Uuid>,
+    keys: impl Iterator<Item = (DimensionKey, Option<&'a str>)>,
+    timestamp: i64,
+) -> Result<(), DBError> {
+    let mut referenced: BTreeSet<DimensionKey> = BTreeSet::new();
+    let mut pending: HashMap<DimensionKey, Option<&'a str>> = HashMap::new();
+    for (key, detail) in keys {
+        if !referenced.insert(key.clone()) || resolved.contains_key(&key) {
+            continue;
+        }
+        match cache.get(&key) {
+            Some(id) => {
+                resolved.insert(key, id);
+            }
+            None => {
+                pending.insert(key, detail);
+            }
+        }
+    }
+
+    resolve_aliases(conn, &mut pending, resolved).await?;
+
+    let mut by_dimension: HashMap<Dimension, Vec<(DimensionKey, Option<&str>)>> = HashMap::new();
+    for (key, detail) in pending {
+        by_dimension
+            .entry(key.dimension)
+            .or_default()
+            .push((key, detail));
+    }
+
+    for (dimension, rows) in &by_dimension {
+        for chunk in rows.chunks(CHUNK_SIZE) {
+            insert_missing(conn, *dimension, chunk, timestamp).await?;
+            fetch_ids(conn, *dimension, chunk, resolved).await?;
+        }
+    }
+
+    let mut touched: HashMap<Dimension, BTreeSet<Uuid>> = HashMap::new();
+    for key in &referenced {
+        if let Some(id) = resolved.get(key) {
+            touched.entry(key.dimension).or_default().insert(*id);
+        }
+    }
+
+    for (dimension, ids) in touched {
+        let ids: Vec<Uuid> = ids.into_iter().collect();
+        for chunk in ids.chunks(CHUNK_SIZE) {
+            let mut qb = QueryBuilder::<Sqlite>::new("UPDATE ");
+            qb.push(dimension.table())
+                .push(" SET last_updated = ")
+                .push_bind(timestamp)
+                .push(" WHERE id IN (");
+            let mut sep = qb.separated(", ");
+            for id in chunk {
+                sep.push_bind(*id);
+            }
+            qb.push(")");
+            qb.build().execute(&mut *conn).await?;
+        }
+    }
+
+    Ok(())
+}
+
+async fn insert_missing(
+    conn: &mut SqliteConnection,
+    dimension: Dimension,
+    rows: &[(DimensionKey, Option<&str>)],
+    timestamp: i64,
+) -> Result<(), DBError> {
+    let scoped = dimension.is_project_scoped();
+    let detail_column = dimension.detail_column();
+
+    let mut qb = QueryBuilder::<Sqlite>::new("INSERT INTO ");
+    qb.push(dimension.table()).push(" (id, ");
+    if scoped {
+        qb.push("project_id, ");
+    }
+    qb.push(dimension.key_column()).push(", ");
+    if let Some(column) = detail_column {
+        qb.push(column).push(", ");
+    }
+    qb.push("last_updated) ");
+
+    qb.push_values(rows, |mut b, (key, detail)| {
+        b.push_bind(Uuid::now_v7());
+        if scoped {
+            b.push_bind(key.project_id);
+        }
+        b.push_bind(key.name.clone());
+        if detail_column.is_some() {
+            b.push_bind(detail.map(str::to_string));
+        }
+        b.push_bind(timestamp);
+    });
+
+    if scoped {
+        qb.push(" ON CONFLICT(project_id, name) DO NOTHING");
+    } else {
+        qb.push(" ON CONFLICT(")
+            .push(dimension.key_column())
+            .push(") DO NOTHING");
+    }
+
+    qb.build().execute(&mut *conn).await?;
+    Ok(())
+}
+
+async fn fetch_ids(
+    conn: &mut SqliteConnection,
+    dimension: Dimension,
+    rows: &[(DimensionKey, Option<&str>)],
+    resolved: &mut HashMap<DimensionKey, Uuid>,
+) -> Result<(), DBError> {
+    let scoped = dimension.is_project_scoped();
+
+    let mut qb = QueryBuilder::<Sqlite>::new("SELECT id, ");
+    if scoped {
+        qb.push("project_id, name FROM ")
+            .push(dimension.table())
+            .push(" WHERE (project_id, name) IN (VALUES ");
+        let mut sep = qb.separated(", ");
+        for (key, _) in rows {
+            sep.push("(")
+                .push_bind_unseparated(key.project_id)
+                .push_unseparated(", ")
+                .push_bind_unseparated(key.name.clone())
+                .push_unseparated(")");
+        }
+    } else {
+        let key_column = dimension.key_column();
+        qb.push("NULL AS project_id, ")
+            .push(key_column)
+            .push(" AS name FROM ")
+            .push(dimension.table())
+            .push(" WHERE ")
+            .push(key_column)
+            .push(" IN (");
+        let mut sep = qb.separated(", ");
+        for (key, _) in rows {
+            sep.push_bind(key.name.clone());
+        }
+    }
+    qb.push(")");
+
+    let fetched = qb.build().fetch_all(&mut *conn).await?;
+    for row in fetched {
+        let key = DimensionKey {
+            dimension,
+            project_id: row.try_get("project_id")?,
+            name: row.try_get("name")?,
+        };
+        resolved.insert(key, row.try_get("id")?);
+    }
+
+    Ok(())
+}
+
+#[cfg(all(test, feature = "server"))]
+mod tests {
+    use super::*;
+
+    fn event(app: &str, project: &str, branch: Option<&str>) -> EventInput {
+        EventInput {
+            timestamp: None,
+            duration: Some(60),
+            category: "Coding".into(),
+            app_name: app.into(),
+            entity_name: "main.rs".into(),
+            entity_type: "File".into(),
+            project_name: project.into(),
+            project_path: format!("/tmp/{project}"),
+            branch_name: branch.map(String::from),
+            language_name: Some("Rust".into()),
+            source_name: "skopio-vscode".into(),
+            end_timestamp: None,
+            tags: Vec::new(),
+        }
+    }
+
+    async fn setup_db() -> DBContext {
+        DBContext::new("sqlite::memory:").await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_resolves_shared_dimensions_once() {
+        let db = setup_db().await;
+        let a = event("Code", "skopio", Some("main"));
+        let b = event("Code", "skopio", Some("dev"));
+
+        let dims = resolve_dimensions(&db, &[&a, &b]).await.unwrap();
+        assert_eq!(dims[0].app_id, dims[1].app_id);
+        assert_eq!(dims[0].entity_id, dims[1].entity_id);
+        assert_ne!(dims[0].branch_id, dims[1].branch_id);
+
+        let apps: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM apps")
+            .fetch_one(db.pool())
+            .await
+            .unwrap();
+        assert_eq!(apps, 1);
+    }
+
+    #[tokio::test]
+    async fn test_reuses_ids_across_batches() {
+        let db = setup_db().await;
+        let first = resolve_dimensions(&db, &[&event("Code", "skopio", None)])
+            .await
+            .unwrap();
+
+        db.dimension_cache().clear();
+        let second = resolve_dimensions(&db, &[&event("Code", "skopio", None)])
+            .await
+            .unwrap();
+
+        assert_eq!(first[0].project_id, second[0].project_id);
+        assert_eq!(first[0].entity_id, second[0].entity_id);
+        assert!(second[0].branch_id.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_projects_are_keyed_by_root_path() {
+        let db = setup_db().await;
+        let work = event("Code", "api", None);
+        let mut oss = event("Code", "api", None);
+        oss.project_path = "/oss/api".into();
+        let mut work_trailing = event("Code", "api-renamed", None);
+        work_trailing.project_path = "/tmp/api/".into();
+
+        let dims = resolve_dimensions(&db, &[&work, &oss, &work_trailing])
+            .await
+            .unwrap();
+        assert_ne!(dims[0].project_id, dims[1].project_id);
+        assert_eq!(dims[0].project_id, dims[2].project_id);
+
+        let names: Vec<String> = sqlx::query_scalar("SELECT name FROM projects ORDER BY root_path")
+            .fetch_all(db.pool())
+            .await
+            .unwrap();
+        assert_eq!(names, ["api", "api"]);
+    }
+
+    #[test]
+    fn test_normalize_root_path() {
+        assert_eq!(normalize_root_path("/tmp/api/", "api"), "/tmp/api");
+        assert_eq!(normalize_root_path("C:\\code\\api\\", "api"), "C:/code/api");
+        assert_eq!(normalize_root_path("  ", "api"), "api");
+        assert_eq!(
+            normalize_root_path("https://github.com/Skopio-app/skopio", "github.com"),
+            "https://github.com"
+        );
+    }
+}
@@ -1,5 +1,4 @@
 use crate::{DBContext, error::DBError};
-use chrono::Utc;
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
@@ -12,51 +11,6 @@ pub struct Entity {
 }
 
 impl Entity {
-    /// Finds an entity by name under a specific project, or inserts it if it doesn't exist.
-    pub async fn find_or_insert(
-        db_context: &DBContext,
-        project_id: Uuid,
-        name: &str,
-        entity_type: &str,
-    ) -> Result<Uuid, DBError> {
-        let record = sqlx::query!(
-            "SELECT id FROM entities WHERE project_id = ? AND name = ?",
-            project_id,
-            name
-        )
-        .fetch_optional(db_context.pool())
-        .await?;
-
-        let timestamp = Utc::now().timestamp();
-
-        if let Some(row) = record {
-            sqlx::query!(
-                "UPDATE entities SET last_updated = ? WHERE id = ?",
-                timestamp,
-                row.id
-            )
-            .execute(db_context.pool())
-            .await?;
-            let id = Uuid::from_slice(&row.id)?;
-            return Ok(id);
-        }
-
-        let id = uuid::Uuid::now_v7();
-        let result = sqlx::query!(
-            "INSERT INTO entities (id, project_id, name, type, last_updated) VALUES (?, ?, ?, ?, ?) RETURNING id",
-            id,
-            project_id,
-            name,
-            entity_type,
-            timestamp
-        )
-        .fetch_one(db_context.pool())
-        .await?;
-
-        let result_id = Uuid::from_slice(&result.id)?;
-        Ok(result_id)
-    }
-
     pub async fn all_by_project(
         db_context: &DBContext,
         project_id: Uuid,
@@ -84,6 +38,7 @@ impl Entity {
         sqlx::query!("DELETE FROM entities WHERE id = ?", self.id)
             .execute(db_context.pool())
             .await?;
+        db_context.dimension_cache().clear();
         Ok(())
     }
 }
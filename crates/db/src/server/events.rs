@@ -1,21 +1,30 @@
-use std::collections::HashMap;
-
 use chrono::{DateTime, Utc};
-use common::models::outputs::{EventGroup, EventGroupResult, FullEvent};
+use common::models::outputs::{
+    EventGroup, EventGroupResult, EventOverlap, EventPage, FullEvent, OverlapEvent,
+};
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
-use sqlx::{QueryBuilder, Row, Sqlite};
+use sqlx::{QueryBuilder, Row, Sqlite, sqlite::SqliteRow};
 
 use crate::{
     DBContext,
     error::DBError,
     server::{
+        corrections::fetch_deleted_ids,
+        dimensions::EventDimensions,
+        source::source_wins_expr,
         summary::SummaryQueryBuilder,
-        utils::query::{QueryBuilderExt, group_key_info},
+        utils::{
+            cursor::EventCursor,
+            query::{QueryBuilderExt, group_keys_info},
+        },
     },
 };
 
+/// Maximum number of events written by a single `INSERT` statement
+const INSERT_CHUNK_SIZE: usize = 500;
+
 #[derive(Serialize, Deserialize, Debug, sqlx::FromRow)]
 pub struct Event {
     pub id: Uuid,
@@ -32,79 +41,217 @@ pub struct Event {
 }
 
 impl Event {
-    // Bulk inserts new events into the database
-    pub async fn bulk_create(db_context: &DBContext, events: &[Self]) -> Result<u64, DBError> {
+    /// Builds an event over `[timestamp, end_timestamp]` with the given dimensions, keyed
+    /// by [`Event::ingest_id`]
+    pub fn from_dimensions(
+        dims: &EventDimensions,
+        timestamp: i64,
+        duration: Option<i64>,
+        end_timestamp: Option<i64>,
+    ) -> Self {
+        let mut event = Self {
+            id: Uuid::nil(),
+            timestamp,
+            duration,
+            category_id: dims.category_id,
+            app_id: dims.app_id,
+            entity_id: Some(dims.entity_id),
+            project_id: Some(dims.project_id),
+            branch_id: dims.branch_id,
+            language_id: dims.language_id,
+            source_id: dims.source_id,
+            end_timestamp,
+        };
+        event.id = event.ingest_id();
+        event
+    }
+
+    /// The id the event is stored under at ingest, derived from its dimension ids and
+    /// span so that an event sent again is recognised as a duplicate. Missing ids and
+    /// end timestamps are keyed as nil and zero.
+    pub fn ingest_id(&self) -> Uuid {
+        let key = format!(
+            "{}|{}|{}|{}|{}|{}|{}|{}|{}",
+            self.app_id,
+            self.entity_id.unwrap_or_default(),
+            self.project_id.unwrap_or_default(),
+            self.category_id,
+            self.source_id,
+            self.timestamp,
+            self.end_timestamp.unwrap_or_default(),
+            self.branch_id.unwrap_or_default(),
+            self.language_id.unwrap_or_default(),
+        );
+        Uuid::new_v5(&Uuid::NAMESPACE_URL, key.as_bytes())
+    }
+
+    /// Bulk inserts new events into the database, returning the ids of the rows that were inserted.
+    /// Events whose id already exists, or was deleted by a correction, are skipped.
+    ///
+    /// Rows are written with multi-row inserts of a few hundred events each,
+    /// all inside one transaction.
+    pub async fn bulk_create(
+        db_context: &DBContext,
+        events: &[Self],
+    ) -> Result<Vec<Uuid>, DBError> {
         if events.is_empty() {
-            return Ok(0);
+            return Ok(Vec::new());
         }
 
         let mut tx = db_context.pool().begin().await?;
-        let mut total_inserted: u64 = 0;
-
-        for ev in events {
-            let res = sqlx::query!(
-                "
-            INSERT INTO events (
-                id, timestamp, duration, category_id, app_id,
-                entity_id, project_id, branch_id, language_id, source_id, end_timestamp
-            )
-            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
-            ON CONFLICT(id) DO NOTHING
-            ",
-                ev.id,
-                ev.timestamp,
-                ev.duration,
-                ev.category_id,
-                ev.app_id,
-                ev.entity_id,
-                ev.project_id,
-                ev.branch_id,
-                ev.language_id,
-                ev.source_id,
-                ev.end_timestamp
-            )
-            .execute(&mut *tx)
-            .await?;
-
-            total_inserted += res.rows_affected();
+        let mut inserted = Vec::with_capacity(events.len());
+
+        for chunk in events.chunks(INSERT_CHUNK_SIZE) {
+            let deleted = fetch_deleted_ids(&mut tx, chunk.iter().map(|ev| ev.id)).await?;
+            let chunk: Vec<&Self> = chunk
+                .iter()
+                .filter(|ev| !deleted.contains(&ev.id))
+                .collect();
+            if chunk.is_empty() {
+                continue;
+            }
+
+            let mut qb = QueryBuilder::<Sqlite>::new(
+                "INSERT INTO events (
+                    id, timestamp, duration, category_id, app_id,
+                    entity_id, project_id, branch_id, language_id, source_id, end_timestamp
+                ) ",
+            );
+            qb.push_values(chunk, |mut b, ev| {
+                b.push_bind(ev.id)
+                    .push_bind(ev.timestamp)
+                    .push_bind(ev.duration)
+                    .push_bind(ev.category_id)
+                    .push_bind(ev.app_id)
+                    .push_bind(ev.entity_id)
+                    .push_bind(ev.project_id)
+                    .push_bind(ev.branch_id)
+                    .push_bind(ev.language_id)
+                    .push_bind(ev.source_id)
+                    .push_bind(ev.end_timestamp);
+            });
+            qb.push(" ON CONFLICT(id) DO NOTHING RETURNING id");
+
+            let rows = qb.build().fetch_all(&mut *tx).await?;
+            for row in rows {
+                inserted.push(row.try_get("id")?);
+            }
         }
 
         tx.commit().await?;
-        Ok(total_inserted)
+        Ok(inserted)
+    }
+
+    /// Lists spans where events from different sources overlap, in chronological order.
+    ///
+    /// Each span names the event it is credited to under the source priority policy
+    /// and the event that loses it. Only overlaps intersecting `[start, end)` are returned,
+    /// and only events intersecting it are joined.
+    pub async fn find_overlaps(
+        db_context: &DBContext,
+        start: i64,
+        end: i64,
+        limit: u32,
+    ) -> Result<Vec<EventOverlap>, DBError> {
+        let mut qb = QueryBuilder::<Sqlite>::new(
+            "
+            SELECT
+                MAX(kept.timestamp, shadowed.timestamp) AS overlap_start,
+                MIN(kept.end_timestamp, shadowed.end_timestamp) AS overlap_end,
+                kept.id AS kept_id,
+                kept_source.name AS kept_source,
+                kept_app.name AS kept_app,
+                kept_entity.name AS kept_entity,
+                shadowed.id AS shadowed_id,
+                shadowed_source.name AS shadowed_source,
+                shadowed_app.name AS shadowed_app,
+                shadowed_entity.name AS shadowed_entity
+            FROM events AS shadowed
+            JOIN events AS kept
+                ON kept.source_id != shadowed.source_id
+                AND kept.timestamp < shadowed.end_timestamp
+                AND kept.end_timestamp > shadowed.timestamp
+            JOIN sources AS kept_source ON kept.source_id = kept_source.id
+            JOIN sources AS shadowed_source ON shadowed.source_id = shadowed_source.id
+            LEFT JOIN apps AS kept_app ON kept.app_id = kept_app.id
+            LEFT JOIN apps AS shadowed_app ON shadowed.app_id = shadowed_app.id
+            LEFT JOIN entities AS kept_entity ON kept.entity_id = kept_entity.id
+            LEFT JOIN entities AS shadowed_entity ON shadowed.entity_id = shadowed_entity.id
+            WHERE ",
+        );
+        qb.push(source_wins_expr("kept_source", "shadowed_source"));
+
+        for side in ["kept", "shadowed"] {
+            qb.push(format!(" AND {side}.timestamp < "))
+                .push_bind(end)
+                .push(format!(" AND {side}.end_timestamp > "))
+                .push_bind(start);
+        }
+
+        qb.push(" ORDER BY overlap_start, kept.id, shadowed.id LIMIT ")
+            .push_bind(limit);
+
+        let rows = qb.build().fetch_all(db_context.pool()).await?;
+
+        rows.into_iter()
+            .map(|row| -> Result<EventOverlap, DBError> {
+                let overlap_start: i64 = row.try_get("overlap_start")?;
+                let overlap_end: i64 = row.try_get("overlap_end")?;
+
+                Ok(EventOverlap {
+                    start: DateTime::<Utc>::from_timestamp(overlap_start, 0).unwrap_or_default(),
+                    end: DateTime::<Utc>::from_timestamp(overlap_end, 0).unwrap_or_default(),
+                    duration: overlap_end - overlap_start,
+                    kept: OverlapEvent {
+                        id: row.try_get("kept_id")?,
+                        source: row.try_get("kept_source")?,
+                        app: row.try_get("kept_app")?,
+                        entity: row.try_get("kept_entity")?,
+                    },
+                    shadowed: OverlapEvent {
+                        id: row.try_get("shadowed_id")?,
+                        source: row.try_get("shadowed_source")?,
+                        app: row.try_get("shadowed_app")?,
+                        entity: row.try_get("shadowed_entity")?,
+                    },
+                })
+            })
+            .collect()
     }
 }
 
+/// Columns of a [`FullEvent`], selected from `events` with the standard joins
+pub(crate) const FULL_EVENT_COLUMNS: &str = "
+    SELECT
+        events.id,
+        events.timestamp,
+        events.end_timestamp,
+        events.duration,
+        apps.name AS app,
+        categories.name AS category,
+        entities.name AS entity,
+        entities.type AS entity_type,
+        projects.name AS project,
+        branches.name AS branch,
+        languages.name AS language,
+        sources.name AS source
+    ";
+
 impl SummaryQueryBuilder {
     /// Fetches events within the configured time range and optional filters,
     /// and returns them either as a flat list or grouped by a chosen dimension
     pub async fn fetch_event_range(&self, db: &DBContext) -> Result<EventGroupResult, DBError> {
-        let is_grouped = self.filters.group_by.is_some();
-        let (group_key, inner_tbl) = group_key_info(self.filters.group_by);
+        let (group_keys, inner_tbls) = group_keys_info(&self.filters.groups());
+        let is_grouped = !group_keys.is_empty();
 
-        let mut qb = QueryBuilder::<Sqlite>::new(
-            "
-            SELECT
-                events.id,
-                events.timestamp,
-                events.end_timestamp,
-                events.duration,
-                apps.name AS app,
-                categories.name AS category,
-                entities.name AS entity,
-                entities.type AS entity_type,
-                projects.name AS project,
-                branches.name AS branch,
-                languages.name AS language,
-                sources.name AS source
-            ",
-        );
+        let mut qb = QueryBuilder::<Sqlite>::new(FULL_EVENT_COLUMNS);
 
-        if is_grouped {
-            qb.push(", ").push(group_key).push(" AS group_key");
+        for (i, key) in group_keys.iter().enumerate() {
+            qb.push(", ").push(key).push(" AS group_key_").push(i);
         }
 
         qb.push(" FROM events ");
-        qb.append_standard_joins(inner_tbl);
+        qb.append_standard_joins_with(&inner_tbls);
 
         qb.push(" WHERE 1=1");
         qb.append_date_range(
@@ -116,8 +263,8 @@ impl SummaryQueryBuilder {
         qb.append_all_filters(&self.filters);
 
         qb.push(" ORDER BY ");
-        if is_grouped {
-            qb.push(group_key).push(", ");
+        for key in &group_keys {
+            qb.push(key).push(", ");
         }
         qb.push("events.timestamp");
 
@@ -141,51 +288,283 @@ impl SummaryQueryBuilder {
         let rows = query.fetch_all(db.pool()).await?;
 
         let mut flat_events = Vec::new();
-        let mut grouped_events: HashMap<String, Vec<FullEvent>> = HashMap::new();
+        let mut groups: Vec<EventGroup> = Vec::new();
 
         for row in rows {
-            let timestamp: DateTime<Utc> =
-                DateTime::<Utc>::from_timestamp(row.try_get::<i64, _>("timestamp")?, 0)
-                    .unwrap_or_default();
-            let end_timestamp: Option<DateTime<Utc>> =
-                DateTime::<Utc>::from_timestamp(row.try_get::<i64, _>("end_timestamp")?, 0);
-
-            let id = row.try_get("id").map(Uuid::from_slice).unwrap()?;
-
-            let event = FullEvent {
-                id,
-                timestamp,
-                end_timestamp,
-                duration: row.try_get("duration")?,
-                category: row
-                    .try_get::<Option<String>, _>("category")?
-                    .unwrap_or_default(),
-                app: row.try_get("app")?,
-                entity: row.try_get("entity")?,
-                entity_type: row.try_get("entity_type")?,
-                project: row.try_get("project")?,
-                branch: row.try_get("branch")?,
-                language: row.try_get("language")?,
-                source: row.try_get("source")?,
-            };
+            let event = full_event_from_row(&row)?;
 
             if is_grouped {
-                let group_key = row.try_get::<String, _>("group_key")?;
-                grouped_events.entry(group_key).or_default().push(event);
+                let path = (0..group_keys.len())
+                    .map(|i| row.try_get(format!("group_key_{i}").as_str()))
+                    .collect::<Result<Vec<String>, _>>()?;
+                insert_grouped(&mut groups, &path, event);
             } else {
                 flat_events.push(event);
             }
         }
 
         if is_grouped {
-            let grouped_vec = grouped_events
-                .into_iter()
-                .map(|(group, events)| EventGroup { group, events })
-                .collect();
-
-            Ok(EventGroupResult::Grouped(grouped_vec))
+            Ok(EventGroupResult::Grouped(groups))
         } else {
             Ok(EventGroupResult::Flat(flat_events))
         }
     }
+
+    /// Fetches up to `limit` events matching the configured range and filters, ordered by
+    /// start and id, that come after `after`. Grouping is ignored.
+    ///
+    /// The page carries a cursor for the next page unless it is the last one.
+    pub async fn fetch_event_page(
+        &self,
+        db: &DBContext,
+        after: Option<EventCursor>,
+        limit: u32,
+    ) -> Result<EventPage, DBError> {
+        let mut qb = QueryBuilder::<Sqlite>::new(FULL_EVENT_COLUMNS);
+        qb.push(" FROM events ");
+        qb.append_standard_joins(None);
+
+        qb.push(" WHERE 1=1");
+        qb.append_date_range(
+            self.filters.start,
+            self.filters.end,
+            "events.timestamp",
+            "events.end_timestamp",
+        );
+        qb.append_all_filters(&self.filters);
+
+        if let Some(cursor) = after {
+            qb.push(" AND (events.timestamp > ")
+                .push_bind(cursor.timestamp)
+                .push(" OR (events.timestamp = ")
+                .push_bind(cursor.timestamp)
+                .push(" AND events.id > ")
+                .push_bind(cursor.id)
+                .push("))");
+        }
+
+        // One extra row tells whether another page follows
+        qb.push(" ORDER BY events.timestamp, events.id LIMIT ")
+            .push_bind(i64::from(limit) + 1);
+
+        let rows = qb.build().fetch_all(db.pool()).await?;
+        let events = rows
+            .iter()
+            .map(full_event_from_row)
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(into_page(events, limit))
+    }
+}
+
+/// Truncates `events` to `limit` and wraps them in a page, pointing the cursor at the
+/// last event kept when more events were fetched
+pub(crate) fn into_page(mut events: Vec<FullEvent>, limit: u32) -> EventPage {
+    let has_more = events.len() > limit as usize;
+    events.truncate(limit as usize);
+
+    let next_cursor = events.last().filter(|_| has_more).map(|event| {
+        EventCursor {
+            timestamp: event.timestamp.timestamp(),
+            id: event.id,
+        }
+        .encode()
+    });
+
+    EventPage {
+        events,
+        next_cursor,
+    }
+}
+
+pub(crate) fn full_event_from_row(row: &SqliteRow) -> Result<FullEvent, DBError> {
+    let timestamp: DateTime<Utc> =
+        DateTime::<Utc>::from_timestamp(row.try_get::<i64, _>("timestamp")?, 0).unwrap_or_default();
+    let end_timestamp: Option<DateTime<Utc>> =
+        DateTime::<Utc>::from_timestamp(row.try_get::<i64, _>("end_timestamp")?, 0);
+
+    let id = row.try_get("id").map(Uuid::from_slice).unwrap()?;
+
+    Ok(FullEvent {
+        id,
+        timestamp,
+        end_timestamp,
+        duration: row.try_get("duration")?,
+        category: row
+            .try_get::<Option<String>, _>("category")?
+            .unwrap_or_default(),
+        app: row.try_get("app")?,
+        entity: row.try_get("entity")?,
+        entity_type: row.try_get("entity_type")?,
+        project: row.try_get("project")?,
+        branch: row.try_get("branch")?,
+        language: row.try_get("language")?,
+        source: row.try_get("source")?,
+    })
+}
+
+/// Adds `event` to the group found by following `path`, creating groups as needed.
+/// Events are expected in `path` order, so a group only ever grows at the end.
+fn insert_grouped(groups: &mut Vec<EventGroup>, path: &[String], event: FullEvent) {
+    let Some((key, rest)) = path.split_first() else {
+        return;
+    };
+
+    if groups.last().is_none_or(|g| &g.group != key) {
+        groups.push(EventGroup {
+            group: key.clone(),
+            events: Vec::new(),
+            subgroups: Vec::new(),
+        });
+    }
+
+    if let Some(group) = groups.last_mut() {
+        if rest.is_empty() {
+            group.events.push(event);
+        } else {
+            insert_grouped(&mut group.subgroups, rest, event);
+        }
+    }
+}
+
+#[cfg(all(test, feature = "server"))]
+mod tests {
+    use super::*;
+    use crate::server::test_utils::TestEvent;
+
+    fn event(project: &str) -> FullEvent {
+        FullEvent {
+            id: Uuid::now_v7(),
+            timestamp: Utc::now(),
+            end_timestamp: None,
+            duration: Some(60),
+            category: "Coding".into(),
+            app: Some("Code".into()),
+            entity: None,
+            entity_type: None,
+            project: Some(project.into()),
+            branch: None,
+            language: None,
+            source: "skopio-vscode".into(),
+        }
+    }
+
+    #[test]
+    fn test_insert_grouped_nests_paths() {
+        let path = |keys: &[&str]| keys.iter().map(|k| k.to_string()).collect::<Vec<_>>();
+
+        let mut groups = Vec::new();
+        insert_grouped(&mut groups, &path(&["skopio", "Rust"]), event("skopio"));
+        insert_grouped(&mut groups, &path(&["skopio", "Rust"]), event("skopio"));
+        insert_grouped(
+            &mut groups,
+            &path(&["skopio", "TypeScript"]),
+            event("skopio"),
+        );
+        insert_grouped(&mut groups, &path(&["other", "Rust"]), event("other"));
+
+        assert_eq!(groups.len(), 2);
+        assert!(groups[0].events.is_empty());
+        let languages: Vec<&str> = groups[0]
+            .subgroups
+            .iter()
+            .map(|g| g.group.as_str())
+            .collect();
+        assert_eq!(languages, ["Rust", "TypeScript"]);
+        assert_eq!(groups[0].subgroups[0].events.len(), 2);
+        assert_eq!(groups[1].subgroups[0].events.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_fetch_event_page_walks_all_events() {
+        let db = DBContext::new("sqlite::memory:").await.unwrap();
+        for (start, entity) in [
+            (300, "main.rs"),
+            (100, "main.rs"),
+            (200, "main.rs"),
+            (200, "lib.rs"),
+            (400, "main.rs"),
+        ] {
+            TestEvent::new(start, start + 60)
+                .entity(entity)
+                .insert(&db)
+                .await;
+        }
+
+        let builder = SummaryQueryBuilder::new().start(0).end(1_000);
+        let mut after = None;
+        let mut starts = Vec::new();
+        let mut pages = 0;
+
+        loop {
+            let page = builder.fetch_event_page(&db, after, 2).await.unwrap();
+            starts.extend(page.events.iter().map(|e| e.timestamp.timestamp()));
+            pages += 1;
+
+            match page.next_cursor {
+                Some(cursor) => after = EventCursor::decode(&cursor),
+                None => break,
+            }
+        }
+
+        assert_eq!(starts, [100, 200, 200, 300, 400]);
+        assert_eq!(pages, 3);
+    }
+
+    #[tokio::test]
+    async fn test_find_overlaps_within_range() {
+        let db = DBContext::new("sqlite::memory:").await.unwrap();
+        TestEvent::new(0, 100).insert(&db).await;
+        TestEvent::new(50, 150)
+            .source("skopio-desktop")
+            .insert(&db)
+            .await;
+        TestEvent::new(1_000, 1_100).insert(&db).await;
+        TestEvent::new(1_000, 1_100)
+            .source("skopio-desktop")
+            .insert(&db)
+            .await;
+
+        let overlaps = Event::find_overlaps(&db, 0, 500, 10).await.unwrap();
+        assert_eq!(overlaps.len(), 1);
+        assert_eq!(overlaps[0].start.timestamp(), 50);
+        assert_eq!(overlaps[0].duration, 50);
+
+        // The overlap ends where the range starts
+        assert!(
+            Event::find_overlaps(&db, 100, 500, 10)
+                .await
+                .unwrap()
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn test_ingest_id_keeps_the_stored_key_format() {
+        let [category, app, entity, project, source] = [1, 2, 3, 4, 5].map(Uuid::from_u128);
+        let event = Event {
+            id: Uuid::nil(),
+            timestamp: 100,
+            duration: Some(60),
+            category_id: category,
+            app_id: app,
+            entity_id: Some(entity),
+            project_id: Some(project),
+            branch_id: None,
+            language_id: None,
+            source_id: source,
+            end_timestamp: Some(160),
+        };
+
+        // Ids of stored events were derived from this key, so it must not change
+        let key = format!(
+            "{app}|{entity}|{project}|{category}|{source}|100|160|{}|{}",
+            Uuid::nil(),
+            Uuid::nil()
+        );
+        assert_eq!(
+            event.ingest_id(),
+            Uuid::new_v5(&Uuid::NAMESPACE_URL, key.as_bytes())
+        );
+    }
 }
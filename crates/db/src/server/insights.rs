@@ -1,23 +1,36 @@
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, HashMap};
 
 use async_trait::async_trait;
+use chrono::{
+    DateTime, Datelike, Local, NaiveDate, NaiveDateTime, TimeZone, Timelike, Utc, Weekday,
+};
 use common::{
-    models::{Group, InsightBucket, InsightType, outputs::InsightResult},
-    time::{TimeBucket, insight::InsightRange},
+    models::{
+        Group, HeatmapMetric, InsightBucket, InsightType,
+        outputs::{
+            HeatmapSeries, HourlyHeatmap, InsightResult, SessionInsight, StreakInsight, StreakRun,
+        },
+    },
+    time::{TimeBucket, Tz, WeekStart, bucket::bucket_spans_in, insight::InsightRange},
 };
 use sqlx::{QueryBuilder, Sqlite};
 
 use crate::{
     DBContext,
     error::DBError,
-    server::utils::query::{
-        BucketStep, QueryBuilderExt, bucket_step, group_key_info, push_next_end_with,
+    server::{
+        sessions::{DEFAULT_SESSION_GAP, fetch_sessions, session_stats},
+        utils::{
+            query::{QueryBuilderExt, group_key_info, push_buckets_cte},
+            summary_filter::SummaryFilters,
+        },
     },
 };
 
 #[derive(sqlx::FromRow)]
-struct YearResult {
-    year: Option<String>,
+struct YearRange {
+    first_year: Option<i32>,
+    last_year: Option<i32>,
 }
 
 #[derive(sqlx::FromRow)]
@@ -32,6 +45,19 @@ struct MostActiveDayRow {
     total: i64,
 }
 
+#[derive(sqlx::FromRow)]
+struct DayTotalRow {
+    date: String,
+    total: i64,
+}
+
+#[derive(sqlx::FromRow)]
+struct HeatmapRow {
+    slot: i64,
+    label: String,
+    total: i64,
+}
+
 #[derive(sqlx::FromRow)]
 struct AvgRow {
     key: i64,
@@ -48,6 +74,9 @@ pub trait InsightProvider {
 #[derive(Debug, Clone)]
 pub struct Insights;
 
+/// Minutes of activity a day needs by default to count towards a streak
+pub const DEFAULT_STREAK_MINUTES: i64 = 1;
+
 #[derive(Debug)]
 pub struct InsightQuery {
     pub insight_type: InsightType,
@@ -55,6 +84,21 @@ pub struct InsightQuery {
     pub group_by: Option<Group>,
     pub limit: Option<usize>,
     pub bucket: Option<InsightBucket>,
+    /// Longest break in seconds that still continues a session, [`DEFAULT_SESSION_GAP`] when `None`
+    pub session_gap: Option<i64>,
+    /// Minutes of activity a day needs to count towards a streak, [`DEFAULT_STREAK_MINUTES`] when `None`
+    pub min_daily_minutes: Option<i64>,
+    /// Weekdays that neither count towards nor break a streak
+    pub excluded_days: Vec<Weekday>,
+    /// Whether heatmap cells hold total or average seconds
+    pub metric: HeatmapMetric,
+    /// Dimension filters (apps, projects, categories, ...) limiting which events every insight counts
+    pub filters: SummaryFilters,
+    pub exclude_afk: bool,
+    /// Timezone that days, months and years are computed in, the server's local one when `None`
+    pub timezone: Option<Tz>,
+    /// First day of the week for "yyyy-W##" ranges
+    pub week_start: WeekStart,
 }
 
 #[async_trait]
@@ -65,20 +109,50 @@ impl InsightProvider for Insights {
     ) -> Result<InsightResult, DBError> {
         match query.insight_type {
             InsightType::ActiveYears => {
-                let rows: Vec<YearResult> = sqlx::query_as!(
-                    YearResult,
-                    "
-                    SELECT DISTINCT strftime('%Y', timestamp, 'unixepoch', 'localtime') as year
-                    FROM events
-                    ORDER BY year DESC
-                    "
+                // UTC years bound the local ones to within a year either side
+                let bounds: YearRange = sqlx::query_as(
+                    "SELECT
+                        CAST(strftime('%Y', MIN(timestamp), 'unixepoch') AS INTEGER) AS first_year,
+                        CAST(strftime('%Y', MAX(timestamp), 'unixepoch') AS INTEGER) AS last_year
+                    FROM events",
                 )
-                .fetch_all(db_context.pool())
+                .fetch_one(db_context.pool())
                 .await?;
 
-                let years = rows
+                let (Some(first_year), Some(last_year)) = (bounds.first_year, bounds.last_year)
+                else {
+                    return Ok(InsightResult::ActiveYears(Vec::new()));
+                };
+
+                let year_start = |year: i32| {
+                    NaiveDate::from_ymd_opt(year, 1, 1)
+                        .map(|d| Utc.from_utc_datetime(&d.and_time(Default::default())))
+                        .ok_or(DBError::Unsupported("Event timestamps are out of range"))
+                };
+                let spans = bucket_spans_in(
+                    query.timezone,
+                    query.week_start,
+                    year_start(first_year - 1)?,
+                    year_start(last_year + 2)?,
+                    Some(TimeBucket::Year),
+                );
+
+                let mut qb = QueryBuilder::<Sqlite>::new("");
+                push_buckets_cte(&mut qb, &spans);
+                qb.push("SELECT bucket_label FROM buckets WHERE EXISTS (SELECT 1 FROM events ");
+                qb.append_standard_joins(None);
+                qb.push(
+                    " WHERE events.timestamp >= buckets.start_ts AND events.timestamp < buckets.end_ts",
+                );
+                qb.append_all_filters(&query.filters);
+                qb.push(") ORDER BY buckets.start_ts DESC");
+
+                let labels: Vec<String> =
+                    qb.build_query_scalar().fetch_all(db_context.pool()).await?;
+
+                let years = labels
                     .into_iter()
-                    .filter_map(|r| r.year.and_then(|y| y.parse::<i32>().ok()))
+                    .filter_map(|label| label.parse::<i32>().ok())
                     .collect();
 
                 Ok(InsightResult::ActiveYears(years))
@@ -102,7 +176,11 @@ impl InsightProvider for Insights {
 
                 let mut qb = QueryBuilder::<Sqlite>::new("SELECT ");
                 qb.push(group_key).push(" AS label, COALESCE(SUM(");
-                qb.push_overlap_duration(&start_epoch.to_string(), &end_epoch.to_string());
+                qb.push_active_duration(
+                    &start_epoch.to_string(),
+                    &end_epoch.to_string(),
+                    query.exclude_afk,
+                );
                 qb.push("), 0) AS total_duration FROM events ");
 
                 qb.append_standard_joins(inner_tbl);
@@ -114,6 +192,7 @@ impl InsightProvider for Insights {
                     "events.timestamp",
                     "events.end_timestamp",
                 );
+                qb.append_all_filters(&query.filters);
 
                 qb.push(" GROUP BY ").push(group_key);
                 qb.push(" ORDER BY total_duration DESC LIMIT ");
@@ -146,49 +225,32 @@ impl InsightProvider for Insights {
                 let range_start = start.timestamp();
                 let range_end = end.timestamp();
 
-                let step = BucketStep::Seconds(86_400);
-
-                let mut qb = QueryBuilder::<Sqlite>::new(
-                    "WITH RECURSIVE buckets(start_ts, end_ts) AS ( SELECT ",
+                let spans = bucket_spans_in(
+                    query.timezone,
+                    query.week_start,
+                    start,
+                    end,
+                    Some(TimeBucket::Day),
                 );
+                if spans.is_empty() {
+                    return Ok(InsightResult::MostActiveDay {
+                        date: String::new(),
+                        total_duration: 0,
+                    });
+                }
 
-                qb.push_bind(range_start)
-                    .push(", MIN(")
-                    .push_bind(range_end)
-                    .push(", ");
-                push_next_end_with(
-                    &mut qb,
-                    |q| {
-                        q.push_bind(range_start);
-                    },
-                    &step,
-                );
-                qb.push(") UNION ALL SELECT end_ts, MIN(")
-                    .push_bind(range_end)
-                    .push(", ");
-                push_next_end_with(
-                    &mut qb,
-                    |q| {
-                        q.push("end_ts");
-                    },
-                    &step,
-                );
-                qb.push(") FROM buckets WHERE end_ts < ")
-                    .push_bind(range_end)
-                    .push(") ");
+                let mut qb = QueryBuilder::<Sqlite>::new("");
+                push_buckets_cte(&mut qb, &spans);
 
-                qb.push(
-                    "SELECT \
-                        strftime('%Y-%m-%d', datetime(buckets.start_ts,'unixepoch','localtime')) AS date, \
-                        COALESCE(SUM(",
-                );
-                qb.push_overlap_duration("buckets.start_ts", "buckets.end_ts");
+                qb.push("SELECT buckets.bucket_label AS date, COALESCE(SUM(");
+                qb.push_active_duration("buckets.start_ts", "buckets.end_ts", query.exclude_afk);
                 qb.push(
                     "), 0) AS total \
                      FROM buckets \
-                     JOIN events ON events.end_timestamp > buckets.start_ts AND events.timestamp < buckets.end_ts \
-                     WHERE 1=1",
+                     JOIN events ON events.end_timestamp > buckets.start_ts AND events.timestamp < buckets.end_ts ",
                 );
+                qb.append_standard_joins(None);
+                qb.push(" WHERE 1=1");
 
                 qb.append_date_range(
                     Some(range_start),
@@ -196,6 +258,7 @@ impl InsightProvider for Insights {
                     "events.timestamp",
                     "events.end_timestamp",
                 );
+                qb.append_all_filters(&query.filters);
 
                 qb.push(" GROUP BY date ORDER BY total DESC LIMIT 1");
 
@@ -222,57 +285,42 @@ impl InsightProvider for Insights {
                 let range_start = start.timestamp();
                 let range_end = end.timestamp();
 
-                let (key_expr, step): (&'static str, BucketStep) = match bucket {
-                    InsightBucket::Day => (
-                        "CAST(strftime('%w', datetime(buckets.start_ts,'unixepoch','localtime')) AS INTEGER)",
-                        BucketStep::Seconds(86_400),
-                    ),
-                    InsightBucket::Month => (
-                        "CAST(strftime('%m', datetime(buckets.start_ts,'unixepoch','localtime')) AS INTEGER)",
-                        bucket_step(Some(TimeBucket::Month)),
-                    ),
+                // Each bucket is labelled with its weekday (0 = Sunday) or month number
+                let (time_bucket, key_of): (TimeBucket, fn(NaiveDate) -> u32) = match bucket {
+                    InsightBucket::Day => (TimeBucket::Day, |d: NaiveDate| {
+                        d.weekday().num_days_from_sunday()
+                    }),
+                    InsightBucket::Month => (TimeBucket::Month, |d: NaiveDate| d.month()),
                     _ => return Err(DBError::Unsupported("Only day or month is supported")),
                 };
 
-                let (group_key, inner_tbl) = group_key_info(query.group_by);
-
-                let mut qb = QueryBuilder::<Sqlite>::new(
-                    "WITH RECURSIVE buckets(start_ts, end_ts) AS ( SELECT ",
+                let mut spans = bucket_spans_in(
+                    query.timezone,
+                    query.week_start,
+                    start,
+                    end,
+                    Some(time_bucket),
                 );
+                if spans.is_empty() {
+                    return Ok(InsightResult::AggregatedAverage(BTreeMap::new()));
+                }
+                for span in &mut spans {
+                    span.label = key_of(span.local_start.date()).to_string();
+                }
 
-                qb.push_bind(range_start)
-                    .push(", MIN(")
-                    .push_bind(range_end)
-                    .push(", ");
-                push_next_end_with(
-                    &mut qb,
-                    |q| {
-                        q.push_bind(range_start);
-                    },
-                    &step,
-                );
-                qb.push(") UNION ALL SELECT end_ts, MIN(")
-                    .push_bind(range_end)
-                    .push(", ");
-                push_next_end_with(
-                    &mut qb,
-                    |q| {
-                        q.push("end_ts");
-                    },
-                    &step,
-                );
-                qb.push(") FROM buckets WHERE end_ts < ")
-                    .push_bind(range_end)
-                    .push(") ");
+                let (group_key, inner_tbl) = group_key_info(query.group_by);
+
+                let mut qb = QueryBuilder::<Sqlite>::new("");
+                push_buckets_cte(&mut qb, &spans);
 
                 qb.push(
                     "SELECT key, label, ROUND(AVG(total_seconds), 2) AS avg_duration \
                      FROM ( \
                         SELECT ",
                 );
-                qb.push(key_expr).push(" AS key, ");
+                qb.push("CAST(buckets.bucket_label AS INTEGER) AS key, ");
                 qb.push(group_key).push(" AS label, COALESCE(SUM(");
-                qb.push_overlap_duration("buckets.start_ts", "buckets.end_ts");
+                qb.push_active_duration("buckets.start_ts", "buckets.end_ts", query.exclude_afk);
                 qb.push(
                     "), 0) AS total_seconds \
                         FROM buckets \
@@ -287,6 +335,7 @@ impl InsightProvider for Insights {
                     "events.timestamp",
                     "events.end_timestamp",
                 );
+                qb.append_all_filters(&query.filters);
 
                 qb.push(
                     " GROUP BY buckets.start_ts, label, key \
@@ -325,6 +374,354 @@ impl InsightProvider for Insights {
 
                 Ok(InsightResult::AggregatedAverage(map))
             }
+
+            InsightType::Sessions => {
+                let Some(InsightRange { start, end }) = query.insight_range else {
+                    return Err(DBError::MissingField("insight_range"));
+                };
+
+                let gap = query.session_gap.unwrap_or(DEFAULT_SESSION_GAP);
+                if gap < 0 {
+                    return Err(DBError::Unsupported("Session gap must not be negative"));
+                }
+
+                let sessions = fetch_sessions(db_context, start, end, gap, &query.filters).await?;
+                let stats = session_stats(&sessions);
+
+                Ok(InsightResult::Sessions(SessionInsight { sessions, stats }))
+            }
+
+            InsightType::Streak => {
+                let min_seconds = query.min_daily_minutes.unwrap_or(DEFAULT_STREAK_MINUTES) * 60;
+                if min_seconds < 0 {
+                    return Err(DBError::Unsupported(
+                        "Minimum daily minutes must not be negative",
+                    ));
+                }
+
+                let now = Utc::now();
+                let today = match query.timezone {
+                    Some(tz) => now.with_timezone(&tz).date_naive(),
+                    None => now.with_timezone(&Local).date_naive(),
+                };
+
+                let (start, end) = match query.insight_range {
+                    Some(InsightRange { start, end }) => (start, end.min(now)),
+                    None => {
+                        let mut qb =
+                            QueryBuilder::<Sqlite>::new("SELECT MIN(events.timestamp) FROM events");
+                        qb.append_standard_joins(None);
+                        qb.push(" WHERE 1=1");
+                        qb.append_all_filters(&query.filters);
+
+                        let first: Option<i64> =
+                            qb.build_query_scalar().fetch_one(db_context.pool()).await?;
+                        let Some(first) = first.and_then(|ts| DateTime::from_timestamp(ts, 0))
+                        else {
+                            return Ok(InsightResult::Streak(StreakInsight::default()));
+                        };
+                        (first, now)
+                    }
+                };
+
+                let spans = bucket_spans_in(
+                    query.timezone,
+                    query.week_start,
+                    start,
+                    end,
+                    Some(TimeBucket::Day),
+                );
+                if spans.is_empty() {
+                    return Ok(InsightResult::Streak(StreakInsight::default()));
+                }
+
+                let mut qb = QueryBuilder::<Sqlite>::new("");
+                push_buckets_cte(&mut qb, &spans);
+
+                qb.push("SELECT buckets.bucket_label AS date, COALESCE(SUM(");
+                qb.push_active_duration("buckets.start_ts", "buckets.end_ts", query.exclude_afk);
+                qb.push(
+                    "), 0) AS total \
+                     FROM buckets \
+                     JOIN events ON events.end_timestamp > buckets.start_ts AND events.timestamp < buckets.end_ts ",
+                );
+                qb.append_standard_joins(None);
+                qb.push(" WHERE 1=1");
+                qb.append_all_filters(&query.filters);
+                qb.push(" GROUP BY date");
+
+                let rows: Vec<DayTotalRow> =
+                    qb.build_query_as().fetch_all(db_context.pool()).await?;
+                let totals: HashMap<String, i64> =
+                    rows.into_iter().map(|r| (r.date, r.total)).collect();
+
+                let days: Vec<(NaiveDate, bool)> = spans
+                    .iter()
+                    .map(|span| (span.local_start.date(), span))
+                    .filter(|(date, _)| !query.excluded_days.contains(&date.weekday()))
+                    .map(|(date, span)| {
+                        let total = totals.get(&span.label).copied().unwrap_or(0);
+                        (date, total >= min_seconds.max(1))
+                    })
+                    .collect();
+
+                Ok(InsightResult::Streak(find_streaks(&days, today)))
+            }
+
+            InsightType::HourlyHeatmap => {
+                let Some(InsightRange { start, end }) = query.insight_range else {
+                    return Err(DBError::MissingField("insight_range"));
+                };
+
+                let first_day = query.week_start.weekday();
+                let weekdays = std::iter::successors(Some(first_day), |d| Some(d.succ()))
+                    .take(7)
+                    .map(|d| d.to_string())
+                    .collect();
+
+                // Each hour is labelled with its slot in the week, counting how often
+                // each slot occurs so that totals can be averaged
+                let mut spans = bucket_spans_in(
+                    query.timezone,
+                    query.week_start,
+                    start,
+                    end,
+                    Some(TimeBucket::Hour),
+                );
+                if spans.is_empty() {
+                    return Ok(InsightResult::HourlyHeatmap(HourlyHeatmap {
+                        weekdays,
+                        series: Vec::new(),
+                    }));
+                }
+                let mut occurrences = [0u32; HEATMAP_SLOTS];
+                for span in &mut spans {
+                    let slot = heatmap_slot(span.local_start, first_day);
+                    occurrences[slot] += 1;
+                    span.label = slot.to_string();
+                }
+
+                let (group_key, inner_tbl) = group_key_info(query.group_by);
+
+                let mut qb = QueryBuilder::<Sqlite>::new("");
+                push_buckets_cte(&mut qb, &spans);
+
+                qb.push("SELECT CAST(buckets.bucket_label AS INTEGER) AS slot, ");
+                qb.push(group_key).push(" AS label, COALESCE(SUM(");
+                qb.push_active_duration("buckets.start_ts", "buckets.end_ts", query.exclude_afk);
+                qb.push(
+                    "), 0) AS total \
+                     FROM buckets \
+                     JOIN events ON events.end_timestamp > buckets.start_ts AND events.timestamp < buckets.end_ts ",
+                );
+                qb.append_standard_joins(inner_tbl);
+                qb.push(" WHERE 1=1");
+                qb.append_all_filters(&query.filters);
+                qb.push(" GROUP BY slot, label");
+
+                let rows: Vec<HeatmapRow> =
+                    qb.build_query_as().fetch_all(db_context.pool()).await?;
+
+                let mut totals: HashMap<String, [i64; HEATMAP_SLOTS]> = HashMap::new();
+                for row in rows {
+                    let Ok(slot) = usize::try_from(row.slot) else {
+                        continue;
+                    };
+                    if slot < HEATMAP_SLOTS {
+                        totals.entry(row.label).or_insert([0; HEATMAP_SLOTS])[slot] += row.total;
+                    }
+                }
+
+                let mut totals: Vec<(String, [i64; HEATMAP_SLOTS])> = totals.into_iter().collect();
+                totals.sort_by(|a, b| {
+                    let sum = |cells: &[i64]| cells.iter().sum::<i64>();
+                    sum(&b.1).cmp(&sum(&a.1)).then_with(|| a.0.cmp(&b.0))
+                });
+                if let Some(limit) = query.limit {
+                    totals.truncate(limit);
+                }
+
+                let series = totals
+                    .into_iter()
+                    .map(|(group, cells)| {
+                        let values = cells
+                            .chunks(24)
+                            .zip(occurrences.chunks(24))
+                            .map(|(day, counts)| {
+                                day.iter()
+                                    .zip(counts)
+                                    .map(|(&total, &count)| match query.metric {
+                                        HeatmapMetric::Total => total as f64,
+                                        HeatmapMetric::Average if count > 0 => {
+                                            total as f64 / count as f64
+                                        }
+                                        HeatmapMetric::Average => 0.0,
+                                    })
+                                    .collect()
+                            })
+                            .collect();
+                        HeatmapSeries { group, values }
+                    })
+                    .collect();
+
+                Ok(InsightResult::HourlyHeatmap(HourlyHeatmap {
+                    weekdays,
+                    series,
+                }))
+            }
+        }
+    }
+}
+
+/// Number of cells in an hourly heatmap, one per hour of the week
+const HEATMAP_SLOTS: usize = 7 * 24;
+
+/// Returns the heatmap cell of a local hour, for weeks starting on `first_day`
+fn heatmap_slot(local: NaiveDateTime, first_day: Weekday) -> usize {
+    let day = (local.weekday().num_days_from_monday() + 7 - first_day.num_days_from_monday()) % 7;
+    (day * 24 + local.hour()) as usize
+}
+
+/// Finds the current and the longest run of active days in `days`, which are in order
+/// and skip excluded weekdays.
+///
+/// The current run ends on the last day, unless that day is `today` and not active yet,
+/// in which case it ends on the day before.
+fn find_streaks(days: &[(NaiveDate, bool)], today: NaiveDate) -> StreakInsight {
+    let run = |start: NaiveDate, end: NaiveDate, days: u32| StreakRun {
+        days,
+        start: Some(start.format("%Y-%m-%d").to_string()),
+        end: Some(end.format("%Y-%m-%d").to_string()),
+    };
+
+    let mut longest = StreakRun::default();
+    let mut current: Option<(NaiveDate, NaiveDate, u32)> = None;
+
+    for (i, &(date, active)) in days.iter().enumerate() {
+        if active {
+            let (start, len) = match current {
+                Some((start, _, len)) => (start, len + 1),
+                None => (date, 1),
+            };
+            current = Some((start, date, len));
+            if len > longest.days {
+                longest = run(start, date, len);
+            }
+        } else if !(date == today && i == days.len() - 1) {
+            current = None;
         }
     }
+
+    StreakInsight {
+        current: current
+            .map(|(start, end, len)| run(start, end, len))
+            .unwrap_or_default(),
+        longest,
+    }
+}
+
+#[cfg(all(test, feature = "server"))]
+mod tests {
+    use super::*;
+    use crate::server::test_utils::TestEvent;
+
+    fn query(insight_type: InsightType, filters: SummaryFilters) -> InsightQuery {
+        InsightQuery {
+            insight_type,
+            insight_range: Some(InsightRange {
+                start: DateTime::from_timestamp(0, 0).unwrap(),
+                end: DateTime::from_timestamp(86_400, 0).unwrap(),
+            }),
+            group_by: Some(Group::Language),
+            limit: Some(10),
+            bucket: Some(InsightBucket::Week),
+            session_gap: None,
+            min_daily_minutes: None,
+            excluded_days: Vec::new(),
+            metric: HeatmapMetric::Total,
+            filters,
+            exclude_afk: false,
+            timezone: Some(Tz::UTC),
+            week_start: WeekStart::Monday,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_insights_apply_filters() {
+        let db = DBContext::new("sqlite::memory:").await.unwrap();
+        TestEvent::new(3_600, 4_200)
+            .project("skopio")
+            .insert(&db)
+            .await;
+        TestEvent::new(7_200, 9_000)
+            .project("scratch-1")
+            .language("TypeScript")
+            .insert(&db)
+            .await;
+
+        let filters = SummaryFilters {
+            projects: Some(vec!["!scratch-*".into()]),
+            ..Default::default()
+        };
+
+        let top = Insights::execute(&db, query(InsightType::TopN, filters.clone()))
+            .await
+            .unwrap();
+        let InsightResult::TopN(top) = top else {
+            panic!("unexpected result: {top:?}");
+        };
+        assert_eq!(top, [("Rust".to_string(), 600)]);
+
+        let day = Insights::execute(&db, query(InsightType::MostActiveDay, filters))
+            .await
+            .unwrap();
+        let InsightResult::MostActiveDay { total_duration, .. } = day else {
+            panic!("unexpected result: {day:?}");
+        };
+        assert_eq!(total_duration, 600);
+    }
+
+    fn days(start: NaiveDate, active: &[bool]) -> Vec<(NaiveDate, bool)> {
+        active
+            .iter()
+            .enumerate()
+            .map(|(i, &a)| (start + chrono::Duration::days(i as i64), a))
+            .collect()
+    }
+
+    #[test]
+    fn test_streaks_current_and_longest() {
+        let start = NaiveDate::from_ymd_opt(2025, 6, 1).unwrap();
+        let days = days(start, &[true, true, true, false, true, true]);
+        let today = NaiveDate::from_ymd_opt(2025, 6, 6).unwrap();
+
+        let streaks = find_streaks(&days, today);
+        assert_eq!(streaks.longest.days, 3);
+        assert_eq!(streaks.longest.start.as_deref(), Some("2025-06-01"));
+        assert_eq!(streaks.current.days, 2);
+        assert_eq!(streaks.current.end.as_deref(), Some("2025-06-06"));
+    }
+
+    #[test]
+    fn test_heatmap_slot_follows_week_start() {
+        // 2025-06-08 is a Sunday
+        let local = NaiveDate::from_ymd_opt(2025, 6, 8)
+            .unwrap()
+            .and_hms_opt(14, 30, 0)
+            .unwrap();
+        assert_eq!(heatmap_slot(local, Weekday::Mon), 6 * 24 + 14);
+        assert_eq!(heatmap_slot(local, Weekday::Sun), 14);
+    }
+
+    #[test]
+    fn test_inactive_today_keeps_current_streak() {
+        let start = NaiveDate::from_ymd_opt(2025, 6, 1).unwrap();
+        let days = days(start, &[true, true, false]);
+
+        let today = NaiveDate::from_ymd_opt(2025, 6, 3).unwrap();
+        assert_eq!(find_streaks(&days, today).current.days, 2);
+
+        let later = NaiveDate::from_ymd_opt(2025, 6, 4).unwrap();
+        assert_eq!(find_streaks(&days, later).current.days, 0);
+    }
 }
@@ -1,5 +1,4 @@
 use crate::{DBContext, error::DBError};
-use chrono::Utc;
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
@@ -10,45 +9,6 @@ pub struct Language {
 }
 
 impl Language {
-    pub async fn find_or_insert(
-        db_context: &DBContext,
-        name: &Option<String>,
-    ) -> Result<Option<Uuid>, DBError> {
-        if let Some(language) = name {
-            let record = sqlx::query!("SELECT id FROM languages WHERE name = ?", language)
-                .fetch_optional(db_context.pool())
-                .await?;
-
-            let timestamp = Utc::now().timestamp();
-
-            if let Some(row) = record {
-                sqlx::query!(
-                    "UPDATE languages SET last_updated = ? WHERE id = ?",
-                    timestamp,
-                    row.id
-                )
-                .execute(db_context.pool())
-                .await?;
-                let id = Uuid::from_slice(&row.id)?;
-                return Ok(Some(id));
-            }
-
-            let id = uuid::Uuid::now_v7();
-            let result = sqlx::query!(
-                "INSERT INTO languages (id, name, last_updated) VALUES (?, ?, ?) RETURNING id",
-                id,
-                name,
-                timestamp
-            )
-            .fetch_one(db_context.pool())
-            .await?;
-            let result_id = Uuid::from_slice(&result.id)?;
-            Ok(Some(result_id))
-        } else {
-            Ok(None)
-        }
-    }
-
     pub async fn all(db_context: &DBContext) -> Result<Vec<Self>, DBError> {
         let rows = sqlx::query_as!(
             Self,
@@ -69,6 +29,7 @@ impl Language {
         sqlx::query!("DELETE FROM languages WHERE id = ?", self.id)
             .execute(db_context.pool())
             .await?;
+        db_context.dimension_cache().clear();
         Ok(())
     }
 }
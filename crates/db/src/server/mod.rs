@@ -1,12 +1,23 @@
 pub mod afk_events;
 pub mod apps;
+pub mod archive;
+pub mod billing;
 pub mod branches;
 pub mod categories;
+pub mod corrections;
+pub mod dimensions;
 pub mod entities;
 pub mod events;
 pub mod insights;
 pub mod languages;
 pub mod projects;
+pub mod rules;
+pub mod search;
+pub mod sessions;
 pub mod source;
 pub mod summary;
+pub mod tags;
+#[cfg(test)]
+pub(crate) mod test_utils;
+pub mod usage;
 pub mod utils;
@@ -1,9 +1,18 @@
-use crate::{DBContext, error::DBError, server::projects::cursor::ProjectCursor};
-use chrono::Utc;
+use std::collections::BTreeSet;
+
+use crate::{
+    DBContext,
+    error::DBError,
+    server::{corrections::insert_rekeyed_ids, projects::cursor::ProjectCursor},
+};
 use common::models::Project;
+use sqlx::{QueryBuilder, Sqlite, SqliteConnection};
 use uuid::Uuid;
 pub mod cursor;
 
+/// Tables holding rows scoped to a project, with the `events` column referring to them
+const PROJECT_SCOPED: [(&str, &str); 2] = [("branches", "branch_id"), ("entities", "entity_id")];
+
 #[derive(Debug, sqlx::FromRow)]
 pub struct ServerProject {
     pub id: Uuid,
@@ -24,44 +33,6 @@ impl From<ServerProject> for Project {
 }
 
 impl ServerProject {
-    pub async fn find_or_insert(
-        db_context: &DBContext,
-        name: &str,
-        root_path: &str,
-    ) -> Result<Uuid, DBError> {
-        let record = sqlx::query!("SELECT id FROM projects WHERE name = ?", name)
-            .fetch_optional(db_context.pool())
-            .await?;
-
-        let timestamp = Utc::now().timestamp();
-
-        if let Some(row) = record {
-            sqlx::query!(
-                "UPDATE projects SET last_updated = ? WHERE id = ?",
-                timestamp,
-                row.id
-            )
-            .execute(db_context.pool())
-            .await?;
-            let id = Uuid::from_slice(&row.id)?;
-            return Ok(id);
-        }
-
-        let id = uuid::Uuid::now_v7();
-        let result = sqlx::query!(
-            "INSERT INTO projects (id, name, root_path, last_updated) VALUES (?, ?, ?, ?) RETURNING id",
-            id,
-            name,
-            root_path,
-            timestamp
-        )
-        .fetch_one(db_context.pool())
-        .await?;
-
-        let result_id = Uuid::from_slice(&result.id)?;
-        Ok(result_id)
-    }
-
     /// Fetches a project by id
     pub async fn find_by_id(db_context: &DBContext, id: Uuid) -> Result<Option<Project>, DBError> {
         let result = sqlx::query_as!(
@@ -70,7 +41,7 @@ impl ServerProject {
             SELECT
                 id  AS "id: Uuid",
                 name,
-                root_path,
+                root_path AS "root_path?",
                 last_updated
             FROM projects
             WHERE id = ?
@@ -83,6 +54,15 @@ impl ServerProject {
         Ok(result.map(Into::into))
     }
 
+    /// Root path of the most recently used project named `name`, if any
+    pub async fn latest_root_path(
+        db_context: &DBContext,
+        name: &str,
+    ) -> Result<Option<String>, DBError> {
+        let mut conn = db_context.pool().acquire().await?;
+        latest_root_path_with(&mut conn, name).await
+    }
+
     pub async fn fetch_paginated(
         db_context: &DBContext,
         after: Option<ProjectCursor>,
@@ -97,7 +77,7 @@ impl ServerProject {
                 SELECT
                     id  AS "id: Uuid",
                     name,
-                    root_path,
+                    root_path AS "root_path?",
                     last_updated
                 FROM projects
                 WHERE
@@ -120,7 +100,7 @@ impl ServerProject {
                 SELECT
                     id  AS "id: Uuid",
                     name,
-                    root_path,
+                    root_path AS "root_path?",
                     last_updated
                 FROM projects
                 ORDER BY last_updated DESC
@@ -201,7 +181,7 @@ impl ServerProject {
             SELECT
                 p.id    AS "id: Uuid",
                 p.name,
-                p.root_path,
+                p.root_path AS "root_path?",
                 p.last_updated
             FROM projects_fts fts
             JOIN projects_fts_map m ON m.docid = fts.rowid
@@ -232,4 +212,418 @@ impl ServerProject {
 
         Ok(projects)
     }
+
+    /// Changes the display name of a project, returning `None` if it does not exist.
+    /// The search index follows through the `projects_au` trigger.
+    pub async fn rename(
+        db_context: &DBContext,
+        id: Uuid,
+        name: &str,
+    ) -> Result<Option<Project>, DBError> {
+        let result = sqlx::query("UPDATE projects SET name = ? WHERE id = ?")
+            .bind(name)
+            .bind(id)
+            .execute(db_context.pool())
+            .await?;
+        if result.rows_affected() == 0 {
+            return Ok(None);
+        }
+
+        db_context.dimension_cache().clear();
+        Self::find_by_id(db_context, id).await
+    }
+
+    /// Merges the `sources` projects into `target`, returning `None` if any of them
+    /// does not exist.
+    ///
+    /// Events, branches and entities are moved to `target`, and the source projects
+    /// are deleted. Branches and entities whose name already exists in `target` are
+    /// folded into the existing row, and events keep the tags of their old project
+    /// as their own. The root paths of the source projects are kept as aliases of
+    /// `target`, so events reported under them later are credited to it. Moved events
+    /// keep their ids, and the ids they would now be ingested under are recorded as
+    /// deleted, so re-sending them does not store them again.
+    pub async fn merge(
+        db_context: &DBContext,
+        target: Uuid,
+        sources: &[Uuid],
+    ) -> Result<Option<Project>, DBError> {
+        let sources: BTreeSet<Uuid> = sources.iter().copied().filter(|&id| id != target).collect();
+        if sources.is_empty() {
+            return Err(DBError::Unsupported(
+                "At least one other project is needed to merge",
+            ));
+        }
+
+        let mut tx = db_context.pool().begin().await?;
+
+        let mut qb = QueryBuilder::<Sqlite>::new("SELECT COUNT(*) FROM projects WHERE id IN (");
+        let mut sep = qb.separated(", ");
+        sep.push_bind(target);
+        for source in &sources {
+            sep.push_bind(*source);
+        }
+        qb.push(")");
+
+        let found: i64 = qb.build_query_scalar().fetch_one(&mut *tx).await?;
+        if found as usize != sources.len() + 1 {
+            return Ok(None);
+        }
+
+        for source in &sources {
+            merge_into(&mut tx, *source, target).await?;
+        }
+
+        tx.commit().await?;
+        db_context.dimension_cache().clear();
+        Self::find_by_id(db_context, target).await
+    }
+}
+
+/// Root path of the most recently used project named `name`
+pub(crate) async fn latest_root_path_with(
+    conn: &mut SqliteConnection,
+    name: &str,
+) -> Result<Option<String>, DBError> {
+    let root_path = sqlx::query_scalar::<_, String>(
+        "SELECT root_path FROM projects WHERE name = ?
+        ORDER BY last_updated DESC LIMIT 1",
+    )
+    .bind(name)
+    .fetch_optional(&mut *conn)
+    .await?;
+
+    Ok(root_path)
+}
+
+/// Moves everything of the `source` project to `target` and deletes `source`
+async fn merge_into(
+    conn: &mut SqliteConnection,
+    source: Uuid,
+    target: Uuid,
+) -> Result<(), DBError> {
+    let moved: Vec<Uuid> = sqlx::query_scalar("SELECT id FROM events WHERE project_id = ?")
+        .bind(source)
+        .fetch_all(&mut *conn)
+        .await?;
+
+    for (table, column) in PROJECT_SCOPED {
+        // Rows with a name `target` already has are replaced by the existing row
+        sqlx::query(&format!(
+            "UPDATE events SET {column} = (
+                SELECT t.id FROM {table} s
+                JOIN {table} t ON t.name = s.name AND t.project_id = ?
+                WHERE s.id = events.{column}
+            )
+            WHERE {column} IN (
+                SELECT s.id FROM {table} s
+                JOIN {table} t ON t.name = s.name AND t.project_id = ?
+                WHERE s.project_id = ?
+            )"
+        ))
+        .bind(target)
+        .bind(target)
+        .bind(source)
+        .execute(&mut *conn)
+        .await?;
+
+        sqlx::query(&format!(
+            "DELETE FROM {table}
+            WHERE project_id = ?
+              AND name IN (SELECT name FROM {table} WHERE project_id = ?)"
+        ))
+        .bind(source)
+        .bind(target)
+        .execute(&mut *conn)
+        .await?;
+
+        sqlx::query(&format!(
+            "UPDATE {table} SET project_id = ? WHERE project_id = ?"
+        ))
+        .bind(target)
+        .bind(source)
+        .execute(&mut *conn)
+        .await?;
+    }
+
+    // Events keep the tags of their old project without spreading them to `target`
+    sqlx::query(
+        "INSERT INTO event_tags (event_id, tag_id)
+        SELECT events.id, project_tags.tag_id FROM events
+        JOIN project_tags ON project_tags.project_id = events.project_id
+        WHERE events.project_id = ?
+        ON CONFLICT DO NOTHING",
+    )
+    .bind(source)
+    .execute(&mut *conn)
+    .await?;
+
+    sqlx::query("UPDATE events SET project_id = ? WHERE project_id = ?")
+        .bind(target)
+        .bind(source)
+        .execute(&mut *conn)
+        .await?;
+    insert_rekeyed_ids(conn, &moved).await?;
+
+    // Aliases of `source`, and its own root path, now resolve to `target`
+    sqlx::query("UPDATE project_aliases SET project_id = ? WHERE project_id = ?")
+        .bind(target)
+        .bind(source)
+        .execute(&mut *conn)
+        .await?;
+
+    sqlx::query(
+        "INSERT INTO project_aliases (root_path, project_id)
+        SELECT root_path, ? FROM projects WHERE id = ?
+        ON CONFLICT(root_path) DO UPDATE SET project_id = excluded.project_id",
+    )
+    .bind(target)
+    .bind(source)
+    .execute(&mut *conn)
+    .await?;
+
+    sqlx::query("DELETE FROM projects WHERE id = ?")
+        .bind(source)
+        .execute(&mut *conn)
+        .await?;
+
+    Ok(())
+}
+
+#[cfg(all(test, feature = "server"))]
+mod tests {
+    use super::*;
+    use crate::server::test_utils::TestEvent;
+
+    /// Stores a one minute event starting at `start` in the project at `path`,
+    /// returning the project id
+    async fn project_at(db: &DBContext, path: &str, start: i64) -> Uuid {
+        TestEvent::new(start, start + 60)
+            .project_path(path)
+            .insert(db)
+            .await
+            .project_id
+            .unwrap()
+    }
+
+    async fn count(db: &DBContext, sql: &str) -> i64 {
+        sqlx::query_scalar(sql).fetch_one(db.pool()).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_root_path_migration_keeps_events() {
+        use sqlx::migrate::Migrator;
+        use sqlx::sqlite::{SqliteConnectOptions, SqlitePoolOptions};
+        use std::str::FromStr;
+
+        let dir = tempfile::tempdir().unwrap();
+        let url = format!("sqlite://{}", dir.path().join("skopio.db").display());
+        let options = SqliteConnectOptions::from_str(&url)
+            .unwrap()
+            .create_if_missing(true)
+            .foreign_keys(true);
+        let pool = SqlitePoolOptions::new()
+            .connect_with(options)
+            .await
+            .unwrap();
+
+        let all = sqlx::migrate!("./migrations/server");
+        let before = Migrator {
+            migrations: all
+                .iter()
+                .filter(|m| m.version < 20261017120000)
+                .cloned()
+                .collect(),
+            ..Migrator::DEFAULT
+        };
+        crate::connection::run_migrations(&pool, &before)
+            .await
+            .unwrap();
+
+        // Two names reported for the same checkout, each with a branch, entity and event
+        let (app, category, source) = (Uuid::now_v7(), Uuid::now_v7(), Uuid::now_v7());
+        sqlx::query("INSERT INTO apps (id, name) VALUES (?, 'Code')")
+            .bind(app)
+            .execute(&pool)
+            .await
+            .unwrap();
+        sqlx::query("INSERT INTO categories (id, name) VALUES (?, 'Coding')")
+            .bind(category)
+            .execute(&pool)
+            .await
+            .unwrap();
+        sqlx::query("INSERT INTO sources (id, name) VALUES (?, 'skopio-vscode')")
+            .bind(source)
+            .execute(&pool)
+            .await
+            .unwrap();
+        for (index, (name, path)) in [("api", "/work/api"), ("api-v2", "/work/api/")]
+            .into_iter()
+            .enumerate()
+        {
+            let (project, branch, entity) = (Uuid::now_v7(), Uuid::now_v7(), Uuid::now_v7());
+            sqlx::query(
+                "INSERT INTO projects (id, name, root_path, last_updated) VALUES (?, ?, ?, ?)",
+            )
+            .bind(project)
+            .bind(name)
+            .bind(path)
+            .bind(index as i64)
+            .execute(&pool)
+            .await
+            .unwrap();
+            sqlx::query("INSERT INTO branches (id, project_id, name) VALUES (?, ?, 'main')")
+                .bind(branch)
+                .bind(project)
+                .execute(&pool)
+                .await
+                .unwrap();
+            sqlx::query(
+                "INSERT INTO entities (id, project_id, name, type) VALUES (?, ?, 'main.rs', 'File')",
+            )
+            .bind(entity)
+            .bind(project)
+            .execute(&pool)
+            .await
+            .unwrap();
+            sqlx::query(
+                "INSERT INTO events (
+                    id, timestamp, duration, category_id, app_id,
+                    entity_id, project_id, branch_id, source_id, end_timestamp
+                ) VALUES (?, ?, 60, ?, ?, ?, ?, ?, ?, ?)",
+            )
+            .bind(Uuid::now_v7())
+            .bind(index as i64 * 60)
+            .bind(category)
+            .bind(app)
+            .bind(entity)
+            .bind(project)
+            .bind(branch)
+            .bind(source)
+            .bind(index as i64 * 60 + 60)
+            .execute(&pool)
+            .await
+            .unwrap();
+        }
+        pool.close().await;
+
+        let db = DBContext::new(&url).await.unwrap();
+        assert_eq!(count(&db, "SELECT COUNT(*) FROM events").await, 2);
+        assert_eq!(count(&db, "SELECT COUNT(*) FROM projects").await, 1);
+        assert_eq!(count(&db, "SELECT COUNT(*) FROM branches").await, 1);
+        assert_eq!(count(&db, "SELECT COUNT(*) FROM entities").await, 1);
+        assert_eq!(
+            count(
+                &db,
+                "SELECT COUNT(*) FROM events
+                 JOIN projects ON projects.id = events.project_id
+                 JOIN branches ON branches.id = events.branch_id
+                 JOIN entities ON entities.id = events.entity_id
+                 WHERE projects.name = 'api-v2' AND projects.root_path = '/work/api'"
+            )
+            .await,
+            2
+        );
+        assert_eq!(
+            count(&db, "SELECT COUNT(*) FROM pragma_foreign_key_check").await,
+            0
+        );
+    }
+
+    #[tokio::test]
+    async fn test_merge_folds_branches_and_entities() {
+        let db = DBContext::new("sqlite::memory:").await.unwrap();
+        let target = project_at(&db, "/work/api", 0).await;
+        let source = project_at(&db, "/oss/api", 60).await;
+
+        let merged = ServerProject::merge(&db, target, &[source])
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(merged.id, target);
+        assert_eq!(count(&db, "SELECT COUNT(*) FROM projects").await, 1);
+        assert_eq!(count(&db, "SELECT COUNT(*) FROM branches").await, 1);
+        assert_eq!(count(&db, "SELECT COUNT(*) FROM entities").await, 1);
+        assert_eq!(count(&db, "SELECT COUNT(*) FROM projects_fts").await, 1);
+
+        let missing = ServerProject::merge(&db, target, &[Uuid::now_v7()])
+            .await
+            .unwrap();
+        assert!(missing.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_merged_root_path_resolves_to_target() {
+        let db = DBContext::new("sqlite::memory:").await.unwrap();
+        let target = project_at(&db, "/work/api", 0).await;
+        let source = project_at(&db, "/oss/api", 60).await;
+        let older = project_at(&db, "/old/api", 120).await;
+
+        ServerProject::merge(&db, source, &[older])
+            .await
+            .unwrap()
+            .unwrap();
+        ServerProject::merge(&db, target, &[source])
+            .await
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(project_at(&db, "/oss/api", 180).await, target);
+        assert_eq!(project_at(&db, "/old/api", 240).await, target);
+        assert_eq!(count(&db, "SELECT COUNT(*) FROM projects").await, 1);
+        assert_eq!(count(&db, "SELECT COUNT(*) FROM entities").await, 1);
+    }
+
+    #[tokio::test]
+    async fn test_merged_events_are_not_ingested_again() {
+        let db = DBContext::new("sqlite::memory:").await.unwrap();
+        let target = project_at(&db, "/work/api", 0).await;
+        let moved = TestEvent::new(60, 120)
+            .project_path("/oss/api")
+            .entity("lib.rs")
+            .insert(&db)
+            .await;
+        let source = moved.project_id.unwrap();
+
+        ServerProject::merge(&db, target, &[source])
+            .await
+            .unwrap()
+            .unwrap();
+
+        // The client re-sends the event under the merged root path
+        TestEvent::new(60, 120)
+            .project_path("/oss/api")
+            .entity("lib.rs")
+            .insert(&db)
+            .await;
+        assert_eq!(count(&db, "SELECT COUNT(*) FROM events").await, 2);
+        assert_eq!(
+            count(&db, "SELECT COUNT(*) FROM events WHERE timestamp = 60").await,
+            1
+        );
+    }
+
+    #[tokio::test]
+    async fn test_rename_updates_search_index() {
+        let db = DBContext::new("sqlite::memory:").await.unwrap();
+        let id = project_at(&db, "/work/api", 0).await;
+
+        let renamed = ServerProject::rename(&db, id, "billing")
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(renamed.name, "billing");
+        assert_eq!(renamed.root_path.as_deref(), Some("/work/api"));
+
+        let found = ServerProject::search_project(&db, "bill", 10)
+            .await
+            .unwrap();
+        assert_eq!(found.len(), 1);
+        assert!(
+            ServerProject::search_project(&db, "api", 10)
+                .await
+                .unwrap()
+                .is_empty()
+        );
+    }
 }
@@ -0,0 +1,958 @@
+use std::collections::HashMap;
+
+use chrono::{DateTime, Utc};
+use common::models::{
+    Rule, RuleActions, RuleConditions,
+    inputs::{EventInput, EventPatchInput, RuleInput},
+    outputs::{RuleApplyReport, RuleChange, ValueChange},
+};
+use regex::{Regex, RegexBuilder};
+use sqlx::{QueryBuilder, Sqlite, SqliteConnection};
+use uuid::Uuid;
+
+use crate::{
+    DBContext,
+    error::DBError,
+    server::{
+        events::Event,
+        projects::ServerProject,
+        tags::{add_event_tags, event_tag_names, rule_tag_names, set_rule_tags, tag_names},
+        utils::query::QueryBuilderExt,
+    },
+};
+
+/// Events read per query when rules are applied to a range
+const APPLY_PAGE_SIZE: i64 = 500;
+
+/// Changes listed in a [`RuleApplyReport`]; the counts cover all of them
+const MAX_REPORTED_CHANGES: usize = 500;
+
+const RULE_COLUMNS: &str = "SELECT id, name, priority, enabled, match_app, match_entity, \
+    match_domain, match_path, match_project, match_source, set_category, set_project FROM rules";
+
+#[derive(Debug, sqlx::FromRow)]
+struct RuleRow {
+    id: Uuid,
+    name: String,
+    priority: i64,
+    enabled: bool,
+    match_app: Option<String>,
+    match_entity: Option<String>,
+    match_domain: Option<String>,
+    match_path: Option<String>,
+    match_project: Option<String>,
+    match_source: Option<String>,
+    set_category: Option<String>,
+    set_project: Option<String>,
+}
+
+impl From<RuleRow> for Rule {
+    fn from(row: RuleRow) -> Self {
+        Rule {
+            id: row.id,
+            name: row.name,
+            priority: row.priority,
+            enabled: row.enabled,
+            conditions: RuleConditions {
+                app: row.match_app,
+                entity: row.match_entity,
+                domain: row.match_domain,
+                path: row.match_path,
+                project: row.match_project,
+                source: row.match_source,
+            },
+            actions: RuleActions {
+                category: row.set_category,
+                project: row.set_project,
+                tags: None,
+            },
+        }
+    }
+}
+
+#[derive(Debug, sqlx::FromRow)]
+struct SubjectRow {
+    id: Uuid,
+    timestamp: i64,
+    app: Option<String>,
+    category: Option<String>,
+    entity: Option<String>,
+    entity_type: Option<String>,
+    project: Option<String>,
+    root_path: Option<String>,
+    source: Option<String>,
+}
+
+impl SubjectRow {
+    fn subject(&self) -> RuleSubject<'_> {
+        RuleSubject {
+            app: self.app.as_deref().unwrap_or_default(),
+            entity: self.entity.as_deref().unwrap_or_default(),
+            entity_type: self.entity_type.as_deref().unwrap_or_default(),
+            project: self.project.as_deref().unwrap_or_default(),
+            project_path: self.root_path.as_deref().unwrap_or_default(),
+            source: self.source.as_deref().unwrap_or_default(),
+        }
+    }
+}
+
+/// Stored recategorization rules
+pub struct Rules;
+
+impl Rules {
+    /// Lists every rule in evaluation order
+    pub async fn all(db: &DBContext) -> Result<Vec<Rule>, DBError> {
+        let rows: Vec<RuleRow> =
+            sqlx::query_as(&format!("{RULE_COLUMNS} ORDER BY priority DESC, id"))
+                .fetch_all(db.pool())
+                .await?;
+        let mut tags = rule_tag_names(db).await?;
+
+        Ok(rows
+            .into_iter()
+            .map(|row| {
+                let mut rule = Rule::from(row);
+                rule.actions.tags = tags.remove(&rule.id);
+                rule
+            })
+            .collect())
+    }
+
+    pub async fn create(db: &DBContext, input: RuleInput) -> Result<Rule, DBError> {
+        let mut rule = validate(Uuid::now_v7(), input)?;
+        let mut tx = db.pool().begin().await?;
+
+        insert(&mut tx, &rule).await?;
+        rule.actions.tags = store_tags(&mut tx, &rule).await?;
+
+        tx.commit().await?;
+        Ok(rule)
+    }
+
+    /// Stores a rule under the id it had in an archive, returning `false` if a rule with
+    /// that id already exists
+    pub(crate) async fn restore(
+        conn: &mut SqliteConnection,
+        id: Uuid,
+        input: RuleInput,
+    ) -> Result<bool, DBError> {
+        let rule = validate(id, input)?;
+        if !insert(conn, &rule).await? {
+            return Ok(false);
+        }
+        store_tags(conn, &rule).await?;
+        Ok(true)
+    }
+
+    /// Replaces a rule, returning `None` if it does not exist
+    pub async fn update(
+        db: &DBContext,
+        id: Uuid,
+        input: RuleInput,
+    ) -> Result<Option<Rule>, DBError> {
+        let mut rule = validate(id, input)?;
+        let mut tx = db.pool().begin().await?;
+
+        let result = sqlx::query(
+            "UPDATE rules
+            SET name = ?, priority = ?, enabled = ?, match_app = ?, match_entity = ?,
+                match_domain = ?, match_path = ?, match_project = ?, match_source = ?,
+                set_category = ?, set_project = ?, last_updated = ?
+            WHERE id = ?",
+        )
+        .bind(&rule.name)
+        .bind(rule.priority)
+        .bind(rule.enabled)
+        .bind(&rule.conditions.app)
+        .bind(&rule.conditions.entity)
+        .bind(&rule.conditions.domain)
+        .bind(&rule.conditions.path)
+        .bind(&rule.conditions.project)
+        .bind(&rule.conditions.source)
+        .bind(&rule.actions.category)
+        .bind(&rule.actions.project)
+        .bind(Utc::now().timestamp())
+        .bind(id)
+        .execute(&mut *tx)
+        .await?;
+        if result.rows_affected() == 0 {
+            return Ok(None);
+        }
+        rule.actions.tags = store_tags(&mut tx, &rule).await?;
+
+        tx.commit().await?;
+        Ok(Some(rule))
+    }
+
+    /// Deletes a rule, returning `false` if it does not exist
+    pub async fn delete(db: &DBContext, id: Uuid) -> Result<bool, DBError> {
+        let result = sqlx::query("DELETE FROM rules WHERE id = ?")
+            .bind(id)
+            .execute(db.pool())
+            .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+}
+
+/// Inserts a rule unless its id is taken, returning whether it was inserted
+async fn insert(conn: &mut SqliteConnection, rule: &Rule) -> Result<bool, DBError> {
+    let result = sqlx::query(
+        "INSERT INTO rules (id, name, priority, enabled, match_app, match_entity,
+            match_domain, match_path, match_project, match_source, set_category,
+            set_project, last_updated)
+        VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+        ON CONFLICT(id) DO NOTHING",
+    )
+    .bind(rule.id)
+    .bind(&rule.name)
+    .bind(rule.priority)
+    .bind(rule.enabled)
+    .bind(&rule.conditions.app)
+    .bind(&rule.conditions.entity)
+    .bind(&rule.conditions.domain)
+    .bind(&rule.conditions.path)
+    .bind(&rule.conditions.project)
+    .bind(&rule.conditions.source)
+    .bind(&rule.actions.category)
+    .bind(&rule.actions.project)
+    .bind(Utc::now().timestamp())
+    .execute(&mut *conn)
+    .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+/// Stores the tags a rule adds, returning them as stored
+async fn store_tags(
+    conn: &mut SqliteConnection,
+    rule: &Rule,
+) -> Result<Option<Vec<String>>, DBError> {
+    let names = rule.actions.tags.as_deref().unwrap_or_default();
+    let stored = set_rule_tags(conn, rule.id, names).await?;
+    Ok((!stored.is_empty()).then_some(stored))
+}
+
+/// Builds the rule stored under `id` from `input`, rejecting rules that would match
+/// every event, change nothing or contain invalid patterns
+fn validate(id: Uuid, input: RuleInput) -> Result<Rule, DBError> {
+    let name = input.name.trim();
+    if name.is_empty() {
+        return Err(DBError::MissingField("name"));
+    }
+
+    let rule = Rule {
+        id,
+        name: name.to_string(),
+        priority: input.priority.unwrap_or(0),
+        enabled: input.enabled.unwrap_or(true),
+        conditions: RuleConditions {
+            app: non_empty(input.conditions.app),
+            entity: non_empty(input.conditions.entity),
+            domain: non_empty(input.conditions.domain),
+            path: non_empty(input.conditions.path),
+            project: non_empty(input.conditions.project),
+            source: non_empty(input.conditions.source),
+        },
+        actions: RuleActions {
+            category: non_empty(input.actions.category),
+            project: non_empty(input.actions.project),
+            tags: input
+                .actions
+                .tags
+                .map(|tags| tag_names(&tags).into_iter().map(String::from).collect())
+                .filter(|tags: &Vec<String>| !tags.is_empty()),
+        },
+    };
+
+    let conditions = &rule.conditions;
+    if [
+        &conditions.app,
+        &conditions.entity,
+        &conditions.domain,
+        &conditions.path,
+        &conditions.project,
+        &conditions.source,
+    ]
+    .iter()
+    .all(|condition| condition.is_none())
+    {
+        return Err(DBError::Unsupported("A rule needs at least one condition"));
+    }
+    let actions = &rule.actions;
+    if actions.category.is_none() && actions.project.is_none() && actions.tags.is_none() {
+        return Err(DBError::Unsupported("A rule needs at least one action"));
+    }
+
+    CompiledRule::new(&rule)?;
+    Ok(rule)
+}
+
+fn non_empty(value: Option<String>) -> Option<String> {
+    value
+        .map(|v| v.trim().to_string())
+        .filter(|v| !v.is_empty())
+}
+
+/// The fields of an event that rules match on
+#[derive(Debug, Clone, Copy)]
+pub struct RuleSubject<'a> {
+    pub app: &'a str,
+    pub entity: &'a str,
+    pub entity_type: &'a str,
+    pub project: &'a str,
+    /// Project path as reported, or the stored root path
+    pub project_path: &'a str,
+    pub source: &'a str,
+}
+
+impl<'a> From<&'a EventInput> for RuleSubject<'a> {
+    fn from(event: &'a EventInput) -> Self {
+        Self {
+            app: &event.app_name,
+            entity: &event.entity_name,
+            entity_type: &event.entity_type,
+            project: &event.project_name,
+            project_path: &event.project_path,
+            source: &event.source_name,
+        }
+    }
+}
+
+impl RuleSubject<'_> {
+    /// Host of the visited URL, from the entity or the project path
+    fn host(&self) -> Option<&str> {
+        split_url(self.entity)
+            .or_else(|| split_url(self.project_path))
+            .map(|(host, _)| host)
+    }
+
+    /// Path of the visited URL. Browser events store it as the entity of a project
+    /// keyed by the origin.
+    fn url_path(&self) -> Option<&str> {
+        if let Some((_, path)) = split_url(self.entity) {
+            return Some(path);
+        }
+        let url_entity =
+            self.entity_type.eq_ignore_ascii_case("url") || split_url(self.project_path).is_some();
+        (url_entity && self.entity.starts_with('/')).then_some(self.entity)
+    }
+}
+
+/// Splits a URL into its host, without credentials or port, and the rest
+fn split_url(value: &str) -> Option<(&str, &str)> {
+    let (_, rest) = value.split_once("://")?;
+    let (authority, path) = rest.find('/').map_or((rest, "/"), |i| rest.split_at(i));
+    let host = authority.rsplit('@').next().unwrap_or(authority);
+    let host = host.split(':').next().unwrap_or(host);
+
+    (!host.is_empty()).then_some((host, path))
+}
+
+/// Values the rules set on an event
+#[derive(Debug, Default, PartialEq)]
+pub struct RuleOutcome<'a> {
+    pub category: Option<&'a str>,
+    pub project: Option<&'a str>,
+    /// Tags added by every matching rule
+    pub tags: Vec<&'a str>,
+    /// The rules that set a value or add tags, highest priority first
+    pub rules: Vec<Uuid>,
+}
+
+impl RuleOutcome<'_> {
+    /// The tags of the outcome missing from `current`
+    fn missing_tags(&self, current: &[String]) -> Vec<String> {
+        self.tags
+            .iter()
+            .filter(|tag| !current.iter().any(|c| c.eq_ignore_ascii_case(tag)))
+            .map(|tag| tag.to_string())
+            .collect()
+    }
+}
+
+#[derive(Debug)]
+struct CompiledRule {
+    id: Uuid,
+    app: Option<Regex>,
+    entity: Option<Regex>,
+    domain: Option<String>,
+    path: Option<Regex>,
+    project: Option<Regex>,
+    source: Option<Regex>,
+    actions: RuleActions,
+}
+
+impl CompiledRule {
+    fn new(rule: &Rule) -> Result<Self, DBError> {
+        let conditions = &rule.conditions;
+        let glob = |value: &Option<String>| value.as_deref().map(glob_regex).transpose();
+
+        Ok(Self {
+            id: rule.id,
+            app: glob(&conditions.app)?,
+            entity: glob(&conditions.entity)?,
+            domain: conditions
+                .domain
+                .as_deref()
+                .map(|d| d.trim_start_matches("*.").trim_matches('.').to_lowercase()),
+            path: conditions.path.as_deref().map(Regex::new).transpose()?,
+            project: glob(&conditions.project)?,
+            source: glob(&conditions.source)?,
+            actions: rule.actions.clone(),
+        })
+    }
+
+    fn matches(&self, subject: &RuleSubject) -> bool {
+        let glob = |pattern: &Option<Regex>, value: &str| {
+            pattern.as_ref().is_none_or(|p| p.is_match(value))
+        };
+
+        glob(&self.app, subject.app)
+            && glob(&self.entity, subject.entity)
+            && glob(&self.project, subject.project)
+            && glob(&self.source, subject.source)
+            && self.domain.as_ref().is_none_or(|domain| {
+                subject.host().is_some_and(|host| {
+                    let host = host.to_lowercase();
+                    host == *domain
+                        || host
+                            .strip_suffix(domain.as_str())
+                            .is_some_and(|sub| sub.ends_with('.'))
+                })
+            })
+            && self.path.as_ref().is_none_or(|path| {
+                subject
+                    .url_path()
+                    .is_some_and(|url_path| path.is_match(url_path))
+            })
+    }
+}
+
+/// Translates a GLOB pattern, with the semantics of SQLite's `GLOB`, into an
+/// anchored regular expression. Values without wildcards match exactly.
+fn glob_regex(pattern: &str) -> Result<Regex, regex::Error> {
+    let chars: Vec<char> = pattern.chars().collect();
+    let mut regex = String::from("^");
+    let mut i = 0;
+
+    while i < chars.len() {
+        match chars[i] {
+            '*' => regex.push_str(".*"),
+            '?' => regex.push('.'),
+            '[' => {
+                let mut j = i + 1;
+                let negated = chars.get(j) == Some(&'^');
+                if negated {
+                    j += 1;
+                }
+                // A `]` right after the opening bracket is a member of the class
+                let first = j;
+                if chars.get(j) == Some(&']') {
+                    j += 1;
+                }
+
+                match chars[j..].iter().position(|&c| c == ']') {
+                    Some(len) => {
+                        regex.push('[');
+                        if negated {
+                            regex.push('^');
+                        }
+                        for &member in &chars[first..j + len] {
+                            if member == '-' {
+                                regex.push('-');
+                            } else {
+                                regex.push_str(&regex::escape(&member.to_string()));
+                            }
+                        }
+                        regex.push(']');
+                        i = j + len;
+                    }
+                    // An unterminated class is matched literally
+                    None => regex.push_str(r"\["),
+                }
+            }
+            c => regex.push_str(&regex::escape(&c.to_string())),
+        }
+        i += 1;
+    }
+    regex.push('$');
+
+    RegexBuilder::new(&regex).dot_matches_new_line(true).build()
+}
+
+/// The enabled rules, compiled for evaluation in priority order
+#[derive(Debug, Default)]
+pub struct RuleSet {
+    rules: Vec<CompiledRule>,
+}
+
+impl RuleSet {
+    /// Loads the enabled rules
+    pub async fn load(db: &DBContext) -> Result<Self, DBError> {
+        let rules = Rules::all(db)
+            .await?
+            .iter()
+            .filter(|rule| rule.enabled)
+            .map(CompiledRule::new)
+            .collect::<Result<_, _>>()?;
+
+        Ok(Self { rules })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    /// Evaluates the rules against an event. The highest priority matching rule that
+    /// sets a value decides it, while the tags of all matching rules are added.
+    pub fn evaluate(&self, subject: &RuleSubject) -> RuleOutcome<'_> {
+        let mut outcome = RuleOutcome::default();
+
+        for rule in &self.rules {
+            let sets_category = outcome.category.is_none() && rule.actions.category.is_some();
+            let sets_project = outcome.project.is_none() && rule.actions.project.is_some();
+            let adds_tags = rule.actions.tags.is_some();
+            if !(sets_category || sets_project || adds_tags) || !rule.matches(subject) {
+                continue;
+            }
+
+            if sets_category {
+                outcome.category = rule.actions.category.as_deref();
+            }
+            if sets_project {
+                outcome.project = rule.actions.project.as_deref();
+            }
+            for tag in rule.actions.tags.iter().flatten() {
+                if !outcome.tags.iter().any(|t| t.eq_ignore_ascii_case(tag)) {
+                    outcome.tags.push(tag);
+                }
+            }
+            outcome.rules.push(rule.id);
+        }
+
+        outcome
+    }
+
+    /// Applies the rules to events about to be ingested, returning the rewritten
+    /// event for each input the rules change
+    pub async fn rewrite(
+        &self,
+        db: &DBContext,
+        events: &[&EventInput],
+    ) -> Result<Vec<Option<EventInput>>, DBError> {
+        let mut root_paths: HashMap<&str, String> = HashMap::new();
+        let mut rewritten = Vec::with_capacity(events.len());
+
+        for event in events {
+            let outcome = self.evaluate(&RuleSubject::from(*event));
+            let category = outcome.category.filter(|c| *c != event.category);
+            let project = outcome.project.filter(|p| *p != event.project_name);
+            let tags = outcome.missing_tags(&event.tags);
+            if category.is_none() && project.is_none() && tags.is_empty() {
+                rewritten.push(None);
+                continue;
+            }
+
+            let mut event = (*event).clone();
+            event.tags.extend(tags);
+            if let Some(category) = category {
+                event.category = category.to_string();
+            }
+            if let Some(project) = project {
+                if !root_paths.contains_key(project) {
+                    // The most recently used project with that name, or a new path-less one
+                    let root_path = ServerProject::latest_root_path(db, project)
+                        .await?
+                        .unwrap_or_default();
+                    root_paths.insert(project, root_path);
+                }
+                event.project_name = project.to_string();
+                event.project_path = root_paths[project].clone();
+            }
+            rewritten.push(Some(event));
+        }
+
+        Ok(rewritten)
+    }
+
+    /// Re-runs the rules over the events overlapping `[start, end)`, storing the
+    /// changes in one transaction unless `dry_run` is set. Events keep their ids.
+    pub async fn apply_to_range(
+        &self,
+        db: &DBContext,
+        start: i64,
+        end: i64,
+        dry_run: bool,
+    ) -> Result<RuleApplyReport, DBError> {
+        let mut report = RuleApplyReport {
+            dry_run,
+            ..Default::default()
+        };
+        let mut after: Option<(i64, Uuid)> = None;
+        let mut tx = db.pool().begin().await?;
+
+        loop {
+            let mut qb = QueryBuilder::<Sqlite>::new(
+                "SELECT events.id, events.timestamp, apps.name AS app,
+                    categories.name AS category, entities.name AS entity,
+                    entities.type AS entity_type, projects.name AS project,
+                    projects.root_path, sources.name AS source
+                FROM events
+                LEFT JOIN apps ON apps.id = events.app_id
+                LEFT JOIN categories ON categories.id = events.category_id
+                LEFT JOIN entities ON entities.id = events.entity_id
+                LEFT JOIN projects ON projects.id = events.project_id
+                LEFT JOIN sources ON sources.id = events.source_id
+                WHERE 1=1",
+            );
+            qb.append_date_range(
+                Some(start),
+                Some(end),
+                "events.timestamp",
+                "events.end_timestamp",
+            );
+            if let Some((timestamp, id)) = after {
+                qb.push(" AND (events.timestamp > ")
+                    .push_bind(timestamp)
+                    .push(" OR (events.timestamp = ")
+                    .push_bind(timestamp)
+                    .push(" AND events.id > ")
+                    .push_bind(id)
+                    .push("))");
+            }
+            qb.push(" ORDER BY events.timestamp, events.id LIMIT ")
+                .push_bind(APPLY_PAGE_SIZE);
+
+            let rows: Vec<SubjectRow> = qb.build_query_as().fetch_all(&mut *tx).await?;
+            let Some(last) = rows.last() else {
+                break;
+            };
+            after = Some((last.timestamp, last.id));
+
+            for row in &rows {
+                report.scanned += 1;
+                let outcome = self.evaluate(&row.subject());
+                let tags = if outcome.tags.is_empty() {
+                    Vec::new()
+                } else {
+                    event_tag_names(&mut tx, row.id).await?
+                };
+                let Some(change) = change_for(row, outcome, &tags) else {
+                    continue;
+                };
+
+                if !dry_run {
+                    if change.category.is_some() || change.project.is_some() {
+                        let patch = EventPatchInput {
+                            category: change.category.as_ref().map(|c| c.to.clone()),
+                            project_name: change.project.as_ref().map(|p| p.to.clone()),
+                            ..Default::default()
+                        };
+                        Event::update_with(&mut tx, db.dimension_cache(), row.id, patch).await?;
+                    }
+                    add_event_tags(&mut tx, row.id, &change.tags).await?;
+                }
+
+                report.changed += 1;
+                if report.changes.len() < MAX_REPORTED_CHANGES {
+                    report.changes.push(change);
+                }
+            }
+
+            if (rows.len() as i64) < APPLY_PAGE_SIZE {
+                break;
+            }
+        }
+
+        if !dry_run {
+            tx.commit().await?;
+        }
+        Ok(report)
+    }
+}
+
+/// The change an outcome makes to a stored event carrying `tags`, if any
+fn change_for(row: &SubjectRow, outcome: RuleOutcome, tags: &[String]) -> Option<RuleChange> {
+    let change = |from: &Option<String>, to: Option<&str>| {
+        to.filter(|to| from.as_deref() != Some(*to))
+            .map(|to| ValueChange {
+                from: from.clone(),
+                to: to.to_string(),
+            })
+    };
+    let category = change(&row.category, outcome.category);
+    let project = change(&row.project, outcome.project);
+    let tags = outcome.missing_tags(tags);
+    if category.is_none() && project.is_none() && tags.is_empty() {
+        return None;
+    }
+
+    Some(RuleChange {
+        event_id: row.id,
+        timestamp: DateTime::from_timestamp(row.timestamp, 0).unwrap_or_default(),
+        app: row.app.clone(),
+        entity: row.entity.clone(),
+        rules: outcome.rules,
+        category,
+        project,
+        tags,
+    })
+}
+
+#[cfg(all(test, feature = "server"))]
+mod tests {
+    use super::*;
+    use crate::server::{tags::Tags, test_utils::TestEvent};
+
+    fn browser_event(domain: &str, path: &str) -> EventInput {
+        EventInput {
+            timestamp: None,
+            duration: Some(60),
+            category: "Browsing".into(),
+            app_name: "Firefox".into(),
+            entity_name: path.into(),
+            entity_type: "Url".into(),
+            project_name: domain.into(),
+            project_path: format!("https://{domain}{path}"),
+            branch_name: None,
+            language_name: None,
+            source_name: "skopio-desktop".into(),
+            end_timestamp: None,
+            tags: Vec::new(),
+        }
+    }
+
+    fn rule(name: &str, priority: i64, conditions: RuleConditions, category: &str) -> RuleInput {
+        RuleInput {
+            name: name.into(),
+            priority: Some(priority),
+            enabled: None,
+            conditions,
+            actions: RuleActions {
+                category: Some(category.into()),
+                project: None,
+                tags: None,
+            },
+        }
+    }
+
+    #[tokio::test]
+    async fn test_rules_are_evaluated_in_priority_order() {
+        let db = DBContext::new("sqlite::memory:").await.unwrap();
+        let wiki = Rules::create(
+            &db,
+            rule(
+                "Wiki",
+                10,
+                RuleConditions {
+                    domain: Some("wiki.example.com".into()),
+                    ..Default::default()
+                },
+                "Learning",
+            ),
+        )
+        .await
+        .unwrap();
+        Rules::create(
+            &db,
+            rule(
+                "Example",
+                0,
+                RuleConditions {
+                    domain: Some("example.com".into()),
+                    ..Default::default()
+                },
+                "Browsing",
+            ),
+        )
+        .await
+        .unwrap();
+        let review = Rules::create(
+            &db,
+            RuleInput {
+                name: "Reviews".into(),
+                priority: Some(5),
+                enabled: None,
+                conditions: RuleConditions {
+                    path: Some(r"^/[^/]+/[^/]+/pull/\d+".into()),
+                    ..Default::default()
+                },
+                actions: RuleActions {
+                    category: Some("Code Reviewing".into()),
+                    project: Some("skopio".into()),
+                    tags: None,
+                },
+            },
+        )
+        .await
+        .unwrap();
+
+        let rules = RuleSet::load(&db).await.unwrap();
+
+        let event = browser_event("wiki.example.com", "/pages/onboarding");
+        let outcome = rules.evaluate(&RuleSubject::from(&event));
+        assert_eq!(outcome.category, Some("Learning"));
+        assert_eq!(outcome.rules, [wiki.id]);
+
+        let event = browser_event("github.com", "/Skopio-app/skopio/pull/42");
+        let outcome = rules.evaluate(&RuleSubject::from(&event));
+        assert_eq!(outcome.category, Some("Code Reviewing"));
+        assert_eq!(outcome.project, Some("skopio"));
+        assert_eq!(outcome.rules, [review.id]);
+
+        let event = browser_event("notexample.com", "/");
+        assert_eq!(
+            rules.evaluate(&RuleSubject::from(&event)),
+            RuleOutcome::default()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_apply_to_range_previews_then_stores_changes() {
+        let db = DBContext::new("sqlite::memory:").await.unwrap();
+        let wiki = browser_event("wiki.example.com", "/pages/onboarding");
+        let id = TestEvent::from_input(&wiki, 0, 60).insert(&db).await.id;
+        TestEvent::from_input(&browser_event("news.site", "/"), 60, 120)
+            .insert(&db)
+            .await;
+        // Outside the range
+        TestEvent::from_input(&wiki, 5_000, 5_060).insert(&db).await;
+
+        Rules::create(
+            &db,
+            rule(
+                "Wiki",
+                0,
+                RuleConditions {
+                    domain: Some("example.com".into()),
+                    app: Some("Fire*".into()),
+                    ..Default::default()
+                },
+                "Learning",
+            ),
+        )
+        .await
+        .unwrap();
+        let rules = RuleSet::load(&db).await.unwrap();
+
+        let preview = rules.apply_to_range(&db, 0, 1_000, true).await.unwrap();
+        assert_eq!((preview.scanned, preview.changed), (2, 1));
+        let change = &preview.changes[0];
+        assert_eq!(change.event_id, id);
+        assert_eq!(
+            change.category.as_ref().unwrap().from.as_deref(),
+            Some("Browsing")
+        );
+        assert_eq!(change.category.as_ref().unwrap().to, "Learning");
+        assert!(change.project.is_none());
+        assert_eq!(
+            Event::find_by_id(&db, id).await.unwrap().unwrap().category,
+            "Browsing"
+        );
+
+        let report = rules.apply_to_range(&db, 0, 1_000, false).await.unwrap();
+        assert_eq!(report.changed, 1);
+        assert_eq!(
+            Event::find_by_id(&db, id).await.unwrap().unwrap().category,
+            "Learning"
+        );
+
+        let again = rules.apply_to_range(&db, 0, 1_000, true).await.unwrap();
+        assert_eq!(again.changed, 0);
+    }
+
+    #[tokio::test]
+    async fn test_tags_of_every_matching_rule_are_added() {
+        let db = DBContext::new("sqlite::memory:").await.unwrap();
+        let event = browser_event("wiki.example.com", "/pages/onboarding");
+        let id = TestEvent::from_input(&event, 0, 60).insert(&db).await.id;
+        Tags::tag_events(&db, &[(id, &["Docs".to_string()][..])])
+            .await
+            .unwrap();
+
+        let mut wiki = rule(
+            "Wiki",
+            10,
+            RuleConditions {
+                domain: Some("wiki.example.com".into()),
+                ..Default::default()
+            },
+            "Learning",
+        );
+        wiki.actions.tags = Some(vec!["docs".into(), " Client-Acme ".into()]);
+        Rules::create(&db, wiki).await.unwrap();
+        Rules::create(
+            &db,
+            RuleInput {
+                name: "Example".into(),
+                priority: Some(0),
+                enabled: None,
+                conditions: RuleConditions {
+                    domain: Some("example.com".into()),
+                    ..Default::default()
+                },
+                actions: RuleActions {
+                    tags: Some(vec!["Client-Acme".into(), "internal".into()]),
+                    ..Default::default()
+                },
+            },
+        )
+        .await
+        .unwrap();
+
+        let stored = Rules::all(&db).await.unwrap();
+        assert_eq!(
+            stored[0].actions.tags.as_deref(),
+            Some(&["Client-Acme".to_string(), "Docs".to_string()][..])
+        );
+
+        let rules = RuleSet::load(&db).await.unwrap();
+        let outcome = rules.evaluate(&RuleSubject::from(&event));
+        assert_eq!(outcome.category, Some("Learning"));
+        assert_eq!(outcome.tags, ["Client-Acme", "Docs", "internal"]);
+        assert_eq!(outcome.rules.len(), 2);
+
+        let report = rules.apply_to_range(&db, 0, 1_000, false).await.unwrap();
+        assert_eq!(report.changes[0].tags, ["Client-Acme", "internal"]);
+
+        let mut conn = db.pool().acquire().await.unwrap();
+        assert_eq!(
+            event_tag_names(&mut conn, id).await.unwrap(),
+            ["Client-Acme", "Docs", "internal"]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_rejects_rules_without_conditions_or_with_bad_patterns() {
+        let db = DBContext::new("sqlite::memory:").await.unwrap();
+
+        let err = Rules::create(&db, rule("All", 0, RuleConditions::default(), "Other")).await;
+        assert!(matches!(err, Err(DBError::Unsupported(_))));
+
+        let bad_path = RuleConditions {
+            path: Some("(".into()),
+            ..Default::default()
+        };
+        let err = Rules::create(&db, rule("Bad", 0, bad_path, "Other")).await;
+        assert!(matches!(err, Err(DBError::Pattern(_))));
+        assert!(Rules::all(&db).await.unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_glob_regex_follows_sqlite_glob() {
+        let matches = |pattern: &str, value: &str| glob_regex(pattern).unwrap().is_match(value);
+
+        assert!(matches("*.md", "docs/README.md"));
+        assert!(!matches("*.md", "README.mdx"));
+        assert!(matches("Code", "Code"));
+        assert!(!matches("Code", "code"));
+        assert!(matches("v?.[0-9]", "v1.2"));
+        assert!(matches("[^a-z]*", "Zed"));
+        assert!(!matches("[^a-z]*", "zed"));
+        assert!(matches("a[b", "a[b"));
+        assert!(matches("[]]", "]"));
+        assert!(matches("a.b", "a.b"));
+        assert!(!matches("a.b", "axb"));
+    }
+}
@@ -0,0 +1,292 @@
+use std::collections::HashMap;
+
+use common::{
+    models::{Group, inputs::SearchInput, outputs::SearchResult},
+    time::TimeRange,
+};
+use sqlx::{QueryBuilder, Sqlite};
+use uuid::Uuid;
+
+use crate::{
+    DBContext,
+    error::DBError,
+    server::{usage::dimension_columns, utils::query::QueryBuilderExt},
+};
+
+/// Results returned when a search sets no `limit`
+const DEFAULT_SEARCH_LIMIT: u32 = 20;
+
+/// Largest `limit` a search may ask for
+const MAX_SEARCH_LIMIT: u32 = 100;
+
+/// Kinds searched when a search sets no `kinds`
+const SEARCHABLE: [Group; 4] = [Group::Project, Group::Entity, Group::Branch, Group::App];
+
+#[derive(Debug, sqlx::FromRow)]
+struct MatchRow {
+    /// Index of the kind in [`SearchQuery::kinds`]
+    kind: i64,
+    id: Uuid,
+    name: String,
+    project_id: Option<Uuid>,
+    project_name: Option<String>,
+}
+
+/// Searches the full-text indexes of project, entity, branch and app names
+#[derive(Debug, Clone)]
+pub struct SearchQuery {
+    pub terms: String,
+    pub kinds: Vec<Group>,
+    /// `[start, end)` in unix seconds that totals are computed over, all time when `None`
+    pub range: Option<(i64, i64)>,
+    pub exclude_afk: bool,
+    pub limit: u32,
+}
+
+impl From<SearchInput> for SearchQuery {
+    fn from(input: SearchInput) -> Self {
+        let range = input.preset.map(|preset| {
+            let time_range =
+                TimeRange::resolve(preset, input.timezone, input.week_start.unwrap_or_default());
+            (time_range.start().timestamp(), time_range.end().timestamp())
+        });
+
+        let mut kinds = Vec::new();
+        for kind in input.kinds.unwrap_or_else(|| SEARCHABLE.to_vec()) {
+            if !kinds.contains(&kind) {
+                kinds.push(kind);
+            }
+        }
+
+        Self {
+            terms: input.q,
+            kinds,
+            range,
+            exclude_afk: input.exclude_afk.unwrap_or(false),
+            limit: input
+                .limit
+                .unwrap_or(DEFAULT_SEARCH_LIMIT)
+                .clamp(1, MAX_SEARCH_LIMIT),
+        }
+    }
+}
+
+impl SearchQuery {
+    /// Returns the best matches across every kind, ordered by relevance, each with the
+    /// time spent on it.
+    ///
+    /// Every word of the query has to match the start of a word in the name, so
+    /// `auth_mid` finds `src/auth_middleware.rs`.
+    pub async fn execute(&self, db: &DBContext) -> Result<Vec<SearchResult>, DBError> {
+        if self.kinds.iter().any(|kind| !SEARCHABLE.contains(kind)) {
+            return Err(DBError::Unsupported(
+                "Search covers projects, entities, branches and apps",
+            ));
+        }
+
+        let Some(expression) = match_expression(&self.terms) else {
+            return Ok(Vec::new());
+        };
+
+        let mut qb = QueryBuilder::<Sqlite>::new(
+            "SELECT m.kind, m.id, m.name, m.project_id, projects.name AS project_name FROM (",
+        );
+        for (i, kind) in self.kinds.iter().enumerate() {
+            // The map tables reference rows by the same column name as `events`
+            let (table, key) = dimension_columns(*kind);
+            if i > 0 {
+                qb.push(" UNION ALL ");
+            }
+            qb.push("SELECT ")
+                .push(i)
+                .push(" AS kind, d.id, d.name, ")
+                .push(if matches!(kind, Group::Entity | Group::Branch) {
+                    "d.project_id"
+                } else {
+                    "NULL"
+                })
+                .push(" AS project_id, fts.rank AS rank FROM ")
+                .push(table)
+                .push("_fts fts JOIN ")
+                .push(table)
+                .push("_fts_map map ON map.docid = fts.rowid JOIN ")
+                .push(table)
+                .push(" d ON d.id = map.")
+                .push(key)
+                .push(" WHERE fts.name MATCH ")
+                .push_bind(expression.clone());
+        }
+        qb.push(
+            ") m LEFT JOIN projects ON projects.id = m.project_id \
+            ORDER BY m.rank, m.name LIMIT ",
+        )
+        .push_bind(self.limit);
+
+        let rows: Vec<MatchRow> = qb.build_query_as().fetch_all(db.pool()).await?;
+
+        let mut results = Vec::with_capacity(rows.len());
+        for row in rows {
+            results.push(SearchResult {
+                kind: self.kinds[row.kind as usize],
+                id: row.id,
+                name: row.name,
+                project_id: row.project_id,
+                project_name: row.project_name,
+                total_seconds: 0,
+            });
+        }
+
+        for kind in &self.kinds {
+            let ids: Vec<Uuid> = results
+                .iter()
+                .filter(|r| r.kind == *kind)
+                .map(|r| r.id)
+                .collect();
+            if ids.is_empty() {
+                continue;
+            }
+
+            let totals = self.fetch_totals(db, *kind, &ids).await?;
+            for result in results.iter_mut().filter(|r| r.kind == *kind) {
+                result.total_seconds = totals.get(&result.id).copied().unwrap_or(0);
+            }
+        }
+
+        Ok(results)
+    }
+
+    /// Sums the active time of the events referencing each of `ids` within the range
+    async fn fetch_totals(
+        &self,
+        db: &DBContext,
+        kind: Group,
+        ids: &[Uuid],
+    ) -> Result<HashMap<Uuid, i64>, DBError> {
+        let (_, column) = dimension_columns(kind);
+        let (start, end) = self.range.unwrap_or((i64::MIN, i64::MAX));
+
+        let mut qb = QueryBuilder::<Sqlite>::new("SELECT events.");
+        qb.push(column).push(", COALESCE(SUM(");
+        qb.push_active_duration(&start.to_string(), &end.to_string(), self.exclude_afk);
+        qb.push("), 0) FROM events WHERE events.")
+            .push(column)
+            .push(" IN (");
+        let mut separated = qb.separated(", ");
+        for id in ids {
+            separated.push_bind(*id);
+        }
+        qb.push(")");
+        if let Some((start, end)) = self.range {
+            qb.append_date_range(
+                Some(start),
+                Some(end),
+                "events.timestamp",
+                "events.end_timestamp",
+            );
+        }
+        qb.push(" GROUP BY events.").push(column);
+
+        let rows: Vec<(Uuid, i64)> = qb.build_query_as().fetch_all(db.pool()).await?;
+        Ok(rows.into_iter().collect())
+    }
+}
+
+/// Builds an FTS5 expression matching names that contain a word starting with each
+/// term of `terms`, or `None` if there are no terms
+fn match_expression(terms: &str) -> Option<String> {
+    let phrases: Vec<String> = terms
+        .split_whitespace()
+        .map(|term| format!("\"{}\"*", term.replace('"', "\"\"")))
+        .collect();
+
+    (!phrases.is_empty()).then(|| phrases.join(" "))
+}
+
+#[cfg(all(test, feature = "server"))]
+mod tests {
+    use super::*;
+    use crate::server::test_utils::TestEvent;
+
+    fn query(terms: &str, kinds: Vec<Group>, range: Option<(i64, i64)>) -> SearchQuery {
+        SearchQuery {
+            terms: terms.into(),
+            kinds,
+            range,
+            exclude_afk: false,
+            limit: 10,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_search_returns_typed_results_with_totals() {
+        let db = DBContext::new("sqlite::memory:").await.unwrap();
+        TestEvent::new(0, 600)
+            .entity("src/auth_middleware.rs")
+            .branch(Some("feature/auth"))
+            .insert(&db)
+            .await;
+        TestEvent::new(1_000, 1_300)
+            .entity("src/auth_middleware.rs")
+            .branch(Some("feature/auth"))
+            .insert(&db)
+            .await;
+        TestEvent::new(2_000, 2_100)
+            .entity("src/main.rs")
+            .branch(Some("feature/auth"))
+            .insert(&db)
+            .await;
+
+        let results = query("auth_mid", SEARCHABLE.to_vec(), Some((0, 1_200)))
+            .execute(&db)
+            .await
+            .unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].kind, Group::Entity);
+        assert_eq!(results[0].name, "src/auth_middleware.rs");
+        assert_eq!(results[0].project_name.as_deref(), Some("api"));
+        assert_eq!(results[0].total_seconds, 800);
+
+        let results = query("auth", SEARCHABLE.to_vec(), None)
+            .execute(&db)
+            .await
+            .unwrap();
+        let kinds: Vec<Group> = results.iter().map(|r| r.kind).collect();
+        assert_eq!(kinds.len(), 2);
+        assert!(kinds.contains(&Group::Branch));
+        let branch = results.iter().find(|r| r.kind == Group::Branch).unwrap();
+        assert_eq!(branch.total_seconds, 1_000);
+
+        let results = query("code", vec![Group::App], None)
+            .execute(&db)
+            .await
+            .unwrap();
+        assert_eq!(results[0].name, "Code");
+        assert_eq!(results[0].project_id, None);
+    }
+
+    #[tokio::test]
+    async fn test_search_rejects_unindexed_kinds() {
+        let db = DBContext::new("sqlite::memory:").await.unwrap();
+
+        let err = query("rust", vec![Group::Language], None)
+            .execute(&db)
+            .await;
+        assert!(matches!(err, Err(DBError::Unsupported(_))));
+        assert!(
+            query("  ", SEARCHABLE.to_vec(), None)
+                .execute(&db)
+                .await
+                .unwrap()
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn test_match_expression_quotes_terms() {
+        assert_eq!(
+            match_expression("auth \"mid").as_deref(),
+            Some("\"auth\"* \"\"\"mid\"*")
+        );
+        assert_eq!(match_expression(" "), None);
+    }
+}
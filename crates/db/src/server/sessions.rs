@@ -0,0 +1,279 @@
+use std::collections::HashMap;
+
+use chrono::{DateTime, Utc};
+use common::models::outputs::{SessionStats, WorkSession};
+use sqlx::{QueryBuilder, Sqlite};
+
+use crate::{
+    DBContext,
+    error::DBError,
+    server::utils::{query::QueryBuilderExt, summary_filter::SummaryFilters},
+};
+
+/// Longest break between events, in seconds, that still continues a session
+pub const DEFAULT_SESSION_GAP: i64 = 300;
+
+/// A `[start, end)` span of unix seconds
+type Span = (i64, i64);
+
+/// An event clipped to the queried range
+#[derive(Debug, Clone, sqlx::FromRow)]
+struct SessionEvent {
+    start_ts: i64,
+    end_ts: i64,
+    project: Option<String>,
+    category: Option<String>,
+    language: Option<String>,
+}
+
+/// Fetches the work sessions within `[start, end)` built from the events matching `filters`.
+///
+/// Events are merged into one session while the break between them is at most `gap`
+/// seconds and the user did not go AFK during the break. Active time excludes AFK
+/// periods inside the session and counts time recorded by several sources once.
+pub async fn fetch_sessions(
+    db: &DBContext,
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+    gap: i64,
+    filters: &SummaryFilters,
+) -> Result<Vec<WorkSession>, DBError> {
+    let range_start = start.timestamp();
+    let range_end = end.timestamp();
+
+    let mut qb = QueryBuilder::<Sqlite>::new("SELECT MAX(events.timestamp, ");
+    qb.push_bind(range_start)
+        .push(") AS start_ts, MIN(events.end_timestamp, ")
+        .push_bind(range_end)
+        .push(
+            ") AS end_ts, \
+            projects.name AS project, \
+            categories.name AS category, \
+            languages.name AS language \
+            FROM events",
+        );
+    qb.append_standard_joins(None);
+    qb.push(" WHERE events.end_timestamp > ")
+        .push_bind(range_start)
+        .push(" AND events.timestamp < ")
+        .push_bind(range_end);
+    qb.append_all_filters(filters);
+    qb.push(" ORDER BY start_ts");
+
+    let events: Vec<SessionEvent> = qb.build_query_as().fetch_all(db.pool()).await?;
+
+    let afk: Vec<Span> = sqlx::query_as(
+        "SELECT afk_start, afk_end FROM (
+            SELECT
+                afk_start,
+                COALESCE(afk_end, afk_start + COALESCE(duration, 0)) AS afk_end
+            FROM afk_events
+        )
+        WHERE afk_end > ? AND afk_start < ?
+        ORDER BY afk_start",
+    )
+    .bind(range_start)
+    .bind(range_end)
+    .fetch_all(db.pool())
+    .await?;
+
+    Ok(build_sessions(&events, &afk, gap))
+}
+
+/// Computes the count, mean, median and longest active time of `sessions`
+pub fn session_stats(sessions: &[WorkSession]) -> SessionStats {
+    if sessions.is_empty() {
+        return SessionStats::default();
+    }
+
+    let mut durations: Vec<i64> = sessions.iter().map(|s| s.active_seconds).collect();
+    durations.sort_unstable();
+
+    let count = durations.len();
+    let mid = count / 2;
+    let median_seconds = if count.is_multiple_of(2) {
+        (durations[mid - 1] + durations[mid]) as f64 / 2.0
+    } else {
+        durations[mid] as f64
+    };
+
+    SessionStats {
+        count,
+        mean_seconds: durations.iter().sum::<i64>() as f64 / count as f64,
+        median_seconds,
+        longest_seconds: durations[count - 1],
+    }
+}
+
+/// Merges events ordered by start into sessions. Sessions without any active time
+/// (e.g. covered by AFK entirely) are dropped.
+fn build_sessions(events: &[SessionEvent], afk: &[Span], gap: i64) -> Vec<WorkSession> {
+    let afk = merge_spans(afk.to_vec());
+    let mut sessions = Vec::new();
+    let mut group: Vec<&SessionEvent> = Vec::new();
+    let mut group_end = 0;
+
+    for event in events {
+        let continues = !group.is_empty()
+            && event.start_ts - group_end <= gap
+            && !afk_between(&afk, group_end, event.start_ts);
+
+        if !continues {
+            sessions.extend(finish_session(&group, group_end, &afk));
+            group.clear();
+            group_end = event.end_ts;
+        }
+
+        group.push(event);
+        group_end = group_end.max(event.end_ts);
+    }
+
+    sessions.extend(finish_session(&group, group_end, &afk));
+    sessions
+}
+
+/// Whether an AFK period overlaps the break `[from, to)` between two events
+fn afk_between(afk: &[Span], from: i64, to: i64) -> bool {
+    to > from && afk.iter().any(|&(start, end)| start < to && end > from)
+}
+
+fn finish_session(group: &[&SessionEvent], end: i64, afk: &[Span]) -> Option<WorkSession> {
+    let first = group.first()?;
+
+    let busy = merge_spans(group.iter().map(|e| (e.start_ts, e.end_ts)).collect());
+    let active_seconds: i64 = busy
+        .iter()
+        .map(|&(start, end)| {
+            let away: i64 = afk
+                .iter()
+                .map(|&(afk_start, afk_end)| (end.min(afk_end) - start.max(afk_start)).max(0))
+                .sum();
+            end - start - away
+        })
+        .sum();
+
+    if active_seconds <= 0 {
+        return None;
+    }
+
+    let mut projects = HashMap::new();
+    let mut categories = HashMap::new();
+    let mut languages = HashMap::new();
+    for event in group {
+        let seconds = event.end_ts - event.start_ts;
+        for (totals, name) in [
+            (&mut projects, &event.project),
+            (&mut categories, &event.category),
+            (&mut languages, &event.language),
+        ] {
+            if let Some(name) = name {
+                *totals.entry(name.clone()).or_insert(0) += seconds;
+            }
+        }
+    }
+
+    Some(WorkSession {
+        start: DateTime::from_timestamp(first.start_ts, 0).unwrap_or_default(),
+        end: DateTime::from_timestamp(end, 0).unwrap_or_default(),
+        active_seconds,
+        project: by_time(projects).into_iter().next(),
+        category: by_time(categories).into_iter().next(),
+        languages: by_time(languages),
+    })
+}
+
+/// Returns the names ordered by time spent, ties broken by name
+fn by_time(totals: HashMap<String, i64>) -> Vec<String> {
+    let mut totals: Vec<(String, i64)> = totals.into_iter().collect();
+    totals.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    totals.into_iter().map(|(name, _)| name).collect()
+}
+
+/// Merges overlapping or touching spans into disjoint spans ordered by start
+fn merge_spans(mut spans: Vec<Span>) -> Vec<Span> {
+    spans.sort_unstable();
+    let mut merged: Vec<Span> = Vec::with_capacity(spans.len());
+    for (start, end) in spans {
+        match merged.last_mut() {
+            Some(last) if start <= last.1 => last.1 = last.1.max(end),
+            _ => merged.push((start, end)),
+        }
+    }
+    merged
+}
+
+#[cfg(all(test, feature = "server"))]
+mod tests {
+    use super::*;
+
+    fn event(start_ts: i64, end_ts: i64, project: &str, language: &str) -> SessionEvent {
+        SessionEvent {
+            start_ts,
+            end_ts,
+            project: Some(project.into()),
+            category: Some("Coding".into()),
+            language: Some(language.into()),
+        }
+    }
+
+    #[test]
+    fn test_merges_events_within_gap() {
+        let events = [
+            event(0, 600, "skopio", "Rust"),
+            event(700, 1000, "skopio", "TypeScript"),
+            event(2000, 2300, "other", "Rust"),
+        ];
+
+        let sessions = build_sessions(&events, &[], 300);
+        assert_eq!(sessions.len(), 2);
+        assert_eq!(sessions[0].active_seconds, 900);
+        assert_eq!(sessions[0].end.timestamp(), 1000);
+        assert_eq!(sessions[0].languages, ["Rust", "TypeScript"]);
+        assert_eq!(sessions[1].project.as_deref(), Some("other"));
+    }
+
+    #[test]
+    fn test_afk_splits_sessions_and_is_not_active() {
+        let events = [
+            event(0, 600, "skopio", "Rust"),
+            event(700, 1000, "skopio", "Rust"),
+            event(1000, 2000, "skopio", "Rust"),
+        ];
+        // Away during the first break, and for 100 seconds inside the last event
+        let afk = [(620, 680), (1500, 1600)];
+
+        let sessions = build_sessions(&events, &afk, 300);
+        assert_eq!(sessions.len(), 2);
+        assert_eq!(sessions[0].active_seconds, 600);
+        assert_eq!(sessions[1].active_seconds, 1200);
+    }
+
+    #[test]
+    fn test_overlapping_sources_count_once() {
+        let events = [
+            event(0, 600, "skopio", "Rust"),
+            event(300, 900, "skopio", "Rust"),
+        ];
+
+        let sessions = build_sessions(&events, &[], 0);
+        assert_eq!(sessions.len(), 1);
+        assert_eq!(sessions[0].active_seconds, 900);
+    }
+
+    #[test]
+    fn test_session_stats() {
+        let events = [
+            event(0, 100, "a", "Rust"),
+            event(1000, 1400, "a", "Rust"),
+            event(5000, 5200, "a", "Rust"),
+            event(9000, 10000, "a", "Rust"),
+        ];
+
+        let stats = session_stats(&build_sessions(&events, &[], 60));
+        assert_eq!(stats.count, 4);
+        assert_eq!(stats.mean_seconds, 425.0);
+        assert_eq!(stats.median_seconds, 300.0);
+        assert_eq!(stats.longest_seconds, 1000);
+        assert_eq!(session_stats(&[]).count, 0);
+    }
+}
@@ -1,43 +1,43 @@
-use chrono::Utc;
 use uuid::Uuid;
 
 use crate::{DBContext, error::DBError, models::Source};
 
-impl Source {
-    /// Finds an existing source by name or inserts a new one, returning its ID.
-    pub async fn find_or_insert(db_context: &DBContext, name: &str) -> Result<Uuid, DBError> {
-        let record = sqlx::query!("SELECT id FROM sources WHERE name = ?", name)
-            .fetch_optional(db_context.pool())
-            .await?;
+/// Name of the source used by the desktop activity tracker
+pub const DESKTOP_SOURCE: &str = "skopio-desktop";
 
-        let timestamp = Utc::now().timestamp();
+/// Priority of the desktop tracker when no priority is configured.
+/// It only sees the focused window, so it yields to any other source.
+pub const DESKTOP_SOURCE_PRIORITY: i64 = 0;
 
-        if let Some(row) = record {
-            sqlx::query!(
-                "UPDATE sources SET last_updated = ? WHERE id = ?",
-                timestamp,
-                row.id
-            )
-            .execute(db_context.pool())
-            .await?;
-            let id = Uuid::from_slice(&row.id)?;
-            return Ok(id);
-        }
+/// Priority of every other source (editor plugins, CLI) when no priority is configured
+pub const DEFAULT_SOURCE_PRIORITY: i64 = 10;
 
-        let id = Uuid::now_v7();
-        let result = sqlx::query!(
-            "INSERT INTO sources (id, name, last_updated) VALUES (?, ?, ?) RETURNING id",
-            id,
-            name,
-            timestamp
-        )
-        .fetch_one(db_context.pool())
-        .await?;
+/// Returns an SQL expression for the effective priority of the source row aliased as `alias`.
+///
+/// Sources with a `priority` stored in the `sources` table use it, the rest fall back
+/// to [`DESKTOP_SOURCE_PRIORITY`] or [`DEFAULT_SOURCE_PRIORITY`].
+pub(crate) fn source_priority_expr(alias: &str) -> String {
+    format!(
+        "COALESCE({alias}.priority, CASE WHEN {alias}.name = '{DESKTOP_SOURCE}' \
+         THEN {DESKTOP_SOURCE_PRIORITY} ELSE {DEFAULT_SOURCE_PRIORITY} END)"
+    )
+}
 
-        let result_id = Uuid::from_slice(&result.id)?;
-        Ok(result_id)
-    }
+/// Returns an SQL condition that holds when the source aliased as `winner` is credited
+/// over the source aliased as `loser` where their events overlap.
+///
+/// The higher priority wins. Ties are broken by source name so that overlapping
+/// time is always credited to exactly one of the two sources.
+pub(crate) fn source_wins_expr(winner: &str, loser: &str) -> String {
+    let winner_priority = source_priority_expr(winner);
+    let loser_priority = source_priority_expr(loser);
+    format!(
+        "({winner_priority} > {loser_priority} OR ({winner_priority} = {loser_priority} \
+         AND {winner}.name < {loser}.name))"
+    )
+}
 
+impl Source {
     /// Retrieves all sources
     pub async fn get_all(db_context: &DBContext) -> Result<Vec<Self>, DBError> {
         let rows = sqlx::query_as!(
@@ -55,11 +55,29 @@ impl Source {
         Ok(rows)
     }
 
+    /// Sets the overlap priority of a source by name. `None` restores the default policy.
+    ///
+    /// Returns `false` if no source with that name exists.
+    pub async fn set_priority(
+        db_context: &DBContext,
+        name: &str,
+        priority: Option<i64>,
+    ) -> Result<bool, DBError> {
+        let result = sqlx::query("UPDATE sources SET priority = ? WHERE name = ?")
+            .bind(priority)
+            .bind(name)
+            .execute(db_context.pool())
+            .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
     /// Deletes a source
     pub async fn delete(self, db_context: &DBContext) -> Result<(), DBError> {
         sqlx::query!("DELETE FROM sources WHERE id = ?", self.id)
             .execute(db_context.pool())
             .await?;
+        db_context.dimension_cache().clear();
         Ok(())
     }
 }
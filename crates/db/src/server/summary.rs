@@ -1,20 +1,23 @@
 use std::{collections::HashMap, fmt::Debug};
 
+use chrono::{DateTime, Utc};
 use common::{
     models::{
-        Group,
-        inputs::{BucketSummaryInput, SummaryQueryInput},
+        Group, Project,
+        inputs::{BucketSummaryInput, ProjectStatsInput, SummaryCompareInput, SummaryQueryInput},
+        outputs::{GroupComparison, ProjectStats, SummaryComparison},
     },
-    time::{TimeBucket, TimeRange},
+    time::{TimeBucket, TimeRange, Tz, WeekStart, bucket::bucket_spans_in},
 };
-use sqlx::{QueryBuilder, Sqlite};
+use sqlx::{QueryBuilder, Row, Sqlite};
+use uuid::Uuid;
 
 use crate::{
     DBContext,
     error::DBError,
     models::BucketTimeSummary,
     server::utils::{
-        query::{QueryBuilderExt, bucket_step, group_key_info, push_next_end_with},
+        query::{QueryBuilderExt, group_key_info, group_keys_info, push_buckets_cte},
         summary_filter::SummaryFilters,
     },
 };
@@ -67,6 +70,26 @@ impl From<SummaryQueryInput> for SummaryQueryBuilder {
         if let Some(langs) = input.languages {
             builder = builder.languages(langs);
         }
+
+        if let Some(sources) = input.sources {
+            builder = builder.sources(sources);
+        }
+
+        if let Some(types) = input.entity_types {
+            builder = builder.entity_types(types);
+        }
+
+        if let Some(tags) = input.tags {
+            builder = builder.tags(tags);
+        }
+
+        if let Some(exclude_afk) = input.exclude_afk {
+            builder = builder.exclude_afk(exclude_afk);
+        }
+
+        if let Some(tz) = input.timezone {
+            builder = builder.timezone(tz);
+        }
         builder
     }
 }
@@ -75,7 +98,8 @@ impl From<BucketSummaryInput> for SummaryQueryBuilder {
     fn from(input: BucketSummaryInput) -> Self {
         let mut builder = SummaryQueryBuilder::new();
 
-        let time_range = TimeRange::from(input.preset);
+        let week_start = input.week_start.unwrap_or_default();
+        let time_range = TimeRange::resolve(input.preset, input.timezone, week_start);
         builder = builder.start(time_range.start().timestamp());
         builder = builder.end(time_range.end().timestamp());
         builder.filters.time_bucket = time_range.bucket();
@@ -104,26 +128,115 @@ impl From<BucketSummaryInput> for SummaryQueryBuilder {
             builder = builder.languages(langs);
         }
 
+        if let Some(sources) = input.sources {
+            builder = builder.sources(sources);
+        }
+
+        if let Some(types) = input.entity_types {
+            builder = builder.entity_types(types);
+        }
+
+        if let Some(tags) = input.tags {
+            builder = builder.tags(tags);
+        }
+
         if let Some(group) = input.group_by {
             builder = builder.group_by(group);
         }
 
+        if let Some(groups) = input.subgroup_by {
+            builder = builder.subgroup_by(groups);
+        }
+
+        if let Some(exclude_afk) = input.exclude_afk {
+            builder = builder.exclude_afk(exclude_afk);
+        }
+
+        if let Some(tz) = input.timezone {
+            builder = builder.timezone(tz);
+        }
+
+        builder.week_start(week_start)
+    }
+}
+
+impl From<ProjectStatsInput> for SummaryQueryBuilder {
+    /// Resolves the range of the statistics. The project is set by the caller.
+    fn from(input: ProjectStatsInput) -> Self {
+        let week_start = input.week_start.unwrap_or_default();
+        let time_range = TimeRange::resolve(input.preset, input.timezone, week_start);
+        let mut builder = SummaryQueryBuilder::new()
+            .start(time_range.start().timestamp())
+            .end(time_range.end().timestamp())
+            .week_start(week_start);
+
+        if let Some(exclude_afk) = input.exclude_afk {
+            builder = builder.exclude_afk(exclude_afk);
+        }
+
+        if let Some(tz) = input.timezone {
+            builder = builder.timezone(tz);
+        }
+
         builder
     }
 }
 
-/// Raw row returned when querying bucketed summaries.
-/// Used internally before mapping into `BucketTimeSummary`
-#[derive(Debug, sqlx::FromRow)]
-struct RawBucketRow {
-    /// The time bucket label (e.g., "2025-08-01")
-    bucket: String,
-    /// The grouping key value (e.g., project name, app name)
-    group_key: String,
-    /// Total time in seconds aggregated for this bucket + group_key
-    total_seconds: i64,
-    // Extra metadata when grouping by entity (entity type)
-    group_meta: Option<String>,
+impl From<SummaryCompareInput> for SummaryQueryBuilder {
+    /// Applies the filters of a comparison. Both periods are resolved by the caller.
+    fn from(input: SummaryCompareInput) -> Self {
+        let mut builder = SummaryQueryBuilder::new();
+
+        if let Some(apps) = input.apps {
+            builder = builder.apps(apps);
+        }
+
+        if let Some(projects) = input.projects {
+            builder = builder.projects(projects);
+        }
+
+        if let Some(categories) = input.categories {
+            builder = builder.categories(categories)
+        }
+
+        if let Some(entities) = input.entities {
+            builder = builder.entities(entities);
+        }
+
+        if let Some(branches) = input.branches {
+            builder = builder.branches(branches);
+        }
+
+        if let Some(langs) = input.languages {
+            builder = builder.languages(langs);
+        }
+
+        if let Some(sources) = input.sources {
+            builder = builder.sources(sources);
+        }
+
+        if let Some(types) = input.entity_types {
+            builder = builder.entity_types(types);
+        }
+
+        if let Some(tags) = input.tags {
+            builder = builder.tags(tags);
+        }
+
+        if let Some(group) = input.group_by {
+            builder = builder.group_by(group);
+        }
+
+        if let Some(exclude_afk) = input.exclude_afk {
+            builder = builder.exclude_afk(exclude_afk);
+        }
+
+        if let Some(tz) = input.timezone {
+            builder = builder.timezone(tz);
+        }
+
+        builder.week_start(input.week_start.unwrap_or_default())
+    }
 }
 
 impl SummaryQueryParams for SummaryQueryBuilder {
@@ -165,6 +278,11 @@ impl SummaryQueryBuilder {
         self
     }
 
+    pub fn project_id(mut self, id: Uuid) -> Self {
+        self.filters.project_id = Some(id);
+        self
+    }
+
     pub fn categories(mut self, categories: Vec<String>) -> Self {
         self.filters.categories = Some(categories);
         self
@@ -185,16 +303,51 @@ impl SummaryQueryBuilder {
         self
     }
 
+    pub fn sources(mut self, sources: Vec<String>) -> Self {
+        self.filters.sources = Some(sources);
+        self
+    }
+
+    pub fn entity_types(mut self, types: Vec<String>) -> Self {
+        self.filters.entity_types = Some(types);
+        self
+    }
+
+    pub fn tags(mut self, tags: Vec<String>) -> Self {
+        self.filters.tags = Some(tags);
+        self
+    }
+
     pub fn group_by(mut self, field: Group) -> Self {
         self.filters.group_by = Some(field);
         self
     }
 
+    pub fn subgroup_by(mut self, fields: Vec<Group>) -> Self {
+        self.filters.subgroup_by = fields;
+        self
+    }
+
     pub fn time_bucket(mut self, bucket: TimeBucket) -> Self {
         self.filters.time_bucket = Some(bucket);
         self
     }
 
+    pub fn exclude_afk(mut self, exclude_afk: bool) -> Self {
+        self.filters.exclude_afk = exclude_afk;
+        self
+    }
+
+    pub fn timezone(mut self, tz: Tz) -> Self {
+        self.filters.timezone = Some(tz);
+        self
+    }
+
+    pub fn week_start(mut self, week_start: WeekStart) -> Self {
+        self.filters.week_start = week_start;
+        self
+    }
+
     /// Executes a query that returns only the total time (in seconds)
     /// for the current filters
     pub async fn execute_total_time(&self, db: &DBContext) -> Result<i64, DBError> {
@@ -202,7 +355,11 @@ impl SummaryQueryBuilder {
         let end = self.filters.end.unwrap_or(i64::MAX);
 
         let mut qb = QueryBuilder::<Sqlite>::new("SELECT COALESCE(SUM(");
-        qb.push_overlap_duration(&start.to_string(), &end.to_string());
+        qb.push_active_duration(
+            &start.to_string(),
+            &end.to_string(),
+            self.filters.exclude_afk,
+        );
         qb.push("), 0) AS total_seconds FROM events ");
 
         qb.append_standard_joins(None);
@@ -243,49 +400,39 @@ impl SummaryQueryBuilder {
         &self,
         db: &DBContext,
     ) -> Result<Vec<BucketTimeSummary>, DBError> {
-        let (group_key, inner_tbl) = group_key_info(self.filters.group_by);
+        let (group_key, _) = group_key_info(self.filters.group_by);
+        let (group_keys, inner_tbls) = group_keys_info(&self.filters.groups());
+        let subgroup_keys = group_keys.get(1..).unwrap_or_default();
         let needs_entity_type = matches!(self.filters.group_by, Some(Group::Entity));
 
         let range_start = self.filters.start.unwrap_or_default();
         let range_end = self.filters.end.unwrap_or_default();
-        let step = bucket_step(self.filters.time_bucket);
-
-        let mut qb = QueryBuilder::<Sqlite>::new(
-            "WITH RECURSIVE buckets(start_ts, end_ts) AS ( \
-            SELECT ",
+        let spans = bucket_spans_in(
+            self.filters.timezone,
+            self.filters.week_start,
+            DateTime::from_timestamp(range_start, 0).unwrap_or_default(),
+            DateTime::from_timestamp(range_end, 0).unwrap_or_default(),
+            self.filters.time_bucket,
         );
-        qb.push_bind(range_start)
-            .push(", MIN(")
-            .push_bind(range_end)
-            .push(", ");
-        push_next_end_with(
-            &mut qb,
-            |q| {
-                q.push_bind(range_start);
-            },
-            &step,
-        );
-        qb.push(") UNION ALL SELECT end_ts, MIN(")
-            .push_bind(range_end)
-            .push(", ");
-        push_next_end_with(
-            &mut qb,
-            |q| {
-                q.push("end_ts");
-            },
-            &step,
-        );
-        qb.push(") FROM buckets WHERE end_ts < ")
-            .push_bind(range_end)
-            .push(") ");
+        if spans.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let mut qb = QueryBuilder::<Sqlite>::new("");
+        push_buckets_cte(&mut qb, &spans);
 
-        qb.push("SELECT ");
-        qb.push_bucket_label_expr(self.filters.time_bucket);
-        qb.push(" AS bucket, ")
+        qb.push("SELECT buckets.bucket_label AS bucket, ")
             .push(group_key)
-            .push(" AS group_key, ")
-            .push("SUM(");
-        qb.push_overlap_duration("buckets.start_ts", "buckets.end_ts");
+            .push(" AS group_key, ");
+        for (i, key) in subgroup_keys.iter().enumerate() {
+            qb.push(key).push(" AS subgroup_").push(i).push(", ");
+        }
+        qb.push("SUM(");
+        qb.push_active_duration(
+            "buckets.start_ts",
+            "buckets.end_ts",
+            self.filters.exclude_afk,
+        );
         qb.push(") AS total_seconds");
 
         if needs_entity_type {
@@ -296,7 +443,7 @@ impl SummaryQueryBuilder {
 
         qb.push(" FROM buckets JOIN events ON events.end_timestamp > buckets.start_ts AND events.timestamp < buckets.end_ts ");
 
-        qb.append_standard_joins(inner_tbl);
+        qb.append_standard_joins_with(&inner_tbls);
         qb.push(" WHERE 1=1");
 
         qb.append_date_range(
@@ -307,11 +454,12 @@ impl SummaryQueryBuilder {
         );
         qb.append_all_filters(&self.filters);
 
-        qb.push(" GROUP BY ");
-        qb.push_bucket_label_expr(self.filters.time_bucket);
-        qb.push(", ").push(group_key);
+        qb.push(" GROUP BY buckets.bucket_label, ").push(group_key);
+        for key in subgroup_keys {
+            qb.push(", ").push(key);
+        }
 
-        let query = qb.build_query_as::<RawBucketRow>();
+        let query = qb.build();
 
         #[cfg(debug_assertions)]
         {
@@ -329,15 +477,283 @@ impl SummaryQueryBuilder {
 
         let mut records = Vec::with_capacity(rows.len());
         for row in rows {
+            let subgroups = (0..subgroup_keys.len())
+                .map(|i| row.try_get(format!("subgroup_{i}").as_str()))
+                .collect::<Result<Vec<String>, _>>()?;
+
             let mut grouped_values = HashMap::new();
-            grouped_values.insert(row.group_key, row.total_seconds);
+            grouped_values.insert(row.try_get("group_key")?, row.try_get("total_seconds")?);
             records.push(BucketTimeSummary {
-                bucket: row.bucket,
+                bucket: row.try_get("bucket")?,
                 grouped_values,
-                group_meta: row.group_meta,
+                subgroups,
+                group_meta: row.try_get("group_meta")?,
             });
         }
 
         Ok(records)
     }
+
+    /// Compares the per-group totals of the configured range with those of
+    /// `[previous_start, previous_end)`. Both periods share every other filter.
+    pub async fn execute_comparison(
+        &self,
+        db: &DBContext,
+        previous_start: DateTime<Utc>,
+        previous_end: DateTime<Utc>,
+    ) -> Result<SummaryComparison, DBError> {
+        let (Some(start), Some(end)) = (self.filters.start, self.filters.end) else {
+            return Err(DBError::MissingField("start"));
+        };
+
+        let previous = SummaryQueryBuilder {
+            filters: self.filters.clone(),
+        }
+        .start(previous_start.timestamp())
+        .end(previous_end.timestamp());
+
+        let current_totals = self.execute_group_totals(db).await?;
+        let previous_totals = previous.execute_group_totals(db).await?;
+
+        Ok(SummaryComparison {
+            current_start: DateTime::from_timestamp(start, 0).unwrap_or_default(),
+            current_end: DateTime::from_timestamp(end, 0).unwrap_or_default(),
+            previous_start,
+            previous_end,
+            groups: compare_groups(current_totals, previous_totals),
+        })
+    }
+
+    /// Executes every summary shown for `project` over the configured range: the total,
+    /// the first and last activity, the time per language, branch, category and source,
+    /// the `entity_limit` entities with the most time and the time per day.
+    ///
+    /// Other filters still apply. Grouping and bucketing are replaced.
+    pub async fn execute_project_stats(
+        &self,
+        db: &DBContext,
+        project: Project,
+        entity_limit: usize,
+    ) -> Result<ProjectStats, DBError> {
+        let (Some(start), Some(end)) = (self.filters.start, self.filters.end) else {
+            return Err(DBError::MissingField("start"));
+        };
+
+        let filters = SummaryFilters {
+            project_id: Some(project.id),
+            group_by: None,
+            subgroup_by: Vec::new(),
+            time_bucket: None,
+            ..self.filters.clone()
+        };
+        let scoped =
+            |group_by: Option<Group>, time_bucket: Option<TimeBucket>| SummaryQueryBuilder {
+                filters: SummaryFilters {
+                    group_by,
+                    time_bucket,
+                    ..filters.clone()
+                },
+            };
+
+        let total_seconds = scoped(None, None).execute_total_time(db).await?;
+
+        let mut qb = QueryBuilder::<Sqlite>::new("SELECT MAX(MIN(events.timestamp), ");
+        qb.push_bind(start)
+            .push("), MIN(MAX(events.end_timestamp), ")
+            .push_bind(end)
+            .push(") FROM events");
+        qb.append_standard_joins(None);
+        qb.push(" WHERE 1=1");
+        qb.append_date_range(
+            Some(start),
+            Some(end),
+            "events.timestamp",
+            "events.end_timestamp",
+        );
+        qb.append_all_filters(&filters);
+        let (first_activity, last_activity): (Option<i64>, Option<i64>) =
+            qb.build_query_as().fetch_one(db.pool()).await?;
+
+        let breakdown = |group| async move {
+            scoped(Some(group), None)
+                .execute_group_totals(db)
+                .await
+                .map(by_time)
+        };
+        let languages = breakdown(Group::Language).await?;
+        let branches = breakdown(Group::Branch).await?;
+        let categories = breakdown(Group::Category).await?;
+        let sources = breakdown(Group::Source).await?;
+        let mut top_entities = breakdown(Group::Entity).await?;
+        top_entities.truncate(entity_limit);
+
+        let days = scoped(None, Some(TimeBucket::Day));
+        let mut daily_totals: HashMap<String, i64> = days
+            .execute_range_summary_with_bucket(db)
+            .await?
+            .into_iter()
+            .map(|record| (record.bucket, record.grouped_values.into_values().sum()))
+            .collect();
+        let daily = bucket_spans_in(
+            filters.timezone,
+            filters.week_start,
+            DateTime::from_timestamp(start, 0).unwrap_or_default(),
+            DateTime::from_timestamp(end, 0).unwrap_or_default(),
+            Some(TimeBucket::Day),
+        )
+        .into_iter()
+        .map(|span| {
+            let seconds = daily_totals.remove(&span.label).unwrap_or(0);
+            (span.label, seconds)
+        })
+        .collect();
+
+        Ok(ProjectStats {
+            project,
+            total_seconds,
+            first_activity: first_activity.and_then(|ts| DateTime::from_timestamp(ts, 0)),
+            last_activity: last_activity.and_then(|ts| DateTime::from_timestamp(ts, 0)),
+            languages,
+            branches,
+            categories,
+            sources,
+            top_entities,
+            daily,
+        })
+    }
+
+    /// Executes the summary over the whole range, returning the total of each group
+    async fn execute_group_totals(&self, db: &DBContext) -> Result<HashMap<String, i64>, DBError> {
+        let whole_range = SummaryQueryBuilder {
+            filters: SummaryFilters {
+                time_bucket: None,
+                ..self.filters.clone()
+            },
+        };
+
+        let mut totals = HashMap::new();
+        for record in whole_range.execute_range_summary_with_bucket(db).await? {
+            for (group, seconds) in record.grouped_values {
+                *totals.entry(group).or_insert(0) += seconds;
+            }
+        }
+
+        Ok(totals)
+    }
+}
+
+/// Returns the totals ordered by time spent, ties broken by name
+fn by_time(totals: HashMap<String, i64>) -> Vec<(String, i64)> {
+    let mut totals: Vec<(String, i64)> = totals.into_iter().collect();
+    totals.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    totals
+}
+
+/// Pairs up the totals of both periods, including groups present in only one of them
+fn compare_groups(
+    current: HashMap<String, i64>,
+    mut previous: HashMap<String, i64>,
+) -> Vec<GroupComparison> {
+    let paired: Vec<(String, i64, i64)> = current
+        .into_iter()
+        .map(|(group, seconds)| {
+            let before = previous.remove(&group).unwrap_or(0);
+            (group, seconds, before)
+        })
+        .collect();
+
+    let mut groups: Vec<GroupComparison> = paired
+        .into_iter()
+        .chain(
+            previous
+                .into_iter()
+                .map(|(group, before)| (group, 0, before)),
+        )
+        .map(|(group, current, previous)| {
+            let delta = current - previous;
+            GroupComparison {
+                group,
+                current,
+                previous,
+                delta,
+                delta_percent: (previous > 0)
+                    .then(|| (delta as f64 / previous as f64 * 10_000.0).round() / 100.0),
+            }
+        })
+        .collect();
+
+    groups.sort_by(|a, b| {
+        b.current
+            .cmp(&a.current)
+            .then_with(|| b.previous.cmp(&a.previous))
+            .then_with(|| a.group.cmp(&b.group))
+    });
+    groups
+}
+
+#[cfg(all(test, feature = "server"))]
+mod tests {
+    use super::*;
+    use crate::server::test_utils::TestEvent;
+
+    #[test]
+    fn test_compare_groups_keeps_groups_from_either_period() {
+        let current = HashMap::from([("Rust".to_string(), 300), ("Swift".to_string(), 100)]);
+        let previous = HashMap::from([("Rust".to_string(), 200), ("Go".to_string(), 50)]);
+
+        let groups = compare_groups(current, previous);
+        let names: Vec<&str> = groups.iter().map(|g| g.group.as_str()).collect();
+        assert_eq!(names, ["Rust", "Swift", "Go"]);
+
+        assert_eq!(groups[0].delta, 100);
+        assert_eq!(groups[0].delta_percent, Some(50.0));
+        assert_eq!(groups[1].delta_percent, None);
+        assert_eq!(groups[2].delta, -50);
+        assert_eq!(groups[2].delta_percent, Some(-100.0));
+    }
+
+    #[tokio::test]
+    async fn test_project_stats_are_scoped_to_project_id() {
+        let db = DBContext::new("sqlite::memory:").await.unwrap();
+        let day = 86_400;
+        let id = TestEvent::new(3_600, 4_200)
+            .insert(&db)
+            .await
+            .project_id
+            .unwrap();
+        TestEvent::new(day + 3_600, day + 3_900)
+            .entity("lib.rs")
+            .insert(&db)
+            .await;
+        TestEvent::new(day + 7_200, day + 7_500).insert(&db).await;
+        // Another checkout with the same name
+        TestEvent::new(3_600, 9_000)
+            .project_path("/other/api")
+            .insert(&db)
+            .await;
+
+        let project = Project {
+            id,
+            name: "api".into(),
+            root_path: Some("/work/api".into()),
+            last_updated: None,
+        };
+        let stats = SummaryQueryBuilder::new()
+            .start(0)
+            .end(3 * day)
+            .timezone(Tz::UTC)
+            .execute_project_stats(&db, project, 1)
+            .await
+            .unwrap();
+
+        assert_eq!(stats.total_seconds, 1_200);
+        assert_eq!(stats.first_activity.unwrap().timestamp(), 3_600);
+        assert_eq!(stats.last_activity.unwrap().timestamp(), day + 7_500);
+        assert_eq!(stats.languages, [("Rust".to_string(), 1_200)]);
+        assert_eq!(stats.branches, [("main".to_string(), 1_200)]);
+        assert_eq!(stats.top_entities, [("main.rs".to_string(), 900)]);
+        let daily: Vec<i64> = stats.daily.iter().map(|(_, seconds)| *seconds).collect();
+        assert_eq!(daily, [600, 600, 0]);
+        assert_eq!(stats.daily[0].0, "1970-01-01");
+    }
 }
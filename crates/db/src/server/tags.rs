@@ -0,0 +1,448 @@
+use std::collections::HashMap;
+
+use chrono::Utc;
+use common::models::Tag;
+use sqlx::{QueryBuilder, Sqlite, SqliteConnection};
+use uuid::Uuid;
+
+use crate::{DBContext, error::DBError};
+
+/// Maximum number of rows written or looked up by a single statement
+const CHUNK_SIZE: usize = 300;
+
+#[derive(Debug, sqlx::FromRow)]
+struct TagRow {
+    id: Uuid,
+    name: String,
+}
+
+impl From<TagRow> for Tag {
+    fn from(row: TagRow) -> Self {
+        Tag {
+            id: row.id,
+            name: row.name,
+        }
+    }
+}
+
+/// Free-form labels linked to events and projects.
+///
+/// Names are unique regardless of ASCII case, so `Client-Acme` and `client-acme` are
+/// the same tag.
+pub struct Tags;
+
+impl Tags {
+    pub async fn find_by_id(db: &DBContext, id: Uuid) -> Result<Option<Tag>, DBError> {
+        let row: Option<TagRow> = sqlx::query_as("SELECT id, name FROM tags WHERE id = ?")
+            .bind(id)
+            .fetch_optional(db.pool())
+            .await?;
+
+        Ok(row.map(Into::into))
+    }
+
+    pub async fn find_by_name(db: &DBContext, name: &str) -> Result<Option<Tag>, DBError> {
+        let row: Option<TagRow> = sqlx::query_as("SELECT id, name FROM tags WHERE name = ?")
+            .bind(name.trim())
+            .fetch_optional(db.pool())
+            .await?;
+
+        Ok(row.map(Into::into))
+    }
+
+    /// Returns the tag named `name`, creating it if needed
+    pub async fn create(db: &DBContext, name: &str) -> Result<Tag, DBError> {
+        let names = tag_names([name]);
+        if names.is_empty() {
+            return Err(DBError::MissingField("name"));
+        }
+
+        let mut conn = db.pool().acquire().await?;
+        Ok(resolve_tags(&mut conn, &names).await?.remove(0))
+    }
+
+    /// Renames a tag, returning `None` if it does not exist
+    pub async fn rename(db: &DBContext, id: Uuid, name: &str) -> Result<Option<Tag>, DBError> {
+        let name = name.trim();
+        if name.is_empty() {
+            return Err(DBError::MissingField("name"));
+        }
+
+        let result = sqlx::query("UPDATE tags SET name = ?, last_updated = ? WHERE id = ?")
+            .bind(name)
+            .bind(Utc::now().timestamp())
+            .bind(id)
+            .execute(db.pool())
+            .await?;
+        if result.rows_affected() == 0 {
+            return Ok(None);
+        }
+
+        Self::find_by_id(db, id).await
+    }
+
+    /// Deletes a tag and its links, returning `false` if it does not exist
+    pub async fn delete(db: &DBContext, id: Uuid) -> Result<bool, DBError> {
+        let result = sqlx::query("DELETE FROM tags WHERE id = ?")
+            .bind(id)
+            .execute(db.pool())
+            .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Lists the tags of a project by name
+    pub async fn for_project(db: &DBContext, project_id: Uuid) -> Result<Vec<Tag>, DBError> {
+        let rows: Vec<TagRow> = sqlx::query_as(
+            "SELECT tags.id, tags.name FROM project_tags
+            JOIN tags ON tags.id = project_tags.tag_id
+            WHERE project_tags.project_id = ?
+            ORDER BY tags.name COLLATE NOCASE",
+        )
+        .bind(project_id)
+        .fetch_all(db.pool())
+        .await?;
+
+        Ok(rows.into_iter().map(Into::into).collect())
+    }
+
+    /// Replaces the tags of a project, creating missing tags. Every event of the
+    /// project carries them.
+    pub async fn set_for_project(
+        db: &DBContext,
+        project_id: Uuid,
+        names: &[String],
+    ) -> Result<Vec<Tag>, DBError> {
+        let mut tx = db.pool().begin().await?;
+        replace_links(&mut tx, "project_tags", "project_id", project_id, names).await?;
+        tx.commit().await?;
+        Self::for_project(db, project_id).await
+    }
+
+    /// Adds tags to events, creating missing tags. Tags already on an event are kept.
+    pub async fn tag_events(db: &DBContext, links: &[(Uuid, &[String])]) -> Result<(), DBError> {
+        let names = tag_names(links.iter().flat_map(|(_, names)| names.iter()));
+        if names.is_empty() {
+            return Ok(());
+        }
+
+        let mut tx = db.pool().begin().await?;
+        let ids: HashMap<String, Uuid> = resolve_tags(&mut tx, &names)
+            .await?
+            .into_iter()
+            .map(|tag| (tag.name.to_ascii_lowercase(), tag.id))
+            .collect();
+
+        let pairs: Vec<(Uuid, Uuid)> = links
+            .iter()
+            .flat_map(|(event_id, names)| {
+                tag_names(names.iter())
+                    .into_iter()
+                    .filter_map(|name| ids.get(&name.to_ascii_lowercase()))
+                    .map(|tag_id| (*event_id, *tag_id))
+                    .collect::<Vec<_>>()
+            })
+            .collect();
+
+        for chunk in pairs.chunks(CHUNK_SIZE) {
+            let mut qb = QueryBuilder::<Sqlite>::new("INSERT INTO event_tags (event_id, tag_id) ");
+            qb.push_values(chunk, |mut b, (event_id, tag_id)| {
+                b.push_bind(*event_id).push_bind(*tag_id);
+            });
+            qb.push(" ON CONFLICT DO NOTHING");
+            qb.build().execute(&mut *tx).await?;
+        }
+
+        tx.commit().await?;
+        Ok(())
+    }
+}
+
+/// Names of the tags an event carries itself, by name
+pub(crate) async fn event_tag_names(
+    conn: &mut SqliteConnection,
+    event_id: Uuid,
+) -> Result<Vec<String>, DBError> {
+    let names = sqlx::query_scalar(
+        "SELECT tags.name FROM event_tags
+        JOIN tags ON tags.id = event_tags.tag_id
+        WHERE event_tags.event_id = ?
+        ORDER BY tags.name COLLATE NOCASE",
+    )
+    .bind(event_id)
+    .fetch_all(&mut *conn)
+    .await?;
+
+    Ok(names)
+}
+
+/// Replaces the tags an event carries itself, creating missing tags
+pub(crate) async fn set_event_tags(
+    conn: &mut SqliteConnection,
+    event_id: Uuid,
+    names: &[String],
+) -> Result<(), DBError> {
+    replace_links(conn, "event_tags", "event_id", event_id, names).await?;
+    Ok(())
+}
+
+/// Adds tags to an event, creating missing tags. Tags already on the event are kept.
+pub(crate) async fn add_event_tags(
+    conn: &mut SqliteConnection,
+    event_id: Uuid,
+    names: &[String],
+) -> Result<(), DBError> {
+    let tags = resolve_tags(conn, &tag_names(names)).await?;
+    insert_links(conn, "event_tags", "event_id", event_id, &tags).await
+}
+
+/// Adds tags to a project, creating missing tags. Tags already on the project are kept.
+pub(crate) async fn add_project_tags(
+    conn: &mut SqliteConnection,
+    project_id: Uuid,
+    names: &[String],
+) -> Result<(), DBError> {
+    let tags = resolve_tags(conn, &tag_names(names)).await?;
+    insert_links(conn, "project_tags", "project_id", project_id, &tags).await
+}
+
+/// Replaces the tags a rule adds, creating missing tags. Returns their stored names.
+pub(crate) async fn set_rule_tags(
+    conn: &mut SqliteConnection,
+    rule_id: Uuid,
+    names: &[String],
+) -> Result<Vec<String>, DBError> {
+    let tags = replace_links(conn, "rule_tags", "rule_id", rule_id, names).await?;
+    Ok(tags.into_iter().map(|tag| tag.name).collect())
+}
+
+/// Names of the tags each rule adds, by rule id
+pub(crate) async fn rule_tag_names(db: &DBContext) -> Result<HashMap<Uuid, Vec<String>>, DBError> {
+    let rows: Vec<(Uuid, String)> = sqlx::query_as(
+        "SELECT rule_tags.rule_id, tags.name FROM rule_tags
+        JOIN tags ON tags.id = rule_tags.tag_id
+        ORDER BY tags.name COLLATE NOCASE",
+    )
+    .fetch_all(db.pool())
+    .await?;
+
+    let mut names: HashMap<Uuid, Vec<String>> = HashMap::new();
+    for (rule_id, name) in rows {
+        names.entry(rule_id).or_default().push(name);
+    }
+    Ok(names)
+}
+
+/// Gives `target` the tags of every event in `sources`, e.g. the parts of a split or
+/// the events of a merge
+pub(crate) async fn copy_event_tags(
+    conn: &mut SqliteConnection,
+    sources: &[Uuid],
+    target: Uuid,
+) -> Result<(), DBError> {
+    for chunk in sources.chunks(CHUNK_SIZE) {
+        let mut qb = QueryBuilder::<Sqlite>::new(
+            "INSERT INTO event_tags (event_id, tag_id) SELECT DISTINCT ",
+        );
+        qb.push_bind(target)
+            .push(", tag_id FROM event_tags WHERE event_id IN (");
+        let mut separated = qb.separated(", ");
+        for id in chunk {
+            separated.push_bind(*id);
+        }
+        qb.push(") ON CONFLICT DO NOTHING");
+        qb.build().execute(&mut *conn).await?;
+    }
+
+    Ok(())
+}
+
+/// Trimmed, non-empty tag names without case-insensitive duplicates, in order
+pub fn tag_names<'a, I, S>(names: I) -> Vec<&'a str>
+where
+    I: IntoIterator<Item = &'a S>,
+    S: AsRef<str> + ?Sized + 'a,
+{
+    let mut unique: Vec<&str> = Vec::new();
+    for name in names {
+        let name = name.as_ref().trim();
+        if !name.is_empty() && !unique.iter().any(|u| u.eq_ignore_ascii_case(name)) {
+            unique.push(name);
+        }
+    }
+    unique
+}
+
+/// Returns the tags named `names`, in order, creating the missing ones
+async fn resolve_tags(conn: &mut SqliteConnection, names: &[&str]) -> Result<Vec<Tag>, DBError> {
+    let mut found: HashMap<String, Tag> = HashMap::with_capacity(names.len());
+    let now = Utc::now().timestamp();
+
+    for chunk in names.chunks(CHUNK_SIZE) {
+        let mut qb = QueryBuilder::<Sqlite>::new("INSERT INTO tags (id, name, last_updated) ");
+        qb.push_values(chunk, |mut b, name| {
+            b.push_bind(Uuid::now_v7()).push_bind(*name).push_bind(now);
+        });
+        qb.push(" ON CONFLICT(name) DO NOTHING");
+        qb.build().execute(&mut *conn).await?;
+
+        let mut qb = QueryBuilder::<Sqlite>::new("SELECT id, name FROM tags WHERE name IN (");
+        let mut separated = qb.separated(", ");
+        for name in chunk {
+            separated.push_bind(*name);
+        }
+        qb.push(")");
+
+        let rows: Vec<TagRow> = qb.build_query_as().fetch_all(&mut *conn).await?;
+        for row in rows {
+            found.insert(row.name.to_ascii_lowercase(), row.into());
+        }
+    }
+
+    Ok(names
+        .iter()
+        .filter_map(|name| found.get(&name.to_ascii_lowercase()).cloned())
+        .collect())
+}
+
+/// Replaces the tags linked to `owner_id` through `table` with `names`
+async fn replace_links(
+    conn: &mut SqliteConnection,
+    table: &str,
+    owner_column: &str,
+    owner_id: Uuid,
+    names: &[String],
+) -> Result<Vec<Tag>, DBError> {
+    let tags = resolve_tags(conn, &tag_names(names)).await?;
+
+    let mut qb = QueryBuilder::<Sqlite>::new("DELETE FROM ");
+    qb.push(table)
+        .push(" WHERE ")
+        .push(owner_column)
+        .push(" = ");
+    qb.push_bind(owner_id);
+    qb.build().execute(&mut *conn).await?;
+
+    insert_links(conn, table, owner_column, owner_id, &tags).await?;
+    Ok(tags)
+}
+
+/// Links `owner_id` to every tag in `tags` through `table`
+async fn insert_links(
+    conn: &mut SqliteConnection,
+    table: &str,
+    owner_column: &str,
+    owner_id: Uuid,
+    tags: &[Tag],
+) -> Result<(), DBError> {
+    if tags.is_empty() {
+        return Ok(());
+    }
+
+    let mut qb = QueryBuilder::<Sqlite>::new("INSERT INTO ");
+    qb.push(table)
+        .push(" (")
+        .push(owner_column)
+        .push(", tag_id) ");
+    qb.push_values(tags, |mut b, tag| {
+        b.push_bind(owner_id).push_bind(tag.id);
+    });
+    qb.push(" ON CONFLICT DO NOTHING");
+    qb.build().execute(&mut *conn).await?;
+
+    Ok(())
+}
+
+#[cfg(all(test, feature = "server"))]
+mod tests {
+    use super::*;
+    use crate::server::{summary::SummaryQueryBuilder, test_utils::TestEvent};
+    use common::models::Group;
+
+    async fn totals_by_tag(db: &DBContext, tags: Option<Vec<String>>) -> Vec<(String, i64)> {
+        let mut builder = SummaryQueryBuilder::new()
+            .start(0)
+            .end(10_000)
+            .group_by(Group::Tag);
+        if let Some(tags) = tags {
+            builder = builder.tags(tags);
+        }
+
+        let mut totals: Vec<(String, i64)> = builder
+            .execute_range_summary_with_bucket(db)
+            .await
+            .unwrap()
+            .into_iter()
+            .flat_map(|row| row.grouped_values)
+            .collect();
+        totals.sort();
+        totals
+    }
+
+    #[tokio::test]
+    async fn test_events_carry_their_own_and_project_tags() {
+        let db = DBContext::new("sqlite::memory:").await.unwrap();
+        let api = TestEvent::new(0, 600)
+            .tags(&["urgent"])
+            .insert(&db)
+            .await
+            .project_id
+            .unwrap();
+        TestEvent::new(1_000, 1_300).insert(&db).await;
+        TestEvent::new(2_000, 2_100)
+            .project("web")
+            .tags(&["Client-Acme", "client-acme "])
+            .insert(&db)
+            .await;
+        TestEvent::new(3_000, 3_050)
+            .project("scratch")
+            .insert(&db)
+            .await;
+
+        let tags = Tags::set_for_project(&db, api, &["client-acme".into()])
+            .await
+            .unwrap();
+        assert_eq!(tags.len(), 1);
+        assert_eq!(tags[0].name, "Client-Acme");
+
+        assert_eq!(
+            totals_by_tag(&db, None).await,
+            [("Client-Acme".into(), 1_000), ("urgent".into(), 600)]
+        );
+        assert_eq!(
+            totals_by_tag(&db, Some(vec!["!urgent".into()])).await,
+            [("Client-Acme".into(), 400)]
+        );
+
+        let total: i64 = SummaryQueryBuilder::new()
+            .start(0)
+            .end(10_000)
+            .tags(vec!["Client-*".into()])
+            .execute_total_time(&db)
+            .await
+            .unwrap();
+        assert_eq!(total, 1_000);
+    }
+
+    #[tokio::test]
+    async fn test_deleting_a_tag_removes_its_links() {
+        let db = DBContext::new("sqlite::memory:").await.unwrap();
+        let id = TestEvent::new(0, 600)
+            .tags(&["urgent", "review"])
+            .insert(&db)
+            .await
+            .id;
+        let urgent = Tags::find_by_name(&db, "URGENT").await.unwrap().unwrap();
+
+        assert!(Tags::delete(&db, urgent.id).await.unwrap());
+        assert!(!Tags::delete(&db, urgent.id).await.unwrap());
+
+        let mut conn = db.pool().acquire().await.unwrap();
+        assert_eq!(event_tag_names(&mut conn, id).await.unwrap(), ["review"]);
+    }
+
+    #[test]
+    fn test_tag_names_trims_and_dedups() {
+        assert_eq!(tag_names([" a ", "", "B", "b", "a"]), ["a", "B"]);
+    }
+}
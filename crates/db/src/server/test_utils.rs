@@ -0,0 +1,120 @@
+use chrono::DateTime;
+use common::models::inputs::EventInput;
+
+use crate::{
+    DBContext,
+    server::{dimensions::resolve_dimensions, events::Event, tags::Tags},
+};
+
+/// Builds an event for tests and stores it the way ingest does. Defaults to one minute
+/// of `Coding` in `Code` on `main.rs` of project `api`, on branch `main` in Rust.
+pub(crate) struct TestEvent {
+    input: EventInput,
+    start: i64,
+    end: i64,
+}
+
+impl TestEvent {
+    /// An event over `[start, end)`
+    pub(crate) fn new(start: i64, end: i64) -> Self {
+        Self {
+            input: EventInput {
+                timestamp: DateTime::from_timestamp(start, 0),
+                duration: Some(end - start),
+                category: "Coding".into(),
+                app_name: "Code".into(),
+                entity_name: "main.rs".into(),
+                entity_type: "File".into(),
+                project_name: "api".into(),
+                project_path: "/work/api".into(),
+                branch_name: Some("main".into()),
+                language_name: Some("Rust".into()),
+                source_name: "skopio-vscode".into(),
+                end_timestamp: DateTime::from_timestamp(end, 0),
+                tags: Vec::new(),
+            },
+            start,
+            end,
+        }
+    }
+
+    /// An event of `input` over `[start, end)`, ignoring its own span
+    pub(crate) fn from_input(input: &EventInput, start: i64, end: i64) -> Self {
+        Self {
+            input: input.clone(),
+            start,
+            end,
+        }
+    }
+
+    pub(crate) fn app(mut self, name: &str) -> Self {
+        self.input.app_name = name.into();
+        self
+    }
+
+    pub(crate) fn category(mut self, name: &str) -> Self {
+        self.input.category = name.into();
+        self
+    }
+
+    pub(crate) fn entity(mut self, name: &str) -> Self {
+        self.input.entity_name = name.into();
+        self
+    }
+
+    /// Sets the project name, rooted at `/work/<name>`
+    pub(crate) fn project(mut self, name: &str) -> Self {
+        self.input.project_name = name.into();
+        self.input.project_path = format!("/work/{name}");
+        self
+    }
+
+    pub(crate) fn project_path(mut self, path: &str) -> Self {
+        self.input.project_path = path.into();
+        self
+    }
+
+    pub(crate) fn branch(mut self, name: Option<&str>) -> Self {
+        self.input.branch_name = name.map(Into::into);
+        self
+    }
+
+    pub(crate) fn language(mut self, name: &str) -> Self {
+        self.input.language_name = Some(name.into());
+        self
+    }
+
+    pub(crate) fn source(mut self, name: &str) -> Self {
+        self.input.source_name = name.into();
+        self
+    }
+
+    pub(crate) fn tags(mut self, names: &[&str]) -> Self {
+        self.input.tags = names.iter().map(|t| t.to_string()).collect();
+        self
+    }
+
+    /// Stores the event with its tags, returning the stored row
+    pub(crate) async fn insert(self, db: &DBContext) -> Event {
+        let dims = resolve_dimensions(db, &[&self.input])
+            .await
+            .unwrap()
+            .remove(0);
+
+        let event = Event::from_dimensions(
+            &dims,
+            self.start,
+            Some(self.end - self.start),
+            Some(self.end),
+        );
+        Event::bulk_create(db, std::slice::from_ref(&event))
+            .await
+            .unwrap();
+        if !self.input.tags.is_empty() {
+            Tags::tag_events(db, &[(event.id, self.input.tags.as_slice())])
+                .await
+                .unwrap();
+        }
+        event
+    }
+}
@@ -0,0 +1,277 @@
+use chrono::DateTime;
+use common::{
+    models::{
+        DimensionSort, Group,
+        inputs::DimensionListQuery,
+        outputs::{DimensionPage, DimensionUsage},
+    },
+    time::TimeRange,
+};
+use sqlx::{QueryBuilder, Sqlite};
+use uuid::Uuid;
+
+use crate::{
+    DBContext,
+    error::DBError,
+    server::utils::query::{EVENT_TAG_IDS, QueryBuilderExt},
+};
+
+/// Rows per page when a listing sets no `limit`
+const DEFAULT_LIST_LIMIT: u32 = 100;
+
+/// Largest `limit` a listing may ask for
+const MAX_LIST_LIMIT: u32 = 1000;
+
+#[derive(Debug, sqlx::FromRow)]
+struct UsageRow {
+    id: Uuid,
+    name: String,
+    entity_type: Option<String>,
+    total_seconds: Option<i64>,
+    last_seen: Option<i64>,
+}
+
+/// Lists the rows of a dimension table, optionally with the time spent on each
+/// within a range
+#[derive(Debug, Clone)]
+pub struct DimensionUsageQuery {
+    /// `[start, end)` in unix seconds that usage is computed over, none without usage
+    pub range: Option<(i64, i64)>,
+    pub exclude_afk: bool,
+    pub sort: DimensionSort,
+    pub limit: u32,
+    pub offset: u32,
+}
+
+impl From<DimensionListQuery> for DimensionUsageQuery {
+    fn from(input: DimensionListQuery) -> Self {
+        let range = input.preset.map(|preset| {
+            let time_range =
+                TimeRange::resolve(preset, input.timezone, input.week_start.unwrap_or_default());
+            (time_range.start().timestamp(), time_range.end().timestamp())
+        });
+
+        Self {
+            range,
+            exclude_afk: input.exclude_afk.unwrap_or(false),
+            sort: input.sort.unwrap_or_default(),
+            limit: input
+                .limit
+                .unwrap_or(DEFAULT_LIST_LIMIT)
+                .clamp(1, MAX_LIST_LIMIT),
+            offset: input.offset.unwrap_or(0),
+        }
+    }
+}
+
+impl DimensionUsageQuery {
+    /// Fetches one page of the `group` dimension. Branches and entities are listed for
+    /// `project_id` only, other dimensions ignore it.
+    ///
+    /// Usage counts time the way summaries do: clipped to the range, with time recorded
+    /// by several sources credited once, to the source that wins it.
+    pub async fn execute(
+        &self,
+        db: &DBContext,
+        group: Group,
+        project_id: Option<Uuid>,
+    ) -> Result<DimensionPage, DBError> {
+        if self.range.is_none() && !matches!(self.sort, DimensionSort::Name) {
+            return Err(DBError::MissingField("preset"));
+        }
+
+        let (table, column) = dimension_columns(group);
+        // Tags are linked to events through their own tags and those of their project
+        let usage_key = match group {
+            Group::Tag => "tags.id".to_string(),
+            _ => format!("events.{column}"),
+        };
+        let project_id = project_id.filter(|_| matches!(group, Group::Branch | Group::Entity));
+
+        let mut qb = QueryBuilder::<Sqlite>::new("SELECT dim.id, dim.name, ");
+        qb.push(if matches!(group, Group::Entity) {
+            "dim.type"
+        } else {
+            "NULL"
+        })
+        .push(" AS entity_type, ");
+
+        match self.range {
+            Some((start, end)) => {
+                qb.push("COALESCE(usage.total_seconds, 0) AS total_seconds, usage.last_seen FROM ")
+                    .push(table)
+                    .push(" AS dim LEFT JOIN (SELECT ")
+                    .push(&usage_key)
+                    .push(" AS dim_id, SUM(");
+                qb.push_active_duration(&start.to_string(), &end.to_string(), self.exclude_afk);
+                qb.push(") AS total_seconds, MIN(MAX(events.end_timestamp), ")
+                    .push_bind(end)
+                    .push(") AS last_seen FROM events");
+                if matches!(group, Group::Tag) {
+                    qb.push(" JOIN tags ON tags.id IN (")
+                        .push(EVENT_TAG_IDS)
+                        .push(")");
+                }
+                qb.push(" WHERE 1=1");
+                qb.append_date_range(
+                    Some(start),
+                    Some(end),
+                    "events.timestamp",
+                    "events.end_timestamp",
+                );
+                if let Some(project_id) = project_id {
+                    qb.push(" AND events.project_id = ").push_bind(project_id);
+                }
+                qb.push(" GROUP BY ")
+                    .push(&usage_key)
+                    .push(") AS usage ON usage.dim_id = dim.id");
+            }
+            None => {
+                qb.push("NULL AS total_seconds, NULL AS last_seen FROM ")
+                    .push(table)
+                    .push(" AS dim");
+            }
+        }
+
+        qb.push(" WHERE 1=1");
+        if let Some(project_id) = project_id {
+            qb.push(" AND dim.project_id = ").push_bind(project_id);
+        }
+
+        qb.push(" ORDER BY ").push(match self.sort {
+            DimensionSort::Name => "",
+            DimensionSort::TotalTime => "total_seconds DESC, ",
+            DimensionSort::LastSeen => "last_seen DESC, ",
+        });
+        qb.push("dim.name COLLATE NOCASE, dim.id LIMIT ")
+            .push_bind(self.limit)
+            .push(" OFFSET ")
+            .push_bind(self.offset);
+
+        let rows: Vec<UsageRow> = qb.build_query_as().fetch_all(db.pool()).await?;
+
+        let mut count = QueryBuilder::<Sqlite>::new("SELECT COUNT(*) FROM ");
+        count.push(table);
+        if let Some(project_id) = project_id {
+            count.push(" WHERE project_id = ").push_bind(project_id);
+        }
+        let total: i64 = count.build_query_scalar().fetch_one(db.pool()).await?;
+
+        Ok(DimensionPage {
+            data: rows
+                .into_iter()
+                .map(|row| DimensionUsage {
+                    id: row.id,
+                    name: row.name,
+                    entity_type: row.entity_type,
+                    total_seconds: row.total_seconds,
+                    last_seen: row.last_seen.and_then(|ts| DateTime::from_timestamp(ts, 0)),
+                })
+                .collect(),
+            total: total as u32,
+        })
+    }
+}
+
+/// Returns the table of a dimension and the column referencing it, in `events` or,
+/// for tags, in the link tables
+pub(crate) fn dimension_columns(group: Group) -> (&'static str, &'static str) {
+    match group {
+        Group::App => ("apps", "app_id"),
+        Group::Project => ("projects", "project_id"),
+        Group::Language => ("languages", "language_id"),
+        Group::Branch => ("branches", "branch_id"),
+        Group::Category => ("categories", "category_id"),
+        Group::Entity => ("entities", "entity_id"),
+        Group::Source => ("sources", "source_id"),
+        Group::Tag => ("tags", "tag_id"),
+    }
+}
+
+#[cfg(all(test, feature = "server"))]
+mod tests {
+    use super::*;
+    use crate::server::test_utils::TestEvent;
+
+    fn query(range: Option<(i64, i64)>, sort: DimensionSort) -> DimensionUsageQuery {
+        DimensionUsageQuery {
+            range,
+            exclude_afk: false,
+            sort,
+            limit: 10,
+            offset: 0,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_lists_languages_by_usage_in_range() {
+        let db = DBContext::new("sqlite::memory:").await.unwrap();
+        TestEvent::new(0, 600).project("skopio").insert(&db).await;
+        TestEvent::new(1_000, 1_900)
+            .project("skopio")
+            .entity("app.ts")
+            .language("TypeScript")
+            .insert(&db)
+            .await;
+        // Outside the range
+        TestEvent::new(5_000, 9_000)
+            .project("skopio")
+            .entity("lib.rs")
+            .insert(&db)
+            .await;
+
+        let page = query(Some((0, 2_000)), DimensionSort::TotalTime)
+            .execute(&db, Group::Language, None)
+            .await
+            .unwrap();
+        assert_eq!(page.total, 2);
+        let rows: Vec<(&str, Option<i64>)> = page
+            .data
+            .iter()
+            .map(|l| (l.name.as_str(), l.total_seconds))
+            .collect();
+        assert_eq!(rows, [("TypeScript", Some(900)), ("Rust", Some(600))]);
+        assert_eq!(page.data[0].last_seen.unwrap().timestamp(), 1_900);
+
+        let page = query(None, DimensionSort::Name)
+            .execute(&db, Group::Language, None)
+            .await
+            .unwrap();
+        assert_eq!(page.data[0].name, "Rust");
+        assert_eq!(page.data[0].total_seconds, None);
+
+        let err = query(None, DimensionSort::LastSeen)
+            .execute(&db, Group::Language, None)
+            .await;
+        assert!(matches!(err, Err(DBError::MissingField("preset"))));
+    }
+
+    #[tokio::test]
+    async fn test_lists_entities_of_one_project() {
+        let db = DBContext::new("sqlite::memory:").await.unwrap();
+        let id = TestEvent::new(0, 600)
+            .project("skopio")
+            .insert(&db)
+            .await
+            .project_id
+            .unwrap();
+        TestEvent::new(600, 700)
+            .project("skopio")
+            .entity("lib.rs")
+            .insert(&db)
+            .await;
+        TestEvent::new(0, 900).project("other").insert(&db).await;
+
+        let mut entities = query(Some((0, 1_000)), DimensionSort::LastSeen);
+        entities.limit = 1;
+        let page = entities
+            .execute(&db, Group::Entity, Some(id))
+            .await
+            .unwrap();
+        assert_eq!(page.total, 2);
+        assert_eq!(page.data.len(), 1);
+        assert_eq!(page.data[0].name, "lib.rs");
+        assert_eq!(page.data[0].entity_type.as_deref(), Some("File"));
+        assert_eq!(page.data[0].total_seconds, Some(100));
+    }
+}
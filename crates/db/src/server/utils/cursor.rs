@@ -0,0 +1,38 @@
+use uuid::Uuid;
+
+/// Keyset cursor over rows ordered by their start timestamp and id
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EventCursor {
+    pub timestamp: i64,
+    pub id: Uuid,
+}
+
+impl EventCursor {
+    pub fn encode(self) -> String {
+        format!("{}:{}", self.timestamp, self.id)
+    }
+
+    pub fn decode(s: &str) -> Option<Self> {
+        let (ts, id) = s.split_once(':')?;
+        let timestamp = ts.parse::<i64>().ok()?;
+        let id = Uuid::parse_str(id).ok()?;
+        Some(Self { timestamp, id })
+    }
+}
+
+#[cfg(all(test, feature = "server"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cursor_round_trip() {
+        let cursor = EventCursor {
+            timestamp: 1_759_100_000,
+            id: Uuid::now_v7(),
+        };
+
+        assert_eq!(EventCursor::decode(&cursor.encode()), Some(cursor));
+        assert_eq!(EventCursor::decode("not-a-cursor"), None);
+        assert_eq!(EventCursor::decode("12:not-a-uuid"), None);
+    }
+}
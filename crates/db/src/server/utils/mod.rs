@@ -1,2 +1,3 @@
+pub mod cursor;
 pub mod query;
 pub mod summary_filter;
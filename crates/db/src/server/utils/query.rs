@@ -1,12 +1,7 @@
-use common::{models::Group, time::TimeBucket};
+use common::{models::Group, time::bucket::BucketSpan};
 use sqlx::{QueryBuilder, Sqlite};
 
-use crate::server::utils::summary_filter::SummaryFilters;
-
-pub enum BucketStep {
-    Seconds(i64),           // for Hour/Day (fixed length)
-    Calendar(&'static str), // SQLite date modifier "+7 days" | "+1 month" | "+1 year"
-}
+use crate::server::{source::source_wins_expr, utils::summary_filter::SummaryFilters};
 
 pub trait QueryBuilderExt<'qb> {
     fn append_date_range(
@@ -18,10 +13,23 @@ pub trait QueryBuilderExt<'qb> {
     );
 
     fn append_filter_list(&mut self, field: &str, values: &'qb [String]);
+    fn append_tag_filter(&mut self, values: &'qb [String]);
     fn append_all_filters(&mut self, filters: &'qb SummaryFilters);
     fn append_standard_joins(&mut self, inner_join: Option<&str>);
-    fn push_bucket_label_expr(&mut self, bucket: Option<TimeBucket>);
+    fn append_standard_joins_with(&mut self, inner_joins: &[&str]);
     fn push_overlap_duration(&mut self, range_start_field: &str, range_end_field: &str);
+    fn push_covered_duration(
+        &mut self,
+        range_start_field: &str,
+        range_end_field: &str,
+        exclude_afk: bool,
+    );
+    fn push_active_duration(
+        &mut self,
+        range_start_field: &str,
+        range_end_field: &str,
+        exclude_afk: bool,
+    );
 }
 
 impl<'qb> QueryBuilderExt<'qb> for QueryBuilder<'qb, Sqlite> {
@@ -49,20 +57,58 @@ impl<'qb> QueryBuilderExt<'qb> for QueryBuilder<'qb, Sqlite> {
         }
     }
 
-    /// Appends an `IN (...)` filter to the query if the list is not empty.
+    /// Appends a filter on `field` to the query if the list is not empty.
+    ///
+    /// Values are matched exactly unless they contain `*`, `?` or `[`, in which case they
+    /// are case-sensitive GLOB patterns (e.g. `src/**/*.rs`, or `scratch-*` for a prefix).
+    /// `*` also matches `/`, and `**/` matches zero or more directories.
+    /// A leading `!` excludes matching rows instead. Rows must match at least one included
+    /// value, if any, and none of the excluded ones; rows without the dimension are only
+    /// dropped by includes.
     fn append_filter_list(&mut self, field: &str, values: &'qb [String]) {
-        if values.is_empty() {
-            return;
+        let (excluded, included): (Vec<FilterValue>, Vec<FilterValue>) = values
+            .iter()
+            .map(|v| FilterValue::parse(v))
+            .partition(|v| v.exclude);
+
+        if !included.is_empty() {
+            self.push(" AND ");
+            push_filter_match(self, field, &included);
         }
 
-        self.push(" AND ").push(field).push(" IN (");
-        {
-            let mut sep = self.separated(", ");
-            for v in values {
-                sep.push_bind(v);
+        if !excluded.is_empty() {
+            self.push(" AND (").push(field).push(" IS NULL OR NOT (");
+            push_filter_match(self, field, &excluded);
+            self.push("))");
+        }
+    }
+
+    /// Appends a filter on the tags of events, with the values of
+    /// [`append_filter_list`](Self::append_filter_list). An event matches an included
+    /// value when one of its tags does, and is dropped when one of its tags matches an
+    /// excluded value.
+    fn append_tag_filter(&mut self, values: &'qb [String]) {
+        let (excluded, included): (Vec<FilterValue>, Vec<FilterValue>) = values
+            .iter()
+            .map(|v| FilterValue::parse(v))
+            .partition(|v| v.exclude);
+
+        for (values, negated) in [(included, false), (excluded, true)] {
+            if values.is_empty() {
+                continue;
             }
+
+            self.push(if negated {
+                " AND NOT EXISTS ("
+            } else {
+                " AND EXISTS ("
+            })
+            .push("SELECT 1 FROM tags AS filter_tags WHERE filter_tags.id IN (")
+            .push(EVENT_TAG_IDS)
+            .push(") AND ");
+            push_filter_match(self, "filter_tags.name", &values);
+            self.push(")");
         }
-        self.push(")");
     }
 
     /// Appends a full set of optional filters (apps, projects, categories, etc.)
@@ -74,6 +120,9 @@ impl<'qb> QueryBuilderExt<'qb> for QueryBuilder<'qb, Sqlite> {
         if let Some(projects) = &filters.projects {
             self.append_filter_list("projects.name", projects);
         }
+        if let Some(project_id) = filters.project_id {
+            self.push(" AND events.project_id = ").push_bind(project_id);
+        }
         if let Some(categories) = &filters.categories {
             self.append_filter_list("categories.name", categories);
         }
@@ -86,14 +135,29 @@ impl<'qb> QueryBuilderExt<'qb> for QueryBuilder<'qb, Sqlite> {
         if let Some(languages) = &filters.languages {
             self.append_filter_list("languages.name", languages);
         }
+        if let Some(sources) = &filters.sources {
+            self.append_filter_list("sources.name", sources);
+        }
+        if let Some(entity_types) = &filters.entity_types {
+            self.append_filter_list("entities.type", entity_types);
+        }
+        if let Some(tags) = &filters.tags {
+            self.append_tag_filter(tags);
+        }
     }
 
     /// Appends JOIN clauses for events to resolve all foreign keys.
     /// `inner_join` indicates which related table (if any) should be INNER JOINed
     /// instead of LEFT JOINed (i.e., the table that supplies the group_key).
     fn append_standard_joins(&mut self, inner_join: Option<&str>) {
+        self.append_standard_joins_with(inner_join.as_slice());
+    }
+
+    /// Appends the same JOIN clauses as [`append_standard_joins`](Self::append_standard_joins),
+    /// INNER JOINing every table in `inner_joins` (i.e., the tables of a composite group key).
+    fn append_standard_joins_with(&mut self, inner_joins: &[&str]) {
         let j = |tbl: &str| {
-            if inner_join == Some(tbl) {
+            if inner_joins.contains(&tbl) {
                 " JOIN "
             } else {
                 " LEFT JOIN "
@@ -114,27 +178,13 @@ impl<'qb> QueryBuilderExt<'qb> for QueryBuilder<'qb, Sqlite> {
             .push("languages ON events.language_id = languages.id")
             .push(j("sources"))
             .push("sources ON events.source_id = sources.id");
-    }
 
-    /// Formats a SQLite-compatible time bucket expression based on the bucket type.
-    fn push_bucket_label_expr(&mut self, bucket: Option<TimeBucket>) {
-        match bucket {
-            Some(TimeBucket::Hour) => self.push(
-                "strftime('%Y-%m-%d %H:00:00', datetime(buckets.start_ts,'unixepoch','localtime'))",
-            ),
-            Some(TimeBucket::Day) => self
-                .push("strftime('%Y-%m-%d', datetime(buckets.start_ts,'unixepoch','localtime'))"),
-            Some(TimeBucket::Week) => {
-                self.push("strftime('%Y-W%W', datetime(buckets.start_ts,'unixepoch','localtime'))")
-            }
-            Some(TimeBucket::Month) => {
-                self.push("strftime('%Y-%m', datetime(buckets.start_ts,'unixepoch','localtime'))")
-            }
-            Some(TimeBucket::Year) => {
-                self.push("strftime('%Y', datetime(buckets.start_ts,'unixepoch','localtime'))")
-            }
-            None => self.push("'Unbucketed'"),
-        };
+        // An event yields a row per tag, so tags are only joined to group by them
+        if inner_joins.contains(&"tags") {
+            self.push(" JOIN tags ON tags.id IN (")
+                .push(EVENT_TAG_IDS)
+                .push(")");
+        }
     }
 
     /// Appends SQL logic to calculate the overlapping duration between events and a time range.
@@ -197,8 +247,83 @@ impl<'qb> QueryBuilderExt<'qb> for QueryBuilder<'qb, Sqlite> {
         END",
         );
     }
+
+    /// Appends a correlated subquery that computes how much of an event's overlap with a
+    /// time range is covered by events from other sources that win the time and, when
+    /// `exclude_afk` is set, by AFK periods.
+    ///
+    /// Where events from two sources overlap, the time is credited to the source that wins
+    /// under the source priority policy (see `source_wins_expr`). The winning events and
+    /// AFK periods are clipped to the overlap and merged into disjoint islands before their
+    /// lengths are summed, so time covered by several of them is only counted once.
+    /// AFK periods without an end are treated as lasting for their recorded duration.
+    fn push_covered_duration(
+        &mut self,
+        range_start_field: &str,
+        range_end_field: &str,
+        exclude_afk: bool,
+    ) {
+        let lo = format!("MAX(events.timestamp, {range_start_field})");
+        let hi = format!("MIN(events.end_timestamp, {range_end_field})");
+        let afk_end =
+            "COALESCE(afk_events.afk_end, afk_events.afk_start + COALESCE(afk_events.duration, 0))";
+
+        // An interval starts a new island when it begins after every earlier one ended
+        self.push(
+            "(SELECT COALESCE(SUM(island_end - island_start), 0) FROM (\
+            SELECT MIN(cover_start) AS island_start, MAX(cover_end) AS island_end FROM (\
+            SELECT cover_start, cover_end, SUM(starts_island) OVER (\
+            ORDER BY cover_start, cover_end ROWS UNBOUNDED PRECEDING) AS island FROM (\
+            SELECT cover_start, cover_end, COALESCE(cover_start > MAX(cover_end) OVER (\
+            ORDER BY cover_start, cover_end ROWS BETWEEN UNBOUNDED PRECEDING AND 1 PRECEDING\
+            ), 1) AS starts_island FROM (",
+        );
+        self.push(format_args!(
+            "SELECT MAX({lo}, shadowing.timestamp) AS cover_start, \
+            MIN({hi}, shadowing.end_timestamp) AS cover_end \
+            FROM events AS shadowing \
+            JOIN sources AS shadowing_source ON shadowing_source.id = shadowing.source_id \
+            JOIN sources AS own_source ON own_source.id = events.source_id \
+            WHERE shadowing.source_id != events.source_id \
+            AND shadowing.timestamp < {hi} AND shadowing.end_timestamp > {lo} AND {}",
+            source_wins_expr("shadowing_source", "own_source")
+        ));
+        if exclude_afk {
+            self.push(format_args!(
+                " UNION ALL SELECT MAX({lo}, afk_events.afk_start), MIN({hi}, {afk_end}) \
+                FROM afk_events WHERE afk_events.afk_start < {hi} AND {afk_end} > {lo}"
+            ));
+        }
+        self.push(") AS covers) AS marked) AS numbered GROUP BY island) AS islands)");
+    }
+
+    /// Appends the active duration of an event within a time range: the overlap computed by
+    /// `push_overlap_duration`, minus the time covered by overlapping events from other
+    /// sources and, when `exclude_afk` is set, by AFK periods (see `push_covered_duration`).
+    ///
+    /// The cover is measured on timestamps while an event inside the range counts its
+    /// recorded duration, so the result is clamped at zero for events whose duration is
+    /// shorter than their span.
+    fn push_active_duration(
+        &mut self,
+        range_start_field: &str,
+        range_end_field: &str,
+        exclude_afk: bool,
+    ) {
+        self.push("MAX((");
+        self.push_overlap_duration(range_start_field, range_end_field);
+        self.push(") - ");
+        self.push_covered_duration(range_start_field, range_end_field, exclude_afk);
+        self.push(", 0)");
+    }
 }
 
+/// Ids of the tags of the current `events` row: its own tags and those of its project
+pub(crate) const EVENT_TAG_IDS: &str = "SELECT event_tags.tag_id FROM event_tags \
+    WHERE event_tags.event_id = events.id \
+    UNION SELECT project_tags.tag_id FROM project_tags \
+    WHERE project_tags.project_id = events.project_id";
+
 /// Returns (group_key_sql, inner_join_table_name)
 pub fn group_key_info(group: Option<Group>) -> (&'static str, Option<&'static str>) {
     match group {
@@ -209,48 +334,151 @@ pub fn group_key_info(group: Option<Group>) -> (&'static str, Option<&'static st
         Some(Group::Category) => ("categories.name", Some("categories")),
         Some(Group::Language) => ("languages.name", Some("languages")),
         Some(Group::Source) => ("sources.name", Some("sources")),
+        Some(Group::Tag) => ("tags.name", Some("tags")),
         None => ("'Total'", None),
     }
 }
 
-pub fn bucket_step(bucket: Option<TimeBucket>) -> BucketStep {
-    match bucket {
-        Some(TimeBucket::Hour) => BucketStep::Seconds(3600),
-        Some(TimeBucket::Day) => BucketStep::Seconds(86_400),
-        Some(TimeBucket::Week) => BucketStep::Calendar("+7 days"),
-        Some(TimeBucket::Month) => BucketStep::Calendar("+1 month"),
-        Some(TimeBucket::Year) => BucketStep::Calendar("+1 year"),
-        None => BucketStep::Seconds(86_400),
+/// Returns the group key of each dimension in `groups`, in order, and the tables
+/// that supply them
+pub fn group_keys_info(groups: &[Group]) -> (Vec<&'static str>, Vec<&'static str>) {
+    groups
+        .iter()
+        .filter_map(|&group| match group_key_info(Some(group)) {
+            (key, Some(tbl)) => Some((key, tbl)),
+            (_, None) => None,
+        })
+        .unzip()
+}
+
+/// Appends a `buckets(start_ts, end_ts, bucket_label)` CTE listing the given spans.
+///
+/// Bucket boundaries are computed in Rust so that they follow the requested timezone,
+/// including DST transitions. `spans` must not be empty.
+pub fn push_buckets_cte(qb: &mut QueryBuilder<Sqlite>, spans: &[BucketSpan]) {
+    qb.push("WITH buckets(start_ts, end_ts, bucket_label) AS (VALUES ");
+    for (i, span) in spans.iter().enumerate() {
+        if i > 0 {
+            qb.push(", ");
+        }
+        // Labels are generated from date formats, values are inlined to stay clear
+        // of SQLite's bound parameter limit on long ranges
+        qb.push("(")
+            .push(span.start.timestamp())
+            .push(", ")
+            .push(span.end.timestamp())
+            .push(", '")
+            .push(span.label.replace('\'', "''"))
+            .push("')");
     }
+    qb.push(") ");
 }
 
-/// Appends an SQL expression that computes the next bucket’s end timestamp
-/// based on a given start (pushed by `push_start`) and a `BucketStep`.
-/// Supports both fixed‐interval (seconds) and calendar‐aligned (`+1 month`, `+1 year`, etc.) steps.
-pub fn push_next_end_with<F>(qb: &mut QueryBuilder<Sqlite>, push_start: F, step: &BucketStep)
-where
-    F: FnOnce(&mut QueryBuilder<Sqlite>),
-{
-    match step {
-        BucketStep::Seconds(n) => {
-            push_start(qb);
-            qb.push(" + ").push(*n);
+/// A single value of a filter list
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct FilterValue<'a> {
+    value: &'a str,
+    exclude: bool,
+    glob: bool,
+}
+
+impl<'a> FilterValue<'a> {
+    fn parse(raw: &'a str) -> Self {
+        let (value, exclude) = match raw.strip_prefix('!') {
+            Some(rest) => (rest, true),
+            None => (raw, false),
+        };
+
+        Self {
+            value,
+            exclude,
+            glob: value.contains(['*', '?', '[']),
+        }
+    }
+}
+
+/// Most `**/` of one pattern that may match zero directories, each doubling the number
+/// of `GLOB`s pushed for it. Later ones need at least one directory.
+const MAX_GLOBSTARS: usize = 4;
+
+/// Expands a pattern into plain GLOB patterns, since SQLite has no `**`: every `**/`
+/// becomes either nothing or `*/`, which together match zero or more directories.
+fn glob_variants(pattern: &str) -> Vec<String> {
+    let mut variants = vec![String::new()];
+    for (i, part) in pattern.split("**/").enumerate() {
+        if i > 0 && i <= MAX_GLOBSTARS {
+            let deeper: Vec<String> = variants.iter().map(|v| format!("{v}*/")).collect();
+            for variant in deeper {
+                if !variants.contains(&variant) {
+                    variants.push(variant);
+                }
+            }
+        } else if i > 0 {
+            for variant in &mut variants {
+                variant.push_str("*/");
+            }
         }
-        BucketStep::Calendar(modif) => {
-            qb.push("strftime('%s', datetime(");
-            push_start(qb);
-            qb.push(", 'unixepoch', 'localtime', '")
-                .push(modif)
-                .push("'))");
+        for variant in &mut variants {
+            variant.push_str(part);
         }
     }
+    variants
+}
+
+/// Pushes a condition matching `field` against any of `values`: one `IN (...)` list for
+/// the exact values and one `GLOB` per pattern, or per variant of a `**/` pattern.
+fn push_filter_match<'qb>(
+    qb: &mut QueryBuilder<'qb, Sqlite>,
+    field: &str,
+    values: &[FilterValue<'qb>],
+) {
+    let exact: Vec<&str> = values.iter().filter(|v| !v.glob).map(|v| v.value).collect();
+    let patterns: Vec<String> = values
+        .iter()
+        .filter(|v| v.glob)
+        .flat_map(|v| glob_variants(v.value))
+        .collect();
+    let parts = usize::from(!exact.is_empty()) + patterns.len();
+
+    if parts > 1 {
+        qb.push("(");
+    }
+
+    let mut first = true;
+    if !exact.is_empty() {
+        qb.push(field).push(" IN (");
+        {
+            let mut sep = qb.separated(", ");
+            for v in exact {
+                sep.push_bind(v);
+            }
+        }
+        qb.push(")");
+        first = false;
+    }
+
+    for pattern in patterns {
+        if !first {
+            qb.push(" OR ");
+        }
+        qb.push(field).push(" GLOB ").push_bind(pattern);
+        first = false;
+    }
+
+    if parts > 1 {
+        qb.push(")");
+    }
 }
 
 #[cfg(all(test, feature = "server"))]
 mod tests {
     use super::*;
     use crate::server::utils::summary_filter::SummaryFilters;
-    use common::{models::Group, time::TimeBucket};
+    use chrono::{TimeZone, Utc};
+    use common::{
+        models::Group,
+        time::{TimeBucket, WeekStart, bucket::bucket_spans},
+    };
     use sqlx::{Execute, QueryBuilder, Sqlite, SqlitePool};
     use uuid::Uuid;
 
@@ -264,7 +492,19 @@ mod tests {
                     id BLOB PRIMARY KEY,
                     timestamp INTEGER NOT NULL,
                     end_timestamp INTEGER NOT NULL,
-                    duration INTEGER NOT NULL
+                    duration INTEGER NOT NULL,
+                    source_id BLOB
+                )",
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+
+        sqlx::query(
+            "CREATE TABLE sources (
+                    id BLOB PRIMARY KEY,
+                    name TEXT NOT NULL UNIQUE,
+                    priority INTEGER
                 )",
         )
         .execute(&pool)
@@ -297,6 +537,98 @@ mod tests {
         id
     }
 
+    /// Helper to insert a test event recorded by the named source, creating the source on first use
+    async fn insert_source_event(
+        pool: &SqlitePool,
+        source: &str,
+        timestamp: i64,
+        end_timestamp: i64,
+    ) {
+        sqlx::query("INSERT INTO sources (id, name) VALUES (?, ?) ON CONFLICT(name) DO NOTHING")
+            .bind(Uuid::now_v7())
+            .bind(source)
+            .execute(pool)
+            .await
+            .unwrap();
+
+        sqlx::query(
+            "INSERT INTO events (id, timestamp, end_timestamp, duration, source_id)
+                  SELECT ?, ?, ?, ?, id FROM sources WHERE name = ?",
+        )
+        .bind(Uuid::now_v7())
+        .bind(timestamp)
+        .bind(end_timestamp)
+        .bind(end_timestamp - timestamp)
+        .bind(source)
+        .execute(pool)
+        .await
+        .unwrap();
+    }
+
+    /// Helper to build and execute an active duration query for the events of one source
+    async fn calculate_source_active(
+        pool: &SqlitePool,
+        source: &str,
+        range_start: i64,
+        range_end: i64,
+        exclude_afk: bool,
+    ) -> i64 {
+        let mut qb = QueryBuilder::<Sqlite>::new("SELECT COALESCE(SUM(");
+        qb.push_active_duration(
+            &range_start.to_string(),
+            &range_end.to_string(),
+            exclude_afk,
+        );
+        qb.push(
+            "), 0) FROM events JOIN sources ON events.source_id = sources.id WHERE sources.name = ",
+        )
+        .push_bind(source)
+        .push(" AND end_timestamp > ")
+        .push_bind(range_start)
+        .push(" AND timestamp < ")
+        .push_bind(range_end);
+
+        qb.build_query_scalar().fetch_one(pool).await.unwrap()
+    }
+
+    /// Helper to insert a test AFK period, creating the table on first use
+    async fn insert_afk(pool: &SqlitePool, afk_start: i64, afk_end: Option<i64>, duration: i64) {
+        sqlx::query(
+            "CREATE TABLE IF NOT EXISTS afk_events (
+                    id BLOB PRIMARY KEY,
+                    afk_start INTEGER NOT NULL,
+                    afk_end INTEGER,
+                    duration INTEGER
+                )",
+        )
+        .execute(pool)
+        .await
+        .unwrap();
+
+        sqlx::query(
+            "INSERT INTO afk_events (id, afk_start, afk_end, duration) VALUES (?, ?, ?, ?)",
+        )
+        .bind(Uuid::now_v7())
+        .bind(afk_start)
+        .bind(afk_end)
+        .bind(duration)
+        .execute(pool)
+        .await
+        .unwrap();
+    }
+
+    /// Helper to build and execute an active duration query that excludes AFK time
+    async fn calculate_active(pool: &SqlitePool, range_start: i64, range_end: i64) -> i64 {
+        let mut qb = QueryBuilder::<Sqlite>::new("SELECT COALESCE(SUM(");
+        qb.push_active_duration(&range_start.to_string(), &range_end.to_string(), true);
+        qb.push("), 0) FROM events WHERE end_timestamp > ")
+            .push_bind(range_start)
+            .push(" AND timestamp < ")
+            .push_bind(range_end);
+
+        qb.build_query_scalar().fetch_one(pool).await.unwrap()
+    }
+
     /// Helper to build and execute overlap query
     async fn calculate_overlap(pool: &SqlitePool, range_start: i64, range_end: i64) -> i64 {
         let mut qb = QueryBuilder::<Sqlite>::new("SELECT COALESCE(SUM(");
@@ -520,6 +852,117 @@ mod tests {
         );
     }
 
+    #[sqlx::test]
+    async fn test_active_duration_subtracts_afk_inside_range() {
+        let pool = setup_test_db().await;
+
+        // Event: 1000-2000, AFK: 1200-1500 and 1800-2600 (open-ended, duration 800)
+        // Range: 500-2200
+        // Expected: 1000 - 300 - 200 = 500
+        insert_event(&pool, 1000, 2000, 1000).await;
+        insert_afk(&pool, 1200, Some(1500), 300).await;
+        insert_afk(&pool, 1800, None, 800).await;
+
+        let active = calculate_active(&pool, 500, 2200).await;
+        assert_eq!(active, 500, "AFK time within the event should be removed");
+    }
+
+    #[sqlx::test]
+    async fn test_active_duration_clips_afk_to_range() {
+        let pool = setup_test_db().await;
+
+        // Event: 1000-3000, AFK: 500-1500
+        // Range: 1200-2000
+        // Expected: 800 - (1500 - 1200) = 500
+        insert_event(&pool, 1000, 3000, 2000).await;
+        insert_afk(&pool, 500, Some(1500), 1000).await;
+
+        let active = calculate_active(&pool, 1200, 2000).await;
+        assert_eq!(
+            active, 500,
+            "AFK time outside the range should not be removed"
+        );
+    }
+
+    #[sqlx::test]
+    async fn test_active_duration_is_never_negative() {
+        let pool = setup_test_db().await;
+
+        // Event: 1000-2000 with a recorded duration of 300, AFK: 1000-1900
+        // Range: 0-3000
+        // Expected: 0 rather than 300 - 900
+        insert_event(&pool, 1000, 2000, 300).await;
+        insert_afk(&pool, 1000, Some(1900), 900).await;
+
+        let active = calculate_active(&pool, 0, 3000).await;
+        assert_eq!(active, 0, "AFK time should not make active time negative");
+    }
+
+    #[sqlx::test]
+    async fn test_active_duration_credits_plugin_over_desktop() {
+        let pool = setup_test_db().await;
+
+        // Desktop: 1000-2000, plugin: 1200-1500
+        // Range: 0-3000
+        // Expected: desktop loses the 300 seconds the plugin reports
+        insert_source_event(&pool, "skopio-desktop", 1000, 2000).await;
+        insert_source_event(&pool, "skopio-vscode", 1200, 1500).await;
+
+        let desktop = calculate_source_active(&pool, "skopio-desktop", 0, 3000, false).await;
+        let plugin = calculate_source_active(&pool, "skopio-vscode", 0, 3000, false).await;
+        assert_eq!(
+            desktop, 700,
+            "Overlap should be removed from the desktop source"
+        );
+        assert_eq!(plugin, 300, "Plugin should keep its full duration");
+    }
+
+    #[sqlx::test]
+    async fn test_active_duration_respects_configured_priority() {
+        let pool = setup_test_db().await;
+
+        // Desktop: 1000-2000 with raised priority, plugin: 1200-1500
+        // Range: 1300-3000
+        // Expected: plugin loses its 200 seconds inside the range
+        insert_source_event(&pool, "skopio-desktop", 1000, 2000).await;
+        insert_source_event(&pool, "skopio-vscode", 1200, 1500).await;
+        sqlx::query("UPDATE sources SET priority = 20 WHERE name = 'skopio-desktop'")
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        let desktop = calculate_source_active(&pool, "skopio-desktop", 1300, 3000, false).await;
+        let plugin = calculate_source_active(&pool, "skopio-vscode", 1300, 3000, false).await;
+        assert_eq!(desktop, 700);
+        assert_eq!(plugin, 0);
+    }
+
+    #[sqlx::test]
+    async fn test_active_duration_counts_shared_cover_once() {
+        let pool = setup_test_db().await;
+
+        // Desktop: 1000-2000, jetbrains: 1100-1600, vscode: 1300-1800, AFK: 1700-1900
+        // Range: 0-3000
+        // Jetbrains wins over vscode by name, both win over desktop. The desktop is
+        // covered over 1100-1900 once, not once per plugin plus the AFK time.
+        insert_source_event(&pool, "skopio-desktop", 1000, 2000).await;
+        insert_source_event(&pool, "skopio-jetbrains", 1100, 1600).await;
+        insert_source_event(&pool, "skopio-vscode", 1300, 1800).await;
+        insert_afk(&pool, 1700, Some(1900), 200).await;
+
+        let desktop = calculate_source_active(&pool, "skopio-desktop", 0, 3000, true).await;
+        let jetbrains = calculate_source_active(&pool, "skopio-jetbrains", 0, 3000, true).await;
+        let vscode = calculate_source_active(&pool, "skopio-vscode", 0, 3000, true).await;
+        assert_eq!(desktop, 200);
+        assert_eq!(jetbrains, 500);
+        assert_eq!(vscode, 100);
+        assert_eq!(
+            desktop + jetbrains + vscode,
+            800,
+            "Active time should add up to the tracked time outside AFK"
+        );
+    }
+
     #[test]
     fn test_append_date_range_both_bounds() {
         let mut qb = QueryBuilder::<Sqlite>::new("SELECT * FROM events WHERE 1=1");
@@ -589,6 +1032,112 @@ mod tests {
         assert_eq!(original_sql, qb.build().sql());
     }
 
+    #[test]
+    fn test_append_filter_list_patterns_and_excludes() {
+        let mut qb = QueryBuilder::<Sqlite>::new("SELECT * FROM events WHERE 1=1");
+        let entities = vec![
+            "src/**/*.rs".to_string(),
+            "Cargo.toml".to_string(),
+            "!src/generated/*".to_string(),
+        ];
+        qb.append_filter_list("entities.name", &entities);
+
+        let sql = qb.build().sql();
+        assert!(sql.contains(
+            "AND (entities.name IN (?) OR entities.name GLOB ? OR entities.name GLOB ?)"
+        ));
+        assert!(sql.contains("AND (entities.name IS NULL OR NOT (entities.name GLOB ?))"));
+    }
+
+    #[test]
+    fn test_glob_variants_expand_globstars() {
+        assert_eq!(glob_variants("scratch-*"), ["scratch-*"]);
+        assert_eq!(glob_variants("src/**/*.rs"), ["src/*.rs", "src/*/*.rs"]);
+        assert_eq!(
+            glob_variants("**/tests/**/*.rs"),
+            [
+                "tests/*.rs",
+                "*/tests/*.rs",
+                "tests/*/*.rs",
+                "*/tests/*/*.rs"
+            ]
+        );
+        // Repeated globstars collapse into the same variants
+        assert_eq!(glob_variants("**/**/a"), ["a", "*/a", "*/*/a"]);
+    }
+
+    /// Helper returning the project names of the `projects` table matching a filter list
+    async fn filtered_names(pool: &SqlitePool, filter: &[&str]) -> Vec<Option<String>> {
+        let values: Vec<String> = filter.iter().map(|v| v.to_string()).collect();
+        let mut qb = QueryBuilder::<Sqlite>::new("SELECT name FROM projects WHERE 1=1");
+        qb.append_filter_list("projects.name", &values);
+        qb.push(" ORDER BY name");
+
+        qb.build_query_scalar().fetch_all(pool).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_append_filter_list_matches_rows() {
+        let pool = SqlitePool::connect(":memory:").await.unwrap();
+        sqlx::query("CREATE TABLE projects (name TEXT)")
+            .execute(&pool)
+            .await
+            .unwrap();
+        sqlx::query(
+            "INSERT INTO projects (name) VALUES ('skopio'), ('scratch-1'), ('scratch-2'), (NULL)",
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+
+        assert_eq!(
+            filtered_names(&pool, &["scratch-*"]).await,
+            [Some("scratch-1".into()), Some("scratch-2".into())]
+        );
+        assert_eq!(
+            filtered_names(&pool, &["!scratch-*"]).await,
+            [None, Some("skopio".into())]
+        );
+        assert_eq!(
+            filtered_names(&pool, &["s*", "!scratch-2"]).await,
+            [Some("scratch-1".into()), Some("skopio".into())]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_append_filter_list_globstar_matches_any_depth() {
+        let pool = SqlitePool::connect(":memory:").await.unwrap();
+        sqlx::query("CREATE TABLE projects (name TEXT)")
+            .execute(&pool)
+            .await
+            .unwrap();
+        sqlx::query(
+            "INSERT INTO projects (name) VALUES
+            ('src/main.rs'), ('src/db/mod.rs'), ('src/db/server/tags.rs'), ('src/notes.md'),
+            ('tests/api.rs')",
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+
+        assert_eq!(
+            filtered_names(&pool, &["src/**/*.rs"]).await,
+            [
+                Some("src/db/mod.rs".into()),
+                Some("src/db/server/tags.rs".into()),
+                Some("src/main.rs".into())
+            ]
+        );
+        assert_eq!(
+            filtered_names(&pool, &["*.rs", "!src/**/server/*"]).await,
+            [
+                Some("src/db/mod.rs".into()),
+                Some("src/main.rs".into()),
+                Some("tests/api.rs".into())
+            ]
+        );
+    }
+
     #[test]
     fn test_append_all_filters() {
         let mut qb = QueryBuilder::<Sqlite>::new("SELECT * FROM events WHERE 1=1");
@@ -599,6 +1148,9 @@ mod tests {
             entities: None,
             branches: None,
             languages: Some(vec!["Rust".to_string()]),
+            sources: Some(vec!["!skopio-desktop".to_string()]),
+            entity_types: Some(vec!["File".to_string()]),
+            tags: Some(vec!["client-*".to_string(), "!internal".to_string()]),
             ..Default::default()
         };
 
@@ -608,6 +1160,11 @@ mod tests {
         assert!(sql.contains("apps.name IN ("));
         assert!(sql.contains("projects.name IN ("));
         assert!(sql.contains("languages.name IN ("));
+        assert!(sql.contains("sources.name IS NULL OR NOT (sources.name IN ("));
+        assert!(sql.contains("entities.type IN ("));
+        assert!(sql.contains(" AND EXISTS (SELECT 1 FROM tags AS filter_tags"));
+        assert!(sql.contains("filter_tags.name GLOB "));
+        assert!(sql.contains(" AND NOT EXISTS ("));
         assert!(!sql.contains("categories.name"));
     }
 
@@ -624,6 +1181,7 @@ mod tests {
         assert!(sql.contains("LEFT JOIN categories"));
         assert!(sql.contains("LEFT JOIN languages"));
         assert!(sql.contains("LEFT JOIN sources"));
+        assert!(!sql.contains("JOIN tags"));
     }
 
     #[test]
@@ -667,99 +1225,41 @@ mod tests {
             group_key_info(Some(Group::Source)),
             ("sources.name", Some("sources"))
         );
+        assert_eq!(
+            group_key_info(Some(Group::Tag)),
+            ("tags.name", Some("tags"))
+        );
         assert_eq!(group_key_info(None), ("'Total'", None));
     }
 
     #[test]
-    fn test_bucket_step_variants() {
-        match bucket_step(Some(TimeBucket::Hour)) {
-            BucketStep::Seconds(3600) => {}
-            _ => panic!("Expected Hour to be 3600 seconds"),
-        }
-
-        match bucket_step(Some(TimeBucket::Day)) {
-            BucketStep::Seconds(86400) => {}
-            _ => panic!("Expected Day to be 86400 seconds"),
-        }
-
-        match bucket_step(Some(TimeBucket::Week)) {
-            BucketStep::Calendar("+7 days") => {}
-            _ => panic!("Expected Week to be calendar-based"),
-        }
-
-        match bucket_step(Some(TimeBucket::Month)) {
-            BucketStep::Calendar("+1 month") => {}
-            _ => panic!("Expected Month to be calendar-based"),
-        }
-
-        match bucket_step(Some(TimeBucket::Year)) {
-            BucketStep::Calendar("+1 year") => {}
-            _ => panic!("Expected Year to be calendar-based"),
-        }
-
-        match bucket_step(None) {
-            BucketStep::Seconds(86400) => {}
-            _ => panic!("Expected None to default to Day"),
-        }
-    }
+    fn test_group_keys_info_and_composite_joins() {
+        let (keys, tables) = group_keys_info(&[Group::Project, Group::Language]);
+        assert_eq!(keys, ["projects.name", "languages.name"]);
+        assert_eq!(tables, ["projects", "languages"]);
 
-    #[test]
-    fn test_push_bucket_label_expr() {
-        let mut qb = QueryBuilder::<Sqlite>::new("SELECT ");
-        qb.push_bucket_label_expr(Some(TimeBucket::Hour));
-        assert!(qb.build().sql().contains("%Y-%m-%d %H:00:00"));
-
-        let mut qb = QueryBuilder::<Sqlite>::new("SELECT ");
-        qb.push_bucket_label_expr(Some(TimeBucket::Day));
-        assert!(qb.build().sql().contains("%Y-%m-%d"));
-
-        let mut qb = QueryBuilder::<Sqlite>::new("SELECT ");
-        qb.push_bucket_label_expr(Some(TimeBucket::Week));
-        assert!(qb.build().sql().contains("%Y-W%W"));
-
-        let mut qb = QueryBuilder::<Sqlite>::new("SELECT ");
-        qb.push_bucket_label_expr(Some(TimeBucket::Month));
-        assert!(qb.build().sql().contains("%Y-%m"));
-
-        let mut qb = QueryBuilder::<Sqlite>::new("SELECT ");
-        qb.push_bucket_label_expr(Some(TimeBucket::Year));
-        assert!(qb.build().sql().contains("%Y"));
-
-        let mut qb = QueryBuilder::<Sqlite>::new("SELECT ");
-        qb.push_bucket_label_expr(None);
-        assert!(qb.build().sql().contains("'Unbucketed'"));
-    }
-
-    #[test]
-    fn test_push_next_end_with_seconds() {
-        let mut qb = QueryBuilder::<Sqlite>::new("SELECT ");
-        let step = BucketStep::Seconds(3600);
-        push_next_end_with(
-            &mut qb,
-            |q| {
-                q.push("start_ts");
-            },
-            &step,
-        );
+        let mut qb = QueryBuilder::<Sqlite>::new("SELECT * FROM events");
+        qb.append_standard_joins_with(&tables);
 
         let sql = qb.build().sql();
-        assert!(sql.contains("start_ts + 3600"));
+        assert!(sql.contains(" JOIN projects ON"));
+        assert!(!sql.contains("LEFT JOIN projects"));
+        assert!(!sql.contains("LEFT JOIN languages"));
+        assert!(sql.contains("LEFT JOIN apps"));
     }
 
     #[test]
-    fn test_push_next_end_with_calendar() {
-        let mut qb = QueryBuilder::<Sqlite>::new("SELECT ");
-        let step = BucketStep::Calendar("+1 month");
-        push_next_end_with(
-            &mut qb,
-            |q| {
-                q.push("start_ts");
-            },
-            &step,
-        );
+    fn test_push_buckets_cte() {
+        let start = Utc.with_ymd_and_hms(2025, 1, 1, 0, 0, 0).unwrap();
+        let end = Utc.with_ymd_and_hms(2025, 1, 3, 0, 0, 0).unwrap();
+        let spans = bucket_spans(&Utc, WeekStart::Monday, start, end, Some(TimeBucket::Day));
+
+        let mut qb = QueryBuilder::<Sqlite>::new("");
+        push_buckets_cte(&mut qb, &spans);
 
         let sql = qb.build().sql();
-        assert!(sql.contains("strftime('%s', datetime("));
-        assert!(sql.contains("+1 month"));
+        assert!(sql.starts_with("WITH buckets(start_ts, end_ts, bucket_label) AS (VALUES "));
+        assert!(sql.contains("(1735689600, 1735776000, '2025-01-01')"));
+        assert!(sql.contains("(1735776000, 1735862400, '2025-01-02')"));
     }
 }
@@ -1,4 +1,8 @@
-use common::{models::Group, time::TimeBucket};
+use common::{
+    models::Group,
+    time::{TimeBucket, Tz, WeekStart},
+};
+use uuid::Uuid;
 
 /// A reusable trait for extracting query filters
 pub trait SummaryQueryParams {
@@ -6,33 +10,90 @@ pub trait SummaryQueryParams {
     fn end(&self) -> Option<i64>;
     fn apps(&self) -> Option<&Vec<String>>;
     fn projects(&self) -> Option<&Vec<String>>;
+    fn project_id(&self) -> Option<Uuid>;
     fn categories(&self) -> Option<&Vec<String>>;
     fn entities(&self) -> Option<&Vec<String>>;
     fn branches(&self) -> Option<&Vec<String>>;
     fn languages(&self) -> Option<&Vec<String>>;
+    fn sources(&self) -> Option<&Vec<String>>;
+    fn entity_types(&self) -> Option<&Vec<String>>;
+    fn tags(&self) -> Option<&Vec<String>>;
     fn group_by(&self) -> Option<Group>;
+    fn subgroup_by(&self) -> &[Group];
     fn time_bucket(&self) -> Option<TimeBucket>;
+    fn exclude_afk(&self) -> bool;
+    fn timezone(&self) -> Option<Tz>;
+    fn week_start(&self) -> WeekStart;
 }
 
-/// A base struct that holds shared summary query parameters
+/// A base struct that holds shared summary query parameters.
+///
+/// Every dimension list accepts exact values, GLOB patterns and `!`-prefixed excludes
+/// (see [`QueryBuilderExt::append_filter_list`](super::query::QueryBuilderExt::append_filter_list)).
 #[derive(Debug, Clone, Default)]
 pub struct SummaryFilters {
     pub start: Option<i64>,
     pub end: Option<i64>,
     pub apps: Option<Vec<String>>,
     pub projects: Option<Vec<String>>,
+    /// Restricts events to a single project. Unlike `projects`, which matches names,
+    /// this tells apart projects that share a name.
+    pub project_id: Option<Uuid>,
     pub categories: Option<Vec<String>>,
     pub entities: Option<Vec<String>>,
     pub branches: Option<Vec<String>>,
     pub languages: Option<Vec<String>>,
+    pub sources: Option<Vec<String>>,
+    pub entity_types: Option<Vec<String>>,
+    /// Tags of the event or its project. An event matches when any of its tags does.
+    pub tags: Option<Vec<String>>,
     pub group_by: Option<Group>,
+    /// Further dimensions splitting each `group_by` group, in order. Ignored without `group_by`.
+    pub subgroup_by: Vec<Group>,
     pub time_bucket: Option<TimeBucket>,
+    /// Whether time overlapping AFK periods is subtracted from event durations
+    pub exclude_afk: bool,
+    /// Timezone that bucket boundaries and labels are computed in, the server's local one when `None`
+    pub timezone: Option<Tz>,
+    /// First day of week buckets
+    pub week_start: WeekStart,
 }
 
 impl SummaryFilters {
     pub fn builder() -> SummaryFiltersBuilder {
         SummaryFiltersBuilder::default()
     }
+
+    /// Returns every grouping dimension in order, `group_by` first
+    pub fn groups(&self) -> Vec<Group> {
+        match self.group_by {
+            Some(group) => std::iter::once(group)
+                .chain(self.subgroup_by.iter().copied())
+                .collect(),
+            None => Vec::new(),
+        }
+    }
+
+    /// Whether neither a range nor any filter narrows the events matched
+    pub fn is_unrestricted(&self) -> bool {
+        let lists = [
+            &self.apps,
+            &self.projects,
+            &self.categories,
+            &self.entities,
+            &self.branches,
+            &self.languages,
+            &self.sources,
+            &self.entity_types,
+            &self.tags,
+        ];
+        self.start.is_none()
+            && self.end.is_none()
+            && self.project_id.is_none()
+            && lists
+                .iter()
+                .all(|list| list.as_ref().is_none_or(|values| values.is_empty()))
+    }
 }
 
 impl SummaryQueryParams for SummaryFilters {
@@ -52,6 +113,10 @@ impl SummaryQueryParams for SummaryFilters {
         self.projects.as_ref()
     }
 
+    fn project_id(&self) -> Option<Uuid> {
+        self.project_id
+    }
+
     fn categories(&self) -> Option<&Vec<String>> {
         self.categories.as_ref()
     }
@@ -68,13 +133,41 @@ impl SummaryQueryParams for SummaryFilters {
         self.languages.as_ref()
     }
 
+    fn sources(&self) -> Option<&Vec<String>> {
+        self.sources.as_ref()
+    }
+
+    fn entity_types(&self) -> Option<&Vec<String>> {
+        self.entity_types.as_ref()
+    }
+
+    fn tags(&self) -> Option<&Vec<String>> {
+        self.tags.as_ref()
+    }
+
     fn group_by(&self) -> Option<Group> {
         self.group_by
     }
 
+    fn subgroup_by(&self) -> &[Group] {
+        &self.subgroup_by
+    }
+
     fn time_bucket(&self) -> Option<TimeBucket> {
         self.time_bucket
     }
+
+    fn exclude_afk(&self) -> bool {
+        self.exclude_afk
+    }
+
+    fn timezone(&self) -> Option<Tz> {
+        self.timezone
+    }
+
+    fn week_start(&self) -> WeekStart {
+        self.week_start
+    }
 }
 
 /// Builder for Summary Filters
@@ -104,6 +197,11 @@ impl SummaryFiltersBuilder {
         self
     }
 
+    pub fn project_id(mut self, value: Uuid) -> Self {
+        self.filters.project_id = Some(value);
+        self
+    }
+
     pub fn entities(mut self, values: Vec<String>) -> Self {
         self.filters.entities = Some(values);
         self
@@ -124,16 +222,51 @@ impl SummaryFiltersBuilder {
         self
     }
 
+    pub fn sources(mut self, values: Vec<String>) -> Self {
+        self.filters.sources = Some(values);
+        self
+    }
+
+    pub fn entity_types(mut self, values: Vec<String>) -> Self {
+        self.filters.entity_types = Some(values);
+        self
+    }
+
+    pub fn tags(mut self, values: Vec<String>) -> Self {
+        self.filters.tags = Some(values);
+        self
+    }
+
     pub fn group_by(mut self, value: Group) -> Self {
         self.filters.group_by = Some(value);
         self
     }
 
+    pub fn subgroup_by(mut self, values: Vec<Group>) -> Self {
+        self.filters.subgroup_by = values;
+        self
+    }
+
     pub fn time_bucket(mut self, value: TimeBucket) -> Self {
         self.filters.time_bucket = Some(value);
         self
     }
 
+    pub fn exclude_afk(mut self, value: bool) -> Self {
+        self.filters.exclude_afk = value;
+        self
+    }
+
+    pub fn timezone(mut self, value: Tz) -> Self {
+        self.filters.timezone = Some(value);
+        self
+    }
+
+    pub fn week_start(mut self, value: WeekStart) -> Self {
+        self.filters.week_start = value;
+        self
+    }
+
     pub fn build(self) -> SummaryFilters {
         self.filters
     }
@@ -40,6 +40,32 @@ pub async fn update_synced_in(
     Ok(())
 }
 
+/// Records the reason the server rejected each row in `table`
+#[allow(dead_code)]
+pub async fn update_rejected_in(
+    db_context: &DBContext,
+    table: &str,
+    rejected: &[(i64, String)],
+) -> Result<(), DBError> {
+    if rejected.is_empty() {
+        return Ok(());
+    }
+
+    let query = format!("UPDATE {} SET rejected_reason = ? WHERE id = ?", table);
+    let mut tx = db_context.pool().begin().await?;
+
+    for (id, reason) in rejected {
+        sqlx::query(&query)
+            .bind(reason)
+            .bind(id)
+            .execute(&mut *tx)
+            .await?;
+    }
+
+    tx.commit().await?;
+    Ok(())
+}
+
 #[allow(dead_code)]
 #[cfg(debug_assertions)]
 pub async fn explain_query(pool: &SqlitePool, sql: &str) -> Result<(), DBError> {